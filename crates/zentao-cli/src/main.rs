@@ -0,0 +1,1142 @@
+// 禅道命令行工具：复用 zentao-core 的客户端、模型、配置和token文件，方便在CI和脚本中查询Bug
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use zentao_core::annotation::{self, Annotation, Annotations};
+use zentao_core::bug_report::{self, ReportFormat};
+use zentao_core::client::{self, MyBugsMode, ZentaoClient, UNAUTHORIZED};
+use zentao_core::config::{load_config_from_file, save_config_to_file, GitConfig, ZentaoConfig, DEFAULT_PROFILE};
+use zentao_core::duplicate::{self, BugFingerprint};
+use zentao_core::export::{export_bugs, user_display, ExportFormat};
+use zentao_core::git;
+use zentao_core::git_template;
+use zentao_core::html::{decode_entities, image_paths};
+use zentao_core::http;
+use zentao_core::link::{self, LinkKind};
+use zentao_core::markdown::{html_to_markdown, html_to_plain_text};
+use zentao_core::models::{format_datetime_to_china, Bug};
+use zentao_core::query::Query;
+use zentao_core::redact::RedactingWriter;
+use zentao_core::report::{self, DateRange, Granularity};
+use zentao_core::search::{self, SearchIndex};
+use zentao_core::snapshot::{self, TrendMetric};
+use zentao_core::token::{get_current_timestamp, load_token_from_file, remove_token_file, save_token_to_file};
+use zentao_core::view;
+use zentao_core::watch;
+
+/// 本地git仓库参数，不指定时使用配置中的仓库和匹配规则
+#[derive(Debug, Args)]
+struct GitArgs {
+    /// 仓库路径，可以指定多个
+    #[arg(short, long = "repo", num_args = 1..)]
+    repositories: Vec<String>,
+
+    /// 提取Bug ID的正则表达式，第一个捕获组为Bug ID，可以指定多个
+    #[arg(long = "pattern", num_args = 1..)]
+    patterns: Vec<String>,
+}
+
+impl GitArgs {
+    // 命令行参数优先，其次是保存的配置；都没有仓库时使用当前目录
+    fn config(self) -> Result<GitConfig, String> {
+        let mut config = load_config_from_file(DEFAULT_PROFILE)?.map(|config| config.git).unwrap_or_default();
+        if !self.repositories.is_empty() {
+            config.repositories = self.repositories;
+        }
+        if config.repositories.is_empty() {
+            config.repositories.push(".".to_string());
+        }
+        if !self.patterns.is_empty() {
+            config.patterns = self.patterns;
+        }
+        Ok(config)
+    }
+}
+
+/// 命令行参数
+#[derive(Debug, Parser)]
+#[command(name = "zentao", author, version, about = "禅道Bug查询命令行工具", long_about = None)]
+struct Cli {
+    /// 以JSON格式输出
+    #[arg(long, global = true, default_value_t = false)]
+    json: bool,
+
+    /// 输出调试日志（也可以用 RUST_LOG 环境变量控制）
+    #[arg(short, long, global = true, default_value_t = false)]
+    verbose: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 登录禅道并保存token（与桌面应用共用）
+    Login(LoginArgs),
+    /// 退出登录，删除保存的token
+    Logout,
+    /// 显示当前登录用户
+    Whoami,
+    /// 列出产品
+    Products,
+    /// 列出产品的Bug，可以用 --query 按条件过滤
+    Bugs(BugFilter),
+    /// 列出我的Bug（不区分产品）
+    Mine {
+        /// 范围：assigned（指派给我）、opened（由我创建）或 resolved（由我解决）
+        #[arg(short, long, default_value = "assigned")]
+        mode: MyBugsMode,
+
+        /// 按状态过滤，例如 active、resolved、closed
+        #[arg(short, long)]
+        status: Option<String>,
+    },
+    /// 在Bug标题和重现步骤中全文搜索
+    Search {
+        /// 搜索内容
+        query: String,
+
+        /// 产品ID，可以指定多个，不指定时搜索全部产品
+        #[arg(short, long = "product", num_args = 1..)]
+        products: Vec<i32>,
+
+        /// 最多显示的结果数
+        #[arg(short = 'n', long, default_value_t = search::DEFAULT_LIMIT)]
+        limit: usize,
+    },
+    /// 生成Bug统计报表（默认输出 Markdown）
+    Report {
+        /// 产品ID，可以指定多个，不指定时统计全部产品
+        #[arg(short, long = "product", num_args = 1..)]
+        products: Vec<i32>,
+
+        /// 开始日期（YYYY-MM-DD）
+        #[arg(long)]
+        from: Option<NaiveDate>,
+
+        /// 结束日期（YYYY-MM-DD）
+        #[arg(long)]
+        to: Option<NaiveDate>,
+
+        /// 趋势的统计周期：day 或 week
+        #[arg(long, default_value = "week")]
+        by: Granularity,
+    },
+    /// 查找与指定Bug疑似重复的Bug（比较标题、重现步骤和截图）
+    Duplicates {
+        /// Bug ID
+        id: i32,
+
+        /// 在这些产品中查找，不指定时为该Bug所属的产品
+        #[arg(short, long = "product", num_args = 1..)]
+        products: Vec<i32>,
+
+        /// 最多显示的结果数
+        #[arg(short = 'n', long, default_value_t = duplicate::DEFAULT_LIMIT)]
+        limit: usize,
+
+        /// 不下载和比较截图
+        #[arg(long, default_value_t = false)]
+        no_images: bool,
+    },
+    /// 根据Bug生成分支名和提交信息（模板来自配置）
+    GitTemplates {
+        /// Bug ID
+        id: i32,
+
+        /// 只输出分支名，例如 git checkout -b $(zentao git-templates 123 --branch)
+        #[arg(long, default_value_t = false)]
+        branch: bool,
+    },
+    /// 在本地git仓库中查找提交信息引用了Bug的提交
+    Commits {
+        /// Bug ID
+        id: i32,
+
+        #[command(flatten)]
+        git: GitArgs,
+    },
+    /// 列出提交范围（如 v1.0..v1.1）中的提交引用的Bug
+    RangeBugs {
+        /// 提交范围：a..b、a...b 或单个提交
+        range: String,
+
+        #[command(flatten)]
+        git: GitArgs,
+    },
+    /// 记录产品今天的Bug快照（可以用定时任务每天执行）
+    Snapshot {
+        /// 产品ID，可以指定多个，不指定时记录全部产品
+        #[arg(short, long = "product", num_args = 1..)]
+        products: Vec<i32>,
+    },
+    /// 根据本地快照输出产品的Bug数量趋势
+    Trend {
+        /// 产品ID
+        #[arg(short, long = "product")]
+        product: i32,
+
+        /// 趋势指标：burndown、burnup、status、severity 或 assignee
+        #[arg(short, long, default_value = "burndown")]
+        metric: TrendMetric,
+
+        /// 开始日期（YYYY-MM-DD）
+        #[arg(long)]
+        from: Option<NaiveDate>,
+
+        /// 结束日期（YYYY-MM-DD）
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// 关注Bug并查看关注的Bug的变化（与桌面应用共用关注列表）
+    Watch {
+        #[command(subcommand)]
+        action: WatchAction,
+    },
+    /// 本地笔记、标签和星标（与桌面应用共用，只保存在本机）
+    Note {
+        #[command(subcommand)]
+        action: NoteAction,
+    },
+    /// 管理Bug列表视图（与桌面应用共用）
+    Views {
+        #[command(subcommand)]
+        action: ViewAction,
+    },
+    /// 查看Bug详情
+    Bug {
+        /// Bug ID，也可以是从禅道网页复制的Bug链接或 zentao-viewer://bug/<id>
+        id: String,
+
+        /// 以 Markdown 输出标题、字段和重现步骤，方便贴到聊天工具
+        #[arg(long, default_value_t = false)]
+        markdown: bool,
+
+        /// 下载重现步骤中的图片到该目录，输出中的图片改为本地路径
+        #[arg(long)]
+        images: Option<PathBuf>,
+    },
+    /// 导出Bug报告：字段、重现步骤（内嵌图片）和历史记录，可以发给没有禅道账号的人
+    BugReport {
+        /// Bug ID，也可以是从禅道网页复制的Bug链接或 zentao-viewer://bug/<id>
+        id: String,
+
+        /// 报告格式：html 或 pdf（需要本机安装 Chrome、Edge 或 Chromium）
+        #[arg(short, long, default_value = "html")]
+        format: ReportFormat,
+
+        /// 输出文件路径，默认为当前目录下的 bug-<id>.html 或 bug-<id>.pdf
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 导出产品的Bug列表
+    Export {
+        #[command(flatten)]
+        filter: BugFilter,
+
+        /// 导出格式：csv、markdown 或 json
+        #[arg(short, long, default_value = "csv")]
+        format: ExportFormat,
+
+        /// 输出文件路径，不指定时输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum WatchAction {
+    /// 关注Bug
+    Add {
+        /// Bug ID，可以指定多个
+        #[arg(required = true)]
+        ids: Vec<i32>,
+    },
+    /// 取消关注Bug
+    Remove {
+        /// Bug ID，可以指定多个
+        #[arg(required = true)]
+        ids: Vec<i32>,
+    },
+    /// 获取关注的Bug的最新版本，列出关注的Bug和未读的变化
+    List {
+        /// 不获取最新版本，只列出已记录的变化
+        #[arg(long, default_value_t = false)]
+        no_sync: bool,
+    },
+    /// 把变化标记为已读，不指定Bug时标记全部
+    Read {
+        /// Bug ID
+        id: Option<i32>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum NoteAction {
+    /// 查看Bug的本地标注
+    Show {
+        /// Bug ID
+        id: i32,
+    },
+    /// 修改Bug的本地标注，没有指定的部分保持不变
+    Set {
+        /// Bug ID
+        id: i32,
+
+        /// 笔记内容（Markdown），传入空字符串清空笔记
+        #[arg(long)]
+        note: Option<String>,
+
+        /// 添加标签，可以指定多个
+        #[arg(long = "tag", num_args = 1..)]
+        tags: Vec<String>,
+
+        /// 移除标签，可以指定多个
+        #[arg(long = "untag", num_args = 1..)]
+        untags: Vec<String>,
+
+        /// 加星标
+        #[arg(long, default_value_t = false, conflicts_with = "unstar")]
+        star: bool,
+
+        /// 取消星标
+        #[arg(long, default_value_t = false)]
+        unstar: bool,
+    },
+    /// 列出本地标注
+    List {
+        /// 只列出带有该标签的Bug
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// 只列出加了星标的Bug
+        #[arg(long, default_value_t = false)]
+        starred: bool,
+    },
+    /// 导出本地标注为JSON
+    Export {
+        /// 输出文件路径，不指定时输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 导入导出的JSON，默认与本地标注合并并保留较新的版本
+    Import {
+        /// JSON文件路径
+        file: PathBuf,
+
+        /// 先清空本地标注
+        #[arg(long, default_value_t = false)]
+        replace: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ViewAction {
+    /// 列出保存的视图
+    List,
+    /// 重命名视图
+    Rename {
+        /// 原名称
+        name: String,
+        /// 新名称
+        new_name: String,
+    },
+    /// 删除视图
+    Delete {
+        /// 视图名称
+        name: String,
+    },
+    /// 导出视图为JSON，不指定名称时导出全部视图
+    Export {
+        /// 视图名称，可以指定多个
+        names: Vec<String>,
+
+        /// 输出文件路径，不指定时输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 导入导出的视图，与已有视图重名时自动改名
+    Import {
+        /// JSON文件路径
+        file: PathBuf,
+
+        /// 覆盖同名视图
+        #[arg(long, default_value_t = false)]
+        overwrite: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+struct LoginArgs {
+    /// 禅道地址，不指定时使用保存的配置
+    #[arg(long, env = "ZENTAO_URL")]
+    base_url: Option<String>,
+
+    /// 账号，不指定时使用保存的配置
+    #[arg(long, env = "ZENTAO_ACCOUNT")]
+    account: Option<String>,
+
+    /// 密码，建议通过 ZENTAO_PASSWORD 环境变量传入
+    #[arg(long, env = "ZENTAO_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+
+    /// 登录成功后把地址和账号密码保存为配置
+    #[arg(long, default_value_t = false)]
+    save: bool,
+}
+
+#[derive(Debug, Args)]
+struct BugFilter {
+    /// 产品ID，可以指定多个，不指定时查询全部产品
+    #[arg(short, long = "product", num_args = 1..)]
+    products: Vec<i32>,
+
+    /// 查询条件，与桌面应用语法相同，例如 'status:active severity<=2 assignee:me "crash"'
+    #[arg(short, long)]
+    query: Option<Query>,
+
+    /// 按状态过滤，例如 active、resolved、closed
+    #[arg(short, long)]
+    status: Option<String>,
+
+    /// 按指派人过滤（账号或姓名）
+    #[arg(short, long)]
+    assignee: Option<String>,
+}
+
+impl BugFilter {
+    fn matches(&self, bug: &Bug) -> bool {
+        if let Some(status) = &self.status
+            && !status_matches(bug, status)
+        {
+            return false;
+        }
+        if let Some(assignee) = &self.assignee {
+            match &bug.assigned_to {
+                Some(user) if user.account == *assignee || user.realname == *assignee => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+// 状态可以用代码（active）或显示名称（激活）过滤
+fn status_matches(bug: &Bug, status: &str) -> bool {
+    bug.status.code.eq_ignore_ascii_case(status) || bug.status.name == status
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    setup_tracing(cli.verbose);
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if e == UNAUTHORIZED {
+                let _ = remove_token_file(DEFAULT_PROFILE);
+                eprintln!("错误: 登录已过期，请重新执行 zentao login");
+            } else {
+                eprintln!("错误: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// 日志输出到标准错误，避免干扰命令输出
+fn setup_tracing(verbose: bool) {
+    use tracing_subscriber::{fmt, EnvFilter};
+
+    let default_level = if verbose { "debug" } else { "warn" };
+    // 与桌面应用一样，输出前屏蔽密码、token和会话ID
+    fmt()
+        .with_writer(|| RedactingWriter::new(std::io::stderr()))
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| default_level.into()))
+        .init();
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Command::Login(args) => login(args).await,
+        Command::Logout => {
+            remove_token_file(DEFAULT_PROFILE)?;
+            println!("已退出登录");
+            Ok(())
+        }
+        Command::Whoami => {
+            let user = session_client()?.get_user_info().await?;
+            if cli.json {
+                print_json(&user)
+            } else {
+                println!("{} ({})", user.realname, user.account);
+                Ok(())
+            }
+        }
+        Command::Products => {
+            let products = session_client()?.get_products().await?;
+            if cli.json {
+                return print_json(&products);
+            }
+            let rows = products
+                .iter()
+                .map(|product| vec![product.id.to_string(), product.name.clone(), product.code.clone(), user_display(&product.po)])
+                .collect::<Vec<_>>();
+            print_table(&["ID", "名称", "代号", "产品负责人"], &rows);
+            Ok(())
+        }
+        Command::Bugs(filter) => {
+            let bugs = fetch_bugs(&filter).await?;
+            if cli.json {
+                return print_json(&bugs);
+            }
+            print_bug_list(&bugs);
+            Ok(())
+        }
+        Command::Mine { mode, status } => {
+            let bugs = session_client()?
+                .get_my_bugs(mode)
+                .await?
+                .into_iter()
+                .filter(|bug| status.as_ref().is_none_or(|status| status_matches(bug, status)))
+                .collect::<Vec<_>>();
+            if cli.json {
+                return print_json(&bugs);
+            }
+            print_bug_list(&bugs);
+            Ok(())
+        }
+        Command::Search { query, products, limit } => {
+            let client = session_client()?;
+            let bugs = client.get_bugs_by_product(&product_ids_or_all(&client, products).await?).await?;
+            let mut index = SearchIndex::default();
+            index.set_notes(load_profile_annotations()?.search_texts());
+            for bug in &bugs {
+                index.upsert(bug);
+            }
+            let hits = index.search(&query, limit);
+            if cli.json {
+                return print_json(&hits);
+            }
+            for hit in &hits {
+                println!("#{}  {}  ({:.2})", hit.bug_id, strip_marks(&hit.title), hit.score);
+                if !hit.snippet.is_empty() {
+                    println!("    {}", strip_marks(&hit.snippet));
+                }
+            }
+            eprintln!("共 {} 个结果", hits.len());
+            Ok(())
+        }
+        Command::Report { products, from, to, by } => {
+            let client = session_client()?;
+            let product_ids = product_ids_or_all(&client, products).await?;
+            let bugs = client.get_bugs_by_product(&product_ids).await?;
+            let mut modules = HashMap::new();
+            for product_id in &product_ids {
+                match client.get_module_names(*product_id).await {
+                    Ok(names) => modules.extend(names),
+                    Err(e) if e == UNAUTHORIZED => return Err(e),
+                    Err(e) => eprintln!("警告: 获取产品{}的模块失败，报表将显示模块ID: {}", product_id, e),
+                }
+            }
+            let report = report::build_report_today(&bugs, &product_ids, &modules, DateRange { from, to }, by);
+            if cli.json {
+                return print_json(&report);
+            }
+            print!("{}", report::report_to_markdown(&report));
+            Ok(())
+        }
+        Command::Duplicates { id, products, limit, no_images } => {
+            let client = session_client()?;
+            let bug = client.get_bug_detail(id).await?;
+            let product_ids = if products.is_empty() { vec![bug.product] } else { products };
+            let bugs = client.get_bugs_by_product(&product_ids).await?;
+            let mut target = BugFingerprint::new(&bug);
+            let mut candidates = bugs.iter().map(BugFingerprint::new).collect::<Vec<_>>();
+            if !no_images {
+                target.images = duplicate::load_image_hashes(&client, &target.image_paths).await?;
+                if !target.images.is_empty() {
+                    for i in duplicate::image_candidates(&target, &candidates) {
+                        candidates[i].images = duplicate::load_image_hashes(&client, &candidates[i].image_paths).await?;
+                    }
+                }
+            }
+            let duplicates = duplicate::find_duplicates(&target, &candidates, limit);
+            if cli.json {
+                return print_json(&duplicates);
+            }
+            for candidate in &duplicates {
+                println!("#{} {:.0}% [{}] {}", candidate.bug_id, candidate.score * 100.0, candidate.status, candidate.title);
+                for evidence in &candidate.evidence {
+                    println!("    {}", evidence.detail);
+                }
+            }
+            eprintln!("共 {} 个疑似重复的Bug", duplicates.len());
+            Ok(())
+        }
+        Command::GitTemplates { id, branch } => {
+            let config = load_config_from_file(DEFAULT_PROFILE)?.map(|config| config.git).unwrap_or_default();
+            let client = session_client()?;
+            let bug = client.get_bug_detail(id).await?;
+            let product_name = match client.get_product_detail(bug.product).await {
+                Ok(product) => product.name,
+                Err(_) => format!("产品{}", bug.product),
+            };
+            let templates = git_template::bug_git_templates(&bug, &product_name, &config);
+            if cli.json {
+                return print_json(&templates);
+            }
+            if branch {
+                println!("{}", templates.branch);
+            } else {
+                println!("{}\n\n{}", templates.branch, templates.commit_message);
+            }
+            Ok(())
+        }
+        Command::Commits { id, git } => {
+            let commits = git::commits_for_bug(&git.config()?, id)?;
+            if cli.json {
+                return print_json(&commits);
+            }
+            for commit in &commits.commits {
+                println!("{} {} {} [{}]", commit.short_id, commit.time.get(..10).unwrap_or_default(), commit.summary, commit.branches.join(", "));
+                for file in &commit.files {
+                    println!("    {}", file);
+                }
+            }
+            eprintln!("共 {} 个提交，涉及分支: {}", commits.commits.len(), commits.branches.join(", "));
+            Ok(())
+        }
+        Command::RangeBugs { range, git } => {
+            let config = git.config()?;
+            // 与桌面应用一样，跳过没有这个提交范围的仓库，全部仓库都没有时才报错
+            let (ranges, skipped) = git::bugs_in_repositories(&config.repositories, &range, &config.patterns)?;
+            for skipped in &skipped {
+                eprintln!("警告: 跳过仓库 {}: {}", skipped.repository, skipped.error);
+            }
+            if cli.json {
+                return print_json(&ranges);
+            }
+            for range_bugs in &ranges {
+                println!("{}", range_bugs.repository);
+                for (bug_id, commits) in git::group_by_bug(range_bugs) {
+                    println!("    #{}: {}", bug_id, commits.join(", "));
+                }
+            }
+            Ok(())
+        }
+        Command::Snapshot { products } => {
+            let client = session_client()?;
+            let product_ids = product_ids_or_all(&client, products).await?;
+            let bugs = client.get_bugs_by_product(&product_ids).await?;
+            snapshot::record_snapshots(&client.urls().root, &product_ids, &bugs)?;
+            eprintln!("已记录 {} 个产品的Bug快照", product_ids.len());
+            Ok(())
+        }
+        Command::Trend { product, metric, from, to } => {
+            let config = load_config_from_file(DEFAULT_PROFILE)?.ok_or("没有找到配置，请先执行 zentao login --save")?;
+            let snapshots = snapshot::load_snapshots(&config.base_url, product)?;
+            let trend = snapshot::build_trend(product, &snapshots, metric, DateRange { from, to });
+            if cli.json {
+                return print_json(&trend);
+            }
+            let mut headers = vec!["日期"];
+            // 按指派人分组时，未指派的Bug的 key 为空
+            headers.extend(trend.series.iter().map(|series| if series.key.is_empty() { "未指派" } else { series.key.as_str() }));
+            let rows = trend
+                .dates
+                .iter()
+                .enumerate()
+                .map(|(i, date)| {
+                    let mut row = vec![date.to_string()];
+                    row.extend(trend.series.iter().map(|series| series.points[i].value.to_string()));
+                    row
+                })
+                .collect::<Vec<_>>();
+            print_table(&headers, &rows);
+            eprintln!("共 {} 个快照", trend.dates.len());
+            Ok(())
+        }
+        Command::Watch { action } => watch_command(action, cli.json).await,
+        Command::Note { action } => note_command(action, cli.json),
+        Command::Views { action } => view_command(action, cli.json),
+        Command::Bug { id, markdown, images } => {
+            let client = session_client()?;
+            let bug = client.get_bug_detail(bug_id_arg(&id)?).await?;
+            let local_images = match images {
+                Some(dir) => download_images(&client, &bug, &dir).await?,
+                None => HashMap::new(),
+            };
+            // 下载过的图片使用本地路径，其余指向禅道服务器
+            let image_url = |path: &str| local_images.get(path).cloned().unwrap_or_else(|| bug.image_url(path));
+            if cli.json {
+                print_json(&bug)
+            } else if markdown {
+                print_bug_markdown(&bug, &html_to_markdown(&bug.steps, image_url));
+                Ok(())
+            } else {
+                print_bug(&bug, &html_to_plain_text(&bug.steps, image_url));
+                Ok(())
+            }
+        }
+        Command::BugReport { id, format, output } => {
+            let id = bug_id_arg(&id)?;
+            let html = bug_report::build_bug_report(&session_client()?, id).await?;
+            let path = output.unwrap_or_else(|| PathBuf::from(bug_report::report_file_name(id, format)));
+            bug_report::write_bug_report(&html, format, &path)?;
+            eprintln!("已导出Bug {} 的报告到 {}", id, path.display());
+            Ok(())
+        }
+        Command::Export { filter, format, output } => {
+            let bugs = fetch_bugs(&filter).await?;
+            let content = export_bugs(&bugs, format)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+                    eprintln!("已导出 {} 个Bug到 {}", bugs.len(), path.display());
+                }
+                None => print!("{}", content),
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn login(args: LoginArgs) -> Result<(), String> {
+    let saved = load_config_from_file(DEFAULT_PROFILE)?;
+    let base_url = args
+        .base_url
+        .or_else(|| saved.as_ref().map(|config| config.base_url.clone()))
+        .ok_or("请通过 --base-url 或 ZENTAO_URL 指定禅道地址")?;
+    let account = args
+        .account
+        .or_else(|| saved.as_ref().map(|config| config.account.clone()))
+        .ok_or("请通过 --account 或 ZENTAO_ACCOUNT 指定账号")?;
+    let password = args
+        .password
+        .or_else(|| saved.as_ref().map(|config| config.password.clone()))
+        .ok_or("请通过 --password 或 ZENTAO_PASSWORD 指定密码")?;
+
+    // 沿用已保存配置中的API模式、网络和轮询设置
+    let config = match saved {
+        Some(saved) => ZentaoConfig {
+            base_url,
+            account,
+            password,
+            ..saved
+        },
+        None => ZentaoConfig {
+            base_url,
+            account,
+            password,
+            api_mode: Default::default(),
+            network: Default::default(),
+            polling: Default::default(),
+            git: Default::default(),
+        },
+    };
+
+    let http = http::build_http_client(&config.network)?;
+    let (token, api) = client::login(
+        &http,
+        &config.base_url,
+        config.account.clone(),
+        config.password.clone(),
+        config.api_mode,
+    )
+    .await?;
+
+    // 与桌面应用一致，token有效期24小时
+    let expire_time = get_current_timestamp() + (24 * 60 * 60);
+    save_token_to_file(DEFAULT_PROFILE, &token, expire_time, &api)?;
+    if args.save {
+        save_config_to_file(DEFAULT_PROFILE, &config)?;
+    }
+
+    println!("登录成功: {}", config.account);
+    Ok(())
+}
+
+async fn watch_command(action: WatchAction, json: bool) -> Result<(), String> {
+    let config = load_config_from_file(DEFAULT_PROFILE)?.ok_or("没有找到配置，请先执行 zentao login --save")?;
+    let (base_url, account) = (config.base_url, config.account);
+    match action {
+        WatchAction::Add { ids } => {
+            let client = session_client()?;
+            for id in ids {
+                let bug = client.get_bug_detail(id).await?;
+                watch::watch_bug(&base_url, &account, &bug)?;
+                eprintln!("已关注 #{} {}", bug.id, bug.title);
+            }
+            Ok(())
+        }
+        WatchAction::Remove { ids } => {
+            for id in ids {
+                if watch::unwatch_bug(&base_url, &account, id)? {
+                    eprintln!("已取消关注 #{}", id);
+                } else {
+                    eprintln!("没有关注 #{}", id);
+                }
+            }
+            Ok(())
+        }
+        WatchAction::List { no_sync } => {
+            if !no_sync {
+                let client = session_client()?;
+                let mut bugs = Vec::new();
+                for id in watch::watched_ids(&base_url, &account)? {
+                    match client.get_bug_detail(id).await {
+                        Ok(bug) => bugs.push(bug),
+                        Err(e) if e == UNAUTHORIZED => return Err(e),
+                        Err(e) => eprintln!("获取Bug{}失败，跳过: {}", id, e),
+                    }
+                }
+                watch::update_watched(&base_url, &account, &bugs)?;
+            }
+            let watched = watch::list_watched(&base_url, &account)?;
+            if json {
+                return print_json(&watched);
+            }
+            for entry in &watched {
+                let unread = entry.unread_count();
+                let badge = if unread > 0 { format!(" （{}条未读）", unread) } else { String::new() };
+                println!("#{} [{}] {}{}", entry.bug_id, entry.status, entry.title, badge);
+                for change in entry.changes.iter().filter(|change| change.unread) {
+                    println!("    {} {}", format_datetime_to_china(&change.at), change.summary);
+                }
+            }
+            eprintln!("共关注 {} 个Bug", watched.len());
+            Ok(())
+        }
+        WatchAction::Read { id } => watch::mark_read(&base_url, &account, id),
+    }
+}
+
+fn note_command(action: NoteAction, json: bool) -> Result<(), String> {
+    let config = load_config_from_file(DEFAULT_PROFILE)?.ok_or("没有找到配置，请先执行 zentao login --save")?;
+    let (base_url, account) = (config.base_url, config.account);
+    match action {
+        NoteAction::Show { id } => {
+            let annotations = annotation::load_annotations(&base_url, &account)?;
+            let Some(found) = annotations.get(id) else {
+                eprintln!("Bug {} 没有本地标注", id);
+                return Ok(());
+            };
+            if json {
+                return print_json(found);
+            }
+            print_annotation(found);
+            Ok(())
+        }
+        NoteAction::Set { id, note, tags, untags, star, unstar } => {
+            let annotations = annotation::load_annotations(&base_url, &account)?;
+            let mut updated = annotations.get(id).cloned().unwrap_or(Annotation { bug_id: id, ..Default::default() });
+            if let Some(note) = note {
+                updated.note = note;
+            }
+            updated.tags.extend(tags);
+            updated.tags.retain(|tag| !untags.iter().any(|untag| untag.eq_ignore_ascii_case(tag)));
+            if star || unstar {
+                updated.starred = star;
+            }
+            match annotation::save_annotation(&base_url, &account, updated)? {
+                Some(saved) if json => print_json(&saved)?,
+                Some(saved) => print_annotation(&saved),
+                None => eprintln!("已删除 Bug {} 的本地标注", id),
+            }
+            Ok(())
+        }
+        NoteAction::List { tag, starred } => {
+            let annotations = annotation::load_annotations(&base_url, &account)?;
+            let matched = annotations
+                .iter()
+                .filter(|found| tag.as_ref().is_none_or(|tag| found.has_tag(tag)))
+                .filter(|found| !starred || found.starred)
+                .collect::<Vec<_>>();
+            if json {
+                return print_json(&matched);
+            }
+            for found in &matched {
+                let first_line = found.note.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+                let star = if found.starred { "★ " } else { "" };
+                println!("{}#{} [{}] {}", star, found.bug_id, found.tags.join(", "), first_line);
+            }
+            eprintln!("共 {} 个本地标注", matched.len());
+            Ok(())
+        }
+        NoteAction::Export { output } => {
+            let content = annotation::export_annotations(&base_url, &account)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+                    eprintln!("已导出本地标注到 {}", path.display());
+                }
+                None => println!("{}", content),
+            }
+            Ok(())
+        }
+        NoteAction::Import { file, replace } => {
+            let content = fs::read_to_string(&file).map_err(|e| format!("读取文件失败: {}", e))?;
+            let summary = annotation::import_annotations(&base_url, &account, &content, replace)?;
+            eprintln!("导入了 {} 个本地标注，跳过 {} 个本地版本相同或更新的标注", summary.imported, summary.skipped);
+            Ok(())
+        }
+    }
+}
+
+fn view_command(action: ViewAction, json: bool) -> Result<(), String> {
+    let config = load_config_from_file(DEFAULT_PROFILE)?.ok_or("没有找到配置，请先执行 zentao login --save")?;
+    let (base_url, account) = (config.base_url, config.account);
+    match action {
+        ViewAction::List => {
+            let views = view::list_views(&base_url, &account)?;
+            if json {
+                return print_json(&views);
+            }
+            for saved in &views {
+                let settings = &saved.settings;
+                let mut parts = Vec::new();
+                if !settings.products.is_empty() {
+                    parts.push(format!("产品: {:?}", settings.products));
+                }
+                if !settings.query.is_empty() {
+                    parts.push(format!("查询: {}", settings.query));
+                }
+                if !settings.group_by.is_empty() {
+                    parts.push(format!("分组: {}", settings.group_by));
+                }
+                println!("{}  {}", saved.name, parts.join("；"));
+            }
+            eprintln!("共 {} 个视图", views.len());
+            Ok(())
+        }
+        ViewAction::Rename { name, new_name } => {
+            let renamed = view::rename_view(&base_url, &account, &name, &new_name)?;
+            eprintln!("已将视图 {} 重命名为 {}", name, renamed.name);
+            Ok(())
+        }
+        ViewAction::Delete { name } => {
+            if view::delete_view(&base_url, &account, &name)? {
+                eprintln!("已删除视图 {}", name);
+            } else {
+                eprintln!("没有找到视图 {}", name);
+            }
+            Ok(())
+        }
+        ViewAction::Export { names, output } => {
+            let content = view::export_views(&base_url, &account, &names)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+                    eprintln!("已导出视图到 {}", path.display());
+                }
+                None => println!("{}", content),
+            }
+            Ok(())
+        }
+        ViewAction::Import { file, overwrite } => {
+            let content = fs::read_to_string(&file).map_err(|e| format!("读取文件失败: {}", e))?;
+            let summary = view::import_views(&base_url, &account, &content, overwrite)?;
+            eprintln!("导入了 {} 个视图，其中 {} 个因重名自动改名: {}", summary.imported.len(), summary.renamed, summary.imported.join(", "));
+            Ok(())
+        }
+    }
+}
+
+fn print_annotation(found: &Annotation) {
+    println!("Bug #{}{}", found.bug_id, if found.starred { " ★" } else { "" });
+    if !found.tags.is_empty() {
+        println!("标签: {}", found.tags.join(", "));
+    }
+    if !found.note.trim().is_empty() {
+        println!("\n{}", found.note.trim_end());
+    }
+}
+
+// 当前配置的账号的本地标注，没有配置时为空
+fn load_profile_annotations() -> Result<Annotations, String> {
+    match load_config_from_file(DEFAULT_PROFILE)? {
+        Some(config) => annotation::load_annotations(&config.base_url, &config.account),
+        None => Ok(Annotations::default()),
+    }
+}
+
+// Bug ID 参数：数字，或指向Bug的链接
+fn bug_id_arg(arg: &str) -> Result<i32, String> {
+    if let Ok(id) = arg.trim().trim_start_matches('#').parse::<i32>() {
+        return Ok(id);
+    }
+    let config = load_config_from_file(DEFAULT_PROFILE)?.ok_or("没有找到配置，请先执行 zentao login --save")?;
+    let target = link::parse_zentao_url(arg, &config.base_url)?;
+    match target.kind {
+        LinkKind::Bug => Ok(target.id),
+        LinkKind::Product => Err(format!("链接指向产品{}，不是Bug", target.id)),
+    }
+}
+
+// 根据保存的配置和token创建客户端
+fn session_client() -> Result<ZentaoClient, String> {
+    let config = load_config_from_file(DEFAULT_PROFILE)?.ok_or("没有找到配置，请先执行 zentao login --save")?;
+    let (token, _, api) = load_token_from_file(DEFAULT_PROFILE)?.ok_or("尚未登录或登录已过期，请先执行 zentao login")?;
+    let http = http::build_http_client(&config.network)?;
+    ZentaoClient::new(http, &config.base_url, token, api)
+}
+
+async fn fetch_bugs(filter: &BugFilter) -> Result<Vec<Bug>, String> {
+    let client = session_client()?;
+    let product_ids = product_ids_or_all(&client, filter.products.clone()).await?;
+    let bugs = client.get_bugs_by_product(&product_ids).await?;
+    let bugs = bugs.into_iter().filter(|bug| filter.matches(bug)).collect::<Vec<_>>();
+
+    let Some(mut query) = filter.query.clone() else {
+        return Ok(bugs);
+    };
+    let me = if query.uses_me() {
+        Some(client.get_user_info().await?.account)
+    } else {
+        None
+    };
+    if query.uses_module_names() {
+        let mut modules = HashMap::new();
+        for product_id in &product_ids {
+            match client.get_module_names(*product_id).await {
+                Ok(names) => modules.extend(names),
+                Err(e) if e == UNAUTHORIZED => return Err(e),
+                Err(e) => eprintln!("警告: 获取产品{}的模块失败，按模块名称过滤的条件将不匹配: {}", product_id, e),
+            }
+        }
+        query.set_module_names(modules);
+    }
+    Ok(query.filter(bugs, me.as_deref(), &load_profile_annotations()?))
+}
+
+// 没有指定产品时使用全部产品
+async fn product_ids_or_all(client: &ZentaoClient, products: Vec<i32>) -> Result<Vec<i32>, String> {
+    if !products.is_empty() {
+        return Ok(products);
+    }
+    Ok(client.get_products().await?.iter().map(|product| product.id).collect())
+}
+
+// 搜索结果中的高亮标记在终端中去掉
+fn strip_marks(html: &str) -> String {
+    decode_entities(&html.replace("<mark>", "").replace("</mark>", ""))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("序列化失败: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+// 以表格输出Bug列表
+fn print_bug_list(bugs: &[Bug]) {
+    let rows = bugs
+        .iter()
+        .map(|bug| {
+            vec![
+                bug.id.to_string(),
+                bug.status.name.clone(),
+                bug.severity.to_string(),
+                bug.pri.to_string(),
+                user_display(&bug.assigned_to),
+                bug.title.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(&["ID", "状态", "严重程度", "优先级", "指派给", "标题"], &rows);
+    eprintln!("共 {} 个Bug", bugs.len());
+}
+
+// 下载重现步骤中的图片，返回图片路径到本地文件路径的映射
+async fn download_images(client: &ZentaoClient, bug: &Bug, dir: &Path) -> Result<HashMap<String, String>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("创建目录失败: {}", e))?;
+    let mut images = HashMap::new();
+    for (i, path) in image_paths(&bug.steps).into_iter().enumerate() {
+        let bytes = client.get_image_bytes(&path).await?;
+        // 附件路径形如 index.php?m=file&f=read&t=png&fileID=1，t 为扩展名
+        let extension = path
+            .split(['?', '&'])
+            .find_map(|param| param.strip_prefix("t="))
+            .filter(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("png");
+        let file = dir.join(format!("bug-{}-{}.{}", bug.id, i + 1, extension));
+        fs::write(&file, bytes).map_err(|e| format!("写入图片失败: {}", e))?;
+        images.insert(path, file.to_string_lossy().into_owned());
+    }
+    if !images.is_empty() {
+        eprintln!("已下载 {} 张图片到 {}", images.len(), dir.display());
+    }
+    Ok(images)
+}
+
+fn print_bug_markdown(bug: &Bug, steps: &str) {
+    println!("# #{} {}", bug.id, bug.title);
+    println!();
+    println!("- 状态: {}", bug.status.name);
+    println!("- 严重程度: {}，优先级: {}，类型: {}", bug.severity, bug.pri, bug.bug_type);
+    println!("- 创建: {} {}", user_display(&bug.opened_by), format_datetime_to_china(&bug.opened_date));
+    println!("- 指派给: {}", user_display(&bug.assigned_to));
+    if !steps.is_empty() {
+        println!();
+        println!("{}", steps);
+    }
+}
+
+fn print_bug(bug: &Bug, steps: &str) {
+    println!("#{} {}", bug.id, bug.title);
+    println!("状态:     {}", bug.status.name);
+    println!("严重程度: {}    优先级: {}    类型: {}", bug.severity, bug.pri, bug.bug_type);
+    println!("产品:     {}    模块: {}", bug.product, bug.module);
+    println!("创建:     {} {}", user_display(&bug.opened_by), format_datetime_to_china(&bug.opened_date));
+    println!("指派给:   {}", user_display(&bug.assigned_to));
+    if let Some(resolved_date) = bug.resolved_date.as_deref().filter(|date| !date.is_empty()) {
+        println!("解决:     {} {}", user_display(&bug.resolved_by), format_datetime_to_china(resolved_date));
+    }
+    if !steps.is_empty() {
+        println!();
+        println!("{}", steps);
+    }
+}
+
+// 终端显示宽度：中文等全角字符占两列
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths = headers.iter().map(|header| display_width(header)).collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        let last = cells.len() - 1;
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                // 最后一列不补空格，避免长标题后面留下多余空白
+                if i == last {
+                    cell.to_string()
+                } else {
+                    format!("{}{}", cell, " ".repeat(widths[i] - display_width(cell)))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    println!("{}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
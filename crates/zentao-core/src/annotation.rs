@@ -0,0 +1,379 @@
+// 本地标注：只保存在本机的Bug笔记（Markdown）、个人标签和星标
+// 按禅道服务器和账号分别保存在 zentao_app/annotations/<服务器>/<账号>.json，
+// 可以导出为JSON文件，重装或换电脑后再导入
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::server;
+
+// 导出文件的格式版本
+const EXPORT_VERSION: u32 = 1;
+
+// 读取-修改-写入标注文件时加锁
+static ANNOTATION_FILE: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(rename = "bugId")]
+    pub bug_id: i32,
+    // Markdown 笔记
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub starred: bool,
+    // 最后修改时间（RFC 3339），导入时保留较新的版本
+    #[serde(rename = "updatedAt")]
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl Annotation {
+    // 没有笔记、标签和星标的标注不保存
+    pub fn is_empty(&self) -> bool {
+        self.note.trim().is_empty() && self.tags.is_empty() && !self.starred
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|existing| existing.eq_ignore_ascii_case(tag))
+    }
+
+    // 参与全文搜索的文本：标签和笔记
+    pub fn search_text(&self) -> String {
+        let mut text = self.tags.join(" ");
+        if !self.note.trim().is_empty() {
+            text.push('\n');
+            text.push_str(&self.note);
+        }
+        text
+    }
+
+    fn updated(&self) -> Option<DateTime<chrono::FixedOffset>> {
+        DateTime::parse_from_rfc3339(&self.updated_at).ok()
+    }
+}
+
+// 去掉首尾空白和空标签，不区分大小写去重，保留第一次出现的写法
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').trim().to_string();
+        if !tag.is_empty() && !normalized.iter().any(|existing| existing.eq_ignore_ascii_case(&tag)) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+// 某个账号的全部标注：Bug ID -> 标注
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    items: BTreeMap<i32, Annotation>,
+}
+
+impl Annotations {
+    pub fn get(&self, bug_id: i32) -> Option<&Annotation> {
+        self.items.get(&bug_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.items.values()
+    }
+
+    // 用过的标签及使用次数，按标签排列
+    pub fn tags(&self) -> BTreeMap<String, usize> {
+        let mut tags = BTreeMap::new();
+        for annotation in self.items.values() {
+            for tag in &annotation.tags {
+                *tags.entry(tag.clone()).or_default() += 1;
+            }
+        }
+        tags
+    }
+
+    // Bug ID -> 参与全文搜索的文本
+    pub fn search_texts(&self) -> BTreeMap<i32, String> {
+        self.items
+            .values()
+            .map(|annotation| (annotation.bug_id, annotation.search_text()))
+            .filter(|(_, text)| !text.trim().is_empty())
+            .collect()
+    }
+
+    // 设置一个Bug的标注，笔记、标签和星标都为空时删除，返回设置后的标注
+    pub(crate) fn set(&mut self, annotation: Annotation) -> Option<Annotation> {
+        let annotation = Annotation {
+            tags: normalize_tags(annotation.tags),
+            ..annotation
+        };
+        if annotation.is_empty() {
+            self.items.remove(&annotation.bug_id);
+            None
+        } else {
+            self.items.insert(annotation.bug_id, annotation.clone());
+            Some(annotation)
+        }
+    }
+
+    // 合并导入的标注。replace 为 true 时先清空；否则两边都有时保留修改时间较新的版本
+    fn merge(&mut self, imported: Vec<Annotation>, replace: bool) -> ImportSummary {
+        if replace {
+            self.items.clear();
+        }
+        let mut summary = ImportSummary::default();
+        for annotation in imported {
+            let annotation = Annotation {
+                tags: normalize_tags(annotation.tags),
+                ..annotation
+            };
+            if annotation.is_empty() {
+                continue;
+            }
+            let newer = self
+                .items
+                .get(&annotation.bug_id)
+                .is_none_or(|existing| annotation.updated() > existing.updated());
+            if newer {
+                self.items.insert(annotation.bug_id, annotation);
+                summary.imported += 1;
+            } else {
+                summary.skipped += 1;
+            }
+        }
+        summary
+    }
+}
+
+// 导出文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationExport {
+    pub version: u32,
+    // 导出时的服务器和账号，仅供参考，导入时不要求一致
+    #[serde(default)]
+    pub server: String,
+    #[serde(default)]
+    pub account: String,
+    #[serde(rename = "exportedAt")]
+    #[serde(default)]
+    pub exported_at: String,
+    pub annotations: Vec<Annotation>,
+}
+
+// 导入结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    // 新增或覆盖的标注数
+    pub imported: usize,
+    // 本地版本相同或更新而跳过的标注数
+    pub skipped: usize,
+}
+
+fn annotation_file_path(base_url: &str, account: &str) -> Result<PathBuf, String> {
+    server::profile_file_path("annotations", base_url, account)
+}
+
+fn load(base_url: &str, account: &str) -> Result<Annotations, String> {
+    let path = annotation_file_path(base_url, account)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Annotations::default()),
+        Err(e) => return Err(format!("读取本地标注失败: {}", e)),
+    };
+    // 笔记是用户手写的内容，文件损坏时不覆盖，直接报错
+    let items = serde_json::from_str::<Vec<Annotation>>(&content)
+        .map_err(|e| format!("解析本地标注文件{:?}失败: {}", path, e))?;
+    Ok(Annotations {
+        items: items.into_iter().map(|annotation| (annotation.bug_id, annotation)).collect(),
+    })
+}
+
+fn save(base_url: &str, account: &str, annotations: &Annotations) -> Result<(), String> {
+    let path = annotation_file_path(base_url, account)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建本地标注目录失败: {}", e))?;
+    }
+    let items = annotations.items.values().collect::<Vec<_>>();
+    let json = serde_json::to_string_pretty(&items).map_err(|e| format!("序列化本地标注失败: {}", e))?;
+    // 先写临时文件再替换，避免写到一半时损坏笔记
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json).map_err(|e| format!("写入本地标注失败: {}", e))?;
+    fs::rename(&temp, &path).map_err(|e| format!("写入本地标注失败: {}", e))
+}
+
+// 读取账号的全部标注
+pub fn load_annotations(base_url: &str, account: &str) -> Result<Annotations, String> {
+    let _guard = ANNOTATION_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    load(base_url, account)
+}
+
+// 保存一个Bug的标注，笔记、标签和星标都为空时删除，返回保存后的标注
+pub fn save_annotation(base_url: &str, account: &str, annotation: Annotation) -> Result<Option<Annotation>, String> {
+    let _guard = ANNOTATION_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut annotations = load(base_url, account)?;
+    let bug_id = annotation.bug_id;
+    let saved = annotations.set(Annotation {
+        updated_at: Local::now().to_rfc3339(),
+        ..annotation
+    });
+    save(base_url, account, &annotations)?;
+    debug!("已保存Bug{}的本地标注", bug_id);
+    Ok(saved)
+}
+
+// 导出全部标注为JSON
+pub fn export_annotations(base_url: &str, account: &str) -> Result<String, String> {
+    let annotations = load_annotations(base_url, account)?;
+    let export = AnnotationExport {
+        version: EXPORT_VERSION,
+        server: base_url.to_string(),
+        account: account.to_string(),
+        exported_at: Local::now().to_rfc3339(),
+        annotations: annotations.items.into_values().collect(),
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| format!("导出本地标注失败: {}", e))
+}
+
+// 导入导出的JSON。replace 为 true 时先清空本地标注；否则按Bug合并，两边都有时保留修改时间较新的版本
+pub fn import_annotations(base_url: &str, account: &str, json: &str, replace: bool) -> Result<ImportSummary, String> {
+    let export = parse_export(json)?;
+    if !export.account.is_empty() && export.account != account {
+        warn!("导入账号{}导出的本地标注到账号{}", export.account, account);
+    }
+
+    let _guard = ANNOTATION_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    // 覆盖导入时不读取本地文件，文件损坏时也可以用导入恢复
+    let mut annotations = if replace { Annotations::default() } else { load(base_url, account)? };
+    let summary = annotations.merge(export.annotations, replace);
+    save(base_url, account, &annotations)?;
+    debug!("导入了{}个本地标注，跳过{}个", summary.imported, summary.skipped);
+    Ok(summary)
+}
+
+fn parse_export(json: &str) -> Result<AnnotationExport, String> {
+    let export = serde_json::from_str::<AnnotationExport>(json).map_err(|e| format!("标注文件格式不正确: {}", e))?;
+    if export.version > EXPORT_VERSION {
+        return Err(format!("标注文件的版本{}过新，请升级应用后再导入", export.version));
+    }
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(bug_id: i32, note: &str, tags: &[&str], starred: bool, updated_at: &str) -> Annotation {
+        Annotation {
+            bug_id,
+            note: note.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            starred,
+            updated_at: updated_at.to_string(),
+        }
+    }
+
+    fn notes(annotations: &Annotations) -> Vec<(i32, &str)> {
+        annotations.iter().map(|annotation| (annotation.bug_id, annotation.note.as_str())).collect()
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        let tags = ["#Urgent", " urgent ", "##UI", "", "  #  ", "ui", "后端", "#后端"];
+        assert_eq!(normalize_tags(tags.iter().map(|tag| tag.to_string()).collect()), vec!["Urgent", "UI", "后端"]);
+    }
+
+    #[test]
+    fn empty_annotation_deletes_record() {
+        let mut annotations = Annotations::default();
+        let saved = annotations.set(annotation(1, "笔记", &["#a", "A"], false, "")).unwrap();
+        assert_eq!(saved.tags, vec!["a"]);
+        annotations.set(annotation(2, "", &[], true, ""));
+        assert_eq!(annotations.len(), 2);
+
+        // 只有空白的笔记和空标签也算空
+        assert!(annotations.set(annotation(1, "  \n", &["#", " "], false, "")).is_none());
+        assert!(annotations.get(1).is_none());
+        assert!(annotations.set(annotation(3, "", &[], false, "")).is_none());
+        assert_eq!(annotations.len(), 1);
+    }
+
+    #[test]
+    fn search_text_joins_tags_and_note() {
+        assert_eq!(annotation(1, "", &["a", "b"], false, "").search_text(), "a b");
+        assert_eq!(annotation(1, "复现步骤", &["a"], false, "").search_text(), "a\n复现步骤");
+        assert_eq!(annotation(1, "  ", &[], true, "").search_text(), "");
+
+        let mut annotations = Annotations::default();
+        annotations.set(annotation(1, "笔记", &[], false, ""));
+        annotations.set(annotation(2, "", &[], true, ""));
+        annotations.set(annotation(3, "", &["UI"], false, ""));
+        assert_eq!(annotations.search_texts(), BTreeMap::from([(1, "\n笔记".to_string()), (3, "UI".to_string())]));
+        assert_eq!(annotations.tags(), BTreeMap::from([("UI".to_string(), 1)]));
+    }
+
+    #[test]
+    fn import_keeps_newer_versions() {
+        let mut annotations = Annotations::default();
+        annotations.set(annotation(1, "本地旧", &[], false, "2025-01-01T00:00:00+08:00"));
+        annotations.set(annotation(2, "本地新", &[], false, "2025-03-01T00:00:00+08:00"));
+        annotations.set(annotation(3, "本地同时", &[], false, "2025-02-01T00:00:00+08:00"));
+        annotations.set(annotation(4, "本地无时间", &[], false, ""));
+        let summary = annotations.merge(
+            vec![
+                annotation(1, "导入新", &[], false, "2025-02-01T00:00:00+08:00"),
+                annotation(2, "导入旧", &[], false, "2025-02-01T00:00:00+08:00"),
+                // 同一时刻的不同时区写法
+                annotation(3, "导入同时", &[], false, "2025-01-31T16:00:00Z"),
+                annotation(4, "导入有时间", &[], false, "2025-01-01T00:00:00+08:00"),
+                annotation(5, "导入无时间", &["#x"], false, ""),
+                // 空标注不导入
+                annotation(6, "", &[], false, "2025-02-01T00:00:00+08:00"),
+            ],
+            false,
+        );
+        assert_eq!(summary.imported, 3);
+        assert_eq!(summary.skipped, 2);
+        assert_eq!(
+            notes(&annotations),
+            vec![(1, "导入新"), (2, "本地新"), (3, "本地同时"), (4, "导入有时间"), (5, "导入无时间")]
+        );
+        assert_eq!(annotations.get(5).unwrap().tags, vec!["x"]);
+    }
+
+    #[test]
+    fn import_replaces_local_annotations() {
+        let mut annotations = Annotations::default();
+        annotations.set(annotation(1, "本地", &[], false, "2025-03-01T00:00:00+08:00"));
+        annotations.set(annotation(2, "本地", &[], false, ""));
+        let summary = annotations.merge(vec![annotation(1, "导入", &[], false, "2025-01-01T00:00:00+08:00")], true);
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.skipped, 0);
+        assert_eq!(notes(&annotations), vec![(1, "导入")]);
+    }
+
+    #[test]
+    fn parses_exports() {
+        let json = r#"{"version": 1, "annotations": [{"bugId": 7, "tags": ["a"]}]}"#;
+        let export = parse_export(json).unwrap();
+        assert_eq!(export.annotations[0].bug_id, 7);
+        assert!(!export.annotations[0].starred);
+
+        let json = r#"{"version": 2, "annotations": []}"#;
+        assert_eq!(parse_export(json).unwrap_err(), "标注文件的版本2过新，请升级应用后再导入");
+        assert!(parse_export(r#"{"annotations": []}"#).is_err());
+        assert!(parse_export("[]").is_err());
+    }
+}
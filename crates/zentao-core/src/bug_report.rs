@@ -0,0 +1,440 @@
+// Bug报告：把Bug的字段、重现步骤（按白名单净化，图片附件内嵌为 data URL）和历史记录生成一个独立的HTML文件，
+// 方便发给没有禅道账号的外部人员；PDF 由本机安装的 Chrome / Edge / Chromium 以无界面模式打印生成
+use chrono::Local;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+use crate::client::{ZentaoClient, UNAUTHORIZED};
+use crate::export::user_display;
+use crate::html::{escape_html, image_paths, replace_external_images, replace_images, sanitize_html};
+use crate::models::{format_datetime_to_china, parse_bug_datetime, Bug, BugAction};
+
+// 指定生成PDF使用的浏览器（可执行文件路径）
+pub const PDF_BROWSER_ENV: &str = "ZENTAO_PDF_BROWSER";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Html,
+    Pdf,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "html" | "htm" => Ok(ReportFormat::Html),
+            "pdf" => Ok(ReportFormat::Pdf),
+            other => Err(format!("不支持的报告格式: {}（可选 html、pdf）", other)),
+        }
+    }
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+// 报告的默认文件名
+pub fn report_file_name(bug_id: i32, format: ReportFormat) -> String {
+    format!("bug-{}.{}", bug_id, format.extension())
+}
+
+// 下载富文本中的图片，返回图片路径到 data URL 的映射；单张图片失败时跳过，登录失效时返回错误
+pub async fn fetch_images(client: &ZentaoClient, html: &str) -> Result<HashMap<String, String>, String> {
+    let mut images = HashMap::new();
+    for path in image_paths(html) {
+        match client.get_image(&path).await {
+            Ok(data_url) => {
+                images.insert(path, data_url);
+            }
+            Err(e) if e == UNAUTHORIZED => return Err(e),
+            Err(e) => warn!("下载图片{}失败，报告中将不包含该图片: {}", path, e),
+        }
+    }
+    Ok(images)
+}
+
+// 获取Bug详情和图片并生成HTML报告
+pub async fn build_bug_report(client: &ZentaoClient, bug_id: i32) -> Result<String, String> {
+    let bug = client.get_bug_detail(bug_id).await?;
+    // 重现步骤和备注中的图片附件都内嵌到报告中
+    let rich_text = std::iter::once(bug.steps.as_str())
+        .chain(bug.actions.iter().map(|action| action.comment.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let images = fetch_images(client, &rich_text).await?;
+    info!("生成Bug{}的报告，内嵌{}张图片", bug_id, images.len());
+    Ok(render_bug_report(&bug, &images))
+}
+
+// 历史记录按时间排列；接口没有返回历史记录时，根据创建、指派、解决和关闭时间生成
+pub fn bug_timeline(bug: &Bug) -> Vec<BugAction> {
+    let mut timeline = if bug.actions.is_empty() {
+        let mut timeline = vec![BugAction {
+            actor: user_display(&bug.opened_by),
+            action: "opened".to_string(),
+            date: bug.opened_date.clone(),
+            ..BugAction::default()
+        }];
+        let events = [
+            ("assigned", &bug.assigned_date, &bug.assigned_to),
+            ("resolved", &bug.resolved_date, &bug.resolved_by),
+            ("closed", &bug.closed_date, &bug.closed_by),
+        ];
+        for (action, date, user) in events {
+            if let Some(date) = date.as_deref().filter(|date| !date.is_empty()) {
+                timeline.push(BugAction {
+                    actor: user_display(user),
+                    action: action.to_string(),
+                    date: date.to_string(),
+                    ..BugAction::default()
+                });
+            }
+        }
+        timeline
+    } else {
+        bug.actions.clone()
+    };
+    // 同一来源的日期格式一致，按字符串排序即可；排序稳定，同一时间的记录保持原顺序
+    timeline.sort_by(|a, b| a.date.cmp(&b.date));
+    timeline
+}
+
+fn optional_datetime(value: &Option<String>) -> String {
+    value
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .map(format_datetime_to_china)
+        .unwrap_or_default()
+}
+
+// 用户和时间合并为一格，如“张三 2024-01-01 10:00:00”
+fn user_and_date(user: String, date: String) -> String {
+    [user, date].into_iter().filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+// 重现步骤和备注：按白名单净化，图片附件换成内嵌的 data URL；外部图片不下载，标记为未包含，避免打开报告时访问外部地址
+fn render_rich_text(html: &str, images: &HashMap<String, String>) -> String {
+    let html = sanitize_html(html);
+    let html = replace_images(&html, |path| match images.get(path) {
+        Some(data_url) => format!(r#"<img src="{}" alt="" />"#, data_url),
+        None => r#"<span class="missing">[图片无法加载]</span>"#.to_string(),
+    });
+    replace_external_images(&html, |src| {
+        format!(r#"<span class="missing">[外部图片未包含: {}]</span>"#, escape_html(src))
+    })
+}
+
+// 历史记录的时间可能是 RFC 3339，也可能是禅道的 Y-m-d H:i:s，后者原样显示
+fn display_date(date: &str) -> String {
+    match parse_bug_datetime(date) {
+        Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => date.to_string(),
+    }
+}
+
+fn render_timeline(timeline: &[BugAction], images: &HashMap<String, String>) -> String {
+    let mut html = String::from("<ol class=\"timeline\">\n");
+    for action in timeline {
+        let _ = write!(
+            html,
+            "<li><div class=\"action\"><span class=\"date\">{}</span> {} <strong>{}</strong></div>",
+            escape_html(&display_date(&action.date)),
+            escape_html(&action.actor),
+            escape_html(action.action_name()),
+        );
+        if !action.history.is_empty() {
+            html.push_str("<ul class=\"changes\">");
+            for change in &action.history {
+                let _ = write!(
+                    html,
+                    "<li>{}: {} → {}</li>",
+                    escape_html(&change.field),
+                    escape_html(&change.old),
+                    escape_html(&change.new),
+                );
+            }
+            html.push_str("</ul>");
+        }
+        // 备注本身是禅道的富文本，和重现步骤一样处理
+        if !action.comment.trim().is_empty() {
+            let _ = write!(html, "<div class=\"comment\">{}</div>", render_rich_text(&action.comment, images));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ol>\n");
+    html
+}
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", "PingFang SC", "Microsoft YaHei", sans-serif; color: #303133; max-width: 960px; margin: 24px auto; padding: 0 16px; line-height: 1.6; }
+h1 { font-size: 22px; margin-bottom: 4px; }
+h2 { font-size: 17px; border-bottom: 1px solid #dcdfe6; padding-bottom: 4px; margin-top: 28px; }
+.meta { color: #909399; font-size: 13px; }
+table.fields { border-collapse: collapse; width: 100%; }
+table.fields th, table.fields td { border: 1px solid #ebeef5; padding: 6px 10px; text-align: left; vertical-align: top; }
+table.fields th { background: #f5f7fa; width: 120px; font-weight: normal; color: #606266; }
+.steps img { max-width: 100%; height: auto; }
+.steps table { border-collapse: collapse; }
+.steps td, .steps th { border: 1px solid #dcdfe6; padding: 4px 8px; }
+.missing { color: #f56c6c; }
+.timeline { padding-left: 20px; }
+.timeline > li { margin-bottom: 10px; }
+.timeline .date { color: #909399; }
+.changes { color: #606266; font-size: 13px; }
+.comment { background: #f5f7fa; padding: 6px 10px; margin-top: 4px; }
+@media print { body { margin: 0; max-width: none; } h2 { page-break-after: avoid; } .steps img { page-break-inside: avoid; } }
+"#;
+
+// 生成独立的HTML报告，images 为图片路径到 data URL 的映射
+pub fn render_bug_report(bug: &Bug, images: &HashMap<String, String>) -> String {
+    let fields = [
+        ("状态", bug.status.name.clone()),
+        ("严重程度", bug.severity.to_string()),
+        ("优先级", bug.pri.to_string()),
+        ("类型", bug.bug_type.clone()),
+        ("产品", bug.product.to_string()),
+        ("模块", bug.module.to_string()),
+        ("关键词", bug.keywords.clone()),
+        ("操作系统", bug.os.clone()),
+        ("浏览器", bug.browser.clone()),
+        ("创建", user_and_date(user_display(&bug.opened_by), format_datetime_to_china(&bug.opened_date))),
+        ("指派给", user_and_date(user_display(&bug.assigned_to), optional_datetime(&bug.assigned_date))),
+        ("截止日期", bug.deadline.clone().unwrap_or_default()),
+        ("解决", user_and_date(user_display(&bug.resolved_by), optional_datetime(&bug.resolved_date))),
+        ("关闭", user_and_date(user_display(&bug.closed_by), optional_datetime(&bug.closed_date))),
+        ("激活次数", bug.activated_count.to_string()),
+    ];
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\" />\n");
+    let _ = writeln!(html, "<title>Bug #{} {}</title>", bug.id, escape_html(&bug.title));
+    let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+    let _ = writeln!(html, "<h1>Bug #{} {}</h1>", bug.id, escape_html(&bug.title));
+    let _ = writeln!(html, "<div class=\"meta\">导出时间：{}</div>", Local::now().format("%Y-%m-%d %H:%M:%S"));
+
+    html.push_str("<h2>基本信息</h2>\n<table class=\"fields\">\n");
+    for (label, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, escape_html(value));
+    }
+    html.push_str("</table>\n");
+
+    let _ = writeln!(html, "<h2>重现步骤</h2>\n<div class=\"steps\">{}</div>", render_rich_text(&bug.steps, images));
+    let _ = write!(html, "<h2>历史记录</h2>\n{}", render_timeline(&bug_timeline(bug), images));
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+// 查找可以无界面打印PDF的浏览器：先看环境变量，再找常见的安装位置和 PATH
+fn find_browser() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(PDF_BROWSER_ENV).filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+
+    let mut candidates = Vec::new();
+    if cfg!(target_os = "windows") {
+        for var in ["ProgramFiles(x86)", "ProgramFiles", "LOCALAPPDATA"] {
+            if let Some(dir) = env::var_os(var) {
+                let dir = PathBuf::from(dir);
+                candidates.push(dir.join(r"Microsoft\Edge\Application\msedge.exe"));
+                candidates.push(dir.join(r"Google\Chrome\Application\chrome.exe"));
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        for app in ["Google Chrome", "Microsoft Edge", "Chromium"] {
+            candidates.push(PathBuf::from(format!("/Applications/{0}.app/Contents/MacOS/{0}", app)));
+        }
+    }
+    let names = ["google-chrome", "google-chrome-stable", "chromium", "chromium-browser", "microsoft-edge", "msedge", "chrome"];
+    if let Some(paths) = env::var_os("PATH") {
+        for dir in env::split_paths(&paths) {
+            for name in names {
+                candidates.push(dir.join(name));
+                if cfg!(target_os = "windows") {
+                    candidates.push(dir.join(format!("{}.exe", name)));
+                }
+            }
+        }
+    }
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+// 用无界面浏览器把HTML打印为PDF
+pub fn html_to_pdf(html: &str, output: &Path) -> Result<(), String> {
+    let browser = find_browser().ok_or_else(|| {
+        format!("没有找到 Chrome、Edge 或 Chromium，无法生成PDF；可以导出HTML，或通过 {} 指定浏览器", PDF_BROWSER_ENV)
+    })?;
+    debug!("使用{:?}生成PDF", browser);
+
+    // 使用单独的临时目录存放HTML和浏览器配置，不影响正在运行的浏览器
+    let work_dir = env::temp_dir().join(format!("zentao-report-{}-{}", std::process::id(), Local::now().timestamp_millis()));
+    fs::create_dir_all(&work_dir).map_err(|e| format!("创建临时目录失败: {}", e))?;
+    let result = print_to_pdf(&browser, &work_dir, html, output);
+    if let Err(e) = fs::remove_dir_all(&work_dir) {
+        warn!("删除临时目录{:?}失败: {}", work_dir, e);
+    }
+    result
+}
+
+fn print_to_pdf(browser: &Path, work_dir: &Path, html: &str, output: &Path) -> Result<(), String> {
+    let page = work_dir.join("report.html");
+    fs::write(&page, html).map_err(|e| format!("写入临时文件失败: {}", e))?;
+    let url = Url::from_file_path(&page).map_err(|_| format!("临时文件路径无效: {:?}", page))?;
+    // 浏览器在另一个工作目录中运行，输出路径需要是绝对路径
+    let output = std::path::absolute(output).map_err(|e| format!("输出路径无效: {}", e))?;
+    // 先删除已有的文件，否则浏览器失败时会把旧文件当作生成结果
+    match fs::remove_file(&output) {
+        Ok(()) => debug!("已删除旧的报告文件{:?}", output),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("删除已有的文件{:?}失败: {}", output, e)),
+    }
+
+    let result = Command::new(browser)
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--no-first-run")
+        .arg("--no-pdf-header-footer")
+        .arg(format!("--user-data-dir={}", work_dir.join("profile").display()))
+        .arg(format!("--print-to-pdf={}", output.display()))
+        .arg(url.as_str())
+        .output()
+        .map_err(|e| format!("启动浏览器{:?}失败: {}", browser, e))?;
+    if !result.status.success() || !output.is_file() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("浏览器生成PDF失败（{}）: {}", result.status, stderr.trim()));
+    }
+    Ok(())
+}
+
+// 按格式把HTML报告写入文件
+pub fn write_bug_report(html: &str, format: ReportFormat, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    match format {
+        ReportFormat::Html => fs::write(path, html).map_err(|e| format!("写入文件失败: {}", e))?,
+        ReportFormat::Pdf => html_to_pdf(html, path)?,
+    }
+    info!("已导出Bug报告到{:?}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn user(account: &str, realname: &str) -> Value {
+        json!({ "id": 1, "account": account, "avatar": "", "realname": realname })
+    }
+
+    fn bug(title: &str, steps: &str, extra: Value) -> Bug {
+        let mut value = json!({
+            "id": 12, "product": 1, "branch": 0, "module": 0, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": title, "keywords": "", "severity": 3, "pri": 3,
+            "type": "codeerror", "steps": steps, "openedBy": user("zhangsan", "张三"),
+            "openedDate": "2025-01-01 09:00:00", "assignedTo": null, "status": "active"
+        });
+        for (key, field) in extra.as_object().cloned().unwrap_or_default() {
+            value[key] = field;
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn action(action: &str, date: &str) -> BugAction {
+        BugAction { actor: "张三".to_string(), action: action.to_string(), date: date.to_string(), ..BugAction::default() }
+    }
+
+    fn actions(timeline: &[BugAction]) -> Vec<(&str, &str)> {
+        timeline.iter().map(|action| (action.action.as_str(), action.date.as_str())).collect()
+    }
+
+    #[test]
+    fn timeline_is_sorted_by_date() {
+        let mut bug = bug("标题", "", json!({}));
+        bug.actions = vec![
+            action("resolved", "2025-01-03 10:00:00"),
+            action("opened", "2025-01-01 09:00:00"),
+            action("commented", "2025-01-03 10:00:00"),
+            action("assigned", "2025-01-02 08:00:00"),
+        ];
+        // 同一时间的记录保持原顺序
+        assert_eq!(
+            actions(&bug_timeline(&bug)),
+            vec![
+                ("opened", "2025-01-01 09:00:00"),
+                ("assigned", "2025-01-02 08:00:00"),
+                ("resolved", "2025-01-03 10:00:00"),
+                ("commented", "2025-01-03 10:00:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn timeline_is_synthesized_without_actions() {
+        let bug = bug("标题", "", json!({
+            "assignedTo": user("lisi", "李四"), "assignedDate": "2025-01-02 08:00:00",
+            "resolvedBy": user("wangwu", ""), "resolvedDate": "2025-01-04 10:00:00",
+            "closedDate": ""
+        }));
+        let timeline = bug_timeline(&bug);
+        assert_eq!(
+            actions(&timeline),
+            vec![("opened", "2025-01-01 09:00:00"), ("assigned", "2025-01-02 08:00:00"), ("resolved", "2025-01-04 10:00:00")]
+        );
+        let actors = timeline.iter().map(|action| action.actor.as_str()).collect::<Vec<_>>();
+        assert_eq!(actors, vec!["张三", "李四", "wangwu"]);
+    }
+
+    #[test]
+    fn report_escapes_fields_and_sanitizes_rich_text() {
+        let mut bug = bug(
+            "<script>alert('标题')</script>",
+            r#"<p onclick="steal()">步骤<a href="javascript:alert(1)">链接</a></p><iframe src="http://evil"></iframe>"#,
+            json!({ "keywords": "a&b" }),
+        );
+        bug.actions = vec![BugAction {
+            comment: r#"<img src=x onerror=alert(1)>备注"#.to_string(),
+            ..action("commented", "2025-01-02 08:00:00")
+        }];
+        let html = render_bug_report(&bug, &HashMap::new());
+        assert!(html.contains("<h1>Bug #12 &lt;script&gt;alert(&#39;标题&#39;)&lt;/script&gt;</h1>"));
+        assert!(html.contains("<td>a&amp;b</td>"));
+        assert!(html.contains(r#"<div class="steps"><p>步骤<a>链接</a></p></div>"#));
+        assert!(html.contains(r#"<div class="comment"><span class="missing">[外部图片未包含: x]</span>备注</div>"#));
+        assert!(!html.contains("onclick") && !html.contains("onerror") && !html.contains("javascript:") && !html.contains("<iframe"));
+    }
+
+    #[test]
+    fn rich_text_inlines_attachments_and_marks_other_images() {
+        let steps = r#"<img src="{1.png}" alt="index.php?m=file&amp;f=read&amp;fileID=1" /><img src="{2.png}" alt="index.php?m=file&amp;f=read&amp;fileID=2" /><img src="http://cdn.example.com/a.png" />"#;
+        let images = HashMap::from([("index.php?m=file&f=read&fileID=1".to_string(), "data:image/png;base64,AAAA".to_string())]);
+        assert_eq!(
+            render_rich_text(steps, &images),
+            r#"<img src="data:image/png;base64,AAAA" alt="" /><span class="missing">[图片无法加载]</span><span class="missing">[外部图片未包含: http://cdn.example.com/a.png]</span>"#
+        );
+    }
+
+    #[test]
+    fn report_format_parses_extensions() {
+        assert_eq!("HTM".parse::<ReportFormat>(), Ok(ReportFormat::Html));
+        assert_eq!("pdf".parse::<ReportFormat>(), Ok(ReportFormat::Pdf));
+        assert!("docx".parse::<ReportFormat>().is_err());
+        assert_eq!(report_file_name(12, ReportFormat::Pdf), "bug-12.pdf");
+    }
+}
@@ -0,0 +1,714 @@
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tracing::{info, warn, error, debug};
+
+use crate::http::HttpClient;
+use crate::legacy::{self, LegacySession};
+use crate::models::{decode_unicode_escapes, Bug, BugListResponse, Product, UserInfo};
+use crate::redact::{body_preview, MAX_LOGGED_BODY};
+use crate::server::{self, ApiKind, ApiMode, ServerUrls};
+
+// token失效（RESTful API 返回401或旧版API会话过期）时返回的错误
+pub const UNAUTHORIZED: &str = "Unauthorized";
+
+// "我的Bug"的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MyBugsMode {
+    // 指派给我
+    #[default]
+    AssignedTo,
+    // 由我创建
+    OpenedBy,
+    // 由我解决
+    ResolvedBy,
+}
+
+impl FromStr for MyBugsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "assigned" | "assignedto" => Ok(MyBugsMode::AssignedTo),
+            "opened" | "openedby" => Ok(MyBugsMode::OpenedBy),
+            "resolved" | "resolvedby" => Ok(MyBugsMode::ResolvedBy),
+            other => Err(format!("不支持的范围: {}（可选 assigned、opened、resolved）", other)),
+        }
+    }
+}
+
+impl MyBugsMode {
+    // 旧版API "我的地盘" Bug列表的 type 参数
+    pub fn legacy_type(&self) -> &'static str {
+        match self {
+            MyBugsMode::AssignedTo => "assignedTo",
+            MyBugsMode::OpenedBy => "openedBy",
+            MyBugsMode::ResolvedBy => "resolvedBy",
+        }
+    }
+
+    // 判断Bug是否属于指定账号的这个范围
+    pub fn matches(&self, bug: &Bug, account: &str) -> bool {
+        let user = match self {
+            MyBugsMode::AssignedTo => &bug.assigned_to,
+            MyBugsMode::OpenedBy => &bug.opened_by,
+            MyBugsMode::ResolvedBy => &bug.resolved_by,
+        };
+        user.as_ref().is_some_and(|user| user.account == account)
+    }
+}
+
+// 禅道API客户端：根据登录时检测到的API类型访问 RESTful API 或旧版 JSON API
+// 桌面应用和命令行共用
+#[derive(Clone)]
+pub struct ZentaoClient {
+    http: HttpClient,
+    urls: ServerUrls,
+    token: String,
+    api: ApiKind,
+}
+
+// 登录：检测服务器支持的API，旧版禅道使用会话ID代替token
+pub async fn login(
+    http: &HttpClient,
+    base_url: &str,
+    account: String,
+    password: String,
+    api_mode: ApiMode,
+) -> Result<(String, ApiKind), String> {
+    let urls = server::normalize_base_url(base_url)?;
+    match server::detect_api_mode(http, &urls, api_mode).await? {
+        ApiMode::Legacy => {
+            let (session_name, session_id) = legacy::login(http, &urls, &account, &password).await?;
+            Ok((session_id, ApiKind::Legacy { session_name }))
+        }
+        _ => {
+            let token = server::request_token(http, &urls, account, password).await?;
+            Ok((token, ApiKind::Rest))
+        }
+    }
+}
+
+// 将图片数据编码为Base64的data URL
+fn image_data_url(image_path: &str, bytes: &[u8]) -> String {
+    let base64_data = general_purpose::STANDARD.encode(bytes);
+    
+    // 根据文件扩展名确定MIME类型
+    let lower_path = image_path.to_lowercase();
+    let mime_type = if lower_path.ends_with(".png") {
+        "image/png"
+    } else if lower_path.ends_with(".jpg") || lower_path.ends_with(".jpeg") {
+        "image/jpeg"
+    } else if lower_path.ends_with(".gif") {
+        "image/gif"
+    } else if lower_path.ends_with(".webp") {
+        "image/webp"
+    } else {
+        "image/png" // 默认为PNG
+    };
+    
+    format!("data:{};base64,{}", mime_type, base64_data)
+}
+
+impl ZentaoClient {
+    pub fn new(http: HttpClient, base_url: &str, token: String, api: ApiKind) -> Result<Self, String> {
+        let urls = server::normalize_base_url(base_url)?;
+        Ok(ZentaoClient { http, urls, token, api })
+    }
+
+    pub fn urls(&self) -> &ServerUrls {
+        &self.urls
+    }
+
+    // 旧版禅道使用基于会话的 JSON API
+    fn legacy_session(&self) -> Option<LegacySession<'_>> {
+        match &self.api {
+            ApiKind::Legacy { session_name } => Some(LegacySession {
+                name: session_name,
+                id: &self.token,
+            }),
+            ApiKind::Rest => None,
+        }
+    }
+
+    // 获取用户信息
+    pub async fn get_user_info(&self) -> Result<UserInfo, String> {
+        if let Some(session) = self.legacy_session() {
+            return legacy::get_user_info(&self.http, &self.urls, &session).await;
+        }
+
+        let user_url = format!("{}/user", self.urls.api);
+    
+        debug!("获取用户信息: {}", user_url);
+    
+        let request = self
+            .http
+            .get(&user_url)
+            .header("Token", &self.token)
+            .header("Content-Type", "application/json");
+
+        match self.http.send(request).await {
+            Ok(response) => {
+                let status = response.status();
+                debug!("用户信息响应状态: {}", status);
+            
+                if status.is_success() {
+                    match response.text().await {
+                        Ok(text) => {
+                            debug!("用户信息响应: 成功获取 (长度: {})", text.len());
+                        
+                            // 解码 Unicode 转义序列
+                            let decoded_text = decode_unicode_escapes(&text);
+                        
+                            match serde_json::from_str::<Value>(&decoded_text) {
+                                Ok(json) => {
+                                    // 根据实际API响应结构解析用户信息
+                                    // 禅道API返回的是 { "profile": {...} } 格式
+                                    if let Some(profile) = json.get("profile") {
+                                        // 尝试解析为UserInfo结构
+                                        match serde_json::from_value::<UserInfo>(profile.clone()) {
+                                            Ok(user_info) => Ok(user_info),
+                                            Err(e) => {
+                                                let preview = body_preview(&text, 100);
+                                                Err(format!("解析UserInfo失败: {} - 响应预览: {}", e, preview))
+                                            }
+                                        }
+                                    } else {
+                                        let preview = body_preview(&text, 100);
+                                        Err(format!("响应中没有找到profile字段 - 响应预览: {}", preview))
+                                    }
+                                }
+                                Err(e) => Err(format!("解析用户信息JSON失败: {} - 响应内容: {}", e, body_preview(&text, MAX_LOGGED_BODY))),
+                            }
+                        }
+                        Err(e) => Err(format!("读取用户信息响应失败: {}", e)),
+                    }
+                } else {
+                    // 如果是401错误，说明token过期
+                    if status == 401 {
+                        return Err(UNAUTHORIZED.to_string());
+                    }
+                
+                    match response.text().await {
+                        Ok(error_text) => {
+                            // 解码错误信息中的 Unicode 转义序列
+                            let decoded_error = decode_unicode_escapes(&error_text);
+                            Err(format!("获取用户信息失败，状态码: {} - 错误信息: {}", status, body_preview(&decoded_error, MAX_LOGGED_BODY)))
+                        },
+                        Err(_) => Err(format!("获取用户信息失败，状态码: {}", status)),
+                    }
+                }
+            }
+            Err(e) => Err(format!("请求失败: {}", e)),
+        }
+
+    }
+
+    // 获取当前登录用户的Bug，通过 get_user_info 确定"我"
+    pub async fn get_my_bugs(&self, mode: MyBugsMode) -> Result<Vec<Bug>, String> {
+        let me = self.get_user_info().await?.account;
+        self.get_my_bugs_for(&me, mode).await
+    }
+
+    // 获取指定账号的Bug：旧版API有"我的地盘"接口时直接使用，否则读取全部产品的Bug后过滤
+    pub async fn get_my_bugs_for(&self, account: &str, mode: MyBugsMode) -> Result<Vec<Bug>, String> {
+        if let Some(session) = self.legacy_session() {
+            match legacy::get_my_bugs(&self.http, &self.urls, &session, mode.legacy_type()).await {
+                Ok(mut bugs) => {
+                    for bug in &mut bugs {
+                        bug.base_url = Some(self.urls.api.clone());
+                    }
+                    return Ok(bugs);
+                }
+                Err(e) if e == UNAUTHORIZED => return Err(e),
+                Err(e) => warn!("旧版API获取我的Bug失败，改为按产品读取后过滤: {}", e),
+            }
+        }
+
+        let product_ids = self
+            .get_products()
+            .await?
+            .iter()
+            .map(|product| product.id)
+            .collect::<Vec<_>>();
+        let bugs = self.get_bugs_by_product(&product_ids).await?;
+        Ok(bugs
+            .into_iter()
+            .filter(|bug| mode.matches(bug, account))
+            .collect())
+    }
+
+    // 根据产品ID获取Bug列表，单个产品失败时记录日志并跳过
+    pub async fn get_bugs_by_product(&self, product_ids: &[i32]) -> Result<Vec<Bug>, String> {
+        if let Some(session) = self.legacy_session() {
+            let mut all_bugs = Vec::new();
+            for &product_id in product_ids {
+                match legacy::get_bugs(&self.http, &self.urls, &session, product_id).await {
+                    Ok(mut bugs) => {
+                        for bug in &mut bugs {
+                            bug.base_url = Some(self.urls.api.clone());
+                        }
+                        all_bugs.extend(bugs);
+                    }
+                    Err(e) if e == UNAUTHORIZED => return Err(e),
+                    Err(e) => {
+                        error!("获取产品{}Bug列表失败: {}", product_id, e);
+                        continue;
+                    }
+                }
+            }
+            return Ok(all_bugs);
+        }
+    
+        // 合并多个产品的Bug列表
+        let mut all_bugs = Vec::new();
+    
+        for &product_id in product_ids {
+            let bugs_url = format!("{}/products/{}/bugs", self.urls.api, product_id);
+        
+            debug!("获取产品{}的Bug列表: {}", product_id, bugs_url);
+        
+            let request = self
+                .http
+                .get(&bugs_url)
+                .header("Token", &self.token)
+                .header("Content-Type", "application/json");
+
+            match self.http.send(request).await {
+                Ok(response) => {
+                    let status = response.status();
+                    debug!("产品{}Bug列表响应状态: {}", product_id, status);
+                
+                    if status.is_success() {
+                        match response.text().await {
+                            Ok(text) => {
+                                let preview = body_preview(&text, 200);
+                                debug!("产品{}Bug列表响应 (长度: {}): {}", product_id, text.len(), preview);
+                            
+                                // 解码 Unicode 转义序列
+                                let decoded_text = decode_unicode_escapes(&text);
+                            
+                                match serde_json::from_str::<BugListResponse>(&decoded_text) {
+                                    Ok(mut bug_response) => {
+                                        debug!("产品{}解析到{}个Bug", product_id, bug_response.bugs.len());
+                                        // 调试第一个Bug的数据
+                                        if let Some(first_bug) = bug_response.bugs.first() {
+                                            debug!("第一个Bug数据: id={}, opened_date={}", first_bug.id, first_bug.opened_date);
+                                        }
+                                        // 为每个Bug设置base_url
+                                        for bug in &mut bug_response.bugs {
+                                            bug.base_url = Some(self.urls.api.clone());
+                                        }
+                                        all_bugs.extend(bug_response.bugs);
+                                    }
+                                    Err(e) => {
+                                        let preview = body_preview(&text, 100);
+                                        error!("解析产品{}Bug列表JSON失败: {} - 响应预览: {}", product_id, e, preview);
+                                        continue;
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                error!("读取产品{}Bug列表响应失败: {}", product_id, e);
+                                continue;
+                            }
+                        }
+                    } else if status == 401 {
+                        return Err(UNAUTHORIZED.to_string());
+                    } else {
+                        warn!("获取产品{}Bug列表失败，状态码: {}", product_id, status);
+                        continue;
+                    }
+                }
+                Err(e) => {
+                    error!("请求产品{}Bug列表失败: {}", product_id, e);
+                    continue;
+                }
+            }
+        }
+    
+        Ok(all_bugs)
+
+    }
+
+    // 获取Bug详情
+    pub async fn get_bug_detail(&self, bug_id: i32) -> Result<Bug, String> {
+        if let Some(session) = self.legacy_session() {
+            let mut bug = legacy::get_bug_detail(&self.http, &self.urls, &session, bug_id).await?;
+            bug.base_url = Some(self.urls.api.clone());
+            info!("成功解析Bug详情，ID: {}", bug.id);
+            return Ok(bug);
+        }
+
+        let bug_url = format!("{}/bugs/{}", self.urls.api, bug_id);
+    
+        debug!("发送请求获取Bug详情: {}", bug_url);
+    
+        let request = self
+            .http
+            .get(&bug_url)
+            .header("Token", &self.token)
+            .header("Content-Type", "application/json");
+
+        match self.http.send(request).await {
+            Ok(response) => {
+                let status = response.status();
+                debug!("Bug详情响应状态: {}", status);
+            
+                if status.is_success() {
+                    match response.text().await {
+                        Ok(text) => {
+                            // 只打印响应长度和前面部分内容，避免日志过长
+                            let preview = body_preview(&text, MAX_LOGGED_BODY);
+                            debug!("Bug详情响应 (长度: {}): {}", text.len(), preview);
+                        
+                            // 解码 Unicode 转义序列
+                            let decoded_text = decode_unicode_escapes(&text);
+                        
+                            match serde_json::from_str::<Bug>(&decoded_text) {
+                                Ok(mut bug) => {
+                                    // 设置base_url用于图片路径处理
+                                    bug.base_url = Some(self.urls.api.clone());
+                                    info!("成功解析Bug详情，ID: {}", bug.id);
+                                    Ok(bug)
+                                }
+                                Err(e) => {
+                                    let preview = body_preview(&text, 100);
+                                    error!("解析Bug详情JSON失败: {} - 响应预览: {}", e, preview);
+                                    Err(format!("解析Bug详情JSON失败: {} - 响应预览: {}", e, preview))
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!("读取Bug详情响应失败: {}", e);
+                            Err(format!("读取Bug详情响应失败: {}", e))
+                        }
+                    }
+                } else if status == 401 {
+                    Err(UNAUTHORIZED.to_string())
+                } else {
+                    match response.text().await {
+                        Ok(error_text) => {
+                            // 解码错误信息中的 Unicode 转义序列
+                            let decoded_error = decode_unicode_escapes(&error_text);
+                            Err(format!("获取Bug详情失败，状态码: {} - 错误信息: {}", status, body_preview(&decoded_error, MAX_LOGGED_BODY)))
+                        },
+                        Err(_) => Err(format!("获取Bug详情失败，状态码: {}", status)),
+                    }
+                }
+            }
+            Err(e) => {
+                error!("请求Bug详情失败: {}", e);
+                Err(format!("请求失败: {}", e))
+            }
+        }
+
+    }
+
+    // 获取产品列表
+    pub async fn get_products(&self) -> Result<Vec<Product>, String> {
+        if let Some(session) = self.legacy_session() {
+            return legacy::get_products(&self.http, &self.urls, &session).await;
+        }
+
+        let products_url = format!("{}/products", self.urls.api);
+    
+        debug!("获取产品列表: {}", products_url);
+    
+        let request = self
+            .http
+            .get(&products_url)
+            .header("Token", &self.token)
+            .header("Content-Type", "application/json");
+
+        match self.http.send(request).await {
+            Ok(response) => {
+                let status = response.status();
+                debug!("产品列表响应状态: {}", status);
+            
+                if status.is_success() {
+                    match response.text().await {
+                        Ok(text) => {
+                            let preview = body_preview(&text, 300);
+                            debug!("产品列表响应 (长度: {}): {}", text.len(), preview);
+                        
+                            // 解码 Unicode 转义序列
+                            let decoded_text = decode_unicode_escapes(&text);
+                        
+                            match serde_json::from_str::<Value>(&decoded_text) {
+                                Ok(json) => {
+                                    // 检查是否是分页响应格式 {"page":1,"total":2,"limit":100,"products":[]}
+                                    if let (Some(page), Some(total), Some(limit), Some(products)) = (
+                                        json.get("page"),
+                                        json.get("total"), 
+                                        json.get("limit"),
+                                        json.get("products")
+                                    ) {
+                                        debug!("分页响应 - 页码: {}, 总数: {}, 限制: {}", page, total, limit);
+                                        // 解析products数组
+                                        match serde_json::from_value::<Vec<Product>>(products.clone()) {
+                                            Ok(product_list) => {
+                                                debug!("成功解析 {} 个产品", product_list.len());
+                                                Ok(product_list)
+                                            },
+                                            Err(e) => {
+                                                let preview = body_preview(&text, 100);
+                                                Err(format!("解析产品列表失败: {} - 响应预览: {}", e, preview))
+                                            }
+                                        }
+                                    } else if let Some(products) = json.get("products") {
+                                        // 兼容旧格式，直接有products字段但没有分页信息
+                                        match serde_json::from_value::<Vec<Product>>(products.clone()) {
+                                            Ok(product_list) => Ok(product_list),
+                                            Err(e) => Err(format!("解析产品列表失败: {} - 响应内容: {}", e, body_preview(&text, MAX_LOGGED_BODY))),
+                                        }
+                                    } else if json.is_array() {
+                                        // 如果直接是数组格式
+                                        match serde_json::from_value::<Vec<Product>>(json) {
+                                            Ok(product_list) => Ok(product_list),
+                                            Err(e) => Err(format!("解析产品数组失败: {} - 响应内容: {}", e, body_preview(&text, MAX_LOGGED_BODY))),
+                                        }
+                                    } else {
+                                        let preview = body_preview(&text, 100);
+                                        Err(format!("响应格式不正确，期望分页格式 - 响应预览: {}", preview))
+                                    }
+                                }
+                                Err(e) => Err(format!("解析产品列表JSON失败: {} - 响应内容: {}", e, body_preview(&text, MAX_LOGGED_BODY))),
+                            }
+                        }
+                        Err(e) => Err(format!("读取产品列表响应失败: {}", e)),
+                    }
+                } else {
+                    // 如果是401错误，说明token过期
+                    if status == 401 {
+                        return Err(UNAUTHORIZED.to_string());
+                    }
+                
+                    match response.text().await {
+                        Ok(error_text) => {
+                            // 解码错误信息中的 Unicode 转义序列
+                            let decoded_error = decode_unicode_escapes(&error_text);
+                            Err(format!("获取产品列表失败，状态码: {} - 错误信息: {}", status, body_preview(&decoded_error, MAX_LOGGED_BODY)))
+                        },
+                        Err(_) => Err(format!("获取产品列表失败，状态码: {}", status)),
+                    }
+                }
+            }
+            Err(e) => Err(format!("请求失败: {}", e)),
+        }
+
+    }
+
+    // 获取图片数据（Base64编码的data URL），图片路径相对于禅道根地址
+    pub async fn get_image(&self, image_path: &str) -> Result<String, String> {
+        let bytes = self.get_image_bytes(image_path).await?;
+        Ok(image_data_url(image_path, &bytes))
+    }
+
+    // 获取图片的原始数据，图片路径相对于禅道根地址
+    pub async fn get_image_bytes(&self, image_path: &str) -> Result<Vec<u8>, String> {
+        if let Some(session) = self.legacy_session() {
+            let bytes = legacy::read_file(&self.http, &self.urls, &session, image_path).await?;
+            info!("成功获取图片，大小: {} bytes", bytes.len());
+            return Ok(bytes);
+        }
+
+        let image_url = format!("{}/{}", self.urls.root, image_path);
+    
+        debug!("获取图片: {} (从根URL: {})", image_url, self.urls.root);
+    
+        let request = self
+            .http
+            .get(&image_url)
+            .header("Token", &self.token);
+
+        match self.http.send(request).await {
+            Ok(response) => {
+                let status = response.status();
+                debug!("图片响应状态: {}", status);
+            
+                if status.is_success() {
+                    match response.bytes().await {
+                        Ok(bytes) => {
+                            info!("成功获取图片，大小: {} bytes", bytes.len());
+                        
+                            Ok(bytes.to_vec())
+                        }
+                        Err(e) => {
+                            error!("读取图片数据失败: {}", e);
+                            Err(format!("读取图片数据失败: {}", e))
+                        }
+                    }
+                } else if status == 401 {
+                    Err(UNAUTHORIZED.to_string())
+                } else {
+                    match response.text().await {
+                        Ok(error_text) => {
+                            // 解码错误信息中的 Unicode 转义序列
+                            let decoded_error = decode_unicode_escapes(&error_text);
+                            Err(format!("获取图片失败，状态码: {} - 错误信息: {}", status, body_preview(&decoded_error, MAX_LOGGED_BODY)))
+                        },
+                        Err(_) => Err(format!("获取图片失败，状态码: {}", status)),
+                    }
+                }
+            }
+            Err(e) => {
+                error!("请求图片失败: {}", e);
+                Err(format!("请求失败: {}", e))
+            }
+        }
+
+    }
+
+    // 获取产品详情
+    pub async fn get_product_detail(&self, product_id: i32) -> Result<Product, String> {
+        if let Some(session) = self.legacy_session() {
+            return legacy::get_product_detail(&self.http, &self.urls, &session, product_id).await;
+        }
+
+        let product_url = format!("{}/products/{}", self.urls.api, product_id);
+    
+        debug!("获取产品详情: {}", product_url);
+    
+        let request = self
+            .http
+            .get(&product_url)
+            .header("Token", &self.token)
+            .header("Content-Type", "application/json");
+
+        match self.http.send(request).await {
+            Ok(response) => {
+                let status = response.status();
+                debug!("产品详情响应状态: {}", status);
+            
+                if status.is_success() {
+                    match response.text().await {
+                        Ok(text) => {
+                            debug!("产品详情响应: 成功获取 (长度: {})", text.len());
+                        
+                            // 解码 Unicode 转义序列
+                            let decoded_text = decode_unicode_escapes(&text);
+                            debug!("产品详情响应: {}", body_preview(&decoded_text, MAX_LOGGED_BODY));
+                        
+                            match serde_json::from_str::<Value>(&decoded_text) {
+                                Ok(json) => {
+                                    // 直接解析产品对象
+                                    match serde_json::from_value::<Product>(json) {
+                                        Ok(product) => {
+                                            debug!("成功解析产品详情: {}", product.name);
+                                            Ok(product)
+                                        },
+                                        Err(e) => Err(format!("解析产品详情失败: {} - 响应内容: {}", e, body_preview(&text, MAX_LOGGED_BODY))),
+                                    }
+                                }
+                                Err(e) => Err(format!("解析产品详情JSON失败: {} - 响应内容: {}", e, body_preview(&text, MAX_LOGGED_BODY))),
+                            }
+                        }
+                        Err(e) => Err(format!("读取产品详情响应失败: {}", e)),
+                    }
+                } else {
+                    // 如果是401错误，说明token过期
+                    if status == 401 {
+                        return Err(UNAUTHORIZED.to_string());
+                    }
+                
+                    match response.text().await {
+                        Ok(error_text) => {
+                            // 解码错误信息中的 Unicode 转义序列
+                            let decoded_error = decode_unicode_escapes(&error_text);
+                            Err(format!("获取产品详情失败，状态码: {} - 错误信息: {}", status, body_preview(&decoded_error, MAX_LOGGED_BODY)))
+                        },
+                        Err(_) => Err(format!("获取产品详情失败，状态码: {}", status)),
+                    }
+                }
+            }
+            Err(e) => Err(format!("请求失败: {}", e)),
+        }
+    }
+
+    // 获取产品的Bug模块，返回模块ID到完整路径（如“前端/UI”）的映射，用于按模块名称查询
+    pub async fn get_module_names(&self, product_id: i32) -> Result<HashMap<i32, String>, String> {
+        if self.legacy_session().is_some() {
+            return Err("旧版API不支持获取模块名称，请使用模块ID查询".to_string());
+        }
+
+        let modules_url = format!("{}/modules?type=bug&id={}", self.urls.api, product_id);
+        debug!("获取模块: {}", modules_url);
+        let request = self.http.get(&modules_url).header("Token", &self.token);
+        let response = self.http.send(request).await.map_err(|e| format!("请求失败: {}", e))?;
+        let status = response.status();
+        if status == 401 {
+            return Err(UNAUTHORIZED.to_string());
+        }
+        let text = response.text().await.map_err(|e| format!("读取模块响应失败: {}", e))?;
+        if !status.is_success() {
+            return Err(format!("获取模块失败，状态码: {} - 错误信息: {}", status, body_preview(&text, MAX_LOGGED_BODY)));
+        }
+        let json = serde_json::from_str::<Value>(&decode_unicode_escapes(&text))
+            .map_err(|e| format!("解析模块JSON失败: {} - 响应内容: {}", e, body_preview(&text, MAX_LOGGED_BODY)))?;
+        let mut names = HashMap::new();
+        collect_module_names(&json, "", &mut names);
+        debug!("产品{}有{}个模块", product_id, names.len());
+        Ok(names)
+    }
+}
+
+// 遍历模块树：带 id 和 name 的对象为模块，children 为子模块
+fn collect_module_names(value: &Value, parent: &str, names: &mut HashMap<i32, String>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                collect_module_names(item, parent, names);
+            }
+        }
+        Value::Object(object) => {
+            let id = object.get("id").and_then(|id| match id {
+                Value::Number(n) => n.as_i64(),
+                Value::String(s) => s.parse().ok(),
+                _ => None,
+            });
+            let name = object.get("name").and_then(Value::as_str);
+            let path = match (id, name) {
+                (Some(id), Some(name)) if id > 0 => {
+                    let path = if parent.is_empty() { name.to_string() } else { format!("{}/{}", parent, name) };
+                    names.insert(id as i32, path.clone());
+                    path
+                }
+                _ => parent.to_string(),
+            };
+            for (key, child) in object {
+                if matches!(child, Value::Array(_) | Value::Object(_)) && key != "id" {
+                    collect_module_names(child, &path, names);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn module_tree_is_flattened_to_paths() {
+        let tree = json!({
+            "modules": [
+                { "id": 1, "name": "前端", "children": [
+                    { "id": "3", "name": "UI", "children": [] }
+                ]},
+                { "id": 2, "name": "后端" }
+            ]
+        });
+        let mut names = HashMap::new();
+        collect_module_names(&tree, "", &mut names);
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[&1], "前端");
+        assert_eq!(names[&3], "前端/UI");
+        assert_eq!(names[&2], "后端");
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{error, debug};
+
+use crate::git_template::ChineseTitle;
+use crate::http::NetworkConfig;
+use crate::redact::MASK;
+use crate::server::ApiMode;
+
+// 禅道服务器配置
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ZentaoConfig {
+    #[serde(rename = "baseUrl")]
+    pub base_url: String,
+    pub account: String,
+    pub password: String,
+    // API模式：auto | rest | legacy，默认自动检测
+    #[serde(rename = "apiMode")]
+    #[serde(default)]
+    pub api_mode: ApiMode,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub polling: PollingConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    300
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// 后台轮询设置：定时检查指派给我的Bug
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PollingConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // 轮询间隔，最少 30 秒
+    #[serde(rename = "intervalSecs")]
+    #[serde(default = "default_poll_interval_secs")]
+    pub interval_secs: u64,
+    // 是否弹出桌面通知
+    #[serde(default = "default_true")]
+    pub notify: bool,
+    // 每天为全部产品记录一次Bug快照，用于趋势图；与 enabled 无关，关闭轮询后登录期间仍会记录
+    #[serde(rename = "dailySnapshot")]
+    #[serde(default = "default_true")]
+    pub daily_snapshot: bool,
+}
+
+impl Default for PollingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: default_poll_interval_secs(),
+            notify: true,
+            daily_snapshot: true,
+        }
+    }
+}
+
+// 本地git仓库设置：从提交信息中查找引用的Bug
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitConfig {
+    // 本地仓库路径
+    #[serde(default)]
+    pub repositories: Vec<String>,
+    // 提取Bug ID的正则表达式，第一个捕获组为Bug ID
+    #[serde(default = "default_bug_patterns")]
+    pub patterns: Vec<String>,
+    // 分支名和提交信息模板，占位符见 git_template 模块
+    #[serde(rename = "branchTemplate")]
+    #[serde(default = "default_branch_template")]
+    pub branch_template: String,
+    #[serde(rename = "commitTemplate")]
+    #[serde(default = "default_commit_template")]
+    pub commit_template: String,
+    // 分支名中的中文标题转为拼音还是去掉
+    #[serde(rename = "chineseTitle")]
+    #[serde(default)]
+    pub chinese_title: ChineseTitle,
+}
+
+fn default_branch_template() -> String {
+    "bugfix/{id}-{slug}".to_string()
+}
+
+// 默认的提交信息能被默认的Bug引用规则识别
+fn default_commit_template() -> String {
+    "fix: {title} (bug #{id})\n\n产品: {product}".to_string()
+}
+
+// 匹配 bug 123、Bug #123、bug-123 以及 fix #123、closes #123 这样的写法
+pub fn default_bug_patterns() -> Vec<String> {
+    vec![
+        r"(?i)\bbugs?\s*[#:：-]?\s*(\d+)".to_string(),
+        r"(?i)\b(?:fix|fixes|fixed|close|closes|closed|resolve|resolves|resolved)\s+#(\d+)".to_string(),
+    ]
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            repositories: Vec::new(),
+            patterns: default_bug_patterns(),
+            branch_template: default_branch_template(),
+            commit_template: default_commit_template(),
+            chinese_title: ChineseTitle::default(),
+        }
+    }
+}
+
+// 日志中不输出明文密码
+impl fmt::Debug for ZentaoConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZentaoConfig")
+            .field("base_url", &self.base_url)
+            .field("account", &self.account)
+            .field("password", &MASK)
+            .field("api_mode", &self.api_mode)
+            .field("network", &self.network)
+            .field("polling", &self.polling)
+            .field("git", &self.git)
+            .finish()
+    }
+}
+
+// 默认配置档：使用 zentao_app 目录下的配置和token文件，主窗口和命令行都使用它
+pub const DEFAULT_PROFILE: &str = "default";
+
+// 配置档内的文件相对数据目录的路径：默认配置档直接放在 zentao_app 下，其他配置档放在 zentao_app/profiles/<名称>/ 下
+fn profile_relative_path(profile: &str, file_name: &str) -> PathBuf {
+    let mut path = PathBuf::from("zentao_app");
+    let name = profile
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>();
+    if !name.is_empty() && name != DEFAULT_PROFILE {
+        path.push("profiles");
+        path.push(name);
+    }
+    path.push(file_name);
+    path
+}
+
+// 配置档内的文件路径（配置、token）
+pub fn profile_data_path(profile: &str, file_name: &str) -> Result<PathBuf, String> {
+    let data_dir = dirs::data_dir().ok_or("无法获取用户数据目录")?;
+    Ok(data_dir.join(profile_relative_path(profile, file_name)))
+}
+
+// 获取配置档的配置存储文件路径
+pub fn get_config_file_path(profile: &str) -> Result<PathBuf, String> {
+    profile_data_path(profile, "zentao_config.json")
+}
+
+// 保存配置到本地文件
+pub fn save_config_to_file(profile: &str, config: &ZentaoConfig) -> Result<(), String> {
+    let file_path = get_config_file_path(profile)?;
+    
+    // 确保目录存在
+    if let Some(parent) = file_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        return Err(format!("创建目录失败: {}", e));
+    }
+    
+    match serde_json::to_string_pretty(config) {
+        Ok(json_str) => {
+            match fs::write(&file_path, json_str) {
+                Ok(_) => {
+                    debug!("配置已保存到: {:?}", file_path);
+                    Ok(())
+                }
+                Err(e) => Err(format!("写入配置文件失败: {}", e)),
+            }
+        }
+        Err(e) => Err(format!("序列化配置数据失败: {}", e)),
+    }
+}
+
+// 从本地文件加载配置
+pub fn load_config_from_file(profile: &str) -> Result<Option<ZentaoConfig>, String> {
+    let file_path = get_config_file_path(profile)?;
+    
+    if !file_path.exists() {
+        debug!("配置文件不存在: {:?}", file_path);
+        return Ok(None);
+    }
+    
+    match fs::read_to_string(&file_path) {
+        Ok(content) => {
+            match serde_json::from_str::<ZentaoConfig>(&content) {
+                Ok(config) => {
+                    debug!("成功加载配置文件: {:?}", file_path);
+                    Ok(Some(config))
+                }
+                Err(e) => {
+                    error!("解析配置文件失败: {}", e);
+                    // 删除损坏的配置文件
+                    let _ = fs::remove_file(&file_path);
+                    Ok(None)
+                }
+            }
+        }
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Ok(None)
+            } else {
+                Err(format!("读取配置文件失败: {}", e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn default_profile_uses_top_level_files() {
+        assert_eq!(profile_relative_path(DEFAULT_PROFILE, "zentao_config.json"), Path::new("zentao_app/zentao_config.json"));
+        assert_eq!(profile_relative_path("", "zentao_token.json"), Path::new("zentao_app/zentao_token.json"));
+    }
+
+    #[test]
+    fn other_profiles_get_their_own_directory() {
+        assert_eq!(
+            profile_relative_path("work", "zentao_token.json"),
+            Path::new("zentao_app/profiles/work/zentao_token.json")
+        );
+        // 名称中的路径分隔符等字符被替换，不能逃出配置档目录
+        assert_eq!(
+            profile_relative_path("../测试 1", "zentao_config.json"),
+            Path::new("zentao_app/profiles/___测试_1/zentao_config.json")
+        );
+    }
+}
@@ -0,0 +1,128 @@
+// Bug字段比较：用于后台轮询和关注列表，找出两次获取之间变化的字段
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::models::Bug;
+
+// 参与比较的字段（序列化后的字段名）
+pub const TRACKED_FIELDS: &[&str] = &[
+    "title",
+    "status",
+    "severity",
+    "pri",
+    "type",
+    "keywords",
+    "steps",
+    "deadline",
+    "assignedTo",
+    "resolvedBy",
+    "resolvedDate",
+    "closedBy",
+    "closedDate",
+];
+
+// 一个字段的变化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+// 取出参与比较的字段值，关注列表保存这些值作为上一次看到的版本
+pub fn tracked_fields(bug: &Bug) -> BTreeMap<String, Value> {
+    let Ok(value) = serde_json::to_value(bug) else {
+        return BTreeMap::new();
+    };
+    TRACKED_FIELDS
+        .iter()
+        .map(|field| (field.to_string(), value.get(*field).cloned().unwrap_or(Value::Null)))
+        .collect()
+}
+
+// 比较两组字段值，返回变化的字段
+pub fn diff_fields(old: &BTreeMap<String, Value>, new: &BTreeMap<String, Value>) -> Vec<FieldChange> {
+    TRACKED_FIELDS
+        .iter()
+        .filter_map(|field| {
+            let old_value = old.get(*field).cloned().unwrap_or(Value::Null);
+            let new_value = new.get(*field).cloned().unwrap_or(Value::Null);
+            (old_value != new_value).then(|| FieldChange {
+                field: field.to_string(),
+                old: old_value,
+                new: new_value,
+            })
+        })
+        .collect()
+}
+
+// 比较同一个Bug的两个版本，返回变化的字段
+pub fn diff_bugs(old: &Bug, new: &Bug) -> Vec<FieldChange> {
+    diff_fields(&tracked_fields(old), &tracked_fields(new))
+}
+
+// 状态是否为已解决或已关闭
+pub fn is_resolved(bug: &Bug) -> bool {
+    matches!(bug.status.code.as_str(), "resolved" | "closed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bug(status: &str, title: &str, assigned_to: Option<&str>) -> Bug {
+        let assigned_to = assigned_to.map(|account| json!({ "id": 1, "account": account, "avatar": "", "realname": account }));
+        serde_json::from_value(json!({
+            "id": 1, "product": 1, "branch": 0, "module": 0, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": title, "keywords": "", "severity": 3, "pri": 3,
+            "type": "codeerror", "steps": "", "openedBy": null, "openedDate": "2025-01-01",
+            "assignedTo": assigned_to, "status": status
+        }))
+        .unwrap()
+    }
+
+    fn fields(pairs: &[(&str, Value)]) -> BTreeMap<String, Value> {
+        pairs.iter().map(|(field, value)| (field.to_string(), value.clone())).collect()
+    }
+
+    #[test]
+    fn diff_fields_reports_changed_tracked_fields_in_order() {
+        let old = fields(&[("title", json!("登录失败")), ("status", json!("active")), ("pri", json!(3))]);
+        let new = fields(&[("title", json!("登录失败")), ("status", json!("resolved")), ("pri", json!(1)), ("extra", json!(1))]);
+        let changes = diff_fields(&old, &new);
+        let changed = changes.iter().map(|change| change.field.as_str()).collect::<Vec<_>>();
+        assert_eq!(changed, vec!["status", "pri"]);
+        assert_eq!(changes[0].old, json!("active"));
+        assert_eq!(changes[0].new, json!("resolved"));
+        assert!(diff_fields(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn missing_fields_compare_as_null() {
+        let old = fields(&[("deadline", Value::Null)]);
+        let new = fields(&[("keywords", json!("登录"))]);
+        let changes = diff_fields(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "keywords");
+        assert_eq!(changes[0].old, Value::Null);
+    }
+
+    #[test]
+    fn diff_bugs_compares_serialized_fields() {
+        let old = bug("active", "登录失败", None);
+        let new = bug("active", "登录超时", Some("zhangsan"));
+        let changed = diff_bugs(&old, &new).into_iter().map(|change| change.field).collect::<Vec<_>>();
+        assert_eq!(changed, vec!["title", "assignedTo"]);
+        assert!(diff_bugs(&old, &old).is_empty());
+        assert_eq!(tracked_fields(&old).len(), TRACKED_FIELDS.len());
+    }
+
+    #[test]
+    fn resolved_and_closed_bugs_are_resolved() {
+        assert!(is_resolved(&bug("resolved", "", None)));
+        assert!(is_resolved(&bug("closed", "", None)));
+        assert!(!is_resolved(&bug("active", "", None)));
+    }
+}
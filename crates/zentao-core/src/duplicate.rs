@@ -0,0 +1,468 @@
+// 重复Bug检测：比较标题、重现步骤文本和截图，给出疑似重复的Bug及依据
+//
+// 文本按 search::tokenize_query 切分为中文 bigram 和英文单词：标题较短，直接计算 Jaccard 相似度；
+// 重现步骤去掉HTML和禅道的步骤模板后计算 MinHash 签名，用签名估计 Jaccard 相似度。
+// 截图计算 64 位感知哈希（pHash），两张截图的汉明距离足够小时认为是同一画面。
+use base64::{engine::general_purpose, Engine as _};
+use image::imageops::FilterType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::LazyLock;
+use tracing::{debug, warn};
+
+use crate::client::{ZentaoClient, UNAUTHORIZED};
+use crate::html::{html_to_text, image_paths};
+use crate::models::Bug;
+use crate::search::tokenize_query;
+
+pub const DEFAULT_LIMIT: usize = 10;
+// 只比较截图时，先按文本相似度取前这么多个候选Bug，避免下载所有截图
+pub const IMAGE_CANDIDATES: usize = 30;
+// 低于这个分数的不作为疑似重复
+const MIN_SCORE: f64 = 0.3;
+// 各项依据的权重，缺少某项（没有步骤或截图）时按其余项重新归一化
+const TITLE_WEIGHT: f64 = 0.5;
+const STEPS_WEIGHT: f64 = 0.3;
+const IMAGE_WEIGHT: f64 = 0.2;
+// MinHash 签名长度
+const NUM_HASHES: usize = 64;
+// pHash 汉明距离不超过这个值时认为截图相似
+const MAX_IMAGE_DISTANCE: u32 = 10;
+// 依据中列出的共同词数量
+const MAX_COMMON_TERMS: usize = 8;
+
+// 禅道默认的步骤模板，所有Bug都有，不参与比较
+static STEPS_TEMPLATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[(步骤|结果|期望)\]").unwrap());
+
+// 正在填写、还没有提交的Bug
+#[derive(Debug, Clone, Deserialize)]
+pub struct BugDraft {
+    pub product: i32,
+    pub title: String,
+    // 重现步骤（HTML）
+    #[serde(default)]
+    pub steps: String,
+    // 截图，data URL 或 Base64
+    #[serde(default)]
+    pub screenshots: Vec<String>,
+}
+
+// 一张截图的感知哈希
+#[derive(Debug, Clone)]
+pub struct ImageHash {
+    // 图片路径，草稿中的截图为文件名
+    pub path: String,
+    pub hash: u64,
+}
+
+// 用于比较的Bug特征
+#[derive(Debug, Clone)]
+pub struct BugFingerprint {
+    // 草稿（还没有创建的Bug）为 None
+    pub bug_id: Option<i32>,
+    pub product: i32,
+    pub title: String,
+    pub status: String,
+    title_terms: HashSet<String>,
+    // 没有重现步骤时为 None
+    steps_signature: Option<Vec<u64>>,
+    pub images: Vec<ImageHash>,
+    // 重现步骤中的图片路径，需要下载后调用 add_image 计算哈希
+    pub image_paths: Vec<String>,
+}
+
+impl BugFingerprint {
+    pub fn new(bug: &Bug) -> BugFingerprint {
+        let mut fingerprint = BugFingerprint::draft(bug.product, &bug.title, &bug.steps);
+        fingerprint.bug_id = Some(bug.id);
+        fingerprint.status = bug.status.name.clone();
+        fingerprint
+    }
+
+    // 正在填写的Bug，steps 为HTML
+    pub fn draft(product: i32, title: &str, steps: &str) -> BugFingerprint {
+        let steps_text = STEPS_TEMPLATE.replace_all(&html_to_text(steps), "").to_string();
+        BugFingerprint {
+            bug_id: None,
+            product,
+            title: title.to_string(),
+            status: String::new(),
+            title_terms: shingles(title),
+            steps_signature: minhash(&shingles(&steps_text)),
+            images: Vec::new(),
+            image_paths: image_paths(steps),
+        }
+    }
+
+    // 草稿的特征，截图按顺序命名为“截图1”“截图2”，无法解析的截图跳过
+    pub fn from_draft(draft: &BugDraft) -> BugFingerprint {
+        let mut fingerprint = BugFingerprint::draft(draft.product, &draft.title, &draft.steps);
+        for (i, screenshot) in draft.screenshots.iter().enumerate() {
+            let data = screenshot.split_once(";base64,").map_or(screenshot.as_str(), |(_, data)| data);
+            let hash = general_purpose::STANDARD
+                .decode(data.trim())
+                .map_err(|e| format!("截图不是有效的Base64: {}", e))
+                .and_then(|bytes| image_hash(&bytes));
+            match hash {
+                Ok(hash) => fingerprint.add_image(&format!("截图{}", i + 1), hash),
+                Err(e) => warn!("跳过第{}张截图: {}", i + 1, e),
+            }
+        }
+        fingerprint
+    }
+
+    pub fn add_image(&mut self, path: &str, hash: u64) {
+        self.images.push(ImageHash { path: path.to_string(), hash });
+    }
+}
+
+fn shingles(text: &str) -> HashSet<String> {
+    tokenize_query(text).into_iter().map(|token| token.term).collect()
+}
+
+// splitmix64，用一个种子把词的哈希变换为不同的排列
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+fn minhash(shingles: &HashSet<String>) -> Option<Vec<u64>> {
+    if shingles.is_empty() {
+        return None;
+    }
+    let hashes = shingles
+        .iter()
+        .map(|shingle| {
+            let mut hasher = DefaultHasher::new();
+            shingle.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect::<Vec<_>>();
+    let signature = (0..NUM_HASHES as u64)
+        .map(|seed| {
+            let seed = mix(seed);
+            hashes.iter().map(|hash| mix(hash ^ seed)).min().unwrap_or(u64::MAX)
+        })
+        .collect();
+    Some(signature)
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+fn signature_similarity(a: &[u64], b: &[u64]) -> f64 {
+    let same = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    same as f64 / NUM_HASHES as f64
+}
+
+// 计算图片的感知哈希：缩小为 32x32 灰度图，取 DCT 低频 8x8 系数与中位数比较
+pub fn image_hash(bytes: &[u8]) -> Result<u64, String> {
+    const SIZE: usize = 32;
+    const LOW: usize = 8;
+
+    let image = image::load_from_memory(bytes).map_err(|e| format!("解析图片失败: {}", e))?;
+    let gray = image.resize_exact(SIZE as u32, SIZE as u32, FilterType::Triangle).to_luma8();
+    let pixels = gray.pixels().map(|pixel| f64::from(pixel.0[0])).collect::<Vec<_>>();
+
+    // 只需要低频部分，直接按定义计算 8x8 个 DCT 系数
+    let cosines = (0..LOW)
+        .map(|u| {
+            (0..SIZE)
+                .map(|x| ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * SIZE) as f64).cos())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut coefficients = Vec::with_capacity(LOW * LOW);
+    for v in 0..LOW {
+        for u in 0..LOW {
+            let mut sum = 0.0;
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    sum += pixels[y * SIZE + x] * cosines[u][x] * cosines[v][y];
+                }
+            }
+            coefficients.push(sum);
+        }
+    }
+
+    // 直流分量反映整体亮度，不参与中位数
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    let hash = coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, coefficient)| **coefficient > median)
+        .fold(0u64, |hash, (i, _)| hash | (1 << i));
+    Ok(hash)
+}
+
+// 下载图片并计算感知哈希；单张图片下载或解析失败时跳过，登录失效时返回错误
+pub async fn load_image_hashes(client: &ZentaoClient, paths: &[String]) -> Result<Vec<ImageHash>, String> {
+    let mut hashes = Vec::new();
+    for path in paths {
+        let bytes = match client.get_image_bytes(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e == UNAUTHORIZED => return Err(e),
+            Err(e) => {
+                warn!("获取图片{}失败，跳过: {}", path, e);
+                continue;
+            }
+        };
+        let hash = tokio::task::spawn_blocking(move || image_hash(&bytes))
+            .await
+            .map_err(|e| format!("计算图片哈希失败: {}", e))?;
+        match hash {
+            Ok(hash) => hashes.push(ImageHash { path: path.clone(), hash }),
+            Err(e) => debug!("图片{}无法解析，跳过: {}", path, e),
+        }
+    }
+    Ok(hashes)
+}
+
+// 相似依据的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvidenceKind {
+    Title,
+    Steps,
+    Screenshot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Evidence {
+    pub kind: EvidenceKind,
+    // 0~1
+    pub similarity: f64,
+    // 说明，例如共同的词或相似的截图
+    pub detail: String,
+}
+
+// 疑似重复的Bug
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCandidate {
+    #[serde(rename = "bugId")]
+    pub bug_id: i32,
+    pub product: i32,
+    pub title: String,
+    pub status: String,
+    // 0~1，各项依据的加权平均
+    pub score: f64,
+    pub evidence: Vec<Evidence>,
+}
+
+// 比较两个Bug，返回得分和依据；没有任何可比较的内容时返回 None
+fn compare(target: &BugFingerprint, other: &BugFingerprint) -> Option<(f64, Vec<Evidence>)> {
+    let mut evidence = Vec::new();
+    let mut weighted = 0.0;
+    let mut total_weight = 0.0;
+
+    if !target.title_terms.is_empty() && !other.title_terms.is_empty() {
+        let similarity = jaccard(&target.title_terms, &other.title_terms);
+        weighted += TITLE_WEIGHT * similarity;
+        total_weight += TITLE_WEIGHT;
+        if similarity > 0.0 {
+            let mut common = target.title_terms.intersection(&other.title_terms).cloned().collect::<Vec<_>>();
+            common.sort();
+            common.truncate(MAX_COMMON_TERMS);
+            evidence.push(Evidence {
+                kind: EvidenceKind::Title,
+                similarity,
+                detail: format!("标题中的共同词: {}", common.join("、")),
+            });
+        }
+    }
+
+    if let (Some(a), Some(b)) = (&target.steps_signature, &other.steps_signature) {
+        let similarity = signature_similarity(a, b);
+        weighted += STEPS_WEIGHT * similarity;
+        total_weight += STEPS_WEIGHT;
+        if similarity > 0.0 {
+            evidence.push(Evidence {
+                kind: EvidenceKind::Steps,
+                similarity,
+                detail: format!("重现步骤约 {:.0}% 的片段相同", similarity * 100.0),
+            });
+        }
+    }
+
+    if !target.images.is_empty() && !other.images.is_empty() {
+        let best = target
+            .images
+            .iter()
+            .flat_map(|a| other.images.iter().map(move |b| (a, b, (a.hash ^ b.hash).count_ones())))
+            .min_by_key(|(_, _, distance)| *distance);
+        if let Some((a, b, distance)) = best
+            && distance <= MAX_IMAGE_DISTANCE
+        {
+            let similarity = 1.0 - f64::from(distance) / 64.0;
+            weighted += IMAGE_WEIGHT * similarity;
+            evidence.push(Evidence {
+                kind: EvidenceKind::Screenshot,
+                similarity,
+                detail: format!("截图 {} 与 {} 相似（汉明距离 {}）", a.path, b.path, distance),
+            });
+        }
+        total_weight += IMAGE_WEIGHT;
+    }
+
+    if total_weight == 0.0 {
+        return None;
+    }
+    Some((weighted / total_weight, evidence))
+}
+
+// 在候选Bug中查找与目标相似的Bug，按得分从高到低返回最多 limit 个
+pub fn find_duplicates(target: &BugFingerprint, candidates: &[BugFingerprint], limit: usize) -> Vec<DuplicateCandidate> {
+    let mut duplicates = candidates
+        .iter()
+        .filter_map(|other| {
+            let bug_id = other.bug_id?;
+            if target.bug_id == Some(bug_id) {
+                return None;
+            }
+            let (score, evidence) = compare(target, other)?;
+            (score >= MIN_SCORE).then(|| DuplicateCandidate {
+                bug_id,
+                product: other.product,
+                title: other.title.clone(),
+                status: other.status.clone(),
+                score,
+                evidence,
+            })
+        })
+        .collect::<Vec<_>>();
+    duplicates.sort_by(|a, b| b.score.total_cmp(&a.score).then(b.bug_id.cmp(&a.bug_id)));
+    duplicates.truncate(limit);
+    duplicates
+}
+
+// 按文本相似度排序后，前 IMAGE_CANDIDATES 个有截图的候选Bug的下标，用于决定下载哪些截图
+pub fn image_candidates(target: &BugFingerprint, candidates: &[BugFingerprint]) -> Vec<usize> {
+    let mut ranked = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, other)| !other.image_paths.is_empty() && other.bug_id != target.bug_id)
+        .filter_map(|(i, other)| compare(target, other).map(|(score, _)| (i, score)))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.into_iter().take(IMAGE_CANDIDATES).map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageFormat, Luma};
+    use std::io::Cursor;
+
+    fn fingerprint(bug_id: i32, title: &str, steps: &str) -> BugFingerprint {
+        let mut fingerprint = BugFingerprint::draft(1, title, steps);
+        fingerprint.bug_id = Some(bug_id);
+        fingerprint
+    }
+
+    fn png(pixel: impl Fn(u32, u32) -> u8) -> Vec<u8> {
+        let image = GrayImage::from_fn(64, 64, |x, y| Luma([pixel(x, y)]));
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn identical_text_scores_one() {
+        let steps = "<p>[步骤] 打开设置页面，点击保存按钮</p><p>[结果] 页面崩溃</p>";
+        let a = fingerprint(1, "保存设置时页面崩溃", steps);
+        let b = fingerprint(2, "保存设置时页面崩溃", steps);
+        let (score, evidence) = compare(&a, &b).unwrap();
+        assert_eq!(score, 1.0);
+        assert_eq!(evidence.iter().map(|e| e.kind).collect::<Vec<_>>(), [EvidenceKind::Title, EvidenceKind::Steps]);
+    }
+
+    #[test]
+    fn unrelated_text_is_below_threshold() {
+        let target = fingerprint(1, "保存设置时页面崩溃", "<p>点击保存按钮后白屏</p>");
+        let candidates = [fingerprint(2, "Export report has wrong totals", "<p>Sum column is off by one</p>")];
+        let (score, _) = compare(&target, &candidates[0]).unwrap();
+        assert!(score < MIN_SCORE);
+        assert!(find_duplicates(&target, &candidates, DEFAULT_LIMIT).is_empty());
+    }
+
+    #[test]
+    fn steps_template_is_ignored() {
+        let fingerprint = BugFingerprint::draft(1, "标题", "<p>[步骤]</p><p>[结果]</p><p>[期望]</p>");
+        assert!(fingerprint.steps_signature.is_none());
+    }
+
+    #[test]
+    fn minhash_estimates_jaccard() {
+        let a = shingles("one two three four five six seven eight");
+        let b = shingles("one two three four five six seven nine");
+        let estimated = signature_similarity(&minhash(&a).unwrap(), &minhash(&b).unwrap());
+        let exact = jaccard(&a, &b);
+        assert!((estimated - exact).abs() < 0.2, "estimated {} exact {}", estimated, exact);
+        assert_eq!(minhash(&HashSet::new()), None);
+        assert_eq!(jaccard(&HashSet::new(), &HashSet::new()), 0.0);
+    }
+
+    #[test]
+    fn hamming_distance_of_known_hashes() {
+        let mut a = BugFingerprint::draft(1, "", "");
+        let mut b = fingerprint(2, "", "");
+        a.add_image("a.png", 0);
+        b.add_image("b.png", 0b111);
+        let (score, evidence) = compare(&a, &b).unwrap();
+        assert_eq!(evidence[0].kind, EvidenceKind::Screenshot);
+        assert_eq!(evidence[0].similarity, 1.0 - 3.0 / 64.0);
+        assert_eq!(score, 1.0 - 3.0 / 64.0);
+        assert!(evidence[0].detail.contains("汉明距离 3"));
+
+        // 距离超过阈值时不作为依据，但仍计入权重
+        let mut c = fingerprint(3, "", "");
+        c.add_image("c.png", (1 << (MAX_IMAGE_DISTANCE + 1)) - 1);
+        let (score, evidence) = compare(&a, &c).unwrap();
+        assert_eq!(score, 0.0);
+        assert!(evidence.is_empty());
+    }
+
+    #[test]
+    fn phash_is_stable_under_small_changes() {
+        let gradient = image_hash(&png(|x, y| (x * 2 + y) as u8)).unwrap();
+        let brighter = image_hash(&png(|x, y| (x * 2 + y + 20) as u8)).unwrap();
+        let inverted = image_hash(&png(|x, y| 255 - (x * 2 + y) as u8)).unwrap();
+        let stripes = image_hash(&png(|_, y| if (y / 8) % 2 == 0 { 0 } else { 255 })).unwrap();
+
+        assert!((gradient ^ brighter).count_ones() <= MAX_IMAGE_DISTANCE);
+        assert!((gradient ^ inverted).count_ones() > MAX_IMAGE_DISTANCE);
+        assert!((gradient ^ stripes).count_ones() > MAX_IMAGE_DISTANCE);
+        assert!(image_hash(b"not an image").is_err());
+    }
+
+    #[test]
+    fn find_duplicates_filters_sorts_and_limits() {
+        let target = fingerprint(1, "登录页面点击登录按钮没有反应", "");
+        let mut draft = BugFingerprint::draft(1, "登录页面点击登录按钮没有反应", "");
+        draft.bug_id = None;
+        let candidates = [
+            fingerprint(1, "登录页面点击登录按钮没有反应", ""),
+            draft,
+            fingerprint(2, "登录页面点击登录按钮没有反应", ""),
+            fingerprint(3, "登录页面点击按钮没有反应", ""),
+            fingerprint(4, "导出报表失败", ""),
+        ];
+        let duplicates = find_duplicates(&target, &candidates, DEFAULT_LIMIT);
+        assert_eq!(duplicates.iter().map(|d| d.bug_id).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(duplicates[0].score, 1.0);
+        assert!(duplicates[1].score >= MIN_SCORE && duplicates[1].score < 1.0);
+        assert_eq!(find_duplicates(&target, &candidates, 1).len(), 1);
+    }
+}
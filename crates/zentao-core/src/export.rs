@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::models::{format_datetime_to_china, Bug, User};
+
+// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(format!("不支持的导出格式: {}（可选 csv、markdown、json）", other)),
+        }
+    }
+}
+
+impl ExportFormat {
+    // 导出文件的默认扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+const COLUMNS: [&str; 13] = [
+    "ID", "标题", "状态", "严重程度", "优先级", "类型", "产品", "模块", "创建者", "创建时间", "指派给", "解决者", "解决时间",
+];
+// CSV 在最后一列附上纯文本的重现步骤
+const STEPS_COLUMN: &str = "重现步骤";
+
+// 显示用户：优先显示姓名
+pub fn user_display(user: &Option<User>) -> String {
+    match user {
+        Some(user) if !user.realname.is_empty() => user.realname.clone(),
+        Some(user) => user.account.clone(),
+        None => String::new(),
+    }
+}
+
+fn optional_datetime(value: &Option<String>) -> String {
+    value
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .map(format_datetime_to_china)
+        .unwrap_or_default()
+}
+
+// 一行导出数据，与 COLUMNS 对应
+fn bug_row(bug: &Bug) -> Vec<String> {
+    vec![
+        bug.id.to_string(),
+        bug.title.clone(),
+        bug.status.name.clone(),
+        bug.severity.to_string(),
+        bug.pri.to_string(),
+        bug.bug_type.clone(),
+        bug.product.to_string(),
+        bug.module.to_string(),
+        user_display(&bug.opened_by),
+        format_datetime_to_china(&bug.opened_date),
+        user_display(&bug.assigned_to),
+        user_display(&bug.resolved_by),
+        optional_datetime(&bug.resolved_date),
+    ]
+}
+
+// 按指定格式导出Bug列表
+pub fn export_bugs(bugs: &[Bug], format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Csv => Ok(bugs_to_csv(bugs)),
+        ExportFormat::Markdown => Ok(bugs_to_markdown(bugs)),
+        ExportFormat::Json => serde_json::to_string_pretty(bugs).map_err(|e| format!("序列化Bug列表失败: {}", e)),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// CSV 带 UTF-8 BOM，Excel 打开时中文不会乱码
+pub fn bugs_to_csv(bugs: &[Bug]) -> String {
+    let mut output = String::from("\u{feff}");
+    output.push_str(&COLUMNS.join(","));
+    output.push(',');
+    output.push_str(STEPS_COLUMN);
+    output.push_str("\r\n");
+    for bug in bugs {
+        let mut row = bug_row(bug);
+        row.push(bug.steps_text());
+        let row: Vec<String> = row.iter().map(|value| csv_field(value)).collect();
+        output.push_str(&row.join(","));
+        output.push_str("\r\n");
+    }
+    output
+}
+
+fn markdown_cell(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+pub fn bugs_to_markdown(bugs: &[Bug]) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("| {} |\n", COLUMNS.join(" | ")));
+    output.push_str(&format!("|{}\n", " --- |".repeat(COLUMNS.len())));
+    for bug in bugs {
+        let row: Vec<String> = bug_row(bug).iter().map(|value| markdown_cell(value)).collect();
+        output.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    // 表格之后按Bug列出 Markdown 格式的重现步骤
+    for bug in bugs {
+        let steps = bug.steps_markdown();
+        if steps.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("\n## #{} {}\n\n{}\n", bug.id, bug.title.replace(['\r', '\n'], " "), steps));
+    }
+    output
+}
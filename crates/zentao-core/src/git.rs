@@ -0,0 +1,446 @@
+// 关联本地git提交：直接读取本地仓库的提交历史，从提交信息中提取引用的Bug ID
+//
+// 提交信息中的写法由 GitConfig.patterns 中的正则表达式决定，第一个捕获组为Bug ID。
+// 按Bug查找时遍历所有本地分支的历史；按提交范围查找时只遍历范围内的提交。
+use chrono::{DateTime, FixedOffset};
+use git2::{BranchType, Commit, DiffOptions, Oid, Repository, RevparseMode, Sort};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::{debug, warn};
+
+use crate::config::GitConfig;
+
+// 每个仓库最多遍历的提交数，避免超大仓库耗时过长
+const MAX_SCAN_COMMITS: usize = 50_000;
+
+// 从提交信息中提取Bug ID
+#[derive(Debug, Clone)]
+pub struct BugRefPatterns {
+    patterns: Vec<Regex>,
+}
+
+impl BugRefPatterns {
+    pub fn new(patterns: &[String]) -> Result<BugRefPatterns, String> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let regex = Regex::new(pattern).map_err(|e| format!("Bug引用模式无效: {} ({})", pattern, e))?;
+                if regex.captures_len() < 2 {
+                    return Err(format!("Bug引用模式缺少捕获Bug ID的分组: {}", pattern));
+                }
+                Ok(regex)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(BugRefPatterns { patterns })
+    }
+
+    pub fn extract(&self, message: &str) -> BTreeSet<i32> {
+        self.patterns
+            .iter()
+            .flat_map(|pattern| pattern.captures_iter(message))
+            .filter_map(|caps| caps.get(1)?.as_str().parse().ok())
+            .collect()
+    }
+}
+
+// 引用了Bug的提交
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitInfo {
+    // 仓库路径
+    pub repository: String,
+    pub id: String,
+    #[serde(rename = "shortId")]
+    pub short_id: String,
+    // 提交信息的第一行
+    pub summary: String,
+    pub message: String,
+    pub author: String,
+    pub email: String,
+    // 提交时间（RFC 3339）
+    pub time: String,
+    // 提交信息中引用的Bug
+    #[serde(rename = "bugIds")]
+    pub bug_ids: Vec<i32>,
+    // 修改的文件（与第一个父提交比较）
+    pub files: Vec<String>,
+    // 包含这个提交的本地分支
+    pub branches: Vec<String>,
+}
+
+// 与某个Bug相关的提交，以及这些提交涉及的分支和文件
+#[derive(Debug, Clone, Serialize)]
+pub struct BugCommits {
+    #[serde(rename = "bugId")]
+    pub bug_id: i32,
+    pub commits: Vec<CommitInfo>,
+    pub branches: Vec<String>,
+    pub files: Vec<String>,
+}
+
+// 提交范围中引用的Bug
+#[derive(Debug, Clone, Serialize)]
+pub struct RangeBugs {
+    pub repository: String,
+    pub range: String,
+    #[serde(rename = "bugIds")]
+    pub bug_ids: Vec<i32>,
+    // 范围内引用了Bug的提交
+    pub commits: Vec<CommitInfo>,
+}
+
+fn open_repository(path: &str) -> Result<Repository, String> {
+    Repository::discover(path).map_err(|e| format!("打开git仓库{}失败: {}", path, e.message()))
+}
+
+fn git_error(action: &str, e: git2::Error) -> String {
+    format!("{}失败: {}", action, e.message())
+}
+
+// 本地分支名 -> 分支最新提交
+fn local_branches(repo: &Repository) -> Result<Vec<(String, Oid)>, String> {
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local)).map_err(|e| git_error("读取分支", e))? {
+        let (branch, _) = branch.map_err(|e| git_error("读取分支", e))?;
+        let (Ok(Some(name)), Some(target)) = (branch.name(), branch.get().target()) else {
+            continue;
+        };
+        branches.push((name.to_string(), target));
+    }
+    Ok(branches)
+}
+
+fn commit_time(commit: &Commit) -> String {
+    let time = commit.time();
+    FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|offset| DateTime::from_timestamp(time.seconds(), 0).map(|dt| dt.with_timezone(&offset)))
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+// 提交修改的文件，合并提交与第一个父提交比较
+fn changed_files(repo: &Repository, commit: &Commit) -> Result<Vec<String>, String> {
+    let tree = commit.tree().map_err(|e| git_error("读取提交", e))?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree().map_err(|e| git_error("读取提交", e))?),
+        Err(_) => None,
+    };
+    let diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut DiffOptions::new()))
+        .map_err(|e| git_error("比较提交", e))?;
+    let files = diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    Ok(files)
+}
+
+fn commit_info(
+    repo: &Repository,
+    path: &str,
+    commit: &Commit,
+    bug_ids: BTreeSet<i32>,
+    branches: &[(String, Oid)],
+) -> Result<CommitInfo, String> {
+    let id = commit.id();
+    let containing = branches
+        .iter()
+        .filter(|(_, tip)| *tip == id || repo.graph_descendant_of(*tip, id).unwrap_or(false))
+        .map(|(name, _)| name.clone())
+        .collect();
+    let author = commit.author();
+    Ok(CommitInfo {
+        repository: path.to_string(),
+        id: id.to_string(),
+        short_id: id.to_string()[..8].to_string(),
+        summary: commit.summary().unwrap_or_default().to_string(),
+        message: commit.message().unwrap_or_default().to_string(),
+        author: author.name().unwrap_or_default().to_string(),
+        email: author.email().unwrap_or_default().to_string(),
+        time: commit_time(commit),
+        bug_ids: bug_ids.into_iter().collect(),
+        files: changed_files(repo, commit)?,
+        branches: containing,
+    })
+}
+
+// 在一个仓库的所有本地分支中查找引用了Bug的提交
+fn scan_repository(path: &str, patterns: &BugRefPatterns, bug_id: i32) -> Result<Vec<CommitInfo>, String> {
+    let repo = open_repository(path)?;
+    let branches = local_branches(&repo)?;
+    let mut walk = repo.revwalk().map_err(|e| git_error("遍历提交", e))?;
+    walk.set_sorting(Sort::TIME).map_err(|e| git_error("遍历提交", e))?;
+    for (_, tip) in &branches {
+        walk.push(*tip).map_err(|e| git_error("遍历提交", e))?;
+    }
+
+    let mut commits = Vec::new();
+    for (scanned, oid) in walk.enumerate() {
+        if scanned >= MAX_SCAN_COMMITS {
+            warn!("仓库{}的提交超过{}个，只查找了最近的提交", path, MAX_SCAN_COMMITS);
+            break;
+        }
+        let oid = oid.map_err(|e| git_error("遍历提交", e))?;
+        let commit = repo.find_commit(oid).map_err(|e| git_error("读取提交", e))?;
+        let bug_ids = patterns.extract(commit.message().unwrap_or_default());
+        if bug_ids.contains(&bug_id) {
+            commits.push(commit_info(&repo, path, &commit, bug_ids, &branches)?);
+        }
+    }
+    Ok(commits)
+}
+
+// 在配置的所有仓库中查找引用了Bug的提交；打不开的仓库记录警告后跳过
+pub fn commits_for_bug(config: &GitConfig, bug_id: i32) -> Result<BugCommits, String> {
+    let patterns = BugRefPatterns::new(&config.patterns)?;
+    let mut commits = Vec::new();
+    for path in &config.repositories {
+        match scan_repository(path, &patterns, bug_id) {
+            Ok(found) => commits.extend(found),
+            Err(e) => warn!("跳过git仓库{}: {}", path, e),
+        }
+    }
+    // 多个仓库的提交按时间从新到旧排列
+    commits.sort_by_key(|commit| std::cmp::Reverse(DateTime::parse_from_rfc3339(&commit.time).ok()));
+    debug!("Bug{}关联了{}个提交", bug_id, commits.len());
+
+    let branches = commits.iter().flat_map(|commit| commit.branches.iter().cloned()).collect::<BTreeSet<_>>();
+    let files = commits.iter().flat_map(|commit| commit.files.iter().cloned()).collect::<BTreeSet<_>>();
+    Ok(BugCommits {
+        bug_id,
+        commits,
+        branches: branches.into_iter().collect(),
+        files: files.into_iter().collect(),
+    })
+}
+
+// 查找提交范围中引用的Bug。range 可以是 v1.0..v1.1、main...feature 或单个提交
+pub fn bugs_in_range(path: &str, range: &str, patterns: &[String]) -> Result<RangeBugs, String> {
+    let patterns = BugRefPatterns::new(patterns)?;
+    let repo = open_repository(path)?;
+    let branches = local_branches(&repo)?;
+    let spec = repo.revparse(range).map_err(|e| git_error(&format!("解析提交范围{}", range), e))?;
+
+    let mut walk = repo.revwalk().map_err(|e| git_error("遍历提交", e))?;
+    walk.set_sorting(Sort::TIME).map_err(|e| git_error("遍历提交", e))?;
+    let target = |object: Option<&git2::Object>| object.map(|object| object.peel_to_commit().map(|commit| commit.id()));
+    let from = target(spec.from()).transpose().map_err(|e| git_error("解析提交范围", e))?;
+    let to = target(spec.to()).transpose().map_err(|e| git_error("解析提交范围", e))?;
+    let oids = if spec.mode().contains(RevparseMode::SINGLE) {
+        from.into_iter().collect::<Vec<_>>()
+    } else {
+        let (Some(from), Some(to)) = (from, to) else {
+            return Err(format!("无法解析提交范围: {}", range));
+        };
+        walk.push(to).map_err(|e| git_error("遍历提交", e))?;
+        // a...b 包含两边各自的提交，排除共同祖先之前的部分
+        if spec.mode().contains(RevparseMode::MERGE_BASE) {
+            walk.push(from).map_err(|e| git_error("遍历提交", e))?;
+            let base = repo.merge_base(from, to).map_err(|e| git_error("查找共同祖先", e))?;
+            walk.hide(base).map_err(|e| git_error("遍历提交", e))?;
+        } else {
+            walk.hide(from).map_err(|e| git_error("遍历提交", e))?;
+        }
+        walk.take(MAX_SCAN_COMMITS)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| git_error("遍历提交", e))?
+    };
+
+    let mut commits = Vec::new();
+    let mut bug_ids = BTreeSet::new();
+    for oid in oids {
+        let commit = repo.find_commit(oid).map_err(|e| git_error("读取提交", e))?;
+        let ids = patterns.extract(commit.message().unwrap_or_default());
+        if ids.is_empty() {
+            continue;
+        }
+        bug_ids.extend(ids.iter().copied());
+        commits.push(commit_info(&repo, path, &commit, ids, &branches)?);
+    }
+    Ok(RangeBugs {
+        repository: path.to_string(),
+        range: range.to_string(),
+        bug_ids: bug_ids.into_iter().collect(),
+        commits,
+    })
+}
+
+// 查找提交范围时跳过的仓库及原因
+#[derive(Debug, Clone)]
+pub struct SkippedRepository {
+    pub repository: String,
+    pub error: String,
+}
+
+// 在多个仓库中查找提交范围引用的Bug。没有这个范围的仓库跳过，连同原因一起返回；
+// 全部仓库都失败时返回最后一个错误
+pub fn bugs_in_repositories(
+    repositories: &[String],
+    range: &str,
+    patterns: &[String],
+) -> Result<(Vec<RangeBugs>, Vec<SkippedRepository>), String> {
+    let mut ranges = Vec::new();
+    let mut skipped = Vec::new();
+    for repository in repositories {
+        match bugs_in_range(repository, range, patterns) {
+            Ok(range_bugs) => ranges.push(range_bugs),
+            Err(error) => skipped.push(SkippedRepository { repository: repository.clone(), error }),
+        }
+    }
+    if ranges.is_empty()
+        && let Some(last) = skipped.pop()
+    {
+        return Err(last.error);
+    }
+    Ok((ranges, skipped))
+}
+
+// 按Bug分组提交范围中的提交：Bug ID -> 提交ID
+pub fn group_by_bug(range: &RangeBugs) -> BTreeMap<i32, Vec<String>> {
+    let mut groups: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+    for commit in &range.commits {
+        for bug_id in &commit.bug_ids {
+            groups.entry(*bug_id).or_default().push(commit.short_id.clone());
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_bug_patterns;
+    use git2::{Signature, Time};
+    use std::path::PathBuf;
+
+    // 测试用的临时仓库，结束时删除
+    struct TempRepo {
+        dir: PathBuf,
+        repo: Repository,
+    }
+
+    impl TempRepo {
+        fn new(name: &str) -> TempRepo {
+            let dir = std::env::temp_dir().join(format!("zentao-git-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            let repo = Repository::init(&dir).unwrap();
+            TempRepo { dir, repo }
+        }
+
+        fn path(&self) -> &str {
+            self.dir.to_str().unwrap()
+        }
+
+        // 在分支上提交，修改一个文件；时间递增以保证遍历顺序
+        fn commit(&self, branch: &str, parent: Option<Oid>, file: &str, message: &str, time: i64) -> Oid {
+            let parent = parent.map(|oid| self.repo.find_commit(oid).unwrap());
+            let parent_tree = parent.as_ref().map(|commit| commit.tree().unwrap());
+            let mut builder = self.repo.treebuilder(parent_tree.as_ref()).unwrap();
+            let blob = self.repo.blob(message.as_bytes()).unwrap();
+            builder.insert(file, blob, 0o100644).unwrap();
+            let tree = self.repo.find_tree(builder.write().unwrap()).unwrap();
+            let signature = Signature::new("Dev", "dev@example.com", &Time::new(time, 480)).unwrap();
+            let parents = parent.iter().collect::<Vec<_>>();
+            self.repo
+                .commit(Some(&format!("refs/heads/{}", branch)), &signature, &signature, message, &tree, &parents)
+                .unwrap()
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn default_patterns_extract_bug_ids() {
+        let patterns = BugRefPatterns::new(&default_bug_patterns()).unwrap();
+        let extract = |message: &str| patterns.extract(message).into_iter().collect::<Vec<_>>();
+        assert_eq!(extract("fix: 登录失败 (bug #12)"), [12]);
+        assert_eq!(extract("Bug-7 and BUG:8, bugs 9"), [7, 8, 9]);
+        assert_eq!(extract("Fixes #3, closes #4\n\nresolved #5"), [3, 4, 5]);
+        assert_eq!(extract("修复 Bug：21"), [21]);
+        assert!(extract("debug 12, issue #5, prefix#6").is_empty());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!(BugRefPatterns::new(&["bug (".to_string()]).is_err());
+        assert!(BugRefPatterns::new(&[r"bug \d+".to_string()]).is_err());
+    }
+
+    #[test]
+    fn bugs_in_ranges() {
+        let temp = TempRepo::new("range");
+        let c0 = temp.commit("main", None, "README.md", "init", 1_000);
+        let c1 = temp.commit("main", Some(c0), "login.rs", "fix: login crash (bug #1)", 2_000);
+        let c2 = temp.commit("feature", Some(c1), "export.rs", "Fixes #2", 3_000);
+        let c3 = temp.commit("main", Some(c1), "report.rs", "bug-3 报表", 4_000);
+        let patterns = default_bug_patterns();
+        let range = |spec: &str| bugs_in_range(temp.path(), spec, &patterns).unwrap();
+
+        // a..b：b 可达而 a 不可达的提交
+        let result = range(&format!("{}..main", c0));
+        assert_eq!(result.bug_ids, [1, 3]);
+        assert_eq!(result.commits.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), [c3.to_string(), c1.to_string()]);
+        assert_eq!(range("main..feature").bug_ids, [2]);
+
+        // a...b：两边各自的提交，不包含共同祖先
+        let result = range("main...feature");
+        assert_eq!(result.bug_ids, [2, 3]);
+        assert!(result.commits.iter().all(|commit| commit.id != c1.to_string()));
+
+        // 单个提交
+        let result = range(&c2.to_string());
+        assert_eq!(result.bug_ids, [2]);
+        let commit = &result.commits[0];
+        assert_eq!(commit.summary, "Fixes #2");
+        assert_eq!(commit.files, ["export.rs"]);
+        assert_eq!(commit.branches, ["feature"]);
+        assert_eq!(commit.time, "1970-01-01T08:50:00+08:00");
+
+        assert_eq!(group_by_bug(&range(&format!("{}..main", c0)))[&1], [c1.to_string()[..8].to_string()]);
+        assert!(bugs_in_range(temp.path(), "no-such-branch..main", &patterns).is_err());
+    }
+
+    #[test]
+    fn repositories_without_the_range_are_skipped() {
+        let first = TempRepo::new("range-first");
+        let c0 = first.commit("main", None, "README.md", "init", 1_000);
+        first.commit("release", Some(c0), "a.rs", "bug #1", 2_000);
+        let second = TempRepo::new("range-second");
+        second.commit("main", None, "README.md", "init", 1_000);
+        let patterns = default_bug_patterns();
+        let repositories = [first.path().to_string(), second.path().to_string()];
+
+        let (ranges, skipped) = bugs_in_repositories(&repositories, "main..release", &patterns).unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].bug_ids, [1]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].repository, second.path());
+
+        // 所有仓库都没有这个范围时返回错误
+        assert!(bugs_in_repositories(&repositories, "main..no-such-branch", &patterns).is_err());
+        assert!(bugs_in_repositories(&[], "main..release", &patterns).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn commits_for_bug_searches_all_branches() {
+        let temp = TempRepo::new("bug");
+        let c0 = temp.commit("main", None, "README.md", "init", 1_000);
+        let c1 = temp.commit("main", Some(c0), "a.rs", "bug #5 第一次修复", 2_000);
+        temp.commit("feature", Some(c1), "b.rs", "fix #5 again", 3_000);
+        temp.commit("main", Some(c1), "c.rs", "bug #6", 4_000);
+
+        let config = GitConfig {
+            repositories: vec![temp.path().to_string(), "/no/such/repository".to_string()],
+            ..GitConfig::default()
+        };
+        let result = commits_for_bug(&config, 5).unwrap();
+        assert_eq!(result.commits.iter().map(|c| c.summary.as_str()).collect::<Vec<_>>(), ["fix #5 again", "bug #5 第一次修复"]);
+        assert_eq!(result.branches, ["feature", "main"]);
+        assert_eq!(result.files, ["a.rs", "b.rs"]);
+    }
+}
@@ -0,0 +1,213 @@
+// 根据Bug生成分支名和提交信息模板
+//
+// 模板中的占位符：{id} {title} {slug} {product} {productId} {type} {severity} {pri}
+// {slug} 为标题转换成的分支名片段，中文按配置转为拼音或去掉。
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use crate::config::GitConfig;
+use crate::models::Bug;
+use crate::search::is_cjk;
+
+// 分支名中标题片段的最大长度
+const MAX_SLUG_CHARS: usize = 50;
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
+// 分支名中的中文标题如何处理
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChineseTitle {
+    // 转为不带声调的拼音
+    #[default]
+    Pinyin,
+    // 去掉，只保留英文和数字
+    Drop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BugGitTemplates {
+    #[serde(rename = "bugId")]
+    pub bug_id: i32,
+    pub branch: String,
+    #[serde(rename = "commitMessage")]
+    pub commit_message: String,
+}
+
+// 把标题转换为只包含小写字母、数字和连字符的片段，过长时在单词边界截断
+pub fn slugify(title: &str, chinese: ChineseTitle) -> String {
+    let ascii = match chinese {
+        // 每个汉字单独转写并用空格隔开，避免拼音与相邻的字连在一起
+        ChineseTitle::Pinyin => title
+            .chars()
+            .map(|c| {
+                let ascii = deunicode::deunicode_char(c).unwrap_or_default();
+                if is_cjk(c) { format!(" {} ", ascii) } else { ascii.to_string() }
+            })
+            .collect::<String>(),
+        ChineseTitle::Drop => title.chars().map(|c| if c.is_ascii() { c } else { ' ' }).collect(),
+    };
+    let words = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase);
+
+    let mut slug = String::new();
+    for word in words {
+        let extra = if slug.is_empty() { word.len() } else { word.len() + 1 };
+        if slug.len() + extra > MAX_SLUG_CHARS {
+            if slug.is_empty() {
+                slug = word[..MAX_SLUG_CHARS].to_string();
+            }
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word);
+    }
+    slug
+}
+
+// 去掉git分支名中不允许的字符，合并连续的分隔符；{slug} 为空时不留下多余的连字符
+fn sanitize_branch(branch: &str) -> String {
+    let cleaned = branch
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() || c.is_control() || "~^:?*[\\".contains(c) {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+    let parts = cleaned
+        .split('/')
+        .map(|part| {
+            let mut part = part.to_string();
+            while part.contains("--") || part.contains("..") {
+                part = part.replace("--", "-").replace("..", ".");
+            }
+            // 先去掉 .lock 结尾，再去掉两端的分隔符，避免 fix-.lock 留下 fix-
+            let separator = |c: char| c == '-' || c == '.';
+            let mut part = part.trim_matches(separator);
+            while let Some(stripped) = part.strip_suffix(".lock") {
+                part = stripped.trim_matches(separator);
+            }
+            part.to_string()
+        })
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    parts.join("/")
+}
+
+// 一次性替换占位符，避免标题中恰好包含占位符时被再次替换；不认识的占位符原样保留
+fn render(template: &str, bug: &Bug, product_name: &str, slug: &str) -> String {
+    PLACEHOLDER
+        .replace_all(template, |caps: &regex::Captures| match &caps[1] {
+            "id" => bug.id.to_string(),
+            "title" => bug.title.trim().to_string(),
+            "slug" => slug.to_string(),
+            "product" => product_name.to_string(),
+            "productId" => bug.product.to_string(),
+            "type" => bug.bug_type.clone(),
+            "severity" => bug.severity.to_string(),
+            "pri" => bug.pri.to_string(),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+// 按配置中的模板生成分支名和提交信息
+pub fn bug_git_templates(bug: &Bug, product_name: &str, config: &GitConfig) -> BugGitTemplates {
+    let slug = slugify(&bug.title, config.chinese_title);
+    BugGitTemplates {
+        bug_id: bug.id,
+        branch: sanitize_branch(&render(&config.branch_template, bug, product_name, &slug)),
+        commit_message: render(&config.commit_template, bug, product_name, &slug),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bug(title: &str) -> Bug {
+        serde_json::from_value(json!({
+            "id": 42, "product": 3, "branch": 0, "module": 0, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": title, "keywords": "", "severity": 2, "pri": 1,
+            "type": "codeerror", "steps": "", "openedBy": null, "openedDate": "",
+            "assignedTo": null, "status": "active"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn slugifies_chinese_titles() {
+        assert_eq!(slugify("登录页面Crash", ChineseTitle::Pinyin), "deng-lu-ye-mian-crash");
+        assert_eq!(slugify("登录页面Crash", ChineseTitle::Drop), "crash");
+        assert_eq!(slugify("  Fix: NPE in  UserService!! ", ChineseTitle::Drop), "fix-npe-in-userservice");
+        assert_eq!(slugify("全部是中文", ChineseTitle::Drop), "");
+    }
+
+    #[test]
+    fn cuts_slug_on_word_boundaries() {
+        let title = "the quick brown fox jumps over the lazy dog and keeps running";
+        let slug = slugify(title, ChineseTitle::Drop);
+        assert_eq!(slug, "the-quick-brown-fox-jumps-over-the-lazy-dog-and");
+        assert!(slug.len() <= MAX_SLUG_CHARS);
+
+        // 刚好 50 个字符时不截断
+        let title = format!("{} {}", "a".repeat(24), "b".repeat(25));
+        assert_eq!(slugify(&title, ChineseTitle::Drop).len(), MAX_SLUG_CHARS);
+    }
+
+    #[test]
+    fn cuts_single_long_word() {
+        let title = format!("{} tail", "x".repeat(80));
+        assert_eq!(slugify(&title, ChineseTitle::Drop), "x".repeat(MAX_SLUG_CHARS));
+    }
+
+    #[test]
+    fn sanitizes_branch_names() {
+        let cases = [
+            // {slug} 为空时不留下多余的连字符
+            ("bugfix/42-", "bugfix/42"),
+            ("bugfix/-/42", "bugfix/42"),
+            ("bugfix//42", "bugfix/42"),
+            ("fix 42: a~b^c?d*e[f]\\g", "fix-42-a-b-c-d-e-f]-g"),
+            ("a..b--c", "a.b-c"),
+            (".hidden/x.", "hidden/x"),
+            ("fix-.lock", "fix"),
+            ("bugfix/42.lock/x", "bugfix/42/x"),
+            ("a.lock.lock", "a"),
+            ("tab\there", "tab-here"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(sanitize_branch(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn renders_placeholders_once() {
+        let bug = bug(" 标题里有 {id} ");
+        assert_eq!(
+            render("{id}|{title}|{slug}|{product}|{productId}|{type}|{severity}|{pri}", &bug, "商城", "slug"),
+            "42|标题里有 {id}|slug|商城|3|codeerror|2|1"
+        );
+        assert_eq!(render("{unknown} {id} {ID} {}", &bug, "", ""), "{unknown} 42 {ID} {}");
+    }
+
+    #[test]
+    fn builds_templates_from_config() {
+        let templates = bug_git_templates(&bug("登录失败"), "商城", &GitConfig::default());
+        assert_eq!(templates.bug_id, 42);
+        assert_eq!(templates.branch, "bugfix/42-deng-lu-shi-bai");
+        assert_eq!(templates.commit_message, "fix: 登录失败 (bug #42)\n\n产品: 商城");
+
+        let config = GitConfig { chinese_title: ChineseTitle::Drop, ..GitConfig::default() };
+        assert_eq!(bug_git_templates(&bug("登录失败"), "商城", &config).branch, "bugfix/42");
+    }
+}
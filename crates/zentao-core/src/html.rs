@@ -0,0 +1,236 @@
+// 重现步骤等富文本字段的HTML处理
+use regex::Regex;
+use std::sync::LazyLock;
+
+static SCRIPT_STYLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(script|style)[^>]*>.*?</(script|style)\s*>").unwrap());
+static LINE_BREAK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>|</p>|</div>|</li>|</tr>|</h[1-6]>").unwrap());
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+// 禅道在图片的 alt 中保存附件地址：index.php?m=file&amp;f=read&amp;t=png&amp;fileID=1
+static IMAGE_PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<img\s+[^>]*alt="([^"]*index\.php\?m=file[^"]*)"[^>]*/?>"#).unwrap());
+// 连同内容一起删除的元素：脚本、样式和嵌入外部内容的元素
+static UNSAFE_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?is)<(script|style|iframe|object|embed|applet|frameset|noscript|template|svg|math)\b[^>]*>.*?</(script|style|iframe|object|embed|applet|frameset|noscript|template|svg|math)\s*>",
+    )
+    .unwrap()
+});
+static COMMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+// 标签，或者不属于任何标签的 <（转义，避免与后面的内容拼成标签）
+static ELEMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9]*)([^>]*)>|<").unwrap());
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([a-zA-Z_:][-a-zA-Z0-9_:.]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap()
+});
+static IMG_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<img\b[^>]*>").unwrap());
+static SRC_ATTR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\bsrc\s*=\s*"([^"]*)""#).unwrap());
+
+// 净化后保留的标签，其他标签去掉但保留其中的文字
+const ALLOWED_TAGS: &[&str] = &[
+    "a", "b", "big", "blockquote", "br", "caption", "code", "col", "colgroup", "dd", "del", "div", "dl", "dt", "em",
+    "font", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "li", "ol", "p", "pre", "s", "small", "span",
+    "strike", "strong", "sub", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "tr", "u", "ul",
+];
+// 净化后保留的属性；事件属性（on*）和 style 一律去掉
+const ALLOWED_ATTRIBUTES: &[&str] = &[
+    "href", "src", "alt", "title", "width", "height", "colspan", "rowspan", "align", "valign", "color", "size",
+];
+
+// 把HTML粗略转换为纯文本：去掉标签，块级元素换行，解码常见实体，去掉空行
+pub fn html_to_text(html: &str) -> String {
+    let text = SCRIPT_STYLE.replace_all(html, "");
+    let text = LINE_BREAK.replace_all(&text, "\n");
+    let text = TAG.replace_all(&text, "");
+    decode_entities(&text)
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 提取富文本中图片附件的路径（相对于禅道根地址），去掉重复
+pub fn image_paths(html: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for caps in IMAGE_PATH.captures_iter(html) {
+        let path = decode_entities(&caps[1]);
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+// 替换富文本中的图片附件：replace 接收图片路径，返回替换整个 img 标签的内容
+pub fn replace_images(html: &str, mut replace: impl FnMut(&str) -> String) -> String {
+    IMAGE_PATH
+        .replace_all(html, |caps: &regex::Captures| replace(&decode_entities(&caps[1])))
+        .into_owned()
+}
+
+// 替换附件以外的图片（src 不是 data URL 的 img 标签）：replace 接收 src，返回替换整个 img 标签的内容
+// 需要在 replace_images 之后调用，此时附件图片已经换成 data URL
+pub fn replace_external_images(html: &str, mut replace: impl FnMut(&str) -> String) -> String {
+    IMG_TAG
+        .replace_all(html, |caps: &regex::Captures| {
+            let src = SRC_ATTR.captures(&caps[0]).map(|src| decode_entities(&src[1])).unwrap_or_default();
+            if src.trim_start().to_ascii_lowercase().starts_with("data:image/") {
+                caps[0].to_string()
+            } else {
+                replace(&src)
+            }
+        })
+        .into_owned()
+}
+
+// 链接和图片地址只允许 http(s)、mailto、图片 data URL 和相对地址，拒绝 javascript: 等其他协议
+fn is_safe_url(name: &str, url: &str) -> bool {
+    // 浏览器解析协议时忽略空白和控制字符，比较前先去掉
+    let url = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect::<String>().to_ascii_lowercase();
+    let scheme_end = url.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = &url[..end];
+            matches!(scheme, "http" | "https" | "mailto") || (name == "src" && url.starts_with("data:image/"))
+        }
+        _ => true,
+    }
+}
+
+fn sanitize_element(caps: &regex::Captures) -> String {
+    let Some(name) = caps.get(2) else {
+        return "&lt;".to_string();
+    };
+    let name = name.as_str().to_ascii_lowercase();
+    if !ALLOWED_TAGS.contains(&name.as_str()) {
+        return String::new();
+    }
+    if !caps[1].is_empty() {
+        return format!("</{}>", name);
+    }
+
+    let mut element = format!("<{}", name);
+    for attribute in ATTRIBUTE.captures_iter(&caps[3]) {
+        let attribute_name = attribute[1].to_ascii_lowercase();
+        if !ALLOWED_ATTRIBUTES.contains(&attribute_name.as_str()) {
+            continue;
+        }
+        let value = attribute.get(2).or(attribute.get(3)).or(attribute.get(4)).map_or("", |value| value.as_str());
+        let value = decode_entities(value);
+        if matches!(attribute_name.as_str(), "href" | "src") && !is_safe_url(&attribute_name, &value) {
+            continue;
+        }
+        element.push_str(&format!(" {}=\"{}\"", attribute_name, escape_html(&value)));
+    }
+    if caps[3].trim_end().ends_with('/') {
+        element.push_str(" /");
+    }
+    element.push('>');
+    element
+}
+
+// 按白名单净化富文本：删除脚本、iframe、object 等元素和注释，只保留常见的排版标签和安全的属性
+pub fn sanitize_html(html: &str) -> String {
+    let html = UNSAFE_BLOCK.replace_all(html, "");
+    let html = COMMENT.replace_all(&html, "");
+    ELEMENT.replace_all(&html, sanitize_element).into_owned()
+}
+
+// 去掉脚本和样式
+pub fn remove_scripts(html: &str) -> String {
+    SCRIPT_STYLE.replace_all(html, "").into_owned()
+}
+
+// 解码常见的HTML实体，&amp; 放在最后避免重复解码
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+// 转义HTML特殊字符
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_keeps_formatting() {
+        let html = r#"<p align="center">第一步<br/><b>加粗</b></p><table><tr><td colspan="2">格</td></tr></table>"#;
+        assert_eq!(sanitize_html(html), r#"<p align="center">第一步<br /><b>加粗</b></p><table><tr><td colspan="2">格</td></tr></table>"#);
+        assert_eq!(sanitize_html(r#"<a href="http://example.com/?a=1&amp;b=2" title='说明'>链接</a>"#),
+            r#"<a href="http://example.com/?a=1&amp;b=2" title="说明">链接</a>"#);
+    }
+
+    #[test]
+    fn sanitize_drops_event_handlers_and_styles() {
+        assert_eq!(sanitize_html(r#"<img src="1.png" onerror="alert(1)" style="x">"#), r#"<img src="1.png">"#);
+        assert_eq!(sanitize_html(r#"<P ONCLICK=alert(1) class=x>文字</P>"#), "<p>文字</p>");
+    }
+
+    #[test]
+    fn sanitize_drops_unsafe_urls() {
+        assert_eq!(sanitize_html(r#"<a href="javascript:alert(1)">点我</a>"#), "<a>点我</a>");
+        // 不认识的实体原样转义输出，浏览器不会再把它解码为协议的一部分
+        assert_eq!(sanitize_html(r#"<a href=" java&#9;script:alert(1)">x</a>"#), r#"<a href=" java&amp;#9;script:alert(1)">x</a>"#);
+        assert_eq!(sanitize_html(r#"<a href="JaVaScRiPt&colon;alert(1)">x</a>"#), r#"<a href="JaVaScRiPt&amp;colon;alert(1)">x</a>"#);
+        assert_eq!(sanitize_html(r#"<a href="  JAVASCRIPT:alert(1)">x</a>"#), "<a>x</a>");
+        assert_eq!(sanitize_html(r#"<a href="data:text/html;base64,PHNjcmlwdD4=">x</a>"#), "<a>x</a>");
+        assert_eq!(sanitize_html(r#"<img src="data:image/png;base64,AAAA">"#), r#"<img src="data:image/png;base64,AAAA">"#);
+        assert_eq!(sanitize_html(r#"<a href="/zentao/bug-view-1.html">x</a>"#), r#"<a href="/zentao/bug-view-1.html">x</a>"#);
+    }
+
+    #[test]
+    fn sanitize_removes_embedded_content() {
+        let html = "前<iframe src=\"http://evil\">内容</iframe><object data=\"x.swf\"><param></object><script>alert(1)</script><!-- 注释 -->后";
+        assert_eq!(sanitize_html(html), "前后");
+        // 未知标签去掉，保留文字
+        assert_eq!(sanitize_html("<custom-tag><blink>闪烁</blink></custom-tag>"), "闪烁");
+    }
+
+    #[test]
+    fn sanitize_escapes_stray_angle_brackets() {
+        assert_eq!(sanitize_html("a < b"), "a &lt; b");
+        assert_eq!(sanitize_html("<img src=x onerror=alert(1)"), "&lt;img src=x onerror=alert(1)");
+        assert_eq!(sanitize_html("<!DOCTYPE html>正文"), "&lt;!DOCTYPE html>正文");
+    }
+
+    #[test]
+    fn replaces_attachment_images() {
+        let html = r#"<p><img src="{1.png}" alt="index.php?m=file&amp;f=read&amp;t=png&amp;fileID=1" /></p><img src="http://example.com/a.png" />"#;
+        assert_eq!(image_paths(html), vec!["index.php?m=file&f=read&t=png&fileID=1"]);
+        let replaced = replace_images(html, |path| format!("[{}]", path));
+        assert_eq!(replaced, r#"<p>[index.php?m=file&f=read&t=png&fileID=1]</p><img src="http://example.com/a.png" />"#);
+    }
+
+    #[test]
+    fn replaces_external_images_only() {
+        let html = r#"<img src="data:image/png;base64,AAAA" alt="" /><img src="http://example.com/a.png?x=1&amp;y=2"><IMG alt="无地址">"#;
+        let replaced = replace_external_images(html, |src| format!("[{}]", src));
+        assert_eq!(replaced, r#"<img src="data:image/png;base64,AAAA" alt="" />[http://example.com/a.png?x=1&y=2][]"#);
+    }
+
+    #[test]
+    fn converts_html_to_text() {
+        assert_eq!(html_to_text("<p>第一行</p><p>&lt;第二行&gt;&nbsp;</p><style>p{}</style>"), "第一行\n<第二行>");
+        assert_eq!(escape_html(r#"<a href="x">'&'</a>"#), "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;");
+    }
+}
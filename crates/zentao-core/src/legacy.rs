@@ -0,0 +1,729 @@
+// 旧版禅道的 JSON API（index.php?m=...&f=...&t=json），基于会话认证
+// 适用于没有 RESTful API (api.php/v1) 的开源版 12.x 之前的版本和部分企业版
+// 返回的数据统一转换为 RESTful API 的结构，再反序列化为相同的 Bug/Product 模型
+use regex::Regex;
+use serde_json::{Map, Value};
+use tracing::{debug, info, warn};
+
+use crate::client::UNAUTHORIZED;
+use crate::http::HttpClient;
+use crate::models::{decode_unicode_escapes, Bug, Product, UserInfo};
+use crate::redact::body_preview;
+use crate::server::{self, describe_request_error, ServerUrls};
+
+// 旧版API的会话：会话变量名（通常为 zentaosid）和会话ID
+pub struct LegacySession<'a> {
+    pub name: &'a str,
+    pub id: &'a str,
+}
+
+impl LegacySession<'_> {
+    fn cookie(&self) -> String {
+        format!("{}={}", self.name, self.id)
+    }
+}
+
+// 登录：先通过 getconfig 获取新会话，再用账号密码登录该会话
+// 返回 (会话变量名, 会话ID)
+pub async fn login(
+    client: &HttpClient,
+    urls: &ServerUrls,
+    account: &str,
+    password: &str,
+) -> Result<(String, String), String> {
+    let server_config = server::fetch_server_config(client, urls).await?;
+    if server_config.session_name.is_empty() || server_config.session_id.is_empty() {
+        return Err("服务器没有返回会话信息，无法使用旧版API登录".to_string());
+    }
+    let session = LegacySession {
+        name: &server_config.session_name,
+        id: &server_config.session_id,
+    };
+
+    let login_url = format!(
+        "{}/index.php?m=user&f=login&t=json&{}={}",
+        urls.root, session.name, session.id
+    );
+    info!("尝试使用旧版API登录到: {}/index.php?m=user&f=login", urls.root);
+
+    let request = client
+        .post(&login_url)
+        .header("Cookie", session.cookie())
+        .form(&[("account", account), ("password", password), ("keepLogin", "1")]);
+
+    let response = client
+        .send(request)
+        .await
+        .map_err(|e| format!("请求失败: {}", describe_request_error(&e)))?;
+    let status = response.status();
+    debug!("旧版API登录响应状态: {}", status);
+    if !status.is_success() {
+        return Err(format!("登录失败，状态码: {}", status));
+    }
+
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("读取响应内容失败: {}", e))?;
+    let json = serde_json::from_str::<Value>(&decode_unicode_escapes(&text))
+        .map_err(|_| format!("登录失败，响应不是JSON - 响应预览: {}", preview(&text)))?;
+
+    if json.get("status").and_then(Value::as_str) == Some("success") {
+        info!("旧版API登录成功，禅道版本: {}", server_config.version);
+        Ok((server_config.session_name, server_config.session_id))
+    } else {
+        let reason = json
+            .get("reason")
+            .and_then(Value::as_str)
+            .unwrap_or("账号或密码错误");
+        Err(format!("登录失败: {}", reason))
+    }
+}
+
+// 发送旧版API请求，返回解析后的 data 字段
+async fn get_data(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>, query: &str) -> Result<Value, String> {
+    let url = format!(
+        "{}/index.php?{}&t=json&{}={}",
+        urls.root, query, session.name, session.id
+    );
+    debug!("旧版API请求: {}/index.php?{}", urls.root, query);
+
+    let request = client
+        .get(&url)
+        .header("Cookie", session.cookie());
+    let response = client
+        .send(request)
+        .await
+        .map_err(|e| format!("请求失败: {}", describe_request_error(&e)))?;
+    let status = response.status();
+    if status == 401 || status == 403 {
+        return Err(UNAUTHORIZED.to_string());
+    }
+    if !status.is_success() {
+        return Err(format!("请求失败，状态码: {}", status));
+    }
+
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("读取响应内容失败: {}", e))?;
+    let decoded_text = decode_unicode_escapes(&text);
+
+    let Ok(json) = serde_json::from_str::<Value>(&decoded_text) else {
+        // 会话失效时旧版禅道返回跳转到登录页的HTML
+        if is_login_redirect(&text) {
+            warn!("旧版API会话已失效");
+            return Err(UNAUTHORIZED.to_string());
+        }
+        return Err(format!("响应不是JSON - 响应预览: {}", preview(&text)));
+    };
+
+    match json.get("status").and_then(Value::as_str) {
+        Some("success") => {}
+        Some(_) => {
+            let reason = json.get("reason").and_then(Value::as_str).unwrap_or_default();
+            if reason.is_empty() || is_login_redirect(reason) {
+                return Err(UNAUTHORIZED.to_string());
+            }
+            return Err(format!("请求失败: {}", reason));
+        }
+        // 部分版本直接返回数据
+        None => return Ok(json),
+    }
+
+    // data 字段通常是JSON字符串，需要再解析一次
+    match json.get("data") {
+        Some(Value::String(data)) => serde_json::from_str::<Value>(data)
+            .map_err(|e| format!("解析data字段失败: {} - 预览: {}", e, preview(data))),
+        Some(data) => Ok(data.clone()),
+        None => Err(format!("响应中没有找到data字段 - 响应预览: {}", preview(&text))),
+    }
+}
+
+fn is_login_redirect(text: &str) -> bool {
+    text.contains("user-login") || text.contains("m=user&f=login") || text.contains("m=user&amp;f=login")
+}
+
+fn preview(text: &str) -> String {
+    body_preview(text, 100)
+}
+
+// 获取当前用户信息
+pub async fn get_user_info(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>) -> Result<UserInfo, String> {
+    let data = get_data(client, urls, session, "m=my&f=profile").await?;
+    let user = data
+        .get("user")
+        .ok_or_else(|| "响应中没有找到user字段".to_string())?;
+    serde_json::from_value::<UserInfo>(convert_user_info(user))
+        .map_err(|e| format!("解析UserInfo失败: {}", e))
+}
+
+// 获取产品列表
+pub async fn get_products(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>) -> Result<Vec<Product>, String> {
+    let data = get_data(client, urls, session, "m=product&f=all").await?;
+    let users = users_map(&data);
+
+    // 12.x 起返回完整的 productStats，更早的版本只有 {id: name}
+    let products = if let Some(stats) = data.get("productStats") {
+        values_of(stats)
+            .into_iter()
+            .map(|raw| convert_product(raw, &users))
+            .collect::<Vec<_>>()
+    } else if let Some(Value::Object(names)) = data.get("products") {
+        names
+            .iter()
+            .map(|(id, name)| {
+                let mut raw = Map::new();
+                raw.insert("id".to_string(), Value::String(id.clone()));
+                raw.insert("name".to_string(), name.clone());
+                convert_product(&Value::Object(raw), &users)
+            })
+            .collect::<Vec<_>>()
+    } else {
+        return Err("响应中没有找到产品列表".to_string());
+    };
+
+    let products = products
+        .into_iter()
+        .map(|value| serde_json::from_value::<Product>(value).map_err(|e| format!("解析产品列表失败: {}", e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    debug!("旧版API解析到{}个产品", products.len());
+    Ok(products)
+}
+
+// 获取产品详情
+pub async fn get_product_detail(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>, product_id: i32) -> Result<Product, String> {
+    let query = format!("m=product&f=view&productID={}", product_id);
+    let data = get_data(client, urls, session, &query).await?;
+    let users = users_map(&data);
+    let product = data
+        .get("product")
+        .ok_or_else(|| "响应中没有找到product字段".to_string())?;
+    serde_json::from_value::<Product>(convert_product(product, &users))
+        .map_err(|e| format!("解析产品详情失败: {}", e))
+}
+
+// 获取产品的全部Bug
+pub async fn get_bugs(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>, product_id: i32) -> Result<Vec<Bug>, String> {
+    let query = format!(
+        "m=bug&f=browse&productID={}&branch=0&browseType=all&param=0&orderBy=id_desc",
+        product_id
+    );
+    let bugs = get_paged_bugs(client, urls, session, &query).await?;
+    debug!("旧版API产品{}解析到{}个Bug", product_id, bugs.len());
+    Ok(bugs)
+}
+
+// 获取"我的Bug"：bug_type 为 assignedTo、openedBy 或 resolvedBy
+pub async fn get_my_bugs(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>, bug_type: &str) -> Result<Vec<Bug>, String> {
+    let query = format!("m=my&f=bug&type={}&param=0&orderBy=id_desc", bug_type);
+    let bugs = get_paged_bugs(client, urls, session, &query).await?;
+    debug!("旧版API我的Bug({})解析到{}个Bug", bug_type, bugs.len());
+    Ok(bugs)
+}
+
+// Bug列表每页的数量
+const PAGE_SIZE: u64 = 500;
+// 防止异常分页信息导致无限循环
+const MAX_PAGES: u64 = 100;
+
+// 根据响应中的分页信息决定下一页，没有更多页面或达到页数上限时返回 None
+fn next_page(page: u64, data: &Value) -> Option<u64> {
+    let page_total = data
+        .get("pager")
+        .and_then(|pager| pager.get("pageTotal"))
+        .map(as_i64)
+        .unwrap_or(1)
+        .max(1) as u64;
+    (page < page_total && page < MAX_PAGES).then_some(page + 1)
+}
+
+// 按分页逐页读取Bug列表
+async fn get_paged_bugs(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>, query: &str) -> Result<Vec<Bug>, String> {
+    let mut bugs = Vec::new();
+    let mut page = 1;
+    loop {
+        let page_query = format!("{}&recTotal=0&recPerPage={}&pageID={}", query, PAGE_SIZE, page);
+        let data = get_data(client, urls, session, &page_query).await?;
+        let users = users_map(&data);
+
+        if let Some(raw_bugs) = data.get("bugs") {
+            for raw in values_of(raw_bugs) {
+                bugs.push(
+                    serde_json::from_value::<Bug>(convert_bug(raw, &users))
+                        .map_err(|e| format!("解析Bug列表失败: {}", e))?,
+                );
+            }
+        }
+
+        match next_page(page, &data) {
+            Some(next) => page = next,
+            None => break,
+        }
+    }
+    Ok(bugs)
+}
+
+// 获取Bug详情
+pub async fn get_bug_detail(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>, bug_id: i32) -> Result<Bug, String> {
+    let query = format!("m=bug&f=view&bugID={}", bug_id);
+    let data = get_data(client, urls, session, &query).await?;
+    let users = users_map(&data);
+    let bug = data
+        .get("bug")
+        .ok_or_else(|| "响应中没有找到bug字段".to_string())?;
+    let mut bug = convert_bug(bug, &users);
+    if let (Value::Object(bug), Some(actions)) = (&mut bug, data.get("actions")) {
+        let actions = values_of(actions).into_iter().map(|raw| convert_action(raw, &users)).collect();
+        bug.insert("actions".to_string(), Value::Array(actions));
+    }
+    serde_json::from_value::<Bug>(bug).map_err(|e| format!("解析Bug详情失败: {}", e))
+}
+
+// 读取附件（图片）：image_path 形如 index.php?m=file&f=read&t=png&fileID=1
+pub async fn read_file(client: &HttpClient, urls: &ServerUrls, session: &LegacySession<'_>, image_path: &str) -> Result<Vec<u8>, String> {
+    let separator = if image_path.contains('?') { '&' } else { '?' };
+    let file_url = format!(
+        "{}/{}{}{}={}",
+        urls.root,
+        image_path.trim_start_matches('/'),
+        separator,
+        session.name,
+        session.id
+    );
+    debug!("旧版API读取文件: {}/{}", urls.root, image_path);
+
+    let request = client
+        .get(&file_url)
+        .header("Cookie", session.cookie());
+    let response = client
+        .send(request)
+        .await
+        .map_err(|e| format!("请求失败: {}", describe_request_error(&e)))?;
+    let status = response.status();
+    if status == 401 || status == 403 {
+        return Err(UNAUTHORIZED.to_string());
+    }
+    if !status.is_success() {
+        return Err(format!("获取文件失败，状态码: {}", status));
+    }
+
+    // 会话失效时返回的是登录页而不是文件内容
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("读取文件数据失败: {}", e))?;
+    if is_html && is_login_redirect(&String::from_utf8_lossy(&bytes)) {
+        return Err(UNAUTHORIZED.to_string());
+    }
+    Ok(bytes.to_vec())
+}
+
+// ===== 旧版数据结构转换为 RESTful API 结构 =====
+
+// 数组或以ID为键的对象，统一取出其中的元素
+fn values_of(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(items) => items.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+// 账号到姓名的映射（旧版API在 data.users 中返回）
+fn users_map(data: &Value) -> Map<String, Value> {
+    match data.get("users") {
+        Some(Value::Object(users)) => users.clone(),
+        _ => Map::new(),
+    }
+}
+
+fn as_i64(value: &Value) -> i64 {
+    match value {
+        Value::Number(n) => n.as_i64().unwrap_or(0),
+        Value::String(s) => s.trim().parse().unwrap_or(0),
+        Value::Bool(b) => i64::from(*b),
+        _ => 0,
+    }
+}
+
+fn int_field(raw: &Value, key: &str) -> Value {
+    Value::from(raw.get(key).map(as_i64).unwrap_or(0))
+}
+
+// 可选的ID字段：0 或缺失时为 null
+fn optional_int_field(raw: &Value, key: &str) -> Value {
+    match raw.get(key).map(as_i64) {
+        Some(id) if id != 0 => Value::from(id),
+        _ => Value::Null,
+    }
+}
+
+fn string_field(raw: &Value, key: &str) -> Value {
+    match raw.get(key) {
+        Some(Value::String(s)) => Value::String(s.clone()),
+        Some(Value::Number(n)) => Value::String(n.to_string()),
+        _ => Value::String(String::new()),
+    }
+}
+
+fn optional_string_field(raw: &Value, key: &str) -> Value {
+    match raw.get(key) {
+        Some(Value::String(s)) if !s.is_empty() => Value::String(s.clone()),
+        _ => Value::Null,
+    }
+}
+
+// 旧版API的用户字段只有账号，转换为 User 对象
+fn user_field(raw: &Value, key: &str, users: &Map<String, Value>) -> Value {
+    match raw.get(key) {
+        Some(Value::String(account)) if !account.is_empty() => account_to_user(account, users),
+        Some(user @ Value::Object(_)) => user.clone(),
+        _ => Value::Null,
+    }
+}
+
+fn account_to_user(account: &str, users: &Map<String, Value>) -> Value {
+    let realname = users
+        .get(account)
+        .and_then(Value::as_str)
+        .unwrap_or(account);
+    serde_json::json!({
+        "id": 0,
+        "account": account,
+        "avatar": "",
+        "realname": realname,
+    })
+}
+
+// 旧版API的时间为服务器本地时间 "2024-01-02 03:04:05"，按中国时区转换为 RFC 3339
+fn datetime_field(raw: &Value, key: &str) -> Value {
+    let Some(value) = raw.get(key).and_then(Value::as_str) else {
+        return Value::Null;
+    };
+    if value.is_empty() || value.starts_with("0000-00-00") {
+        return Value::Null;
+    }
+    match chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+        Ok(naive) => Value::String(format!("{}+08:00", naive.format("%Y-%m-%dT%H:%M:%S"))),
+        Err(_) => Value::String(value.to_string()),
+    }
+}
+
+// 日期字段（如 deadline），空日期为 null
+fn date_field(raw: &Value, key: &str) -> Value {
+    match raw.get(key).and_then(Value::as_str) {
+        Some(value) if !value.is_empty() && !value.starts_with("0000-00-00") => Value::String(value.to_string()),
+        _ => Value::Null,
+    }
+}
+
+// 旧版 steps 中的图片为 <img src="{ID.ext}" />，补充与 RESTful API 相同的 alt 属性以便前端按需加载
+fn convert_steps(steps: &str) -> String {
+    let re = match Regex::new(r#"<img([^>]*?)src="\{(\d+)\.(\w+)\}"([^>]*?)/?>"#) {
+        Ok(regex) => regex,
+        Err(_) => return steps.to_string(),
+    };
+    re.replace_all(steps, |caps: &regex::Captures| {
+        let path = format!("index.php?m=file&amp;f=read&amp;t={}&amp;fileID={}", &caps[3], &caps[2]);
+        format!(r#"<img{}src="{}" alt="{}"{} />"#, &caps[1], path, path, caps[4].trim_end())
+    })
+    .to_string()
+}
+
+fn convert_bug(raw: &Value, users: &Map<String, Value>) -> Value {
+    let mut bug = Map::new();
+    for key in ["id", "product", "branch", "module", "project", "execution", "toTask", "toStory", "severity", "pri", "activatedCount"] {
+        bug.insert(key.to_string(), int_field(raw, key));
+    }
+    for key in ["task", "story"] {
+        bug.insert(key.to_string(), optional_int_field(raw, key));
+    }
+    for key in ["title", "keywords", "type", "os", "browser"] {
+        bug.insert(key.to_string(), string_field(raw, key));
+    }
+    let steps = raw.get("steps").and_then(Value::as_str).unwrap_or_default();
+    bug.insert("steps".to_string(), Value::String(convert_steps(steps)));
+    for key in ["openedBy", "assignedTo", "resolvedBy", "closedBy"] {
+        bug.insert(key.to_string(), user_field(raw, key, users));
+    }
+    // openedDate 为必填字段
+    let opened_date = match datetime_field(raw, "openedDate") {
+        Value::Null => Value::String(String::new()),
+        value => value,
+    };
+    bug.insert("openedDate".to_string(), opened_date);
+    for key in ["assignedDate", "resolvedDate", "closedDate"] {
+        bug.insert(key.to_string(), datetime_field(raw, key));
+    }
+    bug.insert("deadline".to_string(), date_field(raw, "deadline"));
+    bug.insert("status".to_string(), string_field(raw, "status"));
+    Value::Object(bug)
+}
+
+// 历史记录：操作人转换为姓名，时间转换为 RFC 3339
+fn convert_action(raw: &Value, users: &Map<String, Value>) -> Value {
+    let actor = raw.get("actor").and_then(Value::as_str).unwrap_or_default();
+    let realname = users.get(actor).and_then(Value::as_str).unwrap_or(actor);
+    let date = match datetime_field(raw, "date") {
+        Value::Null => Value::String(String::new()),
+        value => value,
+    };
+    let history = raw
+        .get("history")
+        .map(values_of)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|change| {
+            serde_json::json!({
+                "field": string_field(change, "field"),
+                "old": string_field(change, "old"),
+                "new": string_field(change, "new"),
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "actor": realname,
+        "action": string_field(raw, "action"),
+        "date": date,
+        "comment": string_field(raw, "comment"),
+        "history": history,
+    })
+}
+
+fn convert_product(raw: &Value, users: &Map<String, Value>) -> Value {
+    let mut product = Map::new();
+    for key in ["id", "program", "line"] {
+        product.insert(key.to_string(), int_field(raw, key));
+    }
+    for key in ["name", "code", "desc"] {
+        product.insert(key.to_string(), string_field(raw, key));
+    }
+    let product_type = raw.get("type").and_then(Value::as_str).filter(|s| !s.is_empty()).unwrap_or("normal");
+    product.insert("type".to_string(), Value::String(product_type.to_string()));
+    let acl = raw.get("acl").and_then(Value::as_str).filter(|s| !s.is_empty()).unwrap_or("open");
+    product.insert("acl".to_string(), Value::String(acl.to_string()));
+    for key in ["PO", "QD", "RD", "createdBy"] {
+        product.insert(key.to_string(), user_field(raw, key, users));
+    }
+    // 白名单为逗号分隔的账号
+    let whitelist = raw
+        .get("whitelist")
+        .and_then(Value::as_str)
+        .map(|accounts| {
+            accounts
+                .split(',')
+                .map(str::trim)
+                .filter(|account| !account.is_empty())
+                .map(|account| account_to_user(account, users))
+                .collect::<Vec<_>>()
+        })
+        .filter(|list| !list.is_empty())
+        .map(Value::Array)
+        .unwrap_or(Value::Null);
+    product.insert("whitelist".to_string(), whitelist);
+    let created_date = match datetime_field(raw, "createdDate") {
+        Value::Null => Value::String(String::new()),
+        value => value,
+    };
+    product.insert("createdDate".to_string(), created_date);
+    Value::Object(product)
+}
+
+fn convert_user_info(raw: &Value) -> Value {
+    let mut user = Map::new();
+    for key in ["id", "dept"] {
+        user.insert(key.to_string(), int_field(raw, key));
+    }
+    for key in ["account", "realname"] {
+        user.insert(key.to_string(), string_field(raw, key));
+    }
+    let user_type = raw.get("type").and_then(Value::as_str).filter(|s| !s.is_empty()).unwrap_or("inside");
+    user.insert("type".to_string(), Value::String(user_type.to_string()));
+    for key in ["nickname", "avatar", "birthday", "gender", "mobile", "phone", "weixin", "address"] {
+        user.insert(key.to_string(), optional_string_field(raw, key));
+    }
+    user.insert("join".to_string(), date_field(raw, "join"));
+    let admin = match raw.get("admin") {
+        Some(Value::Bool(admin)) => *admin,
+        Some(value) => as_i64(value) != 0 || value.as_str() == Some("super"),
+        None => false,
+    };
+    user.insert("admin".to_string(), Value::Bool(admin));
+    Value::Object(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 旧版禅道（11.x）m=bug&f=view 返回的 data 字段，数字和日期都是字符串
+    const BUG_VIEW: &str = r#"{
+        "title": "BUG #12 登录失败",
+        "users": {"admin": "管理员", "zhangsan": "张三", "closed": "Closed"},
+        "bug": {
+            "id": "12", "product": "3", "branch": "0", "module": "7", "project": "0", "execution": "",
+            "plan": "0", "story": "0", "storyVersion": "1", "task": "5", "toTask": "0", "toStory": "0",
+            "title": "登录失败", "keywords": "", "severity": "2", "pri": "3", "type": "codeerror",
+            "os": "", "browser": "", "steps": "<p>[步骤]</p><p><img src=\"{21.png}\" alt=\"\" /></p>",
+            "status": "active", "activatedCount": "1", "confirmed": "0",
+            "openedBy": "zhangsan", "openedDate": "2019-05-06 10:11:12",
+            "assignedTo": "admin", "assignedDate": "2019-05-06 10:11:12",
+            "deadline": "0000-00-00", "resolvedBy": "", "resolution": "", "resolvedDate": "0000-00-00 00:00:00",
+            "closedBy": "", "closedDate": "0000-00-00 00:00:00", "deleted": "0"
+        },
+        "actions": {
+            "31": {"id": "31", "objectType": "bug", "objectID": "12", "actor": "zhangsan", "action": "opened",
+                   "date": "2019-05-06 10:11:12", "comment": "", "extra": "", "history": []},
+            "35": {"id": "35", "objectType": "bug", "objectID": "12", "actor": "admin", "action": "edited",
+                   "date": "2019-05-07 09:00:00", "comment": "<p>补充截图</p>", "extra": "",
+                   "history": [{"id": "8", "action": "35", "field": "pri", "old": "2", "new": "3", "diff": ""}]}
+        }
+    }"#;
+
+    #[test]
+    fn converts_bug_view() {
+        let data = serde_json::from_str::<Value>(BUG_VIEW).unwrap();
+        let users = users_map(&data);
+        let bug = convert_bug(&data["bug"], &users);
+        assert_eq!(bug["id"], 12);
+        assert_eq!(bug["severity"], 2);
+        // 空字符串的数字字段为 0，可选ID为 0 时为 null
+        assert_eq!(bug["execution"], 0);
+        assert_eq!(bug["task"], 5);
+        assert_eq!(bug["story"], Value::Null);
+        assert_eq!(bug["openedBy"]["realname"], "张三");
+        assert_eq!(bug["openedDate"], "2019-05-06T10:11:12+08:00");
+        // 0000-00-00 日期和空用户为 null
+        assert_eq!(bug["deadline"], Value::Null);
+        assert_eq!(bug["resolvedDate"], Value::Null);
+        assert_eq!(bug["closedDate"], Value::Null);
+        assert_eq!(bug["resolvedBy"], Value::Null);
+        assert_eq!(bug["closedBy"], Value::Null);
+
+        let parsed = serde_json::from_value::<Bug>(bug).unwrap();
+        assert_eq!(parsed.status.code, "active");
+        assert_eq!(parsed.assigned_to.unwrap().realname, "管理员");
+        assert!(parsed.resolved_date.is_none());
+    }
+
+    #[test]
+    fn missing_opened_date_becomes_empty() {
+        let bug = convert_bug(&json!({"id": 1, "title": "t", "status": "active", "openedDate": "0000-00-00 00:00:00"}), &Map::new());
+        assert_eq!(bug["openedDate"], "");
+        // 不在 users 中的账号用账号作为姓名
+        let user = user_field(&json!({"openedBy": "lisi"}), "openedBy", &Map::new());
+        assert_eq!(user["realname"], "lisi");
+    }
+
+    #[test]
+    fn converts_step_images() {
+        assert_eq!(
+            convert_steps(r#"<p><img src="{21.png}" /></p>"#),
+            r#"<p><img src="index.php?m=file&amp;f=read&amp;t=png&amp;fileID=21" alt="index.php?m=file&amp;f=read&amp;t=png&amp;fileID=21" /></p>"#
+        );
+        assert_eq!(
+            convert_steps(r#"<img style="width:50%" src="{3.jpg}">"#),
+            r#"<img style="width:50%" src="index.php?m=file&amp;f=read&amp;t=jpg&amp;fileID=3" alt="index.php?m=file&amp;f=read&amp;t=jpg&amp;fileID=3" />"#
+        );
+        // 普通图片地址不变
+        assert_eq!(convert_steps(r#"<img src="http://example.com/a.png" />"#), r#"<img src="http://example.com/a.png" />"#);
+    }
+
+    #[test]
+    fn converts_actions() {
+        let data = serde_json::from_str::<Value>(BUG_VIEW).unwrap();
+        let users = users_map(&data);
+        let actions = values_of(&data["actions"]).into_iter().map(|raw| convert_action(raw, &users)).collect::<Vec<_>>();
+        assert_eq!(actions[0]["actor"], "张三");
+        assert_eq!(actions[0]["date"], "2019-05-06T10:11:12+08:00");
+        assert_eq!(actions[1]["actor"], "管理员");
+        assert_eq!(actions[1]["comment"], "<p>补充截图</p>");
+        assert_eq!(actions[1]["history"], json!([{"field": "pri", "old": "2", "new": "3"}]));
+
+        let raw = json!({"actor": "system", "action": "closed", "date": "0000-00-00 00:00:00"});
+        let action = convert_action(&raw, &users);
+        assert_eq!(action["actor"], "system");
+        assert_eq!(action["date"], "");
+        assert_eq!(action["history"], json!([]));
+    }
+
+    #[test]
+    fn converts_products() {
+        // m=product&f=all 中的 productStats
+        let raw = json!({
+            "id": "3", "program": "", "line": "0", "name": "商城", "code": "shop", "desc": null,
+            "type": "", "acl": "private", "PO": "admin", "QD": "", "RD": "zhangsan",
+            "createdBy": "admin", "createdDate": "2018-01-02 03:04:05", "whitelist": ",zhangsan, ,lisi"
+        });
+        let users = serde_json::from_str::<Value>(BUG_VIEW).map(|data| users_map(&data)).unwrap();
+        let product = convert_product(&raw, &users);
+        assert_eq!(product["id"], 3);
+        assert_eq!(product["program"], 0);
+        assert_eq!(product["desc"], "");
+        assert_eq!(product["type"], "normal");
+        assert_eq!(product["acl"], "private");
+        assert_eq!(product["PO"]["realname"], "管理员");
+        assert_eq!(product["QD"], Value::Null);
+        assert_eq!(product["createdDate"], "2018-01-02T03:04:05+08:00");
+        let whitelist = product["whitelist"].as_array().unwrap().iter().map(|user| user["account"].clone()).collect::<Vec<_>>();
+        assert_eq!(whitelist, vec![json!("zhangsan"), json!("lisi")]);
+        serde_json::from_value::<Product>(product).unwrap();
+
+        // 更早的版本只有名称
+        let product = convert_product(&json!({"id": "5", "name": "旧产品"}), &Map::new());
+        assert_eq!(product["whitelist"], Value::Null);
+        assert_eq!(product["acl"], "open");
+        assert_eq!(product["createdDate"], "");
+    }
+
+    #[test]
+    fn converts_user_info() {
+        // m=my&f=profile 中的 user
+        let raw = json!({
+            "id": "1", "dept": "0", "account": "admin", "realname": "管理员", "type": "", "nickname": "",
+            "avatar": "", "birthday": "0000-00-00", "gender": "m", "mobile": "", "join": "0000-00-00", "admin": "super"
+        });
+        let user = convert_user_info(&raw);
+        assert_eq!(user["id"], 1);
+        assert_eq!(user["type"], "inside");
+        assert_eq!(user["nickname"], Value::Null);
+        assert_eq!(user["gender"], "m");
+        assert_eq!(user["join"], Value::Null);
+        assert_eq!(user["admin"], true);
+        let info = serde_json::from_value::<UserInfo>(user).unwrap();
+        assert_eq!(info.account, "admin");
+
+        assert_eq!(convert_user_info(&json!({"admin": "0"}))["admin"], false);
+        assert_eq!(convert_user_info(&json!({"admin": true}))["admin"], true);
+        assert_eq!(convert_user_info(&json!({}))["admin"], false);
+    }
+
+    #[test]
+    fn paging_stops_at_last_page_or_limit() {
+        let pager = |total: Value| json!({"pager": {"recTotal": "1200", "recPerPage": "500", "pageTotal": total}});
+        assert_eq!(next_page(1, &pager(json!("3"))), Some(2));
+        assert_eq!(next_page(2, &pager(json!(3))), Some(3));
+        assert_eq!(next_page(3, &pager(json!("3"))), None);
+        // 没有分页信息或分页信息异常时只读一页
+        assert_eq!(next_page(1, &json!({"bugs": []})), None);
+        assert_eq!(next_page(1, &pager(json!("0"))), None);
+        assert_eq!(next_page(1, &pager(json!("abc"))), None);
+        // 页数过多时在 MAX_PAGES 停止
+        assert_eq!(next_page(MAX_PAGES - 1, &pager(json!(100_000))), Some(MAX_PAGES));
+        assert_eq!(next_page(MAX_PAGES, &pager(json!(100_000))), None);
+    }
+
+    #[test]
+    fn detects_login_redirects() {
+        assert!(is_login_redirect("<html><script>location='/zentao/user-login.html'</script>"));
+        assert!(is_login_redirect("index.php?m=user&amp;f=login"));
+        assert!(!is_login_redirect("{\"status\":\"success\"}"));
+    }
+}
@@ -0,0 +1,26 @@
+// 禅道核心库：数据模型、HTTP客户端、API调用、配置和token存储
+// 桌面应用（standalone/tauri-app）和命令行工具共用这里的逻辑
+pub mod annotation;
+pub mod bug_report;
+pub mod client;
+pub mod config;
+pub mod diff;
+pub mod duplicate;
+pub mod export;
+pub mod git;
+pub mod git_template;
+pub mod html;
+pub mod http;
+pub mod legacy;
+pub mod link;
+pub mod markdown;
+pub mod models;
+pub mod query;
+pub mod redact;
+pub mod report;
+pub mod search;
+pub mod server;
+pub mod snapshot;
+pub mod token;
+pub mod view;
+pub mod watch;
@@ -0,0 +1,208 @@
+// 链接解析：应用自己的 zentao-viewer:// 链接，以及从禅道网页复制的Bug、产品链接
+// 禅道网页链接有两种写法：PATH_INFO 风格的 bug-view-123.html，和 GET 风格的 index.php?m=bug&f=view&bugID=123；
+// 另外也识别 RESTful API 的 api.php/v1/bugs/123
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::server;
+
+// 应用注册的URL协议
+pub const APP_SCHEME: &str = "zentao-viewer";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Bug,
+    Product,
+}
+
+// 链接指向的对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkTarget {
+    pub kind: LinkKind,
+    pub id: i32,
+}
+
+impl LinkTarget {
+    // 对应的 zentao-viewer:// 链接
+    pub fn app_link(&self) -> String {
+        let kind = match self.kind {
+            LinkKind::Bug => "bug",
+            LinkKind::Product => "product",
+        };
+        format!("{}://{}/{}", APP_SCHEME, kind, self.id)
+    }
+}
+
+fn parse_id(text: &str) -> Option<i32> {
+    text.trim().parse::<i32>().ok().filter(|id| *id > 0)
+}
+
+// 模块、方法和第一个参数对应的对象：Bug的各种操作页面指向Bug，Bug列表和产品页面指向产品
+fn target_of(module: &str, method: &str, param: &str) -> Option<LinkTarget> {
+    let id = parse_id(param)?;
+    let kind = match (module.to_ascii_lowercase().as_str(), method.to_ascii_lowercase().as_str()) {
+        ("bug", "browse" | "report" | "create" | "batchcreate") => LinkKind::Product,
+        ("bug", _) => LinkKind::Bug,
+        ("product", _) => LinkKind::Product,
+        _ => return None,
+    };
+    Some(LinkTarget { kind, id })
+}
+
+// 解析 zentao-viewer://bug/123 或 zentao-viewer://product/5，不是应用链接时返回 None
+pub fn parse_app_link(link: &str) -> Option<LinkTarget> {
+    let rest = link.trim().strip_prefix(APP_SCHEME)?.strip_prefix("://")?;
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let mut segments = rest.split('/').filter(|segment| !segment.is_empty());
+    let kind = match segments.next()?.to_ascii_lowercase().as_str() {
+        "bug" | "bugs" => LinkKind::Bug,
+        "product" | "products" => LinkKind::Product,
+        _ => return None,
+    };
+    let id = parse_id(segments.next()?)?;
+    Some(LinkTarget { kind, id })
+}
+
+// PATH_INFO 风格：最后一段为 模块-方法-参数1-参数2….html
+fn parse_path_info(url: &Url) -> Option<LinkTarget> {
+    let segment = url.path_segments()?.rfind(|segment| !segment.is_empty())?;
+    let name = segment
+        .strip_suffix(".html")
+        .or_else(|| segment.strip_suffix(".json"))
+        .unwrap_or(segment);
+    let mut parts = name.split('-');
+    target_of(parts.next()?, parts.next()?, parts.next()?)
+}
+
+// GET 风格：index.php?m=bug&f=view&bugID=123
+fn parse_query(url: &Url) -> Option<LinkTarget> {
+    let param = |names: &[&str]| {
+        url.query_pairs()
+            .find(|(key, _)| names.iter().any(|name| key.eq_ignore_ascii_case(name)))
+            .map(|(_, value)| value.into_owned())
+    };
+    let module = param(&["m"])?;
+    let method = param(&["f"]).unwrap_or_default();
+    // bugID 参数只出现在指向Bug的页面，Bug列表使用 productID
+    if module.eq_ignore_ascii_case("bug") && let Some(id) = param(&["bugID"]) {
+        return parse_id(&id).map(|id| LinkTarget { kind: LinkKind::Bug, id });
+    }
+    target_of(&module, &method, &param(&["productID", "id"])?)
+}
+
+// RESTful API：api.php/v1/bugs/123、api.php/v1/products/5
+fn parse_api_path(url: &Url) -> Option<LinkTarget> {
+    let segments = url.path_segments()?.filter(|segment| !segment.is_empty()).collect::<Vec<_>>();
+    let api = segments.iter().position(|segment| *segment == "api.php")?;
+    let (collection, id) = match &segments[api + 1..] {
+        [_version, collection, id, ..] => (*collection, *id),
+        _ => return None,
+    };
+    let kind = match collection {
+        "bugs" => LinkKind::Bug,
+        "products" => LinkKind::Product,
+        _ => return None,
+    };
+    Some(LinkTarget { kind, id: parse_id(id)? })
+}
+
+// 把粘贴的链接解析为Bug或产品。网页链接必须属于当前配置的服务器（比较主机名和端口，不区分 http/https）
+pub fn parse_zentao_url(link: &str, base_url: &str) -> Result<LinkTarget, String> {
+    let link = link.trim();
+    if link.is_empty() {
+        return Err("链接不能为空".to_string());
+    }
+    if link.starts_with(APP_SCHEME) {
+        return parse_app_link(link).ok_or_else(|| format!("无法识别的链接: {}", link));
+    }
+
+    let with_scheme = if link.contains("://") { link.to_string() } else { format!("http://{}", link) };
+    let url = Url::parse(&with_scheme).map_err(|e| format!("链接无效: {} - {}", link, e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("不支持的链接协议: {}", url.scheme()));
+    }
+    let root = server::normalize_base_url(base_url)?.root;
+    let root = Url::parse(&root).map_err(|e| format!("服务器地址无效: {} - {}", root, e))?;
+    let same_host = url.host_str().zip(root.host_str()).is_some_and(|(a, b)| a.eq_ignore_ascii_case(b));
+    if !same_host || url.port() != root.port() {
+        return Err(format!("链接不属于当前服务器 {}", root.authority()));
+    }
+
+    parse_api_path(&url)
+        .or_else(|| parse_query(&url))
+        .or_else(|| parse_path_info(&url))
+        .ok_or_else(|| format!("链接不是Bug或产品页面: {}", link))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "http://zentao.example.com:8080/zentao/";
+
+    fn bug(id: i32) -> LinkTarget {
+        LinkTarget { kind: LinkKind::Bug, id }
+    }
+
+    fn product(id: i32) -> LinkTarget {
+        LinkTarget { kind: LinkKind::Product, id }
+    }
+
+    #[test]
+    fn parses_app_links() {
+        assert_eq!(parse_app_link("zentao-viewer://bug/123"), Some(bug(123)));
+        assert_eq!(parse_app_link(" zentao-viewer://products/5/?from=mail#top "), Some(product(5)));
+        assert_eq!(parse_app_link("zentao-viewer://story/5"), None);
+        assert_eq!(parse_app_link("zentao-viewer://bug/0"), None);
+        assert_eq!(parse_app_link("http://zentao-viewer/bug/1"), None);
+        assert_eq!(bug(7).app_link(), "zentao-viewer://bug/7");
+        assert_eq!(parse_app_link(&product(9).app_link()), Some(product(9)));
+    }
+
+    #[test]
+    fn parses_zentao_page_links() {
+        let cases = [
+            ("http://zentao.example.com:8080/zentao/bug-view-123.html", bug(123)),
+            ("http://zentao.example.com:8080/zentao/bug-resolve-8-0.html", bug(8)),
+            ("http://zentao.example.com:8080/zentao/index.php?m=bug&f=view&bugID=123", bug(123)),
+            ("http://zentao.example.com:8080/zentao/bug-browse-5.html", product(5)),
+            ("http://zentao.example.com:8080/zentao/index.php?m=bug&f=browse&productID=5", product(5)),
+            ("http://zentao.example.com:8080/zentao/product-view-3.html", product(3)),
+            ("http://zentao.example.com:8080/zentao/api.php/v1/bugs/9", bug(9)),
+            ("http://zentao.example.com:8080/zentao/api.php/v1/products/2", product(2)),
+            // 不区分 http/https
+            ("https://zentao.example.com:8080/zentao/bug-view-1.html", bug(1)),
+        ];
+        for (link, expected) in cases {
+            assert_eq!(parse_zentao_url(link, BASE), Ok(expected), "{}", link);
+        }
+    }
+
+    #[test]
+    fn accepts_links_without_scheme() {
+        assert_eq!(parse_zentao_url("zentao.example.com:8080/zentao/bug-view-42.html", BASE), Ok(bug(42)));
+        assert_eq!(parse_zentao_url("zentao-viewer://bug/42", BASE), Ok(bug(42)));
+    }
+
+    #[test]
+    fn rejects_links_from_other_servers() {
+        for link in [
+            "http://other.example.com:8080/zentao/bug-view-1.html",
+            "http://zentao.example.com:9090/zentao/bug-view-1.html",
+            "http://zentao.example.com/zentao/bug-view-1.html",
+        ] {
+            let error = parse_zentao_url(link, BASE).unwrap_err();
+            assert!(error.contains("不属于当前服务器"), "{}: {}", link, error);
+        }
+    }
+
+    #[test]
+    fn rejects_unrecognized_links() {
+        assert!(parse_zentao_url("", BASE).is_err());
+        assert!(parse_zentao_url("ftp://zentao.example.com:8080/bug-view-1.html", BASE).is_err());
+        assert!(parse_zentao_url("http://zentao.example.com:8080/zentao/story-view-1.html", BASE).is_err());
+        assert!(parse_zentao_url("http://zentao.example.com:8080/zentao/my/", BASE).is_err());
+        assert!(parse_zentao_url("zentao-viewer://task/1", BASE).is_err());
+    }
+}
@@ -0,0 +1,497 @@
+// 重现步骤等富文本转换为 Markdown 和纯文本，用于聊天工具、命令行和导出
+// 支持标题、段落、换行、粗体、斜体、删除线、行内代码、代码块、有序和无序列表（可以嵌套）、表格、链接和图片；
+// 其他标签只保留文字。禅道的图片附件由调用方决定改写为服务器上的完整地址还是本地文件路径
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use crate::html::{decode_entities, remove_scripts};
+
+// 注释、开始或结束标签、文字
+static TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->|<(/?)([a-zA-Z][a-zA-Z0-9]*)((?:[^>\x22']|\x22[^\x22]*\x22|'[^']*')*)>|[^<]+|<").unwrap());
+static ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([a-zA-Z_:][-a-zA-Z0-9_:.]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>/]+))"#).unwrap());
+static WHITESPACE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
+static BLANK_LINES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n{3,}").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Markdown,
+    Text,
+}
+
+// 转换为 Markdown；image_url 接收图片附件的路径（相对于禅道根地址），返回图片在 Markdown 中的地址
+pub fn html_to_markdown(html: &str, image_url: impl Fn(&str) -> String) -> String {
+    Converter::new(Style::Markdown, &image_url).convert(html)
+}
+
+// 转换为纯文本：保留列表编号和表格的行列，链接和图片在文字后面附上地址
+pub fn html_to_plain_text(html: &str, image_url: impl Fn(&str) -> String) -> String {
+    Converter::new(Style::Text, &image_url).convert(html)
+}
+
+// 重现步骤转换的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepsFormat {
+    #[default]
+    Markdown,
+    Text,
+}
+
+// 转换重现步骤，图片附件指向禅道服务器，root 为禅道根地址
+pub fn convert_steps(html: &str, root: &str, format: StepsFormat) -> String {
+    let image_url = |path: &str| attachment_url(root, path);
+    match format {
+        StepsFormat::Markdown => html_to_markdown(html, image_url),
+        StepsFormat::Text => html_to_plain_text(html, image_url),
+    }
+}
+
+// 图片附件在服务器上的完整地址，root 为禅道根地址
+pub fn attachment_url(root: &str, path: &str) -> String {
+    format!("{}/{}", root.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    ATTRIBUTE
+        .captures_iter(attributes)
+        .find(|caps| caps[1].eq_ignore_ascii_case(name))
+        .and_then(|caps| caps.get(2).or_else(|| caps.get(3)).or_else(|| caps.get(4)))
+        .map(|value| decode_entities(value.as_str()))
+}
+
+struct List {
+    ordered: bool,
+    next: usize,
+}
+
+// 正在转换的链接：地址、链接文字在输出中的起始位置，以及开始时所在的输出缓冲区层数
+struct Link {
+    href: String,
+    start: usize,
+    depth: usize,
+}
+
+// 表格的每一行，Markdown 中第一行作为表头
+#[derive(Default)]
+struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+struct Converter<'a> {
+    style: Style,
+    image_url: &'a dyn Fn(&str) -> String,
+    // 输出缓冲区：表格的每个单元格单独转换，转换完成后放入表格
+    buffers: Vec<String>,
+    lists: Vec<List>,
+    links: Vec<Link>,
+    tables: Vec<Table>,
+    pre: bool,
+}
+
+impl<'a> Converter<'a> {
+    fn new(style: Style, image_url: &'a dyn Fn(&str) -> String) -> Self {
+        Converter {
+            style,
+            image_url,
+            buffers: vec![String::new()],
+            lists: Vec::new(),
+            links: Vec::new(),
+            tables: Vec::new(),
+            pre: false,
+        }
+    }
+
+    fn markdown(&self) -> bool {
+        self.style == Style::Markdown
+    }
+
+    fn out(&mut self) -> &mut String {
+        self.buffers.last_mut().expect("至少有一个输出缓冲区")
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out().push_str(text);
+    }
+
+    // 只在 Markdown 中输出的标记
+    fn mark(&mut self, text: &str) {
+        if self.markdown() {
+            self.push(text);
+        }
+    }
+
+    fn in_cell(&self) -> bool {
+        self.buffers.len() > 1
+    }
+
+    // 换行；单元格中的换行用空格代替
+    fn newline(&mut self) {
+        if self.in_cell() {
+            self.push(" ");
+        } else if !self.out().is_empty() && !self.out().ends_with('\n') {
+            self.push("\n");
+        }
+    }
+
+    // 当前行只有列表项的标记，如“- ”“2. ”
+    fn at_list_marker(&mut self) -> bool {
+        let line = self.out().rsplit('\n').next().unwrap_or_default().trim();
+        line == "-" || line.strip_suffix('.').is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    // 块级元素前后空一行（纯文本只换行）；列表项中的段落只换行
+    fn block(&mut self) {
+        if !self.lists.is_empty() {
+            if !self.at_list_marker() {
+                self.newline();
+            }
+            return;
+        }
+        self.newline();
+        if self.markdown() && !self.in_cell() && !self.out().is_empty() && !self.out().ends_with("\n\n") {
+            self.push("\n");
+        }
+    }
+
+    // 行首或空格之后
+    fn after_space(&mut self) -> bool {
+        let out = self.out();
+        out.is_empty() || out.ends_with('\n') || out.ends_with(' ')
+    }
+
+    fn text(&mut self, raw: &str) {
+        let text = decode_entities(raw);
+        if self.pre {
+            self.push(&text);
+            return;
+        }
+        let text = WHITESPACE.replace_all(&text, " ");
+        // 行首不输出空格，避免被当成缩进
+        let text = if self.after_space() { text.trim_start() } else { &text };
+        if text.is_empty() {
+            return;
+        }
+        let text = if self.in_cell() && self.markdown() { text.replace('|', "\\|") } else { text.to_string() };
+        self.push(&text);
+    }
+
+    fn convert(mut self, html: &str) -> String {
+        let html = remove_scripts(html);
+        for caps in TOKEN.captures_iter(&html) {
+            let token = &caps[0];
+            if token.starts_with("<!--") {
+                continue;
+            }
+            match caps.get(2) {
+                Some(tag) => {
+                    let tag = tag.as_str().to_ascii_lowercase();
+                    let attributes = caps.get(3).map_or("", |m| m.as_str());
+                    if caps[1].is_empty() {
+                        self.open(&tag, attributes);
+                    } else {
+                        self.close(&tag);
+                    }
+                }
+                None => self.text(token),
+            }
+        }
+        // 没有闭合的表格也输出
+        while !self.tables.is_empty() {
+            self.close("table");
+        }
+        self.finish()
+    }
+
+    fn finish(mut self) -> String {
+        let out = self.buffers.swap_remove(0);
+        let lines = out.lines().map(str::trim_end);
+        let out = if self.markdown() {
+            BLANK_LINES.replace_all(&lines.collect::<Vec<_>>().join("\n"), "\n\n").into_owned()
+        } else {
+            lines.filter(|line| !line.trim().is_empty()).collect::<Vec<_>>().join("\n")
+        };
+        out.trim().to_string()
+    }
+
+    fn open(&mut self, tag: &str, attributes: &str) {
+        match tag {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block();
+                if self.markdown() && !self.in_cell() {
+                    let level = tag[1..].parse::<usize>().unwrap_or(1);
+                    self.push(&format!("{} ", "#".repeat(level)));
+                }
+            }
+            "p" | "div" | "blockquote" | "section" | "article" => self.block(),
+            "br" => self.newline(),
+            "hr" => {
+                self.block();
+                self.mark("---");
+                self.block();
+            }
+            "strong" | "b" => self.mark("**"),
+            "em" | "i" => self.mark("*"),
+            "del" | "s" | "strike" => self.mark("~~"),
+            "code" if !self.pre => self.mark("`"),
+            "pre" => {
+                self.block();
+                self.mark("```\n");
+                self.pre = true;
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block();
+                } else {
+                    self.newline();
+                }
+                let start = attribute(attributes, "start").and_then(|start| start.parse().ok()).unwrap_or(1);
+                self.lists.push(List { ordered: tag == "ol", next: start });
+            }
+            "li" => {
+                self.newline();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(list) if list.ordered => {
+                        list.next += 1;
+                        format!("{}. ", list.next - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.push(&format!("{}{}", "  ".repeat(depth), marker));
+            }
+            "a" => {
+                let href = attribute(attributes, "href").unwrap_or_default();
+                let start = self.out().len();
+                let depth = self.buffers.len();
+                self.links.push(Link { href, start, depth });
+            }
+            "img" => self.image(attributes),
+            "table" => {
+                self.block();
+                self.tables.push(Table::default());
+            }
+            "tr" => {
+                if let Some(table) = self.tables.last_mut() {
+                    table.rows.push(Vec::new());
+                }
+            }
+            "td" | "th" => {
+                if let Some(table) = self.tables.last_mut() {
+                    if table.rows.is_empty() {
+                        table.rows.push(Vec::new());
+                    }
+                    self.buffers.push(String::new());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, tag: &str) {
+        match tag {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "div" | "blockquote" | "section" | "article" => self.block(),
+            "strong" | "b" => self.mark("**"),
+            "em" | "i" => self.mark("*"),
+            "del" | "s" | "strike" => self.mark("~~"),
+            "code" if !self.pre => self.mark("`"),
+            "pre" if self.pre => {
+                self.pre = false;
+                self.newline();
+                self.mark("```");
+                self.block();
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block();
+                }
+            }
+            "a" => self.close_link(),
+            "td" | "th" => self.close_cell(),
+            "table" => self.close_table(),
+            _ => {}
+        }
+    }
+
+    fn close_link(&mut self) {
+        // 跨越单元格的链接（标签嵌套错误）只保留文字：start 是另一个缓冲区中的位置
+        if self.links.last().is_none_or(|link| link.depth != self.buffers.len()) {
+            return;
+        }
+        let Some(link) = self.links.pop() else {
+            return;
+        };
+        let out = self.out();
+        if link.start > out.len() || link.href.is_empty() || link.href.starts_with("javascript:") {
+            return;
+        }
+        let text = out.split_off(link.start);
+        let text = text.trim();
+        let converted = match self.style {
+            Style::Markdown if text.is_empty() => format!("<{}>", link.href),
+            Style::Markdown => format!("[{}]({})", text, link.href),
+            Style::Text if text.is_empty() || text == link.href => link.href,
+            Style::Text => format!("{} ({})", text, link.href),
+        };
+        self.push(&converted);
+    }
+
+    fn close_cell(&mut self) {
+        if !self.in_cell() || self.tables.is_empty() {
+            return;
+        }
+        let cell = self.buffers.pop().unwrap_or_default();
+        // 单元格中没有闭合的链接随单元格结束
+        let depth = self.buffers.len();
+        self.links.retain(|link| link.depth <= depth);
+        let cell = WHITESPACE.replace_all(cell.trim(), " ").into_owned();
+        if let Some(row) = self.tables.last_mut().and_then(|table| table.rows.last_mut()) {
+            row.push(cell);
+        }
+    }
+
+    fn close_table(&mut self) {
+        if self.tables.is_empty() {
+            return;
+        }
+        // 没有闭合的单元格
+        while self.buffers.len() > self.tables.len() {
+            self.close_cell();
+        }
+        let Some(table) = self.tables.pop() else {
+            return;
+        };
+        let rows = table.rows.into_iter().filter(|row| !row.is_empty()).collect::<Vec<_>>();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let mut lines = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(columns, String::new());
+            match self.style {
+                Style::Markdown => {
+                    lines.push(format!("| {} |", cells.join(" | ")));
+                    if i == 0 {
+                        lines.push(format!("|{}", " --- |".repeat(columns)));
+                    }
+                }
+                Style::Text => lines.push(cells.join(" | ")),
+            }
+        }
+        self.block();
+        let table = lines.join("\n");
+        if self.in_cell() {
+            // 嵌套在单元格中的表格只保留文字
+            self.push(&table.replace('\n', " "));
+        } else {
+            self.push(&table);
+            self.block();
+        }
+    }
+
+    fn image(&mut self, attributes: &str) {
+        // 禅道在 alt 中保存附件路径，src 可能是 {1.png} 这样的占位符
+        let alt = attribute(attributes, "alt").unwrap_or_default();
+        let url = if alt.contains("index.php?m=file") {
+            (self.image_url)(&alt)
+        } else {
+            attribute(attributes, "src").unwrap_or_default()
+        };
+        // 内嵌的 data URL 太长，不输出
+        let url = if url.starts_with("data:") { String::new() } else { url };
+        let image = match (self.style, url.is_empty()) {
+            (Style::Markdown, false) => format!("![图片]({})", url.replace(' ', "%20")),
+            (Style::Markdown, true) | (Style::Text, true) => "[图片]".to_string(),
+            (Style::Text, false) => format!("[图片: {}]", url),
+        };
+        self.push(&image);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "http://zentao.example.com/zentao";
+
+    fn markdown(html: &str) -> String {
+        convert_steps(html, ROOT, StepsFormat::Markdown)
+    }
+
+    fn text(html: &str) -> String {
+        convert_steps(html, ROOT, StepsFormat::Text)
+    }
+
+    #[test]
+    fn headings_and_inline_styles() {
+        assert_eq!(markdown("<h2>标题</h2><p>正文 <strong>粗体</strong> <em>斜体</em> <code>a|b</code></p>"), "## 标题\n\n正文 **粗体** *斜体* `a|b`");
+        assert_eq!(text("<h2>标题</h2><p>正文 <strong>粗体</strong></p>"), "标题\n正文 粗体");
+        assert_eq!(markdown("<p>a &amp; b&nbsp;c<br>d</p><!-- 注释 --><script>alert(1)</script>"), "a & b c\nd");
+    }
+
+    #[test]
+    fn nested_lists() {
+        let html = "<ol start=\"3\"><li>第一步<ul><li>子项</li><li>子项2</li></ul></li><li><p>第二步</p></li></ol><p>之后</p>";
+        assert_eq!(markdown(html), "3. 第一步\n  - 子项\n  - 子项2\n4. 第二步\n\n之后");
+        assert_eq!(text(html), "3. 第一步\n  - 子项\n  - 子项2\n4. 第二步\n之后");
+    }
+
+    #[test]
+    fn tables() {
+        let html = "<table><tr><th>字段</th><th>值</th></tr><tr><td>a|b</td><td><p>多行</p><p>内容</p></td></tr><tr><td>只有一列</td></tr></table>";
+        assert_eq!(markdown(html), "| 字段 | 值 |\n| --- | --- |\n| a\\|b | 多行 内容 |\n| 只有一列 |  |");
+        assert_eq!(text(html), "字段 | 值\na|b | 多行 内容\n只有一列 |");
+        // 没有闭合的表格也输出
+        assert_eq!(markdown("<table><tr><td>x"), "| x |\n| --- |");
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(markdown("<p>见 <a href=\"http://u/1\">文档</a></p>"), "见 [文档](http://u/1)");
+        assert_eq!(markdown("<a href=\"http://u\"></a>"), "<http://u>");
+        assert_eq!(markdown("<a href=\"javascript:void(0)\">点击</a>"), "点击");
+        assert_eq!(text("<a href=\"http://u/1\">文档</a> <a href=\"http://u\">http://u</a>"), "文档 (http://u/1) http://u");
+    }
+
+    #[test]
+    fn images() {
+        let html = "<p><img src=\"{1.png}\" alt=\"/zentao/index.php?m=file&amp;f=read&amp;t=png&amp;fileID=1\" /></p>";
+        assert_eq!(
+            markdown(html),
+            "![图片](http://zentao.example.com/zentao/zentao/index.php?m=file&f=read&t=png&fileID=1)"
+        );
+        assert_eq!(markdown("<img src=\"http://x/a b.png\">"), "![图片](http://x/a%20b.png)");
+        assert_eq!(markdown("<img src=\"data:image/png;base64,AAAA\">"), "[图片]");
+        assert_eq!(text("<img src=\"http://x/a.png\">"), "[图片: http://x/a.png]");
+        assert_eq!(attachment_url("http://z/", "/file.png"), "http://z/file.png");
+    }
+
+    #[test]
+    fn code_blocks_keep_whitespace() {
+        assert_eq!(markdown("<pre>fn main() {\n    x();\n}</pre>"), "```\nfn main() {\n    x();\n}\n```");
+    }
+
+    #[test]
+    fn malformed_html_does_not_panic() {
+        // 链接跨越两个单元格，结束标签在另一个缓冲区中
+        let html = "<table><tr><td>x<a href=\"http://u\">y</td><td>中文中文</a></td></tr></table>";
+        assert_eq!(markdown(html), "| xy | 中文中文 |\n| --- | --- |");
+        assert_eq!(text(html), "xy | 中文中文");
+        for html in [
+            "</a></td></tr></table></ul></pre>",
+            "<a href=\"http://u\"><table><tr><td>中文</a>",
+            "<td>孤立的单元格</td>",
+            "<ul><li>未闭合<ol><li>列表",
+            "<p>不完整的标签 <a href=\"x",
+            "a < b",
+        ] {
+            let _ = markdown(html);
+            let _ = text(html);
+        }
+    }
+}
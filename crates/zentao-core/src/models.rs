@@ -0,0 +1,503 @@
+use serde::{Deserialize, Serialize, Deserializer};
+use serde_json::Value;
+use std::fmt;
+use chrono::{DateTime, Utc, FixedOffset, NaiveDate};
+use tracing::{info, error, debug};
+
+use crate::markdown;
+use crate::redact::MASK;
+use crate::server;
+
+// Unicode 解码函数：处理 JSON 中的 Unicode 转义序列
+pub fn decode_unicode_escapes(input: &str) -> String {
+    // 使用正则表达式处理 Unicode 转义序列
+    use regex::Regex;
+    
+    let re = match Regex::new(r"\\u([0-9a-fA-F]{4})") {
+        Ok(regex) => regex,
+        Err(e) => {
+            error!("创建Unicode解码正则表达式失败: {}", e);
+            return input.to_string();
+        }
+    };
+    
+    let result = re.replace_all(input, |caps: &regex::Captures| {
+        let hex_str = &caps[1];
+        if let Ok(code_point) = u32::from_str_radix(hex_str, 16) {
+            if let Some(unicode_char) = std::char::from_u32(code_point) {
+                unicode_char.to_string()
+            } else {
+                caps[0].to_string() // 保留原始字符
+            }
+        } else {
+            caps[0].to_string() // 保留原始字符
+        }
+    });
+    
+    result.to_string()
+}
+
+// 自定义反序列化函数来处理status字段
+fn deserialize_status<'de, D>(deserializer: D) -> Result<BugStatus, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    match value {
+        Value::String(s) => Ok(BugStatus {
+            code: s.clone(),
+            name: s,
+        }),
+        Value::Object(obj) => {
+            let code = obj.get("code")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+            let name = obj.get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(&code)
+                .to_string();
+            Ok(BugStatus { code, name })
+        }
+        _ => Ok(BugStatus {
+            code: "unknown".to_string(),
+            name: "未知".to_string(),
+        }),
+    }
+}
+
+// 时间格式化函数：将ISO 8601时间转换为中国时间显示
+pub fn format_datetime_to_china(datetime_str: &str) -> String {
+    if datetime_str.is_empty() {
+        return "未知".to_string();
+    }
+    
+    match DateTime::parse_from_rfc3339(datetime_str) {
+        Ok(dt) => {
+            // 转换为中国时区 (UTC+8)
+            let china_offset = FixedOffset::east_opt(8 * 3600).unwrap();
+            let china_time = dt.with_timezone(&china_offset);
+            let formatted = china_time.format("%Y-%m-%d %H:%M:%S").to_string();
+            debug!("时间格式化: {} -> {}", datetime_str, formatted);
+            formatted
+        }
+        Err(_) => {
+            // 如果解析失败，尝试直接解析UTC时间
+            match datetime_str.parse::<DateTime<Utc>>() {
+                Ok(utc_dt) => {
+                    let china_offset = FixedOffset::east_opt(8 * 3600).unwrap();
+                    let china_time = utc_dt.with_timezone(&china_offset);
+                    let formatted = china_time.format("%Y-%m-%d %H:%M:%S").to_string();
+                    debug!("时间格式化(UTC): {} -> {}", datetime_str, formatted);
+                    formatted
+                }
+                Err(e) => {
+                    error!("时间解析失败: {} - {}", datetime_str, e);
+                    datetime_str.to_string() // 如果都失败，返回原字符串
+                }
+            }
+        }
+    }
+}
+
+// 解析Bug中的时间（RFC 3339）并转换为中国时区，空值或格式不对时返回 None
+pub fn parse_bug_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
+    let china_offset = FixedOffset::east_opt(8 * 3600)?;
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&china_offset))
+}
+
+// Bug时间对应的日期（中国时区），也支持截止日期这样的 YYYY-MM-DD
+pub fn parse_bug_date(value: &str) -> Option<NaiveDate> {
+    if let Some(datetime) = parse_bug_datetime(value) {
+        return Some(datetime.date_naive());
+    }
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+// 处理HTML中的图片路径，提取图片路径信息以便前端按需加载
+pub fn process_html_images(html_content: &str, _base_url: &str) -> String {
+    use regex::Regex;
+    
+    debug!("开始处理HTML图片，内容长度: {}", html_content.len());
+    debug!(
+        "HTML内容预览: {}",
+        if html_content.chars().count() > 100 {
+            html_content.chars().take(100).collect::<String>()
+        } else {
+            html_content.to_string()
+        }
+    );
+    
+    // 创建正则表达式来匹配img标签，处理HTML实体编码
+    // 匹配格式：<img ... alt="index.php?m=file&amp;f=read&amp;t=ext&amp;fileID=id" ... />
+    let re = match Regex::new(r#"<img\s+[^>]*alt="([^"]*index\.php\?m=file[^"]*)"[^>]*/?>"#) {
+        Ok(regex) => regex,
+        Err(e) => {
+            error!("创建正则表达式失败: {}", e);
+            return html_content.to_string();
+        }
+    };
+    
+    let result = re.replace_all(html_content, |caps: &regex::Captures| {
+        let original_tag = &caps[0];
+        let alt_content = &caps[1];
+        
+        debug!("找到图片标签: {}", original_tag);
+        debug!("Alt内容: {}", alt_content);
+        
+        // HTML实体解码：将&amp;转换为&
+        let decoded_alt = alt_content.replace("&amp;", "&");
+        
+        // 使用正则表达式添加data-image-path属性
+        let attr_re = match Regex::new(r#"<img(\s+[^>]*?)/?>"#) {
+            Ok(regex) => regex,
+            Err(_) => return original_tag.to_string(),
+        };
+        
+        let updated_tag = attr_re.replace(original_tag, |img_caps: &regex::Captures| {
+            let attributes = &img_caps[1];
+            format!(r#"<img{} data-image-path="{}" data-lazy-load="true" />"#, attributes, decoded_alt)
+        });
+        
+        debug!("图片标记添加: {}", decoded_alt);
+        updated_tag.to_string()
+    });
+    
+    if result != html_content {
+        info!("HTML处理完成，发现并处理了图片");
+    } else {
+        debug!("HTML处理完成，未发现图片");
+    }
+    
+    result.to_string()
+}
+
+// 定义禅道API的数据结构
+#[derive(Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub account: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LoginResponse {
+    pub token: String,
+}
+
+// 日志中不输出明文密码和token
+impl fmt::Debug for LoginRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginRequest")
+            .field("account", &self.account)
+            .field("password", &MASK)
+            .finish()
+    }
+}
+
+impl fmt::Debug for LoginResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginResponse").field("token", &MASK).finish()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BugStatus {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Bug {
+    pub id: i32,
+    pub product: i32,
+    pub branch: i32,
+    pub module: i32,
+    pub project: i32,
+    pub execution: i32,
+    #[serde(rename = "toTask")]
+    pub to_task: i32,
+    #[serde(rename = "toStory")]
+    pub to_story: i32,
+    pub title: String,
+    pub keywords: String,
+    pub severity: i32,
+    pub pri: i32,
+    #[serde(rename = "type")]
+    pub bug_type: String,
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub browser: String,
+    pub steps: String,
+    #[serde(default)]
+    pub task: Option<i32>,
+    #[serde(default)]
+    pub story: Option<i32>,
+    #[serde(rename = "openedBy")]
+    pub opened_by: Option<User>,
+    #[serde(rename = "openedDate")]
+    pub opened_date: String,
+    #[serde(default)]
+    pub deadline: Option<String>,
+    #[serde(rename = "assignedTo")]
+    pub assigned_to: Option<User>,
+    #[serde(rename = "assignedDate")]
+    #[serde(default)]
+    pub assigned_date: Option<String>,
+    #[serde(rename = "resolvedBy")]
+    #[serde(default)]
+    pub resolved_by: Option<User>,
+    #[serde(rename = "resolvedDate")]
+    #[serde(default)]
+    pub resolved_date: Option<String>,
+    #[serde(rename = "closedBy")]
+    #[serde(default)]
+    pub closed_by: Option<User>,
+    #[serde(rename = "closedDate")]
+    #[serde(default)]
+    pub closed_date: Option<String>,
+    // 被激活（重新打开）的次数
+    #[serde(rename = "activatedCount")]
+    #[serde(default)]
+    pub activated_count: i32,
+    #[serde(deserialize_with = "deserialize_status")]
+    pub status: BugStatus,
+    // 历史记录，只有Bug详情接口返回
+    #[serde(default)]
+    pub actions: Vec<BugAction>,
+    // 用于存储基础URL，不参与反序列化，在获取Bug时手动设置
+    #[serde(skip)]
+    pub base_url: Option<String>,
+}
+
+impl Bug {
+    // 图片附件的完整地址；不知道服务器地址时保留相对路径
+    pub fn image_url(&self, path: &str) -> String {
+        match self.base_url.as_deref().and_then(|base_url| server::normalize_base_url(base_url).ok()) {
+            Some(urls) => markdown::attachment_url(&urls.root, path),
+            None => path.to_string(),
+        }
+    }
+
+    // 重现步骤转换为 Markdown，图片指向禅道服务器
+    pub fn steps_markdown(&self) -> String {
+        markdown::html_to_markdown(&self.steps, |path| self.image_url(path))
+    }
+
+    // 重现步骤转换为纯文本
+    pub fn steps_text(&self) -> String {
+        markdown::html_to_plain_text(&self.steps, |path| self.image_url(path))
+    }
+}
+
+// 为Bug实现自定义序列化，添加格式化后的时间字段
+impl Serialize for Bug {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Bug", 25)?;
+        
+        state.serialize_field("id", &self.id)?;
+        state.serialize_field("product", &self.product)?;
+        state.serialize_field("branch", &self.branch)?;
+        state.serialize_field("module", &self.module)?;
+        state.serialize_field("project", &self.project)?;
+        state.serialize_field("execution", &self.execution)?;
+        state.serialize_field("toTask", &self.to_task)?;
+        state.serialize_field("toStory", &self.to_story)?;
+        state.serialize_field("title", &self.title)?;
+        state.serialize_field("keywords", &self.keywords)?;
+        state.serialize_field("severity", &self.severity)?;
+        state.serialize_field("pri", &self.pri)?;
+        state.serialize_field("type", &self.bug_type)?;
+        state.serialize_field("os", &self.os)?;
+        state.serialize_field("browser", &self.browser)?;
+        state.serialize_field("steps", &self.steps)?;
+        // 添加处理过图片路径的steps字段
+        let processed_steps = if let Some(ref base_url) = self.base_url {
+            process_html_images(&self.steps, base_url)
+        } else {
+            self.steps.clone()
+        };
+        state.serialize_field("stepsProcessed", &processed_steps)?;
+        state.serialize_field("task", &self.task)?;
+        state.serialize_field("story", &self.story)?;
+        state.serialize_field("openedBy", &self.opened_by)?;
+        state.serialize_field("openedDate", &self.opened_date)?;
+        state.serialize_field("openedDateFormatted", &format_datetime_to_china(&self.opened_date))?;
+        state.serialize_field("deadline", &self.deadline)?;
+        state.serialize_field("assignedTo", &self.assigned_to)?;
+        state.serialize_field("assignedDate", &self.assigned_date)?;
+        state.serialize_field("assignedDateFormatted", &self.assigned_date.as_ref().map(|d| format_datetime_to_china(d)))?;
+        state.serialize_field("resolvedBy", &self.resolved_by)?;
+        state.serialize_field("resolvedDate", &self.resolved_date)?;
+        state.serialize_field("resolvedDateFormatted", &self.resolved_date.as_ref().map(|d| format_datetime_to_china(d)))?;
+        state.serialize_field("closedBy", &self.closed_by)?;
+        state.serialize_field("closedDate", &self.closed_date)?;
+        state.serialize_field("closedDateFormatted", &self.closed_date.as_ref().map(|d| format_datetime_to_china(d)))?;
+        state.serialize_field("activatedCount", &self.activated_count)?;
+        state.serialize_field("status", &self.status)?;
+        state.serialize_field("actions", &self.actions)?;
+        
+        state.end()
+    }
+}
+
+// 历史记录中一个字段的变化
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ActionChange {
+    #[serde(default)]
+    pub field: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_text")]
+    pub old: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_text")]
+    pub new: String,
+}
+
+// Bug的一条历史记录：创建、指派、解决、关闭、备注等
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BugAction {
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_text")]
+    pub actor: String,
+    // 操作代码，如 opened、assigned、resolved、closed、commented
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_text")]
+    pub action: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_text")]
+    pub date: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_text")]
+    pub comment: String,
+    #[serde(default)]
+    pub history: Vec<ActionChange>,
+}
+
+impl BugAction {
+    // 操作的中文名称，未知的操作显示代码
+    pub fn action_name(&self) -> &str {
+        match self.action.as_str() {
+            "opened" => "创建",
+            "edited" => "编辑",
+            "assigned" => "指派",
+            "confirmed" | "bugconfirmed" => "确认",
+            "resolved" => "解决",
+            "closed" => "关闭",
+            "activated" => "激活",
+            "commented" => "备注",
+            "tostory" => "转需求",
+            "totask" => "转任务",
+            "linked2build" | "linked2release" => "关联版本",
+            "deleted" => "删除",
+            other => other,
+        }
+    }
+}
+
+// 历史记录中的文本字段：字符串、数字，或带姓名的用户对象
+fn deserialize_text<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::String(s) => s,
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Object(obj) => ["realname", "account"]
+            .iter()
+            .filter_map(|key| obj.get(*key).and_then(Value::as_str))
+            .find(|s| !s.is_empty())
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BugListResponse {
+    pub page: i32,
+    pub total: i32,
+    pub limit: i32,
+    pub bugs: Vec<Bug>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub id: i32,
+    #[serde(rename = "type")]
+    pub user_type: String,
+    pub dept: i32,
+    pub account: String,
+    pub realname: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub birthday: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile: Option<String>,  
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weixin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join: Option<String>,
+    pub admin: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct User {
+    pub id: i32,
+    pub account: String,
+    pub avatar: String,
+    pub realname: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Product {
+    pub id: i32,
+    pub program: i32,
+    pub name: String,
+    pub code: String,
+    pub line: i32,
+    #[serde(rename = "PO")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub po: Option<User>,
+    #[serde(rename = "QD")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qd: Option<User>,
+    #[serde(rename = "RD")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rd: Option<User>,
+    #[serde(rename = "type")]
+    pub product_type: String, // normal | branch | platform
+    pub desc: String,
+    pub acl: String, // open | private
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub whitelist: Option<Vec<User>>,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<User>,
+    #[serde(rename = "createdDate")]
+    pub created_date: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductListResponse {
+    pub page: i32,
+    pub total: i32,
+    pub limit: i32,
+    pub products: Vec<Product>,
+}
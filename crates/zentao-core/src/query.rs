@@ -0,0 +1,739 @@
+// Bug查询语言：把 `status:active severity<=2 assignee:me opened>2025-01-01 "crash" -module:UI`
+// 这样的查询字符串解析为条件列表，桌面应用和命令行共用
+//
+// 语法：
+// - 多个条件用空格分隔，全部满足才匹配
+// - `字段:值` 或 `字段=值` 表示相等（文本字段为包含），`:` 后可以用逗号写多个值，满足任意一个即可
+// - 数字和日期字段支持 `!=`、`<`、`<=`、`>`、`>=`
+// - 不带字段的词或 "带引号的短语" 在标题、关键词和重现步骤中搜索
+// - 条件前加 `-` 表示取反
+// - 人员字段的值可以是账号、姓名、`me`（当前登录用户）或 `none`（未指定）
+// - 模块的值可以是ID，也可以是模块名称（在模块路径中查找，需要先通过 set_module_names 提供模块名称）
+// - `tag:`、`note:`、`starred:yes` 按本地标注过滤，不带字段的词也会在本地笔记和标签中搜索
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::annotation::{Annotation, Annotations};
+use crate::models::{parse_bug_date, Bug, User};
+
+// 查询语法错误，position 为出错位置（从0开始的字符下标）
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryError {
+    pub query: String,
+    pub position: usize,
+    pub message: String,
+}
+
+impl QueryError {
+    fn new(query: &str, byte_pos: usize, message: impl Into<String>) -> Self {
+        QueryError {
+            query: query.to_string(),
+            position: query[..byte_pos.min(query.len())].chars().count(),
+            message: message.into(),
+        }
+    }
+}
+
+// 输出错误信息和原查询，并在出错位置下方标出 ^
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent: usize = self.query.chars().take(self.position).map(char_width).sum();
+        write!(
+            f,
+            "查询语法错误（第{}个字符）: {}\n  {}\n  {}^",
+            self.position + 1,
+            self.message,
+            self.query,
+            " ".repeat(indent)
+        )
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+// 中日韩文字在终端中占两列
+fn char_width(c: char) -> usize {
+    if ('\u{1100}'..='\u{115F}').contains(&c)
+        || ('\u{2E80}'..='\u{A4CF}').contains(&c)
+        || ('\u{AC00}'..='\u{D7A3}').contains(&c)
+        || ('\u{F900}'..='\u{FAFF}').contains(&c)
+        || ('\u{FF00}'..='\u{FF60}').contains(&c)
+    {
+        2
+    } else {
+        1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn compare<T: PartialOrd>(&self, left: &T, right: &T) -> bool {
+        match self {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberField {
+    Id,
+    Product,
+    Module,
+    Project,
+    Severity,
+    Pri,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Title,
+    Keywords,
+    Steps,
+    Os,
+    Browser,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UserField {
+    AssignedTo,
+    OpenedBy,
+    ResolvedBy,
+    ClosedBy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateField {
+    Opened,
+    Assigned,
+    Resolved,
+    Closed,
+    Deadline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Number(NumberField),
+    Text(TextField),
+    User(UserField),
+    Date(DateField),
+    Status,
+    Type,
+    // 本地标注
+    Tag,
+    Note,
+    Starred,
+}
+
+impl Field {
+    // 字段名不区分大小写，支持常用别名
+    fn parse(name: &str) -> Option<Field> {
+        let field = match name.to_lowercase().as_str() {
+            "id" => Field::Number(NumberField::Id),
+            "product" => Field::Number(NumberField::Product),
+            "module" => Field::Number(NumberField::Module),
+            "project" => Field::Number(NumberField::Project),
+            "severity" | "sev" => Field::Number(NumberField::Severity),
+            "pri" | "priority" => Field::Number(NumberField::Pri),
+            "title" => Field::Text(TextField::Title),
+            "keywords" | "keyword" => Field::Text(TextField::Keywords),
+            "steps" => Field::Text(TextField::Steps),
+            "os" => Field::Text(TextField::Os),
+            "browser" => Field::Text(TextField::Browser),
+            "assignee" | "assignedto" => Field::User(UserField::AssignedTo),
+            "opener" | "author" | "openedby" => Field::User(UserField::OpenedBy),
+            "resolver" | "resolvedby" => Field::User(UserField::ResolvedBy),
+            "closer" | "closedby" => Field::User(UserField::ClosedBy),
+            "opened" | "openeddate" | "created" => Field::Date(DateField::Opened),
+            "assigneddate" => Field::Date(DateField::Assigned),
+            "resolved" | "resolveddate" => Field::Date(DateField::Resolved),
+            "closed" | "closeddate" => Field::Date(DateField::Closed),
+            "deadline" => Field::Date(DateField::Deadline),
+            "status" => Field::Status,
+            "type" => Field::Type,
+            "tag" | "tags" => Field::Tag,
+            "note" | "notes" => Field::Note,
+            "starred" | "star" | "bookmarked" => Field::Starred,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    // 只有数字和日期字段可以比较大小
+    fn is_ordered(&self) -> bool {
+        matches!(self, Field::Number(_) | Field::Date(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(i64),
+    Date(NaiveDate),
+    Text(String),
+    Bool(bool),
+    Me,
+    None,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    // 在标题、关键词和重现步骤中搜索
+    Text(String),
+    Field { field: Field, op: Op, values: Vec<Value> },
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    negated: bool,
+    condition: Condition,
+}
+
+// 解析后的查询
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    terms: Vec<Term>,
+    // 模块ID到模块路径的映射，用于按模块名称匹配
+    modules: HashMap<i32, String>,
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser { input: s, pos: 0 }.parse()
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, QueryError> {
+        input.parse()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    // 查询中是否用到了 me，用到时需要先获取当前登录账号
+    pub fn uses_me(&self) -> bool {
+        self.terms.iter().any(|term| match &term.condition {
+            Condition::Field { values, .. } => values.contains(&Value::Me),
+            Condition::Text(_) => false,
+        })
+    }
+
+    // 查询中是否按模块名称过滤，是则需要先调用 set_module_names
+    pub fn uses_module_names(&self) -> bool {
+        self.terms.iter().any(|term| match &term.condition {
+            Condition::Field { field: Field::Number(NumberField::Module), values, .. } => {
+                values.iter().any(|value| matches!(value, Value::Text(_)))
+            }
+            _ => false,
+        })
+    }
+
+    // 提供模块ID到模块路径（如“前端/UI”）的映射；没有提供时按名称过滤的条件不匹配
+    pub fn set_module_names(&mut self, modules: HashMap<i32, String>) {
+        self.modules = modules;
+    }
+
+    // me 为当前登录账号，查询中用到 me 但没有提供账号时这些条件不匹配；
+    // annotations 为当前账号的本地标注
+    pub fn matches(&self, bug: &Bug, me: Option<&str>, annotations: &Annotations) -> bool {
+        let annotation = annotations.get(bug.id);
+        self.terms
+            .iter()
+            .all(|term| term.condition.matches(bug, me, annotation, &self.modules) != term.negated)
+    }
+
+    pub fn filter(&self, bugs: Vec<Bug>, me: Option<&str>, annotations: &Annotations) -> Vec<Bug> {
+        bugs.into_iter().filter(|bug| self.matches(bug, me, annotations)).collect()
+    }
+}
+
+impl Condition {
+    fn matches(
+        &self,
+        bug: &Bug,
+        me: Option<&str>,
+        annotation: Option<&Annotation>,
+        modules: &HashMap<i32, String>,
+    ) -> bool {
+        match self {
+            Condition::Text(text) => {
+                [&bug.title, &bug.keywords, &bug.steps]
+                    .iter()
+                    .any(|value| contains_ignore_case(value, text))
+                    || annotation.is_some_and(|annotation| contains_ignore_case(&annotation.search_text(), text))
+            }
+            Condition::Field { field, op, values } => {
+                // != 表示不等于其中任何一个值，其余运算符满足任意一个值即可
+                let (op, negate) = match op {
+                    Op::Ne => (Op::Eq, true),
+                    op => (*op, false),
+                };
+                values
+                    .iter()
+                    .any(|value| field_matches(bug, annotation, *field, op, value, me, modules))
+                    != negate
+            }
+        }
+    }
+}
+
+fn field_matches(
+    bug: &Bug,
+    annotation: Option<&Annotation>,
+    field: Field,
+    op: Op,
+    value: &Value,
+    me: Option<&str>,
+    modules: &HashMap<i32, String>,
+) -> bool {
+    match (field, value) {
+        (Field::Number(NumberField::Module), Value::Text(expected)) => {
+            modules.get(&bug.module).is_some_and(|path| contains_ignore_case(path, expected))
+        }
+        (Field::Number(field), Value::Number(expected)) => {
+            let actual = match field {
+                NumberField::Id => bug.id,
+                NumberField::Product => bug.product,
+                NumberField::Module => bug.module,
+                NumberField::Project => bug.project,
+                NumberField::Severity => bug.severity,
+                NumberField::Pri => bug.pri,
+            };
+            op.compare(&i64::from(actual), expected)
+        }
+        (Field::Date(field), Value::Date(expected)) => {
+            date_of(bug, field).is_some_and(|actual| op.compare(&actual, expected))
+        }
+        (Field::Date(field), Value::None) => date_of(bug, field).is_none(),
+        (Field::Text(field), Value::Text(expected)) => {
+            let actual = match field {
+                TextField::Title => &bug.title,
+                TextField::Keywords => &bug.keywords,
+                TextField::Steps => &bug.steps,
+                TextField::Os => &bug.os,
+                TextField::Browser => &bug.browser,
+            };
+            contains_ignore_case(actual, expected)
+        }
+        (Field::User(field), value) => {
+            let user = match field {
+                UserField::AssignedTo => &bug.assigned_to,
+                UserField::OpenedBy => &bug.opened_by,
+                UserField::ResolvedBy => &bug.resolved_by,
+                UserField::ClosedBy => &bug.closed_by,
+            };
+            user_matches(user, value, me)
+        }
+        (Field::Status, Value::Text(expected)) => {
+            bug.status.code.eq_ignore_ascii_case(expected) || bug.status.name == *expected
+        }
+        (Field::Type, Value::Text(expected)) => bug.bug_type.eq_ignore_ascii_case(expected),
+        (Field::Tag, Value::Text(expected)) => annotation.is_some_and(|annotation| annotation.has_tag(expected)),
+        (Field::Tag, Value::None) => annotation.is_none_or(|annotation| annotation.tags.is_empty()),
+        (Field::Note, Value::Text(expected)) => {
+            annotation.is_some_and(|annotation| contains_ignore_case(&annotation.note, expected))
+        }
+        (Field::Note, Value::None) => annotation.is_none_or(|annotation| annotation.note.trim().is_empty()),
+        (Field::Starred, Value::Bool(expected)) => annotation.is_some_and(|annotation| annotation.starred) == *expected,
+        _ => false,
+    }
+}
+
+fn date_of(bug: &Bug, field: DateField) -> Option<NaiveDate> {
+    let value = match field {
+        DateField::Opened => Some(bug.opened_date.as_str()),
+        DateField::Assigned => bug.assigned_date.as_deref(),
+        DateField::Resolved => bug.resolved_date.as_deref(),
+        DateField::Closed => bug.closed_date.as_deref(),
+        DateField::Deadline => bug.deadline.as_deref(),
+    };
+    value.and_then(parse_bug_date)
+}
+
+fn user_matches(user: &Option<User>, value: &Value, me: Option<&str>) -> bool {
+    let user = user.as_ref().filter(|user| !user.account.is_empty());
+    match value {
+        Value::None => user.is_none(),
+        Value::Me => match (user, me) {
+            (Some(user), Some(me)) => user.account == me,
+            _ => false,
+        },
+        Value::Text(expected) => user.is_some_and(|user| {
+            user.account.eq_ignore_ascii_case(expected) || user.realname == *expected
+        }),
+        _ => false,
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    // 当前位置（字节下标）
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(mut self) -> Result<Query, QueryError> {
+        let mut terms = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.pos >= self.input.len() {
+                break;
+            }
+            terms.push(self.term()?);
+        }
+        Ok(Query { terms, modules: HashMap::new() })
+    }
+
+    fn error(&self, pos: usize, message: impl Into<String>) -> QueryError {
+        QueryError::new(self.input, pos, message)
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // 读取到空白字符为止
+    fn bare_word(&mut self) -> &'a str {
+        let start = self.pos;
+        let len = self.rest().find(char::is_whitespace).unwrap_or(self.rest().len());
+        self.pos += len;
+        &self.input[start..self.pos]
+    }
+
+    // 读取引号中的短语，支持 \" 转义
+    fn quoted(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += offset + 1;
+                    return Ok(text);
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                c => text.push(c),
+            }
+        }
+        Err(self.error(start, "引号没有闭合"))
+    }
+
+    fn term(&mut self) -> Result<Term, QueryError> {
+        let start = self.pos;
+        let negated = self.peek() == Some('-');
+        if negated {
+            self.pos += 1;
+            if self.peek().is_none_or(char::is_whitespace) {
+                return Err(self.error(start, "- 后面缺少条件"));
+            }
+        }
+
+        if self.peek() == Some('"') {
+            let text = self.quoted()?;
+            if text.is_empty() {
+                return Err(self.error(start, "搜索内容不能为空"));
+            }
+            return Ok(Term { negated, condition: Condition::Text(text) });
+        }
+
+        // 字段名由字母和下划线组成，后面紧跟运算符
+        let name_len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphabetic() || c == '_'))
+            .unwrap_or(self.rest().len());
+        let after_name = &self.rest()[name_len..];
+        if name_len > 0 && after_name.starts_with([':', '=', '!', '<', '>']) {
+            let name_pos = self.pos;
+            let name = &self.rest()[..name_len];
+            let field = Field::parse(name).ok_or_else(|| self.error(name_pos, format!("未知字段: {}", name)))?;
+            self.pos += name_len;
+            let condition = self.field_condition(field)?;
+            return Ok(Term { negated, condition });
+        }
+
+        let word = self.bare_word().to_string();
+        Ok(Term { negated, condition: Condition::Text(word) })
+    }
+
+    fn field_condition(&mut self, field: Field) -> Result<Condition, QueryError> {
+        let op_pos = self.pos;
+        let (op, len) = match self.rest() {
+            rest if rest.starts_with("!=") => (Op::Ne, 2),
+            rest if rest.starts_with("<=") => (Op::Le, 2),
+            rest if rest.starts_with(">=") => (Op::Ge, 2),
+            rest if rest.starts_with(':') || rest.starts_with('=') => (Op::Eq, 1),
+            rest if rest.starts_with('<') => (Op::Lt, 1),
+            rest if rest.starts_with('>') => (Op::Gt, 1),
+            _ => return Err(self.error(op_pos, "无效的运算符")),
+        };
+        self.pos += len;
+        if !matches!(op, Op::Eq | Op::Ne) && !field.is_ordered() {
+            return Err(self.error(op_pos, "只有数字和日期字段可以比较大小"));
+        }
+
+        let value_pos = self.pos;
+        let mut values = Vec::new();
+        if self.peek() == Some('"') {
+            let text = self.quoted()?;
+            values.push(self.value(field, &text, value_pos)?);
+        } else {
+            let raw = self.bare_word();
+            if raw.is_empty() {
+                return Err(self.error(value_pos, "缺少值"));
+            }
+            // 逗号分隔的多个值
+            let mut offset = value_pos;
+            for part in raw.split(',') {
+                if part.is_empty() {
+                    return Err(self.error(offset, "缺少值"));
+                }
+                values.push(self.value(field, part, offset)?);
+                offset += part.len() + 1;
+            }
+            if values.len() > 1 && !matches!(op, Op::Eq | Op::Ne) {
+                return Err(self.error(value_pos, "比较大小时只能指定一个值"));
+            }
+        }
+        if !matches!(op, Op::Eq | Op::Ne) && values.iter().any(|value| matches!(value, Value::Text(_))) {
+            return Err(self.error(value_pos, "模块名称不能比较大小，请使用模块ID"));
+        }
+        Ok(Condition::Field { field, op, values })
+    }
+
+    fn value(&self, field: Field, text: &str, pos: usize) -> Result<Value, QueryError> {
+        if text.is_empty() {
+            return Err(self.error(pos, "缺少值"));
+        }
+        match field {
+            // 不是数字时按模块名称匹配
+            Field::Number(NumberField::Module) => {
+                Ok(text.parse().map(Value::Number).unwrap_or_else(|_| Value::Text(text.to_string())))
+            }
+            Field::Number(_) => text
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| self.error(pos, format!("需要数字: {}", text))),
+            Field::Date(_) if text.eq_ignore_ascii_case("none") => Ok(Value::None),
+            Field::Date(_) => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .map(Value::Date)
+                .map_err(|_| self.error(pos, format!("日期格式应为 YYYY-MM-DD: {}", text))),
+            Field::User(_) if text.eq_ignore_ascii_case("me") => Ok(Value::Me),
+            Field::User(_) if text.eq_ignore_ascii_case("none") => Ok(Value::None),
+            Field::Tag | Field::Note if text.eq_ignore_ascii_case("none") => Ok(Value::None),
+            Field::Starred => match text.to_lowercase().as_str() {
+                "yes" | "true" | "1" => Ok(Value::Bool(true)),
+                "no" | "false" | "0" => Ok(Value::Bool(false)),
+                _ => Err(self.error(pos, format!("需要 yes 或 no: {}", text))),
+            },
+            _ => Ok(Value::Text(text.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bug(id: i32, module: i32, status: &str, severity: i32, assignee: &str, opened: &str, title: &str) -> Bug {
+        serde_json::from_value(json!({
+            "id": id, "product": 1, "branch": 0, "module": module, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": title, "keywords": "", "severity": severity, "pri": 3,
+            "type": "codeerror", "steps": "", "openedBy": null, "openedDate": opened,
+            "assignedTo": { "id": 1, "account": assignee, "avatar": "", "realname": "" },
+            "status": status
+        }))
+        .unwrap()
+    }
+
+    fn matching_ids(query: &Query, bugs: &[Bug], me: Option<&str>) -> Vec<i32> {
+        let annotations = Annotations::default();
+        bugs.iter().filter(|bug| query.matches(bug, me, &annotations)).map(|bug| bug.id).collect()
+    }
+
+    #[test]
+    fn parses_request_example() {
+        let query = Query::parse(r#"status:active severity<=2 assignee:me opened>2025-01-01 "crash" -module:UI"#).unwrap();
+        assert_eq!(query.terms.len(), 6);
+        assert!(query.uses_me());
+        assert!(query.uses_module_names());
+        let last = &query.terms[5];
+        assert!(last.negated);
+        assert!(matches!(
+            &last.condition,
+            Condition::Field { field: Field::Number(NumberField::Module), op: Op::Eq, values }
+                if values == &vec![Value::Text("UI".to_string())]
+        ));
+    }
+
+    #[test]
+    fn parses_operators_and_lists() {
+        let query = Query::parse("pri>=2 status:active,resolved id!=7 deadline:none").unwrap();
+        assert!(matches!(&query.terms[0].condition, Condition::Field { op: Op::Ge, values, .. } if values == &vec![Value::Number(2)]));
+        assert!(matches!(&query.terms[1].condition, Condition::Field { values, .. } if values.len() == 2));
+        assert!(matches!(&query.terms[2].condition, Condition::Field { op: Op::Ne, .. }));
+        assert!(matches!(&query.terms[3].condition, Condition::Field { values, .. } if values == &vec![Value::None]));
+        assert!(!query.uses_module_names());
+        assert!(Query::parse("module:12").is_ok_and(|query| !query.uses_module_names()));
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = Query::parse("status:active foo:1").unwrap_err();
+        assert_eq!(error.position, 14);
+        assert!(error.message.contains("foo"));
+
+        assert_eq!(Query::parse(r#"崩溃 "unclosed"#).unwrap_err().position, 3);
+        assert_eq!(Query::parse("a - b").unwrap_err().position, 2);
+        assert_eq!(Query::parse("title>x").unwrap_err().position, 5);
+        assert_eq!(Query::parse("severity:1,x").unwrap_err().position, 11);
+        assert_eq!(Query::parse("opened>2025-13-01").unwrap_err().position, 7);
+        assert_eq!(Query::parse("pri<1,2").unwrap_err().position, 4);
+        assert_eq!(Query::parse("module>UI").unwrap_err().position, 7);
+        assert_eq!(Query::parse("status:").unwrap_err().position, 7);
+    }
+
+    #[test]
+    fn error_display_marks_position() {
+        let error = Query::parse("中文 foo:1").unwrap_err();
+        assert_eq!(error.position, 3);
+        // 两个中文字符各占两列，加上空格共5列
+        assert!(error.to_string().ends_with(&format!("\n  {}^", " ".repeat(5))));
+    }
+
+    #[test]
+    fn evaluates_fields() {
+        let bugs = vec![
+            bug(1, 10, "active", 1, "alice", "2025-03-01 10:00:00", "App crash on start"),
+            bug(2, 10, "active", 3, "alice", "2025-03-01 10:00:00", "Crash in report"),
+            bug(3, 11, "resolved", 2, "bob", "2024-12-01 10:00:00", "Typo"),
+            bug(4, 12, "active", 2, "alice", "2024-06-01 10:00:00", "crash again"),
+        ];
+        let ids = |input: &str, me: Option<&str>| matching_ids(&Query::parse(input).unwrap(), &bugs, me);
+
+        assert_eq!(ids("status:active severity<=2", None), vec![1, 4]);
+        assert_eq!(ids("assignee:me", Some("bob")), vec![3]);
+        assert!(ids("assignee:me", None).is_empty());
+        assert_eq!(ids("opened>2025-01-01", None), vec![1, 2]);
+        assert_eq!(ids("\"crash\" -module:10", None), vec![4]);
+        assert_eq!(ids("status!=active,closed", None), vec![3]);
+        assert_eq!(ids("-CRASH", None), vec![3]);
+        assert_eq!(ids("", None), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn matches_module_names() {
+        let bugs = vec![
+            bug(1, 10, "active", 1, "alice", "2025-03-01", "a"),
+            bug(2, 11, "active", 1, "alice", "2025-03-01", "b"),
+            bug(3, 12, "active", 1, "alice", "2025-03-01", "c"),
+        ];
+        let mut query = Query::parse("-module:UI").unwrap();
+        // 没有模块名称时按名称过滤的条件不匹配
+        assert_eq!(matching_ids(&query, &bugs, None), vec![1, 2, 3]);
+
+        query.set_module_names(HashMap::from([
+            (10, "前端/UI".to_string()),
+            (11, "后端".to_string()),
+            (12, "前端/ui组件".to_string()),
+        ]));
+        assert_eq!(matching_ids(&query, &bugs, None), vec![2]);
+
+        let mut query = Query::parse("module:后端,10").unwrap();
+        query.set_module_names(HashMap::from([(11, "后端".to_string())]));
+        assert_eq!(matching_ids(&query, &bugs, None), vec![1, 2]);
+    }
+
+    #[test]
+    fn full_request_example_matches() {
+        let bugs = vec![
+            bug(1, 10, "active", 2, "me", "2025-02-01", "crash in editor"),
+            bug(2, 20, "active", 2, "me", "2025-02-01", "crash in UI"),
+            bug(3, 10, "active", 3, "me", "2025-02-01", "crash"),
+        ];
+        let mut query =
+            Query::parse(r#"status:active severity<=2 assignee:me opened>2025-01-01 "crash" -module:UI"#).unwrap();
+        query.set_module_names(HashMap::from([(10, "编辑器".to_string()), (20, "UI".to_string())]));
+        assert_eq!(matching_ids(&query, &bugs, Some("me")), vec![1]);
+    }
+
+    #[test]
+    fn matches_local_annotations() {
+        let bugs = (1..=4).map(|id| bug(id, 0, "active", 3, "alice", "2025-03-01", "title")).collect::<Vec<_>>();
+        let mut annotations = Annotations::default();
+        annotations.set(Annotation {
+            bug_id: 1,
+            note: "Crash 时的日志在 /tmp".to_string(),
+            tags: vec!["#Urgent".to_string(), "后端".to_string()],
+            starred: true,
+            ..Annotation::default()
+        });
+        annotations.set(Annotation {
+            bug_id: 2,
+            tags: vec!["ui".to_string()],
+            ..Annotation::default()
+        });
+        annotations.set(Annotation {
+            bug_id: 3,
+            note: "等待复现".to_string(),
+            ..Annotation::default()
+        });
+        let matching = |text: &str| {
+            let query = Query::parse(text).unwrap();
+            query.filter(bugs.clone(), None, &annotations).iter().map(|bug| bug.id).collect::<Vec<_>>()
+        };
+
+        // 标签不区分大小写、完整匹配
+        assert_eq!(matching("tag:urgent"), vec![1]);
+        assert_eq!(matching("tag:UI,后端"), vec![1, 2]);
+        assert_eq!(matching("tag:urg"), Vec::<i32>::new());
+        assert_eq!(matching("tag:none"), vec![3, 4]);
+        assert_eq!(matching("-tag:none"), vec![1, 2]);
+        // 笔记按包含匹配
+        assert_eq!(matching("note:crash"), vec![1]);
+        assert_eq!(matching("note:复现"), vec![3]);
+        assert_eq!(matching("note:none"), vec![2, 4]);
+        assert_eq!(matching("starred:yes"), vec![1]);
+        assert_eq!(matching("starred:no"), vec![2, 3, 4]);
+        // 不带字段的词也在笔记和标签中搜索
+        assert_eq!(matching("日志"), vec![1]);
+        assert_eq!(matching("ui"), vec![2]);
+        assert!(Query::parse("starred:maybe").is_err());
+    }
+}
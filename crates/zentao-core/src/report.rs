@@ -0,0 +1,664 @@
+// Bug统计报表：按状态、严重程度、优先级、类型、指派人和模块计数，
+// 新增与解决趋势、解决耗时、重新激活率和未解决Bug的存续时间
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use crate::export::user_display;
+use crate::models::{parse_bug_date, parse_bug_datetime, Bug};
+
+// 趋势的统计周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Day,
+    Week,
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" | "daily" => Ok(Granularity::Day),
+            "week" | "weekly" => Ok(Granularity::Week),
+            other => Err(format!("不支持的统计周期: {}（可选 day、week）", other)),
+        }
+    }
+}
+
+impl Granularity {
+    // 日期所在周期的第一天，按周统计时从周一开始
+    fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => date - Duration::days(i64::from(date.weekday().num_days_from_monday())),
+        }
+    }
+
+    fn step(&self) -> Duration {
+        match self {
+            Granularity::Day => Duration::days(1),
+            Granularity::Week => Duration::days(7),
+        }
+    }
+}
+
+// 统计的日期范围（包含两端），不指定时不限制
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DateRange {
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+// 分组计数，key 用于程序处理，label 用于显示
+#[derive(Debug, Clone, Serialize)]
+pub struct CountItem {
+    pub key: String,
+    pub label: String,
+    pub count: usize,
+}
+
+// 某个周期内新增和解决的Bug数
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendPoint {
+    // 周期的第一天
+    pub period: NaiveDate,
+    pub opened: usize,
+    pub resolved: usize,
+}
+
+// 解决耗时（小时）
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolveTime {
+    pub count: usize,
+    #[serde(rename = "meanHours")]
+    pub mean_hours: Option<f64>,
+    #[serde(rename = "medianHours")]
+    pub median_hours: Option<f64>,
+}
+
+// 重新激活率：被激活过的Bug占解决过的Bug的比例
+#[derive(Debug, Clone, Serialize)]
+pub struct ReopenRate {
+    pub reopened: usize,
+    pub resolved: usize,
+    pub rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BugReport {
+    #[serde(rename = "productIds")]
+    pub product_ids: Vec<i32>,
+    pub range: DateRange,
+    pub granularity: Granularity,
+    // 统计日期，未解决Bug的存续时间按这一天计算
+    #[serde(rename = "asOf")]
+    pub as_of: NaiveDate,
+    // 范围内新建的Bug数，分组计数都基于这些Bug
+    pub total: usize,
+    #[serde(rename = "byStatus")]
+    pub by_status: Vec<CountItem>,
+    #[serde(rename = "bySeverity")]
+    pub by_severity: Vec<CountItem>,
+    #[serde(rename = "byPri")]
+    pub by_pri: Vec<CountItem>,
+    #[serde(rename = "byType")]
+    pub by_type: Vec<CountItem>,
+    #[serde(rename = "byAssignee")]
+    pub by_assignee: Vec<CountItem>,
+    #[serde(rename = "byModule")]
+    pub by_module: Vec<CountItem>,
+    pub trend: Vec<TrendPoint>,
+    // 范围内解决的Bug的解决耗时
+    #[serde(rename = "resolveTime")]
+    pub resolve_time: ResolveTime,
+    #[serde(rename = "reopenRate")]
+    pub reopen_rate: ReopenRate,
+    // 当前未解决Bug（不受日期范围限制）按存续天数分组
+    pub aging: Vec<CountItem>,
+}
+
+// 未解决Bug的存续天数分组：(最大天数, 显示名称)
+const AGING_BUCKETS: [(i64, &str); 4] = [(7, "7天内"), (30, "8-30天"), (90, "31-90天"), (i64::MAX, "90天以上")];
+
+// 按周期统计趋势时最多输出的周期数，避免范围过大
+const MAX_PERIODS: usize = 1000;
+
+fn severity_label(severity: i32) -> String {
+    match severity {
+        1 => "致命".to_string(),
+        2 => "严重".to_string(),
+        3 => "一般".to_string(),
+        4 => "轻微".to_string(),
+        other => format!("等级{}", other),
+    }
+}
+
+fn pri_label(pri: i32) -> String {
+    match pri {
+        1 => "高".to_string(),
+        2 => "中".to_string(),
+        3 => "低".to_string(),
+        4 => "不指定".to_string(),
+        other => format!("P{}", other),
+    }
+}
+
+fn type_label(bug_type: &str) -> String {
+    let label = match bug_type {
+        "codeerror" => "代码错误",
+        "config" => "配置相关",
+        "install" => "安装部署",
+        "security" => "安全相关",
+        "performance" => "性能问题",
+        "standard" => "标准规范",
+        "automation" => "测试脚本",
+        "designdefect" => "设计缺陷",
+        "others" => "其他",
+        "" => "未指定",
+        other => other,
+    };
+    label.to_string()
+}
+
+// 旧数据可能只有状态代码，没有显示名称
+fn status_label(bug: &Bug) -> String {
+    if bug.status.name != bug.status.code && !bug.status.name.is_empty() {
+        return bug.status.name.clone();
+    }
+    let label = match bug.status.code.as_str() {
+        "active" => "激活",
+        "resolved" => "已解决",
+        "closed" => "已关闭",
+        other => other,
+    };
+    label.to_string()
+}
+
+fn is_open(bug: &Bug) -> bool {
+    bug.status.code == "active"
+}
+
+// 按 key 分组计数，结果按数量从多到少排列
+fn count_by<F>(bugs: &[&Bug], group: F) -> Vec<CountItem>
+where
+    F: Fn(&Bug) -> (String, String),
+{
+    let mut counts: BTreeMap<String, CountItem> = BTreeMap::new();
+    for bug in bugs {
+        let (key, label) = group(bug);
+        counts
+            .entry(key.clone())
+            .or_insert(CountItem { key, label, count: 0 })
+            .count += 1;
+    }
+    let mut items = counts.into_values().collect::<Vec<_>>();
+    items.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    items
+}
+
+// 按固定顺序（严重程度、优先级）计数
+fn count_by_number<F>(bugs: &[&Bug], value: F, label: fn(i32) -> String) -> Vec<CountItem>
+where
+    F: Fn(&Bug) -> i32,
+{
+    let mut counts: BTreeMap<i32, usize> = BTreeMap::new();
+    for bug in bugs {
+        *counts.entry(value(bug)).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(value, count)| CountItem {
+            key: value.to_string(),
+            label: label(value),
+            count,
+        })
+        .collect()
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    })
+}
+
+fn trend(bugs: &[Bug], range: DateRange, granularity: Granularity, as_of: NaiveDate) -> Vec<TrendPoint> {
+    let opened = bugs.iter().filter_map(|bug| parse_bug_date(&bug.opened_date)).collect::<Vec<_>>();
+    let resolved = bugs
+        .iter()
+        .filter_map(|bug| bug.resolved_date.as_deref().and_then(parse_bug_date))
+        .collect::<Vec<_>>();
+
+    // 没有指定开始日期时从最早的新建日期开始
+    let Some(first) = range.from.or_else(|| opened.iter().chain(&resolved).min().copied()) else {
+        return Vec::new();
+    };
+    let last = range.to.unwrap_or(as_of);
+    // 周期过多时只保留最近的部分
+    let first = first.max(last - granularity.step() * (MAX_PERIODS as i32 - 1));
+    if first > last {
+        return Vec::new();
+    }
+
+    let mut points: BTreeMap<NaiveDate, TrendPoint> = BTreeMap::new();
+    let mut period = granularity.period_start(first);
+    while period <= last {
+        points.insert(period, TrendPoint { period, opened: 0, resolved: 0 });
+        period += granularity.step();
+    }
+    for date in opened.iter().filter(|date| (first..=last).contains(*date)) {
+        if let Some(point) = points.get_mut(&granularity.period_start(*date)) {
+            point.opened += 1;
+        }
+    }
+    for date in resolved.iter().filter(|date| (first..=last).contains(*date)) {
+        if let Some(point) = points.get_mut(&granularity.period_start(*date)) {
+            point.resolved += 1;
+        }
+    }
+    points.into_values().collect()
+}
+
+// 生成报表，as_of 为统计日期（通常是今天）
+// modules 为模块ID到名称的映射（ZentaoClient::get_module_names），没有名称的模块显示为模块ID
+pub fn build_report(
+    bugs: &[Bug],
+    product_ids: &[i32],
+    modules: &HashMap<i32, String>,
+    range: DateRange,
+    granularity: Granularity,
+    as_of: NaiveDate,
+) -> BugReport {
+    let in_range = bugs
+        .iter()
+        .filter(|bug| parse_bug_date(&bug.opened_date).is_some_and(|date| range.contains(date)))
+        .collect::<Vec<_>>();
+
+    let by_status = count_by(&in_range, |bug| (bug.status.code.clone(), status_label(bug)));
+    let by_severity = count_by_number(&in_range, |bug| bug.severity, severity_label);
+    let by_pri = count_by_number(&in_range, |bug| bug.pri, pri_label);
+    let by_type = count_by(&in_range, |bug| (bug.bug_type.clone(), type_label(&bug.bug_type)));
+    let by_assignee = count_by(&in_range, |bug| match &bug.assigned_to {
+        Some(user) if !user.account.is_empty() => (user.account.clone(), user_display(&bug.assigned_to)),
+        _ => (String::new(), "未指派".to_string()),
+    });
+    let by_module = count_by(&in_range, |bug| {
+        let label = if bug.module == 0 {
+            "无模块".to_string()
+        } else {
+            modules.get(&bug.module).cloned().unwrap_or_else(|| format!("模块{}", bug.module))
+        };
+        (bug.module.to_string(), label)
+    });
+
+    // 解决耗时：解决日期在范围内的Bug
+    let mut hours = bugs
+        .iter()
+        .filter_map(|bug| {
+            let resolved = parse_bug_datetime(bug.resolved_date.as_deref()?)?;
+            if !range.contains(resolved.date_naive()) {
+                return None;
+            }
+            let opened = parse_bug_datetime(&bug.opened_date)?;
+            let hours = (resolved - opened).num_minutes() as f64 / 60.0;
+            (hours >= 0.0).then_some(hours)
+        })
+        .collect::<Vec<_>>();
+    let mean_hours = (!hours.is_empty()).then(|| hours.iter().sum::<f64>() / hours.len() as f64);
+    let resolve_time = ResolveTime {
+        count: hours.len(),
+        mean_hours,
+        median_hours: median(&mut hours),
+    };
+
+    // 重新激活率：范围内新建、解决过（有解决日期或被激活过）的Bug中被激活过的比例
+    let resolved_once = in_range
+        .iter()
+        .filter(|bug| bug.resolved_date.is_some() || bug.activated_count > 0)
+        .collect::<Vec<_>>();
+    let reopened = resolved_once.iter().filter(|bug| bug.activated_count > 0).count();
+    let reopen_rate = ReopenRate {
+        reopened,
+        resolved: resolved_once.len(),
+        rate: (!resolved_once.is_empty()).then(|| reopened as f64 / resolved_once.len() as f64),
+    };
+
+    let mut aging = AGING_BUCKETS
+        .iter()
+        .map(|(max_days, label)| CountItem {
+            key: if *max_days == i64::MAX { "90+".to_string() } else { max_days.to_string() },
+            label: label.to_string(),
+            count: 0,
+        })
+        .collect::<Vec<_>>();
+    for bug in bugs.iter().filter(|bug| is_open(bug)) {
+        let Some(opened) = parse_bug_date(&bug.opened_date) else {
+            continue;
+        };
+        let days = (as_of - opened).num_days();
+        if let Some(index) = AGING_BUCKETS.iter().position(|(max_days, _)| days <= *max_days) {
+            aging[index].count += 1;
+        }
+    }
+
+    BugReport {
+        product_ids: product_ids.to_vec(),
+        range,
+        granularity,
+        as_of,
+        total: in_range.len(),
+        by_status,
+        by_severity,
+        by_pri,
+        by_type,
+        by_assignee,
+        by_module,
+        trend: trend(bugs, range, granularity, as_of),
+        resolve_time,
+        reopen_rate,
+        aging,
+    }
+}
+
+// 以今天为统计日期生成报表
+pub fn build_report_today(
+    bugs: &[Bug],
+    product_ids: &[i32],
+    modules: &HashMap<i32, String>,
+    range: DateRange,
+    granularity: Granularity,
+) -> BugReport {
+    build_report(bugs, product_ids, modules, range, granularity, Local::now().date_naive())
+}
+
+fn format_hours(hours: Option<f64>) -> String {
+    match hours {
+        Some(hours) if hours >= 24.0 => format!("{:.1}天", hours / 24.0),
+        Some(hours) => format!("{:.1}小时", hours),
+        None => "-".to_string(),
+    }
+}
+
+fn count_table(out: &mut String, title: &str, items: &[CountItem]) {
+    let _ = writeln!(out, "### {}\n", title);
+    if items.is_empty() {
+        out.push_str("无数据\n\n");
+        return;
+    }
+    out.push_str("| 分类 | 数量 |\n| --- | ---: |\n");
+    for item in items {
+        let _ = writeln!(out, "| {} | {} |", item.label.replace('|', "\\|"), item.count);
+    }
+    out.push('\n');
+}
+
+// 把报表渲染为 Markdown
+pub fn report_to_markdown(report: &BugReport) -> String {
+    let mut out = String::new();
+    let range = match (report.range.from, report.range.to) {
+        (Some(from), Some(to)) => format!("{} 至 {}", from, to),
+        (Some(from), None) => format!("{} 起", from),
+        (None, Some(to)) => format!("截至 {}", to),
+        (None, None) => "全部".to_string(),
+    };
+    let products = report
+        .product_ids
+        .iter()
+        .map(i32::to_string)
+        .collect::<Vec<_>>()
+        .join("、");
+
+    let _ = writeln!(out, "# Bug统计报表\n");
+    let _ = writeln!(out, "- 产品: {}", products);
+    let _ = writeln!(out, "- 时间范围: {}", range);
+    let _ = writeln!(out, "- 统计日期: {}", report.as_of);
+    let _ = writeln!(out, "- 新建Bug: {}", report.total);
+    let _ = writeln!(
+        out,
+        "- 解决耗时: 平均 {}，中位数 {}（{}个）",
+        format_hours(report.resolve_time.mean_hours),
+        format_hours(report.resolve_time.median_hours),
+        report.resolve_time.count
+    );
+    let rate = report
+        .reopen_rate
+        .rate
+        .map(|rate| format!("{:.1}%", rate * 100.0))
+        .unwrap_or_else(|| "-".to_string());
+    let _ = writeln!(
+        out,
+        "- 重新激活率: {}（{}/{}）\n",
+        rate, report.reopen_rate.reopened, report.reopen_rate.resolved
+    );
+
+    let _ = writeln!(out, "## 分布\n");
+    count_table(&mut out, "状态", &report.by_status);
+    count_table(&mut out, "严重程度", &report.by_severity);
+    count_table(&mut out, "优先级", &report.by_pri);
+    count_table(&mut out, "类型", &report.by_type);
+    count_table(&mut out, "指派给", &report.by_assignee);
+    count_table(&mut out, "模块", &report.by_module);
+
+    let period = match report.granularity {
+        Granularity::Day => "日期",
+        Granularity::Week => "周（起始日）",
+    };
+    let _ = writeln!(out, "## 新增与解决趋势\n");
+    if report.trend.is_empty() {
+        out.push_str("无数据\n\n");
+    } else {
+        let _ = writeln!(out, "| {} | 新增 | 解决 |\n| --- | ---: | ---: |", period);
+        for point in &report.trend {
+            let _ = writeln!(out, "| {} | {} | {} |", point.period, point.opened, point.resolved);
+        }
+        out.push('\n');
+    }
+
+    let _ = writeln!(out, "## 未解决Bug存续时间\n");
+    out.push_str("| 存续时间 | 数量 |\n| --- | ---: |\n");
+    for item in &report.aging {
+        let _ = writeln!(out, "| {} | {} |", item.label, item.count);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bug(id: i32, module: i32, status: &str, opened: &str, resolved: Option<&str>, activated: i32) -> Bug {
+        serde_json::from_value(json!({
+            "id": id, "product": 1, "branch": 0, "module": module, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": "", "keywords": "", "severity": 3, "pri": 3,
+            "type": "codeerror", "steps": "", "openedBy": null, "openedDate": opened,
+            "assignedTo": null, "resolvedDate": resolved, "activatedCount": activated, "status": status
+        }))
+        .unwrap()
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn range(from: &str, to: &str) -> DateRange {
+        DateRange { from: Some(date(from)), to: Some(date(to)) }
+    }
+
+    fn counts(points: &[TrendPoint]) -> Vec<(String, usize, usize)> {
+        points.iter().map(|point| (point.period.to_string(), point.opened, point.resolved)).collect()
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&mut [5.0, 1.0, 3.0]), Some(3.0));
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), Some(2.5));
+        assert_eq!(median(&mut [7.0]), Some(7.0));
+        assert_eq!(median(&mut []), None);
+    }
+
+    #[test]
+    fn daily_trend_starts_at_first_bug() {
+        let bugs = [
+            bug(1, 0, "resolved", "2025-01-06T10:00:00+08:00", Some("2025-01-08T09:00:00+08:00"), 0),
+            bug(2, 0, "active", "2025-01-07T10:00:00+08:00", None, 0),
+            // UTC 时间按中国时区计算日期
+            bug(3, 0, "active", "2025-01-06T20:00:00Z", None, 0),
+        ];
+        let trend = trend(&bugs, DateRange::default(), Granularity::Day, date("2025-01-08"));
+        assert_eq!(
+            counts(&trend),
+            vec![
+                ("2025-01-06".to_string(), 1, 0),
+                ("2025-01-07".to_string(), 2, 0),
+                ("2025-01-08".to_string(), 0, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_trend_buckets_by_monday() {
+        let bugs = [
+            // 早于开始日期，不计入
+            bug(1, 0, "active", "2025-01-07T10:00:00+08:00", None, 0),
+            bug(2, 0, "active", "2025-01-09T10:00:00+08:00", None, 0),
+            bug(3, 0, "resolved", "2025-01-13T10:00:00+08:00", Some("2025-01-14T10:00:00+08:00"), 0),
+            // 晚于结束日期，不计入
+            bug(4, 0, "active", "2025-01-21T10:00:00+08:00", None, 0),
+        ];
+        let trend = trend(&bugs, range("2025-01-08", "2025-01-20"), Granularity::Week, date("2025-02-01"));
+        assert_eq!(
+            counts(&trend),
+            vec![
+                ("2025-01-06".to_string(), 1, 0),
+                ("2025-01-13".to_string(), 1, 1),
+                ("2025-01-20".to_string(), 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn trend_keeps_latest_periods() {
+        let bugs = [bug(1, 0, "active", "2000-01-01T10:00:00+08:00", None, 0)];
+        let trend = trend(&bugs, range("2000-01-01", "2025-01-01"), Granularity::Day, date("2025-01-01"));
+        assert_eq!(trend.len(), MAX_PERIODS);
+        assert_eq!(trend[0].period, date("2025-01-01") - Duration::days(MAX_PERIODS as i64 - 1));
+        assert_eq!(trend.last().unwrap().period, date("2025-01-01"));
+        assert_eq!(trend.iter().map(|point| point.opened).sum::<usize>(), 0);
+    }
+
+    #[test]
+    fn empty_trend() {
+        assert!(trend(&[], DateRange::default(), Granularity::Day, date("2025-01-01")).is_empty());
+        let bugs = [bug(1, 0, "active", "2025-01-06T10:00:00+08:00", None, 0)];
+        assert!(trend(&bugs, range("2025-02-01", "2025-01-01"), Granularity::Week, date("2025-03-01")).is_empty());
+    }
+
+    #[test]
+    fn resolve_time_and_reopen_rate() {
+        let bugs = [
+            bug(1, 0, "resolved", "2025-01-01T10:00:00+08:00", Some("2025-01-03T10:00:00+08:00"), 0),
+            bug(2, 0, "closed", "2025-01-02T10:00:00+08:00", Some("2025-01-02T12:00:00+08:00"), 1),
+            // 被激活后还没有再次解决
+            bug(3, 0, "active", "2025-01-02T10:00:00+08:00", None, 1),
+            bug(4, 0, "active", "2025-01-03T10:00:00+08:00", None, 0),
+            // 范围外新建、范围内解决：只计入解决耗时
+            bug(5, 0, "resolved", "2024-12-30T10:00:00+08:00", Some("2025-01-04T10:00:00+08:00"), 2),
+        ];
+        let report = build_report(&bugs, &[1], &HashMap::new(), range("2025-01-01", "2025-01-31"), Granularity::Day, date("2025-02-01"));
+        assert_eq!(report.total, 4);
+        assert_eq!(report.resolve_time.count, 3);
+        assert_eq!(report.resolve_time.median_hours, Some(48.0));
+        assert_eq!(report.resolve_time.mean_hours, Some((48.0 + 2.0 + 120.0) / 3.0));
+        assert_eq!(report.reopen_rate.reopened, 2);
+        assert_eq!(report.reopen_rate.resolved, 3);
+        assert_eq!(report.reopen_rate.rate, Some(2.0 / 3.0));
+
+        let report = build_report(&bugs[3..4], &[1], &HashMap::new(), DateRange::default(), Granularity::Day, date("2025-02-01"));
+        assert_eq!(report.resolve_time.mean_hours, None);
+        assert_eq!(report.resolve_time.median_hours, None);
+        assert_eq!(report.reopen_rate.rate, None);
+    }
+
+    #[test]
+    fn aging_buckets_open_bugs() {
+        let as_of = date("2025-06-30");
+        let bugs = [0, 7, 8, 30, 31, 90, 91, 400]
+            .iter()
+            .enumerate()
+            .map(|(index, days)| {
+                let opened = format!("{}T10:00:00+08:00", as_of - Duration::days(*days));
+                bug(index as i32, 0, "active", &opened, None, 0)
+            })
+            .chain([bug(100, 0, "closed", "2020-01-01T10:00:00+08:00", Some("2020-01-02T10:00:00+08:00"), 0)])
+            .collect::<Vec<_>>();
+        // 存续时间不受日期范围限制
+        let report = build_report(&bugs, &[1], &HashMap::new(), range("2025-06-30", "2025-06-30"), Granularity::Day, as_of);
+        let aging = report.aging.iter().map(|item| (item.key.as_str(), item.label.as_str(), item.count)).collect::<Vec<_>>();
+        assert_eq!(
+            aging,
+            vec![("7", "7天内", 2), ("30", "8-30天", 2), ("90", "31-90天", 2), ("90+", "90天以上", 2)]
+        );
+    }
+
+    #[test]
+    fn modules_use_names_when_known() {
+        let bugs = [
+            bug(1, 0, "active", "2025-01-01T10:00:00+08:00", None, 0),
+            bug(2, 5, "active", "2025-01-01T10:00:00+08:00", None, 0),
+            bug(3, 5, "active", "2025-01-01T10:00:00+08:00", None, 0),
+            bug(4, 6, "active", "2025-01-01T10:00:00+08:00", None, 0),
+        ];
+        let modules = HashMap::from([(5, "前端/UI".to_string())]);
+        let report = build_report(&bugs, &[1], &modules, DateRange::default(), Granularity::Day, date("2025-01-02"));
+        let by_module = report.by_module.iter().map(|item| (item.key.as_str(), item.label.as_str(), item.count)).collect::<Vec<_>>();
+        assert_eq!(by_module, vec![("5", "前端/UI", 2), ("0", "无模块", 1), ("6", "模块6", 1)]);
+    }
+
+    #[test]
+    fn renders_markdown() {
+        let bugs = [
+            bug(1, 5, "resolved", "2025-01-06T10:00:00+08:00", Some("2025-01-07T10:00:00+08:00"), 1),
+            bug(2, 0, "active", "2025-01-07T10:00:00+08:00", None, 0),
+        ];
+        let modules = HashMap::from([(5, "前端|后端".to_string())]);
+        let report = build_report(&bugs, &[1, 2], &modules, range("2025-01-06", "2025-01-07"), Granularity::Day, date("2025-01-08"));
+        let markdown = report_to_markdown(&report);
+        assert!(markdown.starts_with("# Bug统计报表\n"));
+        assert!(markdown.contains("- 产品: 1、2\n"));
+        assert!(markdown.contains("- 时间范围: 2025-01-06 至 2025-01-07\n"));
+        assert!(markdown.contains("- 新建Bug: 2\n"));
+        assert!(markdown.contains("- 解决耗时: 平均 1.0天，中位数 1.0天（1个）\n"));
+        assert!(markdown.contains("- 重新激活率: 100.0%（1/1）\n"));
+        assert!(markdown.contains("| 前端\\|后端 | 1 |\n"));
+        assert!(markdown.contains("| 日期 | 新增 | 解决 |\n"));
+        assert!(markdown.contains("| 2025-01-07 | 1 | 1 |\n"));
+        assert!(markdown.contains("| 7天内 | 1 |\n"));
+
+        let report = build_report(&[], &[1], &HashMap::new(), DateRange::default(), Granularity::Week, date("2025-01-08"));
+        let markdown = report_to_markdown(&report);
+        assert!(markdown.contains("- 时间范围: 全部\n"));
+        assert!(markdown.contains("- 重新激活率: -（0/0）\n"));
+        assert!(markdown.contains("### 状态\n\n无数据\n"));
+        assert!(markdown.contains("## 新增与解决趋势\n\n无数据\n"));
+    }
+}
@@ -0,0 +1,498 @@
+// Bug全文检索：对标题、重现步骤（去掉HTML后）和本地笔记建立倒排索引，按 BM25 排序并生成高亮摘要
+//
+// 中文没有空格分词，这里对连续的中日韩文字同时索引单字和相邻两字（bigram），
+// 英文和数字按单词索引并转为小写。查询时多字的中文只使用 bigram，单字查询使用单字。
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::html::{escape_html, html_to_text};
+use crate::models::Bug;
+
+// BM25 参数
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+// 标题中的词按多次出现计算，使标题命中排在前面
+const TITLE_BOOST: u32 = 3;
+// 摘要长度（字符数），以及第一个命中词之前保留的字符数
+const SNIPPET_CHARS: usize = 120;
+const SNIPPET_CONTEXT: usize = 30;
+pub const DEFAULT_LIMIT: usize = 20;
+
+// 分词结果，start/end 为在原文中的字节位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub term: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+// 中日韩文字（汉字、假名、谚文）
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
+// 对文档分词：中文输出单字和 bigram，其他文字按单词输出
+pub fn tokenize(text: &str) -> Vec<Token> {
+    tokenize_with(text, true)
+}
+
+// 对查询分词：中文连续两字以上时只输出 bigram
+pub fn tokenize_query(text: &str) -> Vec<Token> {
+    tokenize_with(text, false)
+}
+
+fn tokenize_with(text: &str, with_unigrams: bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if is_cjk(c) {
+            let mut run = Vec::new();
+            while let Some(&(pos, c)) = chars.peek() {
+                if !is_cjk(c) {
+                    break;
+                }
+                run.push((pos, c));
+                chars.next();
+            }
+            cjk_tokens(&run, with_unigrams, &mut tokens);
+        } else if c.is_alphanumeric() {
+            let mut end = start;
+            while let Some(&(pos, c)) = chars.peek() {
+                if !c.is_alphanumeric() || is_cjk(c) {
+                    break;
+                }
+                end = pos + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token {
+                term: text[start..end].to_lowercase(),
+                start,
+                end,
+            });
+        } else {
+            chars.next();
+        }
+    }
+    tokens
+}
+
+fn cjk_tokens(run: &[(usize, char)], with_unigrams: bool, tokens: &mut Vec<Token>) {
+    if with_unigrams || run.len() == 1 {
+        for &(pos, c) in run {
+            tokens.push(Token {
+                term: c.to_string(),
+                start: pos,
+                end: pos + c.len_utf8(),
+            });
+        }
+    }
+    for pair in run.windows(2) {
+        let (start, first) = pair[0];
+        let (pos, second) = pair[1];
+        tokens.push(Token {
+            term: format!("{}{}", first, second),
+            start,
+            end: pos + second.len_utf8(),
+        });
+    }
+}
+
+// 搜索结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    #[serde(rename = "bugId")]
+    pub bug_id: i32,
+    pub product: i32,
+    pub score: f64,
+    // 标题和摘要已转义HTML，命中的词用 <mark> 标出
+    pub title: String,
+    pub snippet: String,
+}
+
+// 索引中的文档
+#[derive(Debug, Clone)]
+struct Document {
+    product: i32,
+    title: String,
+    // 去掉HTML后的重现步骤
+    text: String,
+    // 标题和步骤的指纹，内容没有变化时不重新分词
+    fingerprint: u64,
+    // 文档中各词的加权词频
+    terms: HashMap<String, u32>,
+    length: u32,
+}
+
+// 倒排索引：词 -> (Bug ID -> 加权词频)
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    documents: HashMap<i32, Document>,
+    postings: HashMap<String, HashMap<i32, u32>>,
+    total_length: u64,
+    // 本地笔记和标签：Bug ID -> 文本，与Bug一起索引
+    notes: HashMap<i32, String>,
+}
+
+fn fingerprint(bug: &Bug) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bug.title.hash(&mut hasher);
+    bug.steps.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl SearchIndex {
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    pub fn contains(&self, bug_id: i32) -> bool {
+        self.documents.contains_key(&bug_id)
+    }
+
+    // 产品下已索引的Bug ID
+    pub fn bug_ids_of_product(&self, product_id: i32) -> Vec<i32> {
+        self.documents
+            .iter()
+            .filter(|(_, document)| document.product == product_id)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    // 添加或更新Bug，标题和步骤都没有变化时跳过，返回是否更新了索引
+    pub fn upsert(&mut self, bug: &Bug) -> bool {
+        let fingerprint = fingerprint(bug);
+        if let Some(document) = self.documents.get_mut(&bug.id)
+            && document.fingerprint == fingerprint
+        {
+            document.product = bug.product;
+            return false;
+        }
+        self.index(bug.id, bug.product, bug.title.clone(), html_to_text(&bug.steps), fingerprint);
+        true
+    }
+
+    fn index(&mut self, bug_id: i32, product: i32, title: String, text: String, fingerprint: u64) {
+        self.remove(bug_id);
+
+        let mut terms: HashMap<String, u32> = HashMap::new();
+        for token in tokenize(&title) {
+            *terms.entry(token.term).or_default() += TITLE_BOOST;
+        }
+        let note = self.notes.get(&bug_id).map(String::as_str).unwrap_or_default();
+        for token in tokenize(&text).into_iter().chain(tokenize(note)) {
+            *terms.entry(token.term).or_default() += 1;
+        }
+        let length = terms.values().sum();
+
+        for (term, frequency) in &terms {
+            self.postings.entry(term.clone()).or_default().insert(bug_id, *frequency);
+        }
+        self.total_length += u64::from(length);
+        self.documents.insert(
+            bug_id,
+            Document {
+                product,
+                title,
+                text,
+                fingerprint,
+                terms,
+                length,
+            },
+        );
+    }
+
+    // 替换全部本地笔记，重新索引笔记有变化的Bug
+    pub fn set_notes(&mut self, notes: BTreeMap<i32, String>) {
+        let mut changed = self
+            .notes
+            .keys()
+            .filter(|bug_id| !notes.contains_key(bug_id))
+            .copied()
+            .collect::<Vec<_>>();
+        changed.extend(
+            notes
+                .iter()
+                .filter(|(bug_id, note)| self.notes.get(bug_id) != Some(*note))
+                .map(|(bug_id, _)| *bug_id),
+        );
+        self.notes = notes.into_iter().collect();
+        for bug_id in changed {
+            if let Some(document) = self.documents.get(&bug_id) {
+                let (product, title, text, fingerprint) =
+                    (document.product, document.title.clone(), document.text.clone(), document.fingerprint);
+                self.index(bug_id, product, title, text, fingerprint);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, bug_id: i32) -> bool {
+        let Some(document) = self.documents.remove(&bug_id) else {
+            return false;
+        };
+        for term in document.terms.keys() {
+            if let Some(posting) = self.postings.get_mut(term) {
+                posting.remove(&bug_id);
+                if posting.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        self.total_length -= u64::from(document.length);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = SearchIndex::default();
+    }
+
+    // 按 BM25 得分从高到低返回最多 limit 个结果
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query_terms = tokenize_query(query)
+            .into_iter()
+            .map(|token| token.term)
+            .collect::<HashSet<_>>();
+        if query_terms.is_empty() || self.documents.is_empty() {
+            return Vec::new();
+        }
+
+        let count = self.documents.len() as f64;
+        let average_length = (self.total_length as f64 / count).max(1.0);
+        let mut scores: HashMap<i32, f64> = HashMap::new();
+        for term in &query_terms {
+            let Some(posting) = self.postings.get(term) else {
+                continue;
+            };
+            let df = posting.len() as f64;
+            let idf = (1.0 + (count - df + 0.5) / (df + 0.5)).ln();
+            for (bug_id, frequency) in posting {
+                let length = f64::from(self.documents[bug_id].length);
+                let tf = f64::from(*frequency);
+                let score = idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * length / average_length));
+                *scores.entry(*bug_id).or_default() += score;
+            }
+        }
+
+        let mut ranked = scores.into_iter().collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.0.cmp(&a.0)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(bug_id, score)| {
+                let document = &self.documents[&bug_id];
+                SearchHit {
+                    bug_id,
+                    product: document.product,
+                    score,
+                    title: highlight(&document.title, &query_terms),
+                    snippet: self.snippet_of(bug_id, &document.text, &query_terms),
+                }
+            })
+            .collect()
+    }
+
+    // 重现步骤中没有命中而本地笔记中有时，从笔记中截取摘要
+    fn snippet_of(&self, bug_id: i32, text: &str, query_terms: &HashSet<String>) -> String {
+        let note = self.notes.get(&bug_id).map(String::as_str).unwrap_or_default();
+        if matched_ranges(text, query_terms).is_empty() && !matched_ranges(note, query_terms).is_empty() {
+            return format!("笔记: {}", snippet(note, query_terms));
+        }
+        snippet(text, query_terms)
+    }
+}
+
+// 命中词在原文中的字节范围，重叠的范围合并
+fn matched_ranges(text: &str, query_terms: &HashSet<String>) -> Vec<(usize, usize)> {
+    let mut ranges = tokenize(text)
+        .into_iter()
+        .filter(|token| query_terms.contains(&token.term))
+        .map(|token| (token.start, token.end))
+        .collect::<Vec<_>>();
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+// 转义HTML并用 <mark> 标出命中的词
+fn highlight(text: &str, query_terms: &HashSet<String>) -> String {
+    mark_ranges(text, &matched_ranges(text, query_terms))
+}
+
+fn mark_ranges(text: &str, ranges: &[(usize, usize)]) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for &(start, end) in ranges {
+        result.push_str(&escape_html(&text[last..start]));
+        result.push_str("<mark>");
+        result.push_str(&escape_html(&text[start..end]));
+        result.push_str("</mark>");
+        last = end;
+    }
+    result.push_str(&escape_html(&text[last..]));
+    result
+}
+
+// 从重现步骤中截取第一个命中词附近的一段文字作为摘要
+fn snippet(text: &str, query_terms: &HashSet<String>) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let ranges = matched_ranges(&text, query_terms);
+
+    // 以字符为单位确定摘要的起止位置，避免截断多字节字符
+    let boundaries = text.char_indices().map(|(pos, _)| pos).chain([text.len()]).collect::<Vec<_>>();
+    let char_count = boundaries.len() - 1;
+    let first_char = ranges
+        .first()
+        .map(|(start, _)| boundaries.partition_point(|pos| pos < start))
+        .unwrap_or(0);
+    let start_char = first_char.saturating_sub(SNIPPET_CONTEXT);
+    let end_char = (start_char + SNIPPET_CHARS).min(char_count);
+    let (start, end) = (boundaries[start_char], boundaries[end_char]);
+
+    let ranges = ranges
+        .into_iter()
+        .filter(|(range_start, range_end)| *range_start >= start && *range_end <= end)
+        .map(|(range_start, range_end)| (range_start - start, range_end - start))
+        .collect::<Vec<_>>();
+    let mut result = mark_ranges(&text[start..end], &ranges);
+    if start > 0 {
+        result.insert(0, '…');
+    }
+    if end < text.len() {
+        result.push('…');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bug(id: i32, title: &str, steps: &str) -> Bug {
+        serde_json::from_value(json!({
+            "id": id, "product": 1, "branch": 0, "module": 0, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": title, "keywords": "", "severity": 3, "pri": 3,
+            "type": "codeerror", "steps": steps, "openedBy": null, "openedDate": "2025-01-01",
+            "assignedTo": null, "status": "active"
+        }))
+        .unwrap()
+    }
+
+    fn terms(tokens: Vec<Token>) -> Vec<String> {
+        tokens.into_iter().map(|token| token.term).collect()
+    }
+
+    fn query_terms(query: &str) -> HashSet<String> {
+        terms(tokenize_query(query)).into_iter().collect()
+    }
+
+    #[test]
+    fn tokenize_mixed_text() {
+        let tokens = tokenize("修复App崩溃, v2.0!");
+        assert_eq!(terms(tokens.clone()), ["修", "复", "修复", "app", "崩", "溃", "崩溃", "v2", "0"]);
+        // 位置为原文中的字节下标
+        assert_eq!((tokens[3].start, tokens[3].end), (6, 9));
+        assert_eq!((tokens[6].start, tokens[6].end), (9, 15));
+        assert!(tokenize(" ,.!？ ").is_empty());
+    }
+
+    #[test]
+    fn tokenize_query_uses_bigrams_for_phrases() {
+        assert_eq!(terms(tokenize_query("登录失败")), ["登录", "录失", "失败"]);
+        assert_eq!(terms(tokenize_query("崩")), ["崩"]);
+        assert_eq!(terms(tokenize_query("Crash 崩溃")), ["crash", "崩溃"]);
+    }
+
+    #[test]
+    fn bm25_ranks_title_and_rare_terms_first() {
+        let mut index = SearchIndex::default();
+        index.upsert(&bug(1, "保存报表", "<p>点击按钮后页面崩溃</p>"));
+        index.upsert(&bug(2, "页面崩溃", "<p>打开页面</p>"));
+        index.upsert(&bug(3, "导出报表", "<p>导出时提示错误</p>"));
+
+        let ids = |query: &str| index.search(query, 10).into_iter().map(|hit| hit.bug_id).collect::<Vec<_>>();
+        // 标题命中排在步骤命中前面
+        assert_eq!(ids("崩溃"), [2, 1]);
+        // 同时命中多个词的排在前面
+        assert_eq!(ids("导出 报表"), [3, 1]);
+        assert!(ids("不存在").is_empty());
+        assert_eq!(index.search("页面", 1).len(), 1);
+
+        let hits = index.search("崩溃", 10);
+        assert!(hits[0].score > hits[1].score);
+    }
+
+    #[test]
+    fn upsert_and_remove_update_postings() {
+        let mut index = SearchIndex::default();
+        assert!(index.upsert(&bug(1, "登录失败", "")));
+        assert!(!index.upsert(&bug(1, "登录失败", "")));
+        assert!(index.upsert(&bug(1, "注册失败", "")));
+        assert!(index.search("登录", 10).is_empty());
+        assert!(index.remove(1));
+        assert!(index.is_empty());
+        assert!(index.postings.is_empty());
+        assert_eq!(index.total_length, 0);
+    }
+
+    #[test]
+    fn highlight_escapes_and_marks() {
+        let marked = highlight("<b>保存</b>失败", &query_terms("保存"));
+        assert_eq!(marked, "&lt;b&gt;<mark>保存</mark>&lt;/b&gt;失败");
+        assert_eq!(highlight("Crash here", &query_terms("crash")), "<mark>Crash</mark> here");
+    }
+
+    #[test]
+    fn snippet_cuts_multibyte_text_on_char_boundaries() {
+        let text = format!("{}崩溃{}", "前".repeat(100), "后".repeat(200));
+        let result = snippet(&text, &query_terms("崩溃"));
+        assert!(result.starts_with('…') && result.ends_with('…'));
+        assert!(result.contains("<mark>崩溃</mark>"));
+        let plain = result.replace("<mark>", "").replace("</mark>", "");
+        assert_eq!(plain.trim_matches('…').chars().count(), SNIPPET_CHARS);
+        assert!(plain.starts_with(&format!("…{}崩溃", "前".repeat(SNIPPET_CONTEXT))));
+
+        // 命中词在开头时不加前省略号，短文本不加后省略号
+        assert_eq!(snippet("崩溃了", &query_terms("崩溃")), "<mark>崩溃</mark>了");
+        // 没有命中时从头截取
+        let result = snippet(&"字".repeat(200), &query_terms("崩溃"));
+        assert!(!result.starts_with('…') && result.ends_with('…'));
+    }
+
+    #[test]
+    fn snippet_drops_marks_outside_window() {
+        let text = format!("崩溃{}崩溃", "中".repeat(SNIPPET_CHARS));
+        let result = snippet(&text, &query_terms("崩溃"));
+        assert_eq!(result.matches("<mark>").count(), 1);
+    }
+
+    #[test]
+    fn notes_are_searched_and_used_for_snippets() {
+        let mut index = SearchIndex::default();
+        index.upsert(&bug(1, "标题", "<p>步骤</p>"));
+        assert!(index.search("复现", 10).is_empty());
+        index.set_notes(BTreeMap::from([(1, "稳定复现".to_string())]));
+        let hits = index.search("复现", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].snippet, "笔记: 稳定<mark>复现</mark>");
+        index.set_notes(BTreeMap::new());
+        assert!(index.search("复现", 10).is_empty());
+    }
+}
@@ -0,0 +1,620 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::http::{self, HttpClient};
+use crate::legacy;
+use crate::config::ZentaoConfig;
+use crate::models::{decode_unicode_escapes, LoginRequest};
+use crate::redact::body_preview;
+
+// 禅道服务器地址，由用户输入的基地址规范化得到
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerUrls {
+    // 禅道根地址，如 http://192.168.181.130:81 或 https://host/zentao
+    pub root: String,
+    // RESTful API 地址：{root}/api.php/v1
+    pub api: String,
+}
+
+// 规范化用户输入的基地址：补全协议，去掉结尾的斜杠、api.php/v1、index.php、查询参数
+pub fn normalize_base_url(base_url: &str) -> Result<ServerUrls, String> {
+    let input = base_url.trim();
+    if input.is_empty() {
+        return Err("服务器地址不能为空".to_string());
+    }
+
+    let with_scheme = if input.contains("://") {
+        input.to_string()
+    } else {
+        format!("http://{}", input)
+    };
+
+    let url = Url::parse(&with_scheme).map_err(|e| format!("服务器地址无效: {} - {}", input, e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("不支持的协议: {}，请使用 http 或 https", url.scheme()));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("服务器地址缺少主机名: {}", input))?;
+
+    let mut path = url.path().trim_end_matches('/');
+    for suffix in ["/api.php/v1", "/api.php", "/index.php"] {
+        if let Some(stripped) = path.strip_suffix(suffix) {
+            path = stripped;
+            break;
+        }
+    }
+    let path = path.trim_end_matches('/');
+
+    let root = match url.port() {
+        Some(port) => format!("{}://{}:{}{}", url.scheme(), host, port, path),
+        None => format!("{}://{}{}", url.scheme(), host, path),
+    };
+    let api = format!("{}/api.php/v1", root);
+
+    Ok(ServerUrls { root, api })
+}
+
+// 服务器地址规范化后转换为目录名，用于按服务器分开保存本地数据
+pub(crate) fn server_dir_name(base_url: &str) -> String {
+    let root = normalize_base_url(base_url)
+        .map(|urls| urls.root)
+        .unwrap_or_else(|_| base_url.to_string());
+    let name = root
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect::<String>();
+    if name.is_empty() { "default".to_string() } else { name }
+}
+
+// 按服务器和账号分别保存的本地数据文件：zentao_app/<kind>/<服务器>/<账号>.json
+pub(crate) fn profile_file_path(kind: &str, base_url: &str, account: &str) -> Result<PathBuf, String> {
+    let mut path = dirs::data_dir().ok_or("无法获取用户数据目录")?;
+    path.push("zentao_app");
+    path.push(kind);
+    path.push(server_dir_name(base_url));
+    let account = account
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '.' || c == '_' { c } else { '_' })
+        .collect::<String>();
+    path.push(format!("{}.json", if account.is_empty() { "default" } else { &account }));
+    Ok(path)
+}
+
+// 配置中的API模式：自动检测，或强制使用某一种API
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiMode {
+    #[default]
+    Auto,
+    Rest,
+    Legacy,
+}
+
+// 当前会话使用的API，随token一起保存
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ApiKind {
+    // RESTful API (api.php/v1)，token 为 API token
+    #[default]
+    Rest,
+    // 旧版 JSON API，token 为会话ID
+    Legacy {
+        #[serde(rename = "sessionName")]
+        session_name: String,
+    },
+}
+
+// 服务器配置（index.php?mode=getconfig 的返回内容）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    pub version: String,
+    #[serde(rename = "requestType")]
+    #[serde(default)]
+    pub request_type: String,
+    #[serde(rename = "sessionName")]
+    #[serde(default)]
+    pub session_name: String,
+    #[serde(rename = "sessionID")]
+    #[serde(default)]
+    pub session_id: String,
+}
+
+// 获取服务器版本等配置信息，所有版本的禅道都支持该接口
+pub async fn fetch_server_config(client: &HttpClient, urls: &ServerUrls) -> Result<ServerConfig, String> {
+    let config_url = format!("{}/index.php?mode=getconfig", urls.root);
+    debug!("获取服务器配置: {}", config_url);
+
+    let response = client
+        .send(client.get(&config_url))
+        .await
+        .map_err(|e| describe_request_error(&e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("获取服务器配置失败，状态码: {}", status));
+    }
+
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("读取服务器配置失败: {}", e))?;
+    serde_json::from_str::<ServerConfig>(&text).map_err(|_| {
+        format!("响应不是禅道服务器配置，请检查地址是否正确 - 响应预览: {}", body_preview(&text, 100))
+    })
+}
+
+// 根据版本号判断是否一定不支持 RESTful API：开源版 12.x 之前的版本没有 api.php/v1
+// 企业版等带前缀的版本号（如 biz、max）无法判断，返回 None
+pub fn rest_api_unsupported(version: &str) -> Option<bool> {
+    let major = version.split('.').next()?.trim().parse::<u32>().ok()?;
+    Some(major < 12)
+}
+
+// 检测应使用的API：配置为自动时，先按版本号判断，再探测 RESTful API 是否可用
+pub async fn detect_api_mode(client: &HttpClient, urls: &ServerUrls, mode: ApiMode) -> Result<ApiMode, String> {
+    if mode != ApiMode::Auto {
+        debug!("使用配置指定的API模式: {:?}", mode);
+        return Ok(mode);
+    }
+
+    match fetch_server_config(client, urls).await {
+        Ok(server_config) => {
+            info!("禅道版本: {}", server_config.version);
+            if rest_api_unsupported(&server_config.version) == Some(true) {
+                info!("禅道版本 {} 不支持 RESTful API，使用旧版 JSON API", server_config.version);
+                return Ok(ApiMode::Legacy);
+            }
+        }
+        Err(e) => warn!("获取禅道版本失败: {}", e),
+    }
+
+    if check_rest_api(client, urls).await? {
+        Ok(ApiMode::Rest)
+    } else {
+        info!("RESTful API 不可用，使用旧版 JSON API");
+        Ok(ApiMode::Legacy)
+    }
+}
+
+// 检查 RESTful API 是否可用：未登录访问 /user 时，支持API的版本返回401
+pub async fn check_rest_api(client: &HttpClient, urls: &ServerUrls) -> Result<bool, String> {
+    let user_url = format!("{}/user", urls.api);
+    let response = client
+        .send(client.get(&user_url))
+        .await
+        .map_err(|e| describe_request_error(&e))?;
+    let status = response.status();
+    debug!("RESTful API 检测响应状态: {}", status);
+
+    if status == 404 {
+        return Ok(false);
+    }
+    let text = response.text().await.unwrap_or_default();
+    Ok(serde_json::from_str::<Value>(&text).is_ok())
+}
+
+// 使用账号密码获取 RESTful API 的token
+pub async fn request_token(
+    client: &HttpClient,
+    urls: &ServerUrls,
+    account: String,
+    password: String,
+) -> Result<String, String> {
+    let login_url = format!("{}/tokens", urls.api);
+    let login_request = LoginRequest { account, password };
+
+    info!("尝试登录到: {}", login_url);
+
+    let request = client
+        .post(&login_url)
+        .header("Content-Type", "application/json")
+        .json(&login_request);
+
+    let response = client
+        .send(request)
+        .await
+        .map_err(|e| format!("请求失败: {}", describe_request_error(&e)))?;
+    let status = response.status();
+    debug!("登录响应状态: {}", status);
+
+    if !status.is_success() {
+        return match response.text().await {
+            Ok(error_text) => {
+                // 解码错误信息中的 Unicode 转义序列
+                let decoded_error = decode_unicode_escapes(&error_text);
+                Err(format!("登录失败，状态码: {} - 错误信息: {}", status, decoded_error))
+            }
+            Err(_) => Err(format!("登录失败，状态码: {}", status)),
+        };
+    }
+
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("读取响应内容失败: {}", e))?;
+    debug!("登录响应: 成功获取token (长度: {})", text.len());
+
+    // 解码 Unicode 转义序列
+    let decoded_text = decode_unicode_escapes(&text);
+    let json = serde_json::from_str::<Value>(&decoded_text)
+        .map_err(|e| format!("解析JSON失败: {} - 响应预览: {}", e, body_preview(&text, 100)))?;
+
+    match json.get("token") {
+        Some(Value::String(token)) => Ok(token.clone()),
+        Some(_) => Err("token字段不是字符串类型".to_string()),
+        None => Err(format!("响应中没有找到token字段: {}", body_preview(&text, 100))),
+    }
+}
+
+// 生成请求错误的说明，区分超时、TLS证书问题和连接失败
+pub fn describe_request_error(error: &reqwest::Error) -> String {
+    let mut details = Vec::new();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        details.push(cause.to_string());
+        source = cause.source();
+    }
+    let detail = if details.is_empty() {
+        error.to_string()
+    } else {
+        details.join(": ")
+    };
+
+    let lowered = detail.to_lowercase();
+    if error.is_timeout() {
+        format!("请求超时: {}", detail)
+    } else if lowered.contains("certificate") || lowered.contains("tls") || lowered.contains("ssl") {
+        format!("TLS握手失败（证书不受信任时可配置CA证书或开启忽略证书）: {}", detail)
+    } else if error.is_connect() {
+        format!("无法连接到服务器: {}", detail)
+    } else {
+        format!("请求失败: {}", detail)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Ok,
+    Warning,
+    Failed,
+    Skipped,
+}
+
+// 连接测试中的单个步骤
+#[derive(Debug, Serialize)]
+pub struct DiagnosisStep {
+    // 步骤标识：url | network | reachability | version | api | login
+    name: String,
+    title: String,
+    status: StepStatus,
+    message: String,
+    #[serde(rename = "elapsedMs")]
+    elapsed_ms: u64,
+}
+
+// 连接测试结果，逐步说明哪一步失败以及原因
+#[derive(Debug, Serialize)]
+pub struct ConnectionDiagnosis {
+    success: bool,
+    #[serde(rename = "rootUrl")]
+    root_url: Option<String>,
+    #[serde(rename = "apiUrl")]
+    api_url: Option<String>,
+    version: Option<String>,
+    #[serde(rename = "restApi")]
+    rest_api: Option<bool>,
+    // 实际使用的API：rest | legacy
+    #[serde(rename = "apiMode")]
+    api_mode: Option<ApiMode>,
+    steps: Vec<DiagnosisStep>,
+}
+
+impl ConnectionDiagnosis {
+    fn record(&mut self, name: &str, title: &str, started: Instant, result: Result<String, (StepStatus, String)>) -> bool {
+        let (status, message) = match result {
+            Ok(message) => (StepStatus::Ok, message),
+            Err((status, message)) => (status, message),
+        };
+        match status {
+            StepStatus::Failed => warn!("连接测试 [{}] 失败: {}", title, message),
+            _ => debug!("连接测试 [{}] {:?}: {}", title, status, message),
+        }
+        self.steps.push(DiagnosisStep {
+            name: name.to_string(),
+            title: title.to_string(),
+            status,
+            message,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        });
+        status != StepStatus::Failed
+    }
+
+    // 前面的步骤失败后，剩余步骤标记为跳过
+    fn skip_remaining(&mut self, remaining: &[(&str, &str)]) {
+        for (name, title) in remaining {
+            self.steps.push(DiagnosisStep {
+                name: name.to_string(),
+                title: title.to_string(),
+                status: StepStatus::Skipped,
+                message: "前面的步骤失败，未执行".to_string(),
+                elapsed_ms: 0,
+            });
+        }
+    }
+}
+
+const STEPS: [(&str, &str); 6] = [
+    ("url", "服务器地址"),
+    ("network", "网络设置"),
+    ("reachability", "连通性与TLS"),
+    ("version", "禅道版本"),
+    ("api", "API可用性"),
+    ("login", "登录"),
+];
+
+// 按步骤测试服务器配置：地址 -> 网络设置 -> 连通性 -> 版本 -> API -> 登录
+pub async fn test_connection(config: &ZentaoConfig) -> ConnectionDiagnosis {
+    let mut diagnosis = ConnectionDiagnosis {
+        success: false,
+        root_url: None,
+        api_url: None,
+        version: None,
+        rest_api: None,
+        api_mode: None,
+        steps: Vec::new(),
+    };
+
+    // 1. 规范化地址
+    let started = Instant::now();
+    let urls = match normalize_base_url(&config.base_url) {
+        Ok(urls) => {
+            let message = format!("根地址: {}，API地址: {}", urls.root, urls.api);
+            diagnosis.record(STEPS[0].0, STEPS[0].1, started, Ok(message));
+            urls
+        }
+        Err(e) => {
+            diagnosis.record(STEPS[0].0, STEPS[0].1, started, Err((StepStatus::Failed, e)));
+            diagnosis.skip_remaining(&STEPS[1..]);
+            return diagnosis;
+        }
+    };
+    diagnosis.root_url = Some(urls.root.clone());
+    diagnosis.api_url = Some(urls.api.clone());
+
+    // 2. 网络设置（代理、证书），测试时不重试，尽快给出结果
+    let started = Instant::now();
+    let mut network = config.network.clone();
+    network.retry.max_attempts = 1;
+    let client = match http::build_http_client(&network) {
+        Ok(client) => {
+            diagnosis.record(STEPS[1].0, STEPS[1].1, started, Ok(describe_network(&network)));
+            client
+        }
+        Err(e) => {
+            diagnosis.record(STEPS[1].0, STEPS[1].1, started, Err((StepStatus::Failed, e)));
+            diagnosis.skip_remaining(&STEPS[2..]);
+            return diagnosis;
+        }
+    };
+
+    // 3. 连通性与TLS：能收到任何HTTP响应即认为可达
+    let started = Instant::now();
+    let result = match client.send(client.get(&format!("{}/", urls.root))).await {
+        Ok(response) => Ok(format!("服务器已响应，状态码: {}", response.status())),
+        Err(e) => Err((StepStatus::Failed, describe_request_error(&e))),
+    };
+    if !diagnosis.record(STEPS[2].0, STEPS[2].1, started, result) {
+        diagnosis.skip_remaining(&STEPS[3..]);
+        return diagnosis;
+    }
+
+    // 4. 版本检测：失败不影响后续步骤
+    let started = Instant::now();
+    let result = match fetch_server_config(&client, &urls).await {
+        Ok(server_config) => {
+            diagnosis.version = Some(server_config.version.clone());
+            Ok(format!("禅道版本: {}", server_config.version))
+        }
+        Err(e) => Err((StepStatus::Warning, format!("无法识别禅道版本: {}", e))),
+    };
+    diagnosis.record(STEPS[3].0, STEPS[3].1, started, result);
+
+    // 5. API可用性：RESTful API 不可用时回退到旧版 JSON API
+    let started = Instant::now();
+    let legacy_expected = diagnosis
+        .version
+        .as_deref()
+        .and_then(rest_api_unsupported)
+        .unwrap_or(false);
+    let result = match config.api_mode {
+        ApiMode::Rest => Ok((ApiMode::Rest, "已配置为使用 RESTful API，跳过检测".to_string())),
+        ApiMode::Legacy => Ok((ApiMode::Legacy, "已配置为使用旧版 JSON API，跳过检测".to_string())),
+        ApiMode::Auto if legacy_expected => {
+            diagnosis.rest_api = Some(false);
+            Ok((ApiMode::Legacy, "该版本不支持 RESTful API，将使用旧版 JSON API".to_string()))
+        }
+        ApiMode::Auto => match check_rest_api(&client, &urls).await {
+            Ok(true) => {
+                diagnosis.rest_api = Some(true);
+                Ok((ApiMode::Rest, "RESTful API (api.php/v1) 可用".to_string()))
+            }
+            Ok(false) => {
+                diagnosis.rest_api = Some(false);
+                Ok((ApiMode::Legacy, "RESTful API (api.php/v1) 不可用，将使用旧版 JSON API".to_string()))
+            }
+            Err(e) => Err(e),
+        },
+    };
+    let api_mode = match result {
+        Ok((api_mode, message)) => {
+            let step = if api_mode == ApiMode::Legacy && config.api_mode == ApiMode::Auto {
+                Err((StepStatus::Warning, message))
+            } else {
+                Ok(message)
+            };
+            diagnosis.record(STEPS[4].0, STEPS[4].1, started, step);
+            diagnosis.api_mode = Some(api_mode);
+            api_mode
+        }
+        Err(e) => {
+            diagnosis.record(STEPS[4].0, STEPS[4].1, started, Err((StepStatus::Failed, e)));
+            diagnosis.skip_remaining(&STEPS[5..]);
+            return diagnosis;
+        }
+    };
+
+    // 6. 登录：只验证账号密码，不保存token
+    let started = Instant::now();
+    let login = if api_mode == ApiMode::Legacy {
+        legacy::login(&client, &urls, &config.account, &config.password)
+            .await
+            .map(|_| ())
+    } else {
+        request_token(&client, &urls, config.account.clone(), config.password.clone())
+            .await
+            .map(|_| ())
+    };
+    let result = match login {
+        Ok(()) => Ok(format!("账号 {} 登录成功", config.account)),
+        Err(e) => Err((StepStatus::Failed, e)),
+    };
+    diagnosis.success = diagnosis.record(STEPS[5].0, STEPS[5].1, started, result);
+
+    info!("连接测试完成，结果: {}", if diagnosis.success { "成功" } else { "失败" });
+    diagnosis
+}
+
+// 网络设置摘要，不包含代理地址中的账号密码
+fn describe_network(network: &http::NetworkConfig) -> String {
+    let mut parts = Vec::new();
+    let has = |value: &Option<String>| value.as_deref().is_some_and(|s| !s.trim().is_empty());
+    parts.push(if has(&network.proxy_url) { "使用代理" } else { "直连" }.to_string());
+    if has(&network.ca_cert_path) {
+        parts.push("已加载额外CA证书".to_string());
+    }
+    if network.accept_invalid_certs {
+        parts.push("不校验证书".to_string());
+    }
+    parts.push(format!(
+        "连接超时{}秒，读取超时{}秒",
+        network.connect_timeout_secs, network.read_timeout_secs
+    ));
+    parts.join("，")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    #[test]
+    fn normalizes_base_urls() {
+        let cases = [
+            ("http://host", "http://host"),
+            ("http://host/", "http://host"),
+            ("  https://host:8443//  ", "https://host:8443"),
+            ("http://host/zentao/", "http://host/zentao"),
+            ("http://host/zentao/api.php/v1", "http://host/zentao"),
+            ("http://host/zentao/api.php/v1/", "http://host/zentao"),
+            ("http://host/api.php", "http://host"),
+            ("http://host/zentao/index.php?m=my", "http://host/zentao"),
+            ("http://host/index.php#/bug", "http://host"),
+            ("192.168.181.130:81", "http://192.168.181.130:81"),
+            ("host/zentao", "http://host/zentao"),
+        ];
+        for (input, root) in cases {
+            let urls = normalize_base_url(input).unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(urls.root, root, "{}", input);
+            assert_eq!(urls.api, format!("{}/api.php/v1", root), "{}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_base_urls() {
+        for input in ["", "   ", "ftp://host", "http://", "http://host:port", "http://[::1"] {
+            assert!(normalize_base_url(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn maps_servers_to_directory_names() {
+        let cases = [
+            ("http://host/", "host"),
+            ("https://host:8443/zentao/api.php/v1", "host_8443_zentao"),
+            ("192.168.1.2:81", "192.168.1.2_81"),
+            ("http://my-host.example.com/index.php", "my-host.example.com"),
+            // 无效地址按原样替换非法字符
+            ("ftp://a b", "ftp___a_b"),
+            ("", "default"),
+        ];
+        for (input, name) in cases {
+            assert_eq!(server_dir_name(input), name, "{}", input);
+        }
+        // 同一服务器的不同写法使用同一目录
+        assert_eq!(server_dir_name("host/zentao"), server_dir_name("http://host/zentao/index.php"));
+    }
+
+    #[test]
+    fn detects_versions_without_rest_api() {
+        let cases = [
+            ("11.7", Some(true)),
+            ("9.8.3", Some(true)),
+            ("12.0", Some(false)),
+            ("18.10", Some(false)),
+            (" 17.6 ", Some(false)),
+            ("biz5.0", None),
+            ("max3.2", None),
+            ("", None),
+        ];
+        for (version, expected) in cases {
+            assert_eq!(rest_api_unsupported(version), expected, "{}", version);
+        }
+    }
+
+    #[tokio::test]
+    async fn describes_connection_failures() {
+        // 绑定后立即释放端口，连接会被拒绝
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let error = reqwest::Client::new()
+            .get(format!("http://127.0.0.1:{}/", port))
+            .send()
+            .await
+            .unwrap_err();
+        let message = describe_request_error(&error);
+        assert!(message.starts_with("无法连接到服务器: "), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn describes_timeouts() {
+        // 接受连接但从不响应
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let error = reqwest::Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap()
+            .get(format!("http://127.0.0.1:{}/", port))
+            .send()
+            .await
+            .unwrap_err();
+        let message = describe_request_error(&error);
+        assert!(message.starts_with("请求超时: "), "{}", message);
+        drop(listener);
+    }
+
+    #[tokio::test]
+    async fn describes_other_request_errors() {
+        let error = reqwest::Client::new().get("http://").send().await.unwrap_err();
+        let message = describe_request_error(&error);
+        assert!(message.starts_with("请求失败: "), "{}", message);
+    }
+}
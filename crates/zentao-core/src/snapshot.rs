@@ -0,0 +1,364 @@
+// Bug快照：每天记录各产品按状态、严重程度和指派人的Bug数量，用于燃尽图和趋势图
+// 快照按禅道服务器和产品分别保存在 zentao_app/snapshots/<服务器>/<产品ID>.json，
+// 同一天多次记录时保留最后一次
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::models::Bug;
+use crate::report::DateRange;
+use crate::server;
+
+// 每个产品最多保留的快照天数
+const MAX_SNAPSHOTS: usize = 3 * 366;
+
+// 读取-修改-写入快照文件时加锁，避免后台轮询和刷新Bug列表同时记录快照
+static SNAPSHOT_FILE: Mutex<()> = Mutex::new(());
+
+// 某个产品某一天的Bug数量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub date: NaiveDate,
+    #[serde(rename = "productId")]
+    pub product_id: i32,
+    // 记录时间（RFC 3339）
+    #[serde(rename = "takenAt")]
+    pub taken_at: String,
+    pub total: usize,
+    // 状态代码 -> 数量
+    #[serde(rename = "byStatus")]
+    pub by_status: BTreeMap<String, usize>,
+    // 严重程度 -> 数量
+    #[serde(rename = "bySeverity")]
+    pub by_severity: BTreeMap<String, usize>,
+    // 指派人账号 -> 数量，未指派为空字符串；只统计未关闭的Bug
+    #[serde(rename = "byAssignee")]
+    pub by_assignee: BTreeMap<String, usize>,
+}
+
+impl Snapshot {
+    pub fn new(product_id: i32, bugs: &[Bug]) -> Snapshot {
+        let now = Local::now();
+        let mut snapshot = Snapshot {
+            date: now.date_naive(),
+            product_id,
+            taken_at: now.to_rfc3339(),
+            total: 0,
+            by_status: BTreeMap::new(),
+            by_severity: BTreeMap::new(),
+            by_assignee: BTreeMap::new(),
+        };
+        for bug in bugs.iter().filter(|bug| bug.product == product_id) {
+            snapshot.total += 1;
+            *snapshot.by_status.entry(bug.status.code.clone()).or_default() += 1;
+            *snapshot.by_severity.entry(bug.severity.to_string()).or_default() += 1;
+            if bug.status.code != "closed" {
+                let account = bug.assigned_to.as_ref().map(|user| user.account.clone()).unwrap_or_default();
+                *snapshot.by_assignee.entry(account).or_default() += 1;
+            }
+        }
+        snapshot
+    }
+
+    fn status_count(&self, code: &str) -> usize {
+        self.by_status.get(code).copied().unwrap_or(0)
+    }
+}
+
+fn snapshot_file_path(base_url: &str, product_id: i32) -> Result<PathBuf, String> {
+    let mut path = dirs::data_dir().ok_or("无法获取用户数据目录")?;
+    path.push("zentao_app");
+    path.push("snapshots");
+    path.push(server::server_dir_name(base_url));
+    path.push(format!("{}.json", product_id));
+    Ok(path)
+}
+
+fn load(base_url: &str, product_id: i32) -> Result<Vec<Snapshot>, String> {
+    let path = snapshot_file_path(base_url, product_id)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("读取快照文件失败: {}", e)),
+    };
+    match serde_json::from_str::<Vec<Snapshot>>(&content) {
+        Ok(snapshots) => Ok(snapshots),
+        Err(e) => {
+            // 文件损坏时重新开始记录
+            warn!("解析快照文件{:?}失败，已忽略: {}", path, e);
+            Ok(Vec::new())
+        }
+    }
+}
+
+fn save_snapshots(base_url: &str, product_id: i32, snapshots: &[Snapshot]) -> Result<(), String> {
+    let path = snapshot_file_path(base_url, product_id)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建快照目录失败: {}", e))?;
+    }
+    let json = serde_json::to_string(snapshots).map_err(|e| format!("序列化快照失败: {}", e))?;
+    // 先写临时文件再替换，避免写到一半时损坏历史快照
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json).map_err(|e| format!("写入快照文件失败: {}", e))?;
+    fs::rename(&temp, &path).map_err(|e| format!("写入快照文件失败: {}", e))
+}
+
+// 读取产品的全部快照（按日期排列），没有快照时返回空列表
+pub fn load_snapshots(base_url: &str, product_id: i32) -> Result<Vec<Snapshot>, String> {
+    let _guard = SNAPSHOT_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    load(base_url, product_id)
+}
+
+// 记录快照，同一天已有快照时替换
+pub fn record_snapshot(base_url: &str, snapshot: Snapshot) -> Result<(), String> {
+    let _guard = SNAPSHOT_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut snapshots = load(base_url, snapshot.product_id)?;
+    snapshots.retain(|existing| existing.date != snapshot.date);
+    let product_id = snapshot.product_id;
+    snapshots.push(snapshot);
+    snapshots.sort_by_key(|snapshot| snapshot.date);
+    if snapshots.len() > MAX_SNAPSHOTS {
+        let excess = snapshots.len() - MAX_SNAPSHOTS;
+        snapshots.drain(..excess);
+    }
+    save_snapshots(base_url, product_id, &snapshots)?;
+    debug!("已记录产品{}的Bug快照", product_id);
+    Ok(())
+}
+
+// 同步Bug列表后为每个产品记录快照，bugs 为这些产品的全部Bug
+pub fn record_snapshots(base_url: &str, product_ids: &[i32], bugs: &[Bug]) -> Result<(), String> {
+    for &product_id in product_ids {
+        record_snapshot(base_url, Snapshot::new(product_id, bugs))?;
+    }
+    Ok(())
+}
+
+// 今天是否已经记录过快照
+pub fn has_snapshot_today(base_url: &str, product_id: i32) -> bool {
+    let today = Local::now().date_naive();
+    load_snapshots(base_url, product_id)
+        .map(|snapshots| snapshots.iter().any(|snapshot| snapshot.date == today))
+        .unwrap_or(false)
+}
+
+// 趋势指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendMetric {
+    // 燃尽：未解决（激活）的Bug数
+    #[default]
+    Burndown,
+    // 燃起：Bug总数和已完成（已解决或已关闭）的Bug数
+    Burnup,
+    // 按状态分组
+    Status,
+    // 按严重程度分组
+    Severity,
+    // 按指派人分组（未关闭的Bug）
+    Assignee,
+}
+
+impl FromStr for TrendMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "burndown" => Ok(TrendMetric::Burndown),
+            "burnup" => Ok(TrendMetric::Burnup),
+            "status" => Ok(TrendMetric::Status),
+            "severity" => Ok(TrendMetric::Severity),
+            "assignee" => Ok(TrendMetric::Assignee),
+            other => Err(format!(
+                "不支持的趋势指标: {}（可选 burndown、burnup、status、severity、assignee）",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendValue {
+    pub date: NaiveDate,
+    pub value: usize,
+}
+
+// 一条时间序列，key 为状态代码、严重程度或账号等
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendSeries {
+    pub key: String,
+    pub points: Vec<TrendValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trend {
+    #[serde(rename = "productId")]
+    pub product_id: i32,
+    pub metric: TrendMetric,
+    // 有快照的日期
+    pub dates: Vec<NaiveDate>,
+    pub series: Vec<TrendSeries>,
+}
+
+// 根据快照生成时间序列，每条序列在每个有快照的日期都有一个点（没有数据时为0）
+pub fn build_trend(product_id: i32, snapshots: &[Snapshot], metric: TrendMetric, range: DateRange) -> Trend {
+    let snapshots = snapshots
+        .iter()
+        .filter(|snapshot| range.contains(snapshot.date))
+        .collect::<Vec<_>>();
+    let dates = snapshots.iter().map(|snapshot| snapshot.date).collect::<Vec<_>>();
+
+    let series_of = |key: &str, value: &dyn Fn(&Snapshot) -> usize| TrendSeries {
+        key: key.to_string(),
+        points: snapshots
+            .iter()
+            .map(|snapshot| TrendValue { date: snapshot.date, value: value(snapshot) })
+            .collect(),
+    };
+    let grouped = |group: &dyn Fn(&Snapshot) -> &BTreeMap<String, usize>| {
+        let mut keys = snapshots
+            .iter()
+            .flat_map(|snapshot| group(snapshot).keys().cloned())
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        keys.iter()
+            .map(|key| series_of(key, &|snapshot| group(snapshot).get(key).copied().unwrap_or(0)))
+            .collect::<Vec<_>>()
+    };
+
+    let series = match metric {
+        TrendMetric::Burndown => vec![series_of("active", &|snapshot| snapshot.status_count("active"))],
+        TrendMetric::Burnup => vec![
+            series_of("total", &|snapshot| snapshot.total),
+            series_of("done", &|snapshot| {
+                snapshot.status_count("resolved") + snapshot.status_count("closed")
+            }),
+        ],
+        TrendMetric::Status => grouped(&|snapshot| &snapshot.by_status),
+        TrendMetric::Severity => grouped(&|snapshot| &snapshot.by_severity),
+        TrendMetric::Assignee => grouped(&|snapshot| &snapshot.by_assignee),
+    };
+
+    Trend { product_id, metric, dates, series }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bug(id: i32, product: i32, status: &str, severity: i32, assigned_to: Option<&str>) -> Bug {
+        let assigned_to = assigned_to.map(|account| json!({ "id": 1, "account": account, "avatar": "", "realname": account }));
+        serde_json::from_value(json!({
+            "id": id, "product": product, "branch": 0, "module": 0, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": "", "keywords": "", "severity": severity, "pri": 3,
+            "type": "codeerror", "steps": "", "openedBy": null, "openedDate": "2025-01-01",
+            "assignedTo": assigned_to, "status": status
+        }))
+        .unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    fn snapshot(day: u32, by_status: &[(&str, usize)], by_assignee: &[(&str, usize)]) -> Snapshot {
+        let counts = |pairs: &[(&str, usize)]| pairs.iter().map(|(key, count)| (key.to_string(), *count)).collect();
+        Snapshot {
+            date: date(day),
+            product_id: 1,
+            taken_at: String::new(),
+            total: by_status.iter().map(|(_, count)| count).sum(),
+            by_status: counts(by_status),
+            by_severity: BTreeMap::new(),
+            by_assignee: counts(by_assignee),
+        }
+    }
+
+    fn values(series: &TrendSeries) -> Vec<usize> {
+        series.points.iter().map(|point| point.value).collect()
+    }
+
+    #[test]
+    fn snapshot_counts_bugs_of_the_product() {
+        let bugs = [
+            bug(1, 1, "active", 1, Some("zhangsan")),
+            bug(2, 1, "active", 3, None),
+            bug(3, 1, "resolved", 3, Some("zhangsan")),
+            bug(4, 1, "closed", 2, Some("lisi")),
+            bug(5, 2, "active", 1, Some("lisi")),
+        ];
+        let snapshot = Snapshot::new(1, &bugs);
+        assert_eq!(snapshot.product_id, 1);
+        assert_eq!(snapshot.date, Local::now().date_naive());
+        assert_eq!(snapshot.total, 4);
+        assert_eq!(snapshot.by_status, BTreeMap::from([("active".to_string(), 2), ("closed".to_string(), 1), ("resolved".to_string(), 1)]));
+        assert_eq!(snapshot.by_severity, BTreeMap::from([("1".to_string(), 1), ("2".to_string(), 1), ("3".to_string(), 2)]));
+        // 已关闭的Bug不计入指派人，未指派为空字符串
+        assert_eq!(snapshot.by_assignee, BTreeMap::from([(String::new(), 1), ("zhangsan".to_string(), 2)]));
+        assert_eq!(Snapshot::new(3, &bugs).total, 0);
+    }
+
+    #[test]
+    fn burndown_and_burnup() {
+        let snapshots = [
+            snapshot(1, &[("active", 5)], &[]),
+            snapshot(2, &[("active", 3), ("resolved", 2)], &[]),
+            snapshot(3, &[("active", 1), ("resolved", 2), ("closed", 3)], &[]),
+        ];
+        let burndown = build_trend(1, &snapshots, TrendMetric::Burndown, DateRange::default());
+        assert_eq!(burndown.dates, vec![date(1), date(2), date(3)]);
+        assert_eq!(burndown.series.len(), 1);
+        assert_eq!(values(&burndown.series[0]), vec![5, 3, 1]);
+
+        let burnup = build_trend(1, &snapshots, TrendMetric::Burnup, DateRange::default());
+        let keys = burnup.series.iter().map(|series| series.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["total", "done"]);
+        assert_eq!(values(&burnup.series[0]), vec![5, 5, 6]);
+        assert_eq!(values(&burnup.series[1]), vec![0, 2, 5]);
+    }
+
+    #[test]
+    fn grouped_series_fill_missing_keys_with_zero() {
+        let snapshots = [
+            snapshot(1, &[("active", 2)], &[("zhangsan", 2)]),
+            snapshot(2, &[("active", 1), ("resolved", 1)], &[("lisi", 1)]),
+        ];
+        let trend = build_trend(1, &snapshots, TrendMetric::Assignee, DateRange::default());
+        let series = trend.series.iter().map(|series| (series.key.as_str(), values(series))).collect::<Vec<_>>();
+        assert_eq!(series, vec![("lisi", vec![0, 1]), ("zhangsan", vec![2, 0])]);
+
+        let trend = build_trend(1, &snapshots, TrendMetric::Status, DateRange::default());
+        let series = trend.series.iter().map(|series| (series.key.as_str(), values(series))).collect::<Vec<_>>();
+        assert_eq!(series, vec![("active", vec![2, 1]), ("resolved", vec![0, 1])]);
+    }
+
+    #[test]
+    fn range_limits_dates() {
+        let snapshots = [
+            snapshot(1, &[("active", 5)], &[]),
+            snapshot(2, &[("active", 4)], &[]),
+            snapshot(3, &[("active", 3)], &[]),
+        ];
+        let range = DateRange { from: Some(date(2)), to: Some(date(3)) };
+        let trend = build_trend(1, &snapshots, TrendMetric::Burndown, range);
+        assert_eq!(trend.dates, vec![date(2), date(3)]);
+        assert_eq!(values(&trend.series[0]), vec![4, 3]);
+
+        let empty = build_trend(1, &snapshots, TrendMetric::Status, DateRange { from: Some(date(4)), to: None });
+        assert!(empty.dates.is_empty());
+        assert!(empty.series.is_empty());
+    }
+
+    #[test]
+    fn metric_parses_case_insensitively() {
+        assert_eq!("BurnUp".parse::<TrendMetric>(), Ok(TrendMetric::Burnup));
+        assert!("velocity".parse::<TrendMetric>().is_err());
+    }
+}
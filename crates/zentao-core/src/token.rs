@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64::{Engine as _, engine::general_purpose};
+use tracing::{warn, error, debug};
+
+use crate::config;
+use crate::redact::MASK;
+use crate::server::ApiKind;
+
+// Token持久化数据结构
+#[derive(Serialize, Deserialize)]
+struct TokenData {
+    token: String,
+    expire_time: u64,
+    encrypted: bool,
+    // 兼容没有该字段的旧token文件
+    #[serde(default)]
+    api: ApiKind,
+}
+
+impl fmt::Debug for TokenData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenData")
+            .field("token", &MASK)
+            .field("expire_time", &self.expire_time)
+            .field("encrypted", &self.encrypted)
+            .field("api", &self.api)
+            .finish()
+    }
+}
+
+// 简单的XOR加密/解密函数
+fn xor_encrypt_decrypt(data: &str, key: &str) -> String {
+    let key_bytes = key.as_bytes();
+    let data_bytes = data.as_bytes();
+    let key_len = key_bytes.len();
+    
+    let encrypted: Vec<u8> = data_bytes
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| b ^ key_bytes[i % key_len])
+        .collect();
+    
+    general_purpose::STANDARD.encode(&encrypted)
+}
+
+fn xor_decrypt(encrypted_data: &str, key: &str) -> Result<String, String> {
+    match general_purpose::STANDARD.decode(encrypted_data) {
+        Ok(decoded) => {
+            let key_bytes = key.as_bytes();
+            let key_len = key_bytes.len();
+            
+            let decrypted: Vec<u8> = decoded
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| b ^ key_bytes[i % key_len])
+                .collect();
+            
+            match String::from_utf8(decrypted) {
+                Ok(s) => Ok(s),
+                Err(e) => Err(format!("UTF8解码失败: {}", e)),
+            }
+        }
+        Err(e) => Err(format!("Base64解码失败: {}", e)),
+    }
+}
+
+// 获取配置档的token存储文件路径
+pub fn get_token_file_path(profile: &str) -> Result<PathBuf, String> {
+    config::profile_data_path(profile, "zentao_token.json")
+}
+
+// 保存token到本地文件
+pub fn save_token_to_file(profile: &str, token: &str, expire_time: u64, api: &ApiKind) -> Result<(), String> {
+    let file_path = get_token_file_path(profile)?;
+    
+    // 确保目录存在
+    if let Some(parent) = file_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        return Err(format!("创建目录失败: {}", e));
+    }
+    
+    // 使用简单的加密密钥（实际项目中应该使用更安全的方式）
+    let encryption_key = "zentao_app_key_2025";
+    let encrypted_token = xor_encrypt_decrypt(token, encryption_key);
+    
+    let token_data = TokenData {
+        token: encrypted_token,
+        expire_time,
+        encrypted: true,
+        api: api.clone(),
+    };
+    
+    match serde_json::to_string_pretty(&token_data) {
+        Ok(json_str) => {
+            match fs::write(&file_path, json_str) {
+                Ok(_) => {
+                    debug!("Token已保存到: {:?}", file_path);
+                    Ok(())
+                }
+                Err(e) => Err(format!("写入文件失败: {}", e)),
+            }
+        }
+        Err(e) => Err(format!("序列化Token数据失败: {}", e)),
+    }
+}
+
+// 从本地文件加载token
+pub fn load_token_from_file(profile: &str) -> Result<Option<(String, u64, ApiKind)>, String> {
+    let file_path = get_token_file_path(profile)?;
+    
+    if !file_path.exists() {
+        debug!("Token文件不存在: {:?}", file_path);
+        return Ok(None);
+    }
+    
+    match fs::read_to_string(&file_path) {
+        Ok(content) => {
+            match serde_json::from_str::<TokenData>(&content) {
+                Ok(token_data) => {
+                    let current_time = get_current_timestamp();
+                    
+                    // 检查token是否过期
+                    if current_time >= token_data.expire_time {
+                        warn!("保存的token已过期");
+                        // 删除过期的token文件
+                        let _ = fs::remove_file(&file_path);
+                        return Ok(None);
+                    }
+                    
+                    // 解密token
+                    if token_data.encrypted {
+                        let encryption_key = "zentao_app_key_2025";
+                        match xor_decrypt(&token_data.token, encryption_key) {
+                            Ok(decrypted_token) => {
+                                debug!("成功加载并解密token，剩余时间: {}秒", token_data.expire_time - current_time);
+                                Ok(Some((decrypted_token, token_data.expire_time, token_data.api)))
+                            }
+                            Err(e) => {
+                                error!("解密token失败: {}", e);
+                                // 删除损坏的token文件
+                                let _ = fs::remove_file(&file_path);
+                                Ok(None)
+                            }
+                        }
+                    } else {
+                        // 兼容未加密的旧格式
+                        Ok(Some((token_data.token, token_data.expire_time, token_data.api)))
+                    }
+                }
+                Err(e) => {
+                    error!("解析token文件失败: {}", e);
+                    // 删除损坏的token文件
+                    let _ = fs::remove_file(&file_path);
+                    Ok(None)
+                }
+            }
+        }
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Ok(None)
+            } else {
+                Err(format!("读取token文件失败: {}", e))
+            }
+        }
+    }
+}
+
+// 删除保存的token文件
+pub fn remove_token_file(profile: &str) -> Result<(), String> {
+    let file_path = get_token_file_path(profile)?;
+    
+    if file_path.exists() {
+        match fs::remove_file(&file_path) {
+            Ok(_) => {
+                debug!("Token文件已删除: {:?}", file_path);
+                Ok(())
+            }
+            Err(e) => Err(format!("删除token文件失败: {}", e)),
+        }
+    } else {
+        Ok(())
+    }
+}
+
+// 获取当前时间戳
+pub fn get_current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
@@ -0,0 +1,392 @@
+// Bug列表视图：命名保存的产品选择、筛选条件或查询、排序、分组和显示的列
+// 按禅道服务器和账号分别保存在 zentao_app/views/<服务器>/<账号>.json，同时保存上一次使用的列表设置；
+// 视图可以导出为JSON交给团队其他成员导入
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::server;
+
+// 导出文件的格式版本
+const EXPORT_VERSION: u32 = 1;
+// 视图名称的最大长度（字符数）
+const MAX_NAME_CHARS: usize = 50;
+
+// 读取-修改-写入视图文件时加锁
+static VIEW_FILE: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+// 表格排序：prop 为列的字段名
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewSort {
+    pub prop: String,
+    pub order: SortOrder,
+}
+
+// Bug列表的设置，字段都可以省略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ViewSettings {
+    // 选中的产品ID
+    #[serde(default)]
+    pub products: Vec<i32>,
+    // 状态代码
+    #[serde(default)]
+    pub status: Vec<String>,
+    #[serde(default)]
+    pub severity: Vec<i32>,
+    // 指派人姓名，unassigned 表示未指派
+    #[serde(rename = "assignedTo")]
+    #[serde(default)]
+    pub assigned_to: Vec<String>,
+    // 列表上方的关键字搜索
+    #[serde(default)]
+    pub keyword: String,
+    // 高级查询，语法见 query 模块
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub sort: Option<ViewSort>,
+    // 分组字段，空字符串表示不分组
+    #[serde(rename = "groupBy")]
+    #[serde(default)]
+    pub group_by: String,
+    // 显示的列，为空时使用默认列
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedView {
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: ViewSettings,
+    // 创建和最后修改时间（RFC 3339）
+    #[serde(rename = "createdAt")]
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+// 视图文件：上一次使用的设置和命名视图
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ViewFile {
+    #[serde(default)]
+    last: Option<ViewSettings>,
+    #[serde(default)]
+    views: Vec<SavedView>,
+}
+
+// 导出文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewExport {
+    pub version: u32,
+    #[serde(rename = "exportedAt")]
+    #[serde(default)]
+    pub exported_at: String,
+    pub views: Vec<SavedView>,
+}
+
+// 导入结果：导入后的视图名称，与已有视图重名且不覆盖时自动改名
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ViewImportSummary {
+    pub imported: Vec<String>,
+    pub renamed: usize,
+}
+
+fn view_file_path(base_url: &str, account: &str) -> Result<PathBuf, String> {
+    server::profile_file_path("views", base_url, account)
+}
+
+fn load(base_url: &str, account: &str) -> Result<ViewFile, String> {
+    let path = view_file_path(base_url, account)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ViewFile::default()),
+        Err(e) => return Err(format!("读取视图文件失败: {}", e)),
+    };
+    match serde_json::from_str::<ViewFile>(&content) {
+        Ok(file) => Ok(file),
+        Err(e) => {
+            // 文件损坏时从空列表开始
+            warn!("解析视图文件{:?}失败，已忽略: {}", path, e);
+            Ok(ViewFile::default())
+        }
+    }
+}
+
+fn save(base_url: &str, account: &str, file: &ViewFile) -> Result<(), String> {
+    let path = view_file_path(base_url, account)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建视图目录失败: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(file).map_err(|e| format!("序列化视图失败: {}", e))?;
+    // 先写临时文件再替换，避免写到一半时损坏视图文件
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json).map_err(|e| format!("写入视图文件失败: {}", e))?;
+    fs::rename(&temp, &path).map_err(|e| format!("写入视图文件失败: {}", e))
+}
+
+// 在锁内读取、修改并保存视图文件，修改出错时不保存
+fn modify<T>(base_url: &str, account: &str, f: impl FnOnce(&mut ViewFile) -> Result<T, String>) -> Result<T, String> {
+    let _guard = VIEW_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = load(base_url, account)?;
+    let result = f(&mut file)?;
+    save(base_url, account, &file)?;
+    Ok(result)
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("视图名称不能为空".to_string());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("视图名称不能超过{}个字符", MAX_NAME_CHARS));
+    }
+    Ok(name.to_string())
+}
+
+// 与已有视图重名时依次尝试“名称 (2)”“名称 (3)”……
+fn unique_name(views: &[SavedView], name: &str) -> String {
+    if !views.iter().any(|view| view.name == name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !views.iter().any(|view| view.name == *candidate))
+        .unwrap_or_default()
+}
+
+// 按保存的顺序列出命名视图
+pub fn list_views(base_url: &str, account: &str) -> Result<Vec<SavedView>, String> {
+    let _guard = VIEW_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(load(base_url, account)?.views)
+}
+
+// 保存视图，同名视图已存在时覆盖设置并保留创建时间
+pub fn save_view(base_url: &str, account: &str, name: &str, settings: ViewSettings) -> Result<SavedView, String> {
+    let name = validate_name(name)?;
+    modify(base_url, account, |file| {
+        let now = Local::now().to_rfc3339();
+        let view = match file.views.iter_mut().find(|view| view.name == name) {
+            Some(view) => {
+                view.settings = settings;
+                view.updated_at = now;
+                view.clone()
+            }
+            None => {
+                let view = SavedView {
+                    name,
+                    settings,
+                    created_at: now.clone(),
+                    updated_at: now,
+                };
+                file.views.push(view.clone());
+                view
+            }
+        };
+        debug!("已保存视图{}", view.name);
+        Ok(view)
+    })
+}
+
+pub fn rename_view(base_url: &str, account: &str, name: &str, new_name: &str) -> Result<SavedView, String> {
+    let new_name = validate_name(new_name)?;
+    modify(base_url, account, |file| rename_in(file, name, new_name))
+}
+
+fn rename_in(file: &mut ViewFile, name: &str, new_name: String) -> Result<SavedView, String> {
+    if new_name != name && file.views.iter().any(|view| view.name == new_name) {
+        return Err(format!("已经有名为{}的视图", new_name));
+    }
+    let view = file
+        .views
+        .iter_mut()
+        .find(|view| view.name == name)
+        .ok_or_else(|| format!("没有找到视图: {}", name))?;
+    view.name = new_name;
+    view.updated_at = Local::now().to_rfc3339();
+    Ok(view.clone())
+}
+
+// 删除视图，返回视图之前是否存在
+pub fn delete_view(base_url: &str, account: &str, name: &str) -> Result<bool, String> {
+    modify(base_url, account, |file| {
+        let before = file.views.len();
+        file.views.retain(|view| view.name != name);
+        Ok(file.views.len() != before)
+    })
+}
+
+// 上一次使用的列表设置，代替 webview 的 localStorage
+pub fn load_last_settings(base_url: &str, account: &str) -> Result<Option<ViewSettings>, String> {
+    let _guard = VIEW_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(load(base_url, account)?.last)
+}
+
+pub fn save_last_settings(base_url: &str, account: &str, settings: ViewSettings) -> Result<(), String> {
+    modify(base_url, account, |file| {
+        file.last = Some(settings);
+        Ok(())
+    })
+}
+
+// 导出视图为JSON，names 为空时导出全部视图
+pub fn export_views(base_url: &str, account: &str, names: &[String]) -> Result<String, String> {
+    let views = list_views(base_url, account)?
+        .into_iter()
+        .filter(|view| names.is_empty() || names.contains(&view.name))
+        .collect::<Vec<_>>();
+    if views.is_empty() {
+        return Err("没有可以导出的视图".to_string());
+    }
+    let export = ViewExport {
+        version: EXPORT_VERSION,
+        exported_at: Local::now().to_rfc3339(),
+        views,
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| format!("导出视图失败: {}", e))
+}
+
+// 导入导出的视图。overwrite 为 true 时覆盖同名视图，否则重名的视图自动改名后添加
+pub fn import_views(base_url: &str, account: &str, json: &str, overwrite: bool) -> Result<ViewImportSummary, String> {
+    let export = parse_export(json)?;
+    modify(base_url, account, |file| import_into(file, export, overwrite))
+}
+
+fn parse_export(json: &str) -> Result<ViewExport, String> {
+    let export = serde_json::from_str::<ViewExport>(json).map_err(|e| format!("视图文件格式不正确: {}", e))?;
+    if export.version > EXPORT_VERSION {
+        return Err(format!("视图文件的版本{}过新，请升级应用后再导入", export.version));
+    }
+    Ok(export)
+}
+
+fn import_into(file: &mut ViewFile, export: ViewExport, overwrite: bool) -> Result<ViewImportSummary, String> {
+    let mut summary = ViewImportSummary::default();
+    let now = Local::now().to_rfc3339();
+    for view in export.views {
+        let name = validate_name(&view.name)?;
+        let view = SavedView { name, updated_at: now.clone(), ..view };
+        if overwrite && let Some(existing) = file.views.iter_mut().find(|existing| existing.name == view.name) {
+            summary.imported.push(view.name.clone());
+            *existing = view;
+            continue;
+        }
+        let name = unique_name(&file.views, &view.name);
+        if name != view.name {
+            summary.renamed += 1;
+        }
+        summary.imported.push(name.clone());
+        file.views.push(SavedView { name, ..view });
+    }
+    debug!("导入了{}个视图", summary.imported.len());
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(name: &str, keyword: &str) -> SavedView {
+        SavedView {
+            name: name.to_string(),
+            settings: ViewSettings { keyword: keyword.to_string(), ..ViewSettings::default() },
+            created_at: "2025-01-01T00:00:00+08:00".to_string(),
+            updated_at: "2025-01-01T00:00:00+08:00".to_string(),
+        }
+    }
+
+    fn file(names: &[&str]) -> ViewFile {
+        ViewFile {
+            last: None,
+            views: names.iter().map(|name| view(name, "old")).collect(),
+        }
+    }
+
+    fn names(file: &ViewFile) -> Vec<&str> {
+        file.views.iter().map(|view| view.name.as_str()).collect()
+    }
+
+    fn export(views: Vec<SavedView>) -> ViewExport {
+        ViewExport { version: EXPORT_VERSION, exported_at: String::new(), views }
+    }
+
+    #[test]
+    fn validates_names() {
+        assert_eq!(validate_name("  我的Bug  ").unwrap(), "我的Bug");
+        assert!(validate_name("").is_err());
+        assert!(validate_name(" \t ").is_err());
+        assert!(validate_name(&"视".repeat(MAX_NAME_CHARS)).is_ok());
+        assert!(validate_name(&"视".repeat(MAX_NAME_CHARS + 1)).is_err());
+    }
+
+    #[test]
+    fn picks_unique_names() {
+        let views = file(&["a", "a (2)", "b"]).views;
+        assert_eq!(unique_name(&views, "c"), "c");
+        assert_eq!(unique_name(&views, "b"), "b (2)");
+        assert_eq!(unique_name(&views, "a"), "a (3)");
+    }
+
+    #[test]
+    fn renames_views() {
+        let mut views = file(&["a", "b"]);
+        let renamed = rename_in(&mut views, "a", "c".to_string()).unwrap();
+        assert_eq!(renamed.name, "c");
+        assert_eq!(renamed.created_at, "2025-01-01T00:00:00+08:00");
+        assert_ne!(renamed.updated_at, renamed.created_at);
+        assert_eq!(names(&views), vec!["c", "b"]);
+
+        // 改成自己的名字不算重名
+        assert!(rename_in(&mut views, "c", "c".to_string()).is_ok());
+        assert_eq!(rename_in(&mut views, "c", "b".to_string()).unwrap_err(), "已经有名为b的视图");
+        assert!(rename_in(&mut views, "missing", "d".to_string()).is_err());
+        assert_eq!(names(&views), vec!["c", "b"]);
+    }
+
+    #[test]
+    fn imports_with_renaming() {
+        let mut views = file(&["a", "b"]);
+        let summary = import_into(&mut views, export(vec![view(" a ", "new"), view("c", "new")]), false).unwrap();
+        assert_eq!(summary.imported, vec!["a (2)", "c"]);
+        assert_eq!(summary.renamed, 1);
+        assert_eq!(names(&views), vec!["a", "b", "a (2)", "c"]);
+        assert_eq!(views.views[0].settings.keyword, "old");
+        assert_eq!(views.views[2].settings.keyword, "new");
+    }
+
+    #[test]
+    fn imports_with_overwrite() {
+        let mut views = file(&["a", "b"]);
+        let summary = import_into(&mut views, export(vec![view("a", "new"), view("c", "new")]), true).unwrap();
+        assert_eq!(summary.imported, vec!["a", "c"]);
+        assert_eq!(summary.renamed, 0);
+        assert_eq!(names(&views), vec!["a", "b", "c"]);
+        assert_eq!(views.views[0].settings.keyword, "new");
+        assert_eq!(views.views[1].settings.keyword, "old");
+    }
+
+    #[test]
+    fn rejects_invalid_imports() {
+        let mut views = file(&["a"]);
+        assert!(import_into(&mut views, export(vec![view("ok", ""), view(" ", "")]), false).is_err());
+
+        let json = serde_json::to_string(&ViewExport { version: EXPORT_VERSION + 1, ..export(Vec::new()) }).unwrap();
+        assert!(parse_export(&json).unwrap_err().contains("过新"));
+        assert!(parse_export("{}").is_err());
+        let json = serde_json::to_string(&export(vec![view("a", "k")])).unwrap();
+        assert_eq!(parse_export(&json).unwrap().views[0].settings.keyword, "k");
+    }
+}
@@ -0,0 +1,404 @@
+// 关注列表：除了指派给自己的Bug，还可以关注任意Bug，每次同步时记录字段变化
+// 关注列表按禅道服务器和账号分别保存在 zentao_app/watch/<服务器>/<账号>.json，
+// 每个关注的Bug保存上一次看到的字段值，与新获取的版本比较后追加一条变化记录并标记为未读
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::diff::{diff_fields, tracked_fields, FieldChange};
+use crate::models::Bug;
+use crate::server;
+
+// 每个Bug最多保留的变化记录数
+const MAX_CHANGES: usize = 100;
+// 状态变化时由“变化人”说明的字段，摘要中不再单独列出
+const STATUS_DETAIL_FIELDS: &[&str] = &["resolvedBy", "resolvedDate", "closedBy", "closedDate"];
+
+// 读取-修改-写入关注列表文件时加锁，避免后台轮询和命令同时写入
+static WATCH_FILE: Mutex<()> = Mutex::new(());
+
+// 一次同步中发现的变化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchChange {
+    // 发现变化的时间（RFC 3339）
+    pub at: String,
+    pub changes: Vec<FieldChange>,
+    // 做出变化的人：解决或关闭时为解决者、关闭者，其他变化无法得知
+    #[serde(default)]
+    pub by: Option<String>,
+    // 可读的摘要，如“状态: 激活 → 已解决（张三）”
+    pub summary: String,
+    pub unread: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedBug {
+    #[serde(rename = "bugId")]
+    pub bug_id: i32,
+    pub product: i32,
+    pub title: String,
+    // 最近一次看到的状态名称
+    pub status: String,
+    // 开始关注的时间（RFC 3339）
+    #[serde(rename = "watchedAt")]
+    pub watched_at: String,
+    // 最近一次同步的时间（RFC 3339）
+    #[serde(rename = "syncedAt")]
+    pub synced_at: String,
+    // 上一次看到的字段值
+    #[serde(rename = "lastSeen")]
+    pub last_seen: BTreeMap<String, Value>,
+    // 变化记录，从旧到新
+    #[serde(default)]
+    pub changes: Vec<WatchChange>,
+}
+
+impl WatchedBug {
+    fn new(bug: &Bug) -> WatchedBug {
+        let now = Local::now().to_rfc3339();
+        WatchedBug {
+            bug_id: bug.id,
+            product: bug.product,
+            title: bug.title.clone(),
+            status: bug.status.name.clone(),
+            watched_at: now.clone(),
+            synced_at: now,
+            last_seen: tracked_fields(bug),
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.changes.iter().filter(|change| change.unread).count()
+    }
+
+    // 与新获取的版本比较，有变化时追加变化记录，返回是否有变化
+    fn update(&mut self, bug: &Bug) -> bool {
+        let now = Local::now().to_rfc3339();
+        let fields = tracked_fields(bug);
+        let changes = diff_fields(&self.last_seen, &fields);
+        self.synced_at = now.clone();
+        if changes.is_empty() {
+            return false;
+        }
+
+        let by = changed_by(&changes, &fields);
+        self.changes.push(WatchChange {
+            at: now,
+            summary: summarize(&changes, by.as_deref()),
+            changes,
+            by,
+            unread: true,
+        });
+        if self.changes.len() > MAX_CHANGES {
+            let excess = self.changes.len() - MAX_CHANGES;
+            self.changes.drain(..excess);
+        }
+        self.product = bug.product;
+        self.title = bug.title.clone();
+        self.status = bug.status.name.clone();
+        self.last_seen = fields;
+        true
+    }
+}
+
+fn field_label(field: &str) -> &str {
+    match field {
+        "title" => "标题",
+        "status" => "状态",
+        "severity" => "严重程度",
+        "pri" => "优先级",
+        "type" => "类型",
+        "keywords" => "关键词",
+        "steps" => "重现步骤",
+        "deadline" => "截止日期",
+        "assignedTo" => "指派给",
+        "resolvedBy" => "解决者",
+        "resolvedDate" => "解决日期",
+        "closedBy" => "关闭者",
+        "closedDate" => "关闭日期",
+        other => other,
+    }
+}
+
+// 字段值的显示文本：用户显示姓名，状态显示名称，空值显示“无”
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "无".to_string(),
+        Value::String(text) if text.is_empty() => "无".to_string(),
+        Value::String(text) => text.clone(),
+        Value::Object(object) => object
+            .get("realname")
+            .or_else(|| object.get("name"))
+            .and_then(Value::as_str)
+            .filter(|name| !name.is_empty())
+            .unwrap_or("无")
+            .to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn user_name(value: Option<&Value>) -> Option<String> {
+    let value = value?;
+    let name = value
+        .get("realname")
+        .and_then(Value::as_str)
+        .filter(|name| !name.is_empty())
+        .or_else(|| value.get("account").and_then(Value::as_str))?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// 状态变为已解决或已关闭时，解决者或关闭者就是做出变化的人
+fn changed_by(changes: &[FieldChange], fields: &BTreeMap<String, Value>) -> Option<String> {
+    let status = changes.iter().find(|change| change.field == "status")?;
+    match status.new.get("code").and_then(Value::as_str)? {
+        "resolved" => user_name(fields.get("resolvedBy")),
+        "closed" => user_name(fields.get("closedBy")),
+        _ => None,
+    }
+}
+
+fn summarize(changes: &[FieldChange], by: Option<&str>) -> String {
+    let status_changed = changes.iter().any(|change| change.field == "status");
+    let summary = changes
+        .iter()
+        .filter(|change| !(status_changed && STATUS_DETAIL_FIELDS.contains(&change.field.as_str())))
+        .map(|change| match change.field.as_str() {
+            // 重现步骤内容较长，只说明有修改
+            "steps" => "重现步骤已修改".to_string(),
+            field => format!(
+                "{}: {} → {}",
+                field_label(field),
+                display_value(&change.old),
+                display_value(&change.new)
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("；");
+    match by {
+        Some(by) => format!("{}（{}）", summary, by),
+        None => summary,
+    }
+}
+
+fn watch_file_path(base_url: &str, account: &str) -> Result<PathBuf, String> {
+    server::profile_file_path("watch", base_url, account)
+}
+
+fn load(base_url: &str, account: &str) -> Result<Vec<WatchedBug>, String> {
+    let path = watch_file_path(base_url, account)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("读取关注列表失败: {}", e)),
+    };
+    match serde_json::from_str::<Vec<WatchedBug>>(&content) {
+        Ok(watched) => Ok(watched),
+        Err(e) => {
+            // 文件损坏时从空列表开始
+            warn!("解析关注列表{:?}失败，已忽略: {}", path, e);
+            Ok(Vec::new())
+        }
+    }
+}
+
+fn save(base_url: &str, account: &str, watched: &[WatchedBug]) -> Result<(), String> {
+    let path = watch_file_path(base_url, account)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建关注列表目录失败: {}", e))?;
+    }
+    let json = serde_json::to_string(watched).map_err(|e| format!("序列化关注列表失败: {}", e))?;
+    // 先写临时文件再替换，避免写到一半时损坏关注列表
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json).map_err(|e| format!("写入关注列表失败: {}", e))?;
+    fs::rename(&temp, &path).map_err(|e| format!("写入关注列表失败: {}", e))
+}
+
+// 在锁内读取、修改并保存关注列表
+fn modify<T>(base_url: &str, account: &str, f: impl FnOnce(&mut Vec<WatchedBug>) -> T) -> Result<T, String> {
+    let _guard = WATCH_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut watched = load(base_url, account)?;
+    let result = f(&mut watched);
+    save(base_url, account, &watched)?;
+    Ok(result)
+}
+
+// 读取关注列表，按开始关注的时间排列
+pub fn list_watched(base_url: &str, account: &str) -> Result<Vec<WatchedBug>, String> {
+    let _guard = WATCH_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    load(base_url, account)
+}
+
+// 关注的Bug ID
+pub fn watched_ids(base_url: &str, account: &str) -> Result<Vec<i32>, String> {
+    Ok(list_watched(base_url, account)?.iter().map(|watched| watched.bug_id).collect())
+}
+
+// 开始关注Bug，以当前版本作为比较的基准；已经关注时返回原有记录
+pub fn watch_bug(base_url: &str, account: &str, bug: &Bug) -> Result<WatchedBug, String> {
+    modify(base_url, account, |watched| {
+        if let Some(existing) = watched.iter().find(|watched| watched.bug_id == bug.id) {
+            return existing.clone();
+        }
+        let entry = WatchedBug::new(bug);
+        watched.push(entry.clone());
+        debug!("开始关注Bug{}", bug.id);
+        entry
+    })
+}
+
+// 取消关注，返回之前是否关注了这个Bug
+pub fn unwatch_bug(base_url: &str, account: &str, bug_id: i32) -> Result<bool, String> {
+    modify(base_url, account, |watched| {
+        let before = watched.len();
+        watched.retain(|watched| watched.bug_id != bug_id);
+        watched.len() != before
+    })
+}
+
+// 用同步得到的Bug更新关注列表，返回本次有变化的关注记录；不在关注列表中的Bug被忽略
+pub fn update_watched(base_url: &str, account: &str, bugs: &[Bug]) -> Result<Vec<WatchedBug>, String> {
+    let _guard = WATCH_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut watched = load(base_url, account)?;
+    let mut updated = false;
+    let mut changed = Vec::new();
+    for bug in bugs {
+        let Some(entry) = watched.iter_mut().find(|watched| watched.bug_id == bug.id) else {
+            continue;
+        };
+        updated = true;
+        if entry.update(bug) {
+            changed.push(entry.clone());
+        }
+    }
+    if updated {
+        save(base_url, account, &watched)?;
+    }
+    if !changed.is_empty() {
+        debug!("{}个关注的Bug有变化", changed.len());
+    }
+    Ok(changed)
+}
+
+// 把变化记录标记为已读；bug_id 为 None 时标记全部
+pub fn mark_read(base_url: &str, account: &str, bug_id: Option<i32>) -> Result<(), String> {
+    modify(base_url, account, |watched| {
+        for entry in watched.iter_mut().filter(|watched| bug_id.is_none_or(|id| watched.bug_id == id)) {
+            for change in &mut entry.changes {
+                change.unread = false;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user(account: &str, realname: &str) -> Value {
+        json!({ "id": 1, "account": account, "avatar": "", "realname": realname })
+    }
+
+    fn bug(title: &str, status: Value, extra: Value) -> Bug {
+        let mut value = json!({
+            "id": 7, "product": 1, "branch": 0, "module": 0, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": title, "keywords": "", "severity": 3, "pri": 3,
+            "type": "codeerror", "steps": "", "openedBy": null, "openedDate": "2025-01-01",
+            "assignedTo": null, "status": status
+        });
+        for (key, field) in extra.as_object().cloned().unwrap_or_default() {
+            value[key] = field;
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn status(code: &str, name: &str) -> Value {
+        json!({ "code": code, "name": name })
+    }
+
+    fn change(field: &str, old: Value, new: Value) -> FieldChange {
+        FieldChange { field: field.to_string(), old, new }
+    }
+
+    #[test]
+    fn update_without_changes_only_syncs() {
+        let active = bug("登录失败", status("active", "激活"), json!({}));
+        let mut watched = WatchedBug::new(&active);
+        assert!(!watched.update(&active));
+        assert!(watched.changes.is_empty());
+        assert_eq!(watched.unread_count(), 0);
+    }
+
+    #[test]
+    fn update_records_resolution_by_resolver() {
+        let active = bug("登录失败", status("active", "激活"), json!({}));
+        let resolved = bug(
+            "登录失败",
+            status("resolved", "已解决"),
+            json!({ "resolvedBy": user("zhangsan", "张三"), "resolvedDate": "2025-01-02 10:00:00" }),
+        );
+        let mut watched = WatchedBug::new(&active);
+        assert!(watched.update(&resolved));
+        assert_eq!(watched.status, "已解决");
+        assert_eq!(watched.unread_count(), 1);
+        let change = &watched.changes[0];
+        assert_eq!(change.by.as_deref(), Some("张三"));
+        // 解决者和解决日期由括号中的变化人说明，摘要中不单独列出
+        assert_eq!(change.summary, "状态: 激活 → 已解决（张三）");
+        assert_eq!(change.changes.len(), 3);
+        assert!(!watched.update(&resolved));
+    }
+
+    #[test]
+    fn update_keeps_latest_changes() {
+        let mut watched = WatchedBug::new(&bug("标题0", status("active", "激活"), json!({})));
+        for i in 1..=MAX_CHANGES + 5 {
+            assert!(watched.update(&bug(&format!("标题{}", i), status("active", "激活"), json!({}))));
+        }
+        assert_eq!(watched.changes.len(), MAX_CHANGES);
+        assert_eq!(watched.changes[0].summary, "标题: 标题5 → 标题6");
+        assert_eq!(watched.changes.last().unwrap().summary, format!("标题: 标题{} → 标题{}", MAX_CHANGES + 4, MAX_CHANGES + 5));
+        assert_eq!(watched.title, format!("标题{}", MAX_CHANGES + 5));
+    }
+
+    #[test]
+    fn changed_by_uses_resolver_or_closer() {
+        let fields = BTreeMap::from([
+            ("resolvedBy".to_string(), user("zhangsan", "张三")),
+            ("closedBy".to_string(), user("lisi", "")),
+        ]);
+        let to = |code: &str| vec![change("status", status("active", "激活"), status(code, code))];
+        assert_eq!(changed_by(&to("resolved"), &fields).as_deref(), Some("张三"));
+        // 没有姓名时使用账号
+        assert_eq!(changed_by(&to("closed"), &fields).as_deref(), Some("lisi"));
+        assert_eq!(changed_by(&to("active"), &fields), None);
+        assert_eq!(changed_by(&to("resolved"), &BTreeMap::new()), None);
+        assert_eq!(changed_by(&[change("title", json!("a"), json!("b"))], &fields), None);
+    }
+
+    #[test]
+    fn summarize_formats_values() {
+        let changes = vec![
+            change("assignedTo", Value::Null, user("zhangsan", "张三")),
+            change("pri", json!(3), json!(1)),
+            change("steps", json!("<p>旧</p>"), json!("<p>新</p>")),
+            change("deadline", json!("2025-01-01"), json!("")),
+            change("resolvedBy", Value::Null, user("lisi", "李四")),
+        ];
+        assert_eq!(
+            summarize(&changes, None),
+            "指派给: 无 → 张三；优先级: 3 → 1；重现步骤已修改；截止日期: 2025-01-01 → 无；解决者: 无 → 李四"
+        );
+        let closed = vec![
+            change("status", status("resolved", "已解决"), status("closed", "已关闭")),
+            change("closedBy", Value::Null, user("lisi", "李四")),
+        ];
+        assert_eq!(summarize(&closed, Some("李四")), "状态: 已解决 → 已关闭（李四）");
+    }
+}
@@ -1,1513 +1,923 @@
-use serde::{Deserialize, Serialize, Deserializer};
-use serde_json::Value;
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
-use chrono::{DateTime, Utc, FixedOffset};
-use std::fs;
-use std::path::PathBuf;
-use base64::{Engine as _, engine::general_purpose};
-use tracing::{info, warn, error, debug};
-
-// Unicode 解码函数：处理 JSON 中的 Unicode 转义序列
-fn decode_unicode_escapes(input: &str) -> String {
-    // 使用正则表达式处理 Unicode 转义序列
-    use regex::Regex;
+use tauri::{AppHandle, Emitter, Manager, State, Window, WindowEvent};
+use tauri_plugin_opener::OpenerExt;
+use tracing::{info, warn, debug};
+
+mod logging;
+mod poller;
+mod state;
+mod windows;
+
+use logging::{setup_tracing, LogEntry, LogSettings};
+use state::{check_unauthorized, AppState, SharedSession};
+use zentao_core::annotation::{self, Annotation, Annotations, ImportSummary};
+use zentao_core::bug_report::{self, ReportFormat};
+use zentao_core::client::{self, MyBugsMode, ZentaoClient};
+use zentao_core::config::{load_config_from_file, save_config_to_file, GitConfig, ZentaoConfig};
+use zentao_core::duplicate::{self, BugDraft, BugFingerprint, DuplicateCandidate};
+use zentao_core::git::{self, BugCommits, BugRefPatterns, RangeBugs};
+use zentao_core::git_template::{self, BugGitTemplates};
+use zentao_core::http;
+use zentao_core::link::{self, LinkTarget};
+use zentao_core::markdown::{self, StepsFormat};
+use zentao_core::models::{Bug, Product, UserInfo};
+use zentao_core::query::Query;
+use zentao_core::report::{self, BugReport, DateRange, Granularity};
+use zentao_core::search::{self, SearchHit};
+use zentao_core::server::{self, ApiKind};
+use zentao_core::snapshot::{self, Trend, TrendMetric};
+use zentao_core::token::{get_current_timestamp, remove_token_file, save_token_to_file};
+use zentao_core::view::{self, SavedView, ViewImportSummary, ViewSettings};
+use zentao_core::watch::{self, WatchedBug};
+
+// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+#[tauri::command]
+fn greet(name: &str) -> String {
+    format!("Hello, {}! You've been greeted from Rust!", name)
+}
+
+// 保存配置
+#[tauri::command]
+async fn save_config(config: ZentaoConfig, state: State<'_, AppState>, window: Window) -> Result<String, String> {
+    debug!("保存配置: {:?}", config);
     
-    let re = match Regex::new(r"\\u([0-9a-fA-F]{4})") {
-        Ok(regex) => regex,
-        Err(e) => {
-            error!("创建Unicode解码正则表达式失败: {}", e);
-            return input.to_string();
-        }
-    };
+    // 先校验网络设置和Bug引用模式，避免保存无法使用的配置
+    http::build_http_client(&config.network)?;
+    BugRefPatterns::new(&config.git.patterns)?;
     
-    let result = re.replace_all(input, |caps: &regex::Captures| {
-        let hex_str = &caps[1];
-        if let Ok(code_point) = u32::from_str_radix(hex_str, 16) {
-            if let Some(unicode_char) = std::char::from_u32(code_point) {
-                unicode_char.to_string()
-            } else {
-                caps[0].to_string() // 保留原始字符
-            }
-        } else {
-            caps[0].to_string() // 保留原始字符
-        }
-    });
+    let session = state.session(window.label()).await;
+    let mut session = session.lock().await;
+    session.set_config(config.clone());
+    
+    // 将配置保存到窗口配置档的文件
+    save_config_to_file(&session.profile, &config)?;
     
-    result.to_string()
+    Ok("配置保存成功".to_string())
 }
 
-// 自定义反序列化函数来处理status字段
-fn deserialize_status<'de, D>(deserializer: D) -> Result<BugStatus, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let value = Value::deserialize(deserializer)?;
-    match value {
-        Value::String(s) => Ok(BugStatus {
-            code: s.clone(),
-            name: s,
-        }),
-        Value::Object(obj) => {
-            let code = obj.get("code")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown")
-                .to_string();
-            let name = obj.get("name")
-                .and_then(|v| v.as_str())
-                .unwrap_or(&code)
-                .to_string();
-            Ok(BugStatus { code, name })
-        }
-        _ => Ok(BugStatus {
-            code: "unknown".to_string(),
-            name: "未知".to_string(),
-        }),
-    }
+// 测试服务器连接：逐步检查地址、网络、TLS、版本、API和登录
+#[tauri::command]
+async fn test_connection(config: ZentaoConfig) -> Result<server::ConnectionDiagnosis, String> {
+    info!("测试连接: {}", config.base_url);
+    Ok(server::test_connection(&config).await)
 }
 
-// 时间格式化函数：将ISO 8601时间转换为中国时间显示
-fn format_datetime_to_china(datetime_str: &str) -> String {
-    if datetime_str.is_empty() {
-        return "未知".to_string();
-    }
-    
-    match DateTime::parse_from_rfc3339(datetime_str) {
-        Ok(dt) => {
-            // 转换为中国时区 (UTC+8)
-            let china_offset = FixedOffset::east_opt(8 * 3600).unwrap();
-            let china_time = dt.with_timezone(&china_offset);
-            let formatted = china_time.format("%Y-%m-%d %H:%M:%S").to_string();
-            debug!("时间格式化: {} -> {}", datetime_str, formatted);
-            formatted
+// 加载配置
+#[tauri::command]
+async fn load_config(state: State<'_, AppState>, window: Window) -> Result<Option<ZentaoConfig>, String> {
+    let session = state.session(window.label()).await;
+    let mut session = session.lock().await;
+    // 首先尝试从窗口配置档的文件加载
+    match load_config_from_file(&session.profile)? {
+        Some(config) => {
+            // 同时更新会话中的配置
+            session.set_config(config.clone());
+            Ok(Some(config))
         }
-        Err(_) => {
-            // 如果解析失败，尝试直接解析UTC时间
-            match datetime_str.parse::<DateTime<Utc>>() {
-                Ok(utc_dt) => {
-                    let china_offset = FixedOffset::east_opt(8 * 3600).unwrap();
-                    let china_time = utc_dt.with_timezone(&china_offset);
-                    let formatted = china_time.format("%Y-%m-%d %H:%M:%S").to_string();
-                    debug!("时间格式化(UTC): {} -> {}", datetime_str, formatted);
-                    formatted
-                }
-                Err(e) => {
-                    error!("时间解析失败: {} - {}", datetime_str, e);
-                    datetime_str.to_string() // 如果都失败，返回原字符串
-                }
-            }
+        None => {
+            // 如果文件中没有，再尝试从会话中读取
+            Ok(session.config.clone())
         }
     }
 }
 
-// 处理HTML中的图片路径，提取图片路径信息以便前端按需加载
-fn process_html_images(html_content: &str, _base_url: &str) -> String {
-    use regex::Regex;
-    
-    debug!("开始处理HTML图片，内容长度: {}", html_content.len());
-    debug!(
-        "HTML内容预览: {}",
-        if html_content.chars().count() > 100 {
-            html_content.chars().take(100).collect::<String>()
-        } else {
-            html_content.to_string()
-        }
-    );
-    
-    // 创建正则表达式来匹配img标签，处理HTML实体编码
-    // 匹配格式：<img ... alt="index.php?m=file&amp;f=read&amp;t=ext&amp;fileID=id" ... />
-    let re = match Regex::new(r#"<img\s+[^>]*alt="([^"]*index\.php\?m=file[^"]*)"[^>]*/?>"#) {
-        Ok(regex) => regex,
-        Err(e) => {
-            error!("创建正则表达式失败: {}", e);
-            return html_content.to_string();
-        }
+// 初始化应用（加载保存的token）
+#[tauri::command]
+async fn initialize_app(app: AppHandle, state: State<'_, AppState>, window: Window) -> Result<bool, String> {
+    let session = state.session(window.label()).await;
+    let logged_in = {
+        let mut session = session.lock().await;
+        session.load_saved();
+        session.is_token_valid()
     };
-    
-    let result = re.replace_all(html_content, |caps: &regex::Captures| {
-        let original_tag = &caps[0];
-        let alt_content = &caps[1];
-        
-        debug!("找到图片标签: {}", original_tag);
-        debug!("Alt内容: {}", alt_content);
-        
-        // HTML实体解码：将&amp;转换为&
-        let decoded_alt = alt_content.replace("&amp;", "&");
-        
-        // 使用正则表达式添加data-image-path属性
-        let attr_re = match Regex::new(r#"<img(\s+[^>]*?)/?>"#) {
-            Ok(regex) => regex,
-            Err(_) => return original_tag.to_string(),
-        };
-        
-        let updated_tag = attr_re.replace(original_tag, |img_caps: &regex::Captures| {
-            let attributes = &img_caps[1];
-            format!(r#"<img{} data-image-path="{}" data-lazy-load="true" />"#, attributes, decoded_alt)
-        });
-        
-        debug!("图片标记添加: {}", decoded_alt);
-        updated_tag.to_string()
-    });
-    
-    if result != html_content {
-        info!("HTML处理完成，发现并处理了图片");
-    } else {
-        debug!("HTML处理完成，未发现图片");
-    }
-    
-    result.to_string()
+    // 启动后台轮询，未登录时轮询任务会等待登录
+    poller::ensure_running(&app, window.label(), &session).await;
+    Ok(logged_in)
 }
 
-// 全局状态管理
-struct AppState {
-    token: Option<String>,
-    token_expire_time: u64,
-    config: Option<ZentaoConfig>,
+// 检查登录状态
+#[tauri::command]
+async fn check_login_status(state: State<'_, AppState>, window: Window) -> Result<bool, String> {
+    Ok(state.session(window.label()).await.lock().await.is_token_valid())
 }
 
-static APP_STATE: Mutex<AppState> = Mutex::new(AppState {
-    token: None,
-    token_expire_time: 0,
-    config: None,
-});
-
-// Token持久化数据结构
-#[derive(Debug, Serialize, Deserialize)]
-struct TokenData {
-    token: String,
-    expire_time: u64,
-    encrypted: bool,
+// 登录到禅道系统
+#[tauri::command]
+async fn login_zentao(
+    base_url: String,
+    account: String,
+    password: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<bool, String> {
+    let session = state.session(window.label()).await;
+    let (client, api_mode) = {
+        let mut session = session.lock().await;
+        // 如果已有有效token，直接返回成功
+        if session.is_token_valid() {
+            return Ok(true);
+        }
+        let api_mode = session.config.as_ref().map(|config| config.api_mode).unwrap_or_default();
+        (session.http_client()?, api_mode)
+    };
+
+    let (token, api) = client::login(&client, &base_url, account, password, api_mode).await?;
+
+    // 保存token和过期时间（24小时）
+    let expire_time = get_current_timestamp() + (24 * 60 * 60);
+    let profile = {
+        let mut session = session.lock().await;
+        session.token = Some(token.clone());
+        session.token_expire_time = expire_time;
+        session.api = api.clone();
+        session.profile.clone()
+    };
+
+    // 保存token到窗口配置档的本地文件
+    if let Err(e) = save_token_to_file(&profile, &token, expire_time, &api) {
+        warn!("保存token到文件失败: {}", e);
+        // 即使保存失败，登录仍然成功，只是下次需要重新登录
+    }
+
+    poller::ensure_running(&app, window.label(), &session).await;
+
+    Ok(true)
 }
 
-// 简单的XOR加密/解密函数
-fn xor_encrypt_decrypt(data: &str, key: &str) -> String {
-    let key_bytes = key.as_bytes();
-    let data_bytes = data.as_bytes();
-    let key_len = key_bytes.len();
+// 退出登录
+#[tauri::command]
+async fn logout_zentao(state: State<'_, AppState>, window: Window) -> Result<(), String> {
+    let profile = {
+        let session = state.session(window.label()).await;
+        let mut session = session.lock().await;
+        session.clear_token();
+        session.api = ApiKind::Rest;
+        session.profile.clone()
+    };
     
-    let encrypted: Vec<u8> = data_bytes
-        .iter()
-        .enumerate()
-        .map(|(i, &b)| b ^ key_bytes[i % key_len])
-        .collect();
+    // 删除窗口配置档保存的token文件
+    if let Err(e) = remove_token_file(&profile) {
+        warn!("删除token文件失败: {}", e);
+        // 即使删除失败，登出仍然成功
+    }
     
-    general_purpose::STANDARD.encode(&encrypted)
+    Ok(())
 }
 
-fn xor_decrypt(encrypted_data: &str, key: &str) -> Result<String, String> {
-    match general_purpose::STANDARD.decode(encrypted_data) {
-        Ok(decoded) => {
-            let key_bytes = key.as_bytes();
-            let key_len = key_bytes.len();
-            
-            let decrypted: Vec<u8> = decoded
-                .iter()
-                .enumerate()
-                .map(|(i, &b)| b ^ key_bytes[i % key_len])
-                .collect();
-            
-            match String::from_utf8(decrypted) {
-                Ok(s) => Ok(s),
-                Err(e) => Err(format!("UTF8解码失败: {}", e)),
-            }
-        }
-        Err(e) => Err(format!("Base64解码失败: {}", e)),
-    }
+// 获取用户信息
+#[tauri::command]
+async fn get_user_info(state: State<'_, AppState>, window: Window) -> Result<UserInfo, String> {
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    check_unauthorized(&session, client.get_user_info().await).await
 }
 
-// 获取token存储文件路径
-fn get_token_file_path() -> Result<PathBuf, String> {
-    match dirs::data_dir() {
-        Some(mut path) => {
-            path.push("zentao_app");
-            path.push("zentao_token.json");
-            Ok(path)
-        }
-        None => Err("无法获取用户数据目录".to_string()),
-    }
+// 根据产品ID获取Bug列表
+#[tauri::command]
+async fn get_bugs_by_product(product_ids: Vec<i32>, state: State<'_, AppState>, window: Window) -> Result<Vec<Bug>, String> {
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    let bugs = check_unauthorized(&session, client.get_bugs_by_product(&product_ids).await).await?;
+    session.lock().await.cache_bugs(&product_ids, &bugs);
+    poller::spawn_refresh_watched(window.app_handle(), window.label(), &session, client);
+    Ok(bugs)
 }
 
-// 获取配置存储文件路径
-fn get_config_file_path() -> Result<PathBuf, String> {
-    match dirs::data_dir() {
-        Some(mut path) => {
-            path.push("zentao_app");
-            path.push("zentao_config.json");
-            Ok(path)
-        }
-        None => Err("无法获取用户数据目录".to_string()),
+// 确保产品的Bug列表已缓存，缓存中没有的产品或 refresh 为 true 时重新获取，获取后在后台同步窗口关注的Bug
+// 不指定产品时使用全部产品，返回实际使用的产品ID
+async fn load_cached_bugs(
+    window: &Window,
+    session: &SharedSession,
+    client: &ZentaoClient,
+    product_ids: Option<Vec<i32>>,
+    refresh: bool,
+) -> Result<Vec<i32>, String> {
+    let product_ids = match product_ids {
+        Some(ids) if !ids.is_empty() => ids,
+        _ => check_unauthorized(session, client.get_products().await)
+            .await?
+            .iter()
+            .map(|product| product.id)
+            .collect(),
+    };
+    let missing = {
+        let session = session.lock().await;
+        product_ids
+            .iter()
+            .copied()
+            .filter(|id| refresh || !session.bug_cache.contains_key(id))
+            .collect::<Vec<_>>()
+    };
+    if !missing.is_empty() {
+        let bugs = check_unauthorized(session, client.get_bugs_by_product(&missing).await).await?;
+        session.lock().await.cache_bugs(&missing, &bugs);
+        poller::spawn_refresh_watched(window.app_handle(), window.label(), session, client.clone());
     }
+    Ok(product_ids)
 }
 
-// 保存token到本地文件
-fn save_token_to_file(token: &str, expire_time: u64) -> Result<(), String> {
-    let file_path = get_token_file_path()?;
-    
-    // 确保目录存在
-    if let Some(parent) = file_path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            return Err(format!("创建目录失败: {}", e));
-        }
-    }
-    
-    // 使用简单的加密密钥（实际项目中应该使用更安全的方式）
-    let encryption_key = "zentao_app_key_2025";
-    let encrypted_token = xor_encrypt_decrypt(token, encryption_key);
-    
-    let token_data = TokenData {
-        token: encrypted_token,
-        expire_time,
-        encrypted: true,
+// 按查询语言过滤Bug，例如 `status:active severity<=2 assignee:me "crash"`
+// 不指定产品时查询全部产品；优先使用缓存的Bug列表
+#[tauri::command]
+async fn query_bugs(
+    query: String,
+    product_ids: Option<Vec<i32>>,
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<Vec<Bug>, String> {
+    let mut query = Query::parse(&query).map_err(|e| e.to_string())?;
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    let product_ids = load_cached_bugs(&window, &session, &client, product_ids, refresh.unwrap_or(false)).await?;
+
+    let me = if query.uses_me() {
+        Some(check_unauthorized(&session, client.get_user_info().await).await?.account)
+    } else {
+        None
     };
-    
-    match serde_json::to_string_pretty(&token_data) {
-        Ok(json_str) => {
-            match fs::write(&file_path, json_str) {
-                Ok(_) => {
-                    debug!("Token已保存到: {:?}", file_path);
-                    Ok(())
-                }
-                Err(e) => Err(format!("写入文件失败: {}", e)),
+    if query.uses_module_names() {
+        let mut modules = std::collections::HashMap::new();
+        for product_id in &product_ids {
+            match check_unauthorized(&session, client.get_module_names(*product_id).await).await {
+                Ok(names) => modules.extend(names),
+                Err(e) if e == client::UNAUTHORIZED => return Err(e),
+                Err(e) => warn!("获取产品{}的模块失败，按模块名称过滤的条件将不匹配: {}", product_id, e),
             }
         }
-        Err(e) => Err(format!("序列化Token数据失败: {}", e)),
+        query.set_module_names(modules);
     }
+    let bugs = {
+        let session = session.lock().await;
+        product_ids
+            .iter()
+            .filter_map(|id| session.bug_cache.get(id))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let annotations = load_profile_annotations(&session).await?;
+    let bugs = query.filter(bugs, me.as_deref(), &annotations);
+    debug!("查询匹配到{}个Bug", bugs.len());
+    Ok(bugs)
 }
 
-// 从本地文件加载token
-fn load_token_from_file() -> Result<Option<(String, u64)>, String> {
-    let file_path = get_token_file_path()?;
-    
-    if !file_path.exists() {
-        debug!("Token文件不存在: {:?}", file_path);
-        return Ok(None);
-    }
-    
-    match fs::read_to_string(&file_path) {
-        Ok(content) => {
-            match serde_json::from_str::<TokenData>(&content) {
-                Ok(token_data) => {
-                    let current_time = get_current_timestamp();
-                    
-                    // 检查token是否过期
-                    if current_time >= token_data.expire_time {
-                        warn!("保存的token已过期");
-                        // 删除过期的token文件
-                        let _ = fs::remove_file(&file_path);
-                        return Ok(None);
-                    }
-                    
-                    // 解密token
-                    if token_data.encrypted {
-                        let encryption_key = "zentao_app_key_2025";
-                        match xor_decrypt(&token_data.token, encryption_key) {
-                            Ok(decrypted_token) => {
-                                debug!("成功加载并解密token，剩余时间: {}秒", token_data.expire_time - current_time);
-                                Ok(Some((decrypted_token, token_data.expire_time)))
-                            }
-                            Err(e) => {
-                                error!("解密token失败: {}", e);
-                                // 删除损坏的token文件
-                                let _ = fs::remove_file(&file_path);
-                                Ok(None)
-                            }
-                        }
-                    } else {
-                        // 兼容未加密的旧格式
-                        Ok(Some((token_data.token, token_data.expire_time)))
-                    }
-                }
-                Err(e) => {
-                    error!("解析token文件失败: {}", e);
-                    // 删除损坏的token文件
-                    let _ = fs::remove_file(&file_path);
-                    Ok(None)
-                }
-            }
-        }
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                Ok(None)
-            } else {
-                Err(format!("读取token文件失败: {}", e))
-            }
-        }
+// 在已缓存Bug的标题和重现步骤中全文搜索，按相关度排序
+// 还没有缓存任何Bug时先获取全部产品的Bug建立索引
+#[tauri::command]
+async fn search_bugs(
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<Vec<SearchHit>, String> {
+    let session = state.session(window.label()).await;
+    if session.lock().await.search_index.is_empty() {
+        let client = session.lock().await.zentao_client()?;
+        load_cached_bugs(&window, &session, &client, None, false).await?;
     }
+    let notes = load_profile_annotations(&session).await?.search_texts();
+    let limit = limit.unwrap_or(search::DEFAULT_LIMIT);
+    let hits = {
+        let mut session = session.lock().await;
+        session.search_index.set_notes(notes);
+        session.search_index.search(&query, limit)
+    };
+    debug!("全文搜索返回{}个结果", hits.len());
+    Ok(hits)
 }
 
-// 删除保存的token文件
-fn remove_token_file() -> Result<(), String> {
-    let file_path = get_token_file_path()?;
-    
-    if file_path.exists() {
-        match fs::remove_file(&file_path) {
-            Ok(_) => {
-                debug!("Token文件已删除: {:?}", file_path);
-                Ok(())
-            }
-            Err(e) => Err(format!("删除token文件失败: {}", e)),
+// 生成Bug统计报表：product_ids 为空时统计全部产品，date_range 限制新建和解决日期
+async fn generate_bug_report(
+    state: &AppState,
+    window: &Window,
+    product_ids: Vec<i32>,
+    date_range: Option<DateRange>,
+    granularity: Option<Granularity>,
+    refresh: bool,
+) -> Result<BugReport, String> {
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    let product_ids = load_cached_bugs(window, &session, &client, Some(product_ids), refresh).await?;
+    let bugs = {
+        let session = session.lock().await;
+        product_ids
+            .iter()
+            .filter_map(|id| session.bug_cache.get(id))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    // 模块名称获取失败（如旧版API）时报表显示模块ID
+    let mut modules = std::collections::HashMap::new();
+    for product_id in &product_ids {
+        match check_unauthorized(&session, client.get_module_names(*product_id).await).await {
+            Ok(names) => modules.extend(names),
+            Err(e) if e == client::UNAUTHORIZED => return Err(e),
+            Err(e) => warn!("获取产品{}的模块失败，报表将显示模块ID: {}", product_id, e),
         }
-    } else {
-        Ok(())
     }
+    Ok(report::build_report_today(
+        &bugs,
+        &product_ids,
+        &modules,
+        date_range.unwrap_or_default(),
+        granularity.unwrap_or_default(),
+    ))
 }
 
-// 初始化应用状态（从本地文件加载token和配置）
-fn initialize_app_state() -> Result<(), String> {
-    let mut state = APP_STATE.lock().unwrap();
-    
-    // 加载token
-    match load_token_from_file() {
-        Ok(Some((token, expire_time))) => {
-            state.token = Some(token);
-            state.token_expire_time = expire_time;
-            info!("应用启动时成功加载保存的token");
-        }
-        Ok(None) => {
-            debug!("应用启动时没有找到有效的保存token");
-        }
-        Err(e) => {
-            warn!("加载保存的token失败: {}", e);
-        }
-    }
-    
-    // 加载配置
-    match load_config_from_file() {
-        Ok(Some(config)) => {
-            state.config = Some(config);
-            info!("应用启动时成功加载保存的配置");
-        }
-        Ok(None) => {
-            debug!("应用启动时没有找到保存的配置");
-        }
-        Err(e) => {
-            warn!("加载保存的配置失败: {}", e);
-        }
-    }
-    
-    Ok(())
+// Bug统计报表（结构化数据，供界面绘制图表）
+#[tauri::command]
+async fn bug_report(
+    product_ids: Vec<i32>,
+    date_range: Option<DateRange>,
+    granularity: Option<Granularity>,
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<BugReport, String> {
+    generate_bug_report(&state, &window, product_ids, date_range, granularity, refresh.unwrap_or(false)).await
 }
 
-// 保存配置到本地文件
-fn save_config_to_file(config: &ZentaoConfig) -> Result<(), String> {
-    let file_path = get_config_file_path()?;
-    
-    // 确保目录存在
-    if let Some(parent) = file_path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            return Err(format!("创建目录失败: {}", e));
-        }
-    }
-    
-    match serde_json::to_string_pretty(config) {
-        Ok(json_str) => {
-            match fs::write(&file_path, json_str) {
-                Ok(_) => {
-                    debug!("配置已保存到: {:?}", file_path);
-                    Ok(())
-                }
-                Err(e) => Err(format!("写入配置文件失败: {}", e)),
+// Bug统计报表的 Markdown 文本
+#[tauri::command]
+async fn bug_report_markdown(
+    product_ids: Vec<i32>,
+    date_range: Option<DateRange>,
+    granularity: Option<Granularity>,
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<String, String> {
+    let report =
+        generate_bug_report(&state, &window, product_ids, date_range, granularity, refresh.unwrap_or(false)).await?;
+    Ok(report::report_to_markdown(&report))
+}
+
+// 计算Bug截图的感知哈希，已计算过的图片从会话缓存中读取
+async fn add_image_hashes(
+    session: &SharedSession,
+    client: &ZentaoClient,
+    fingerprint: &mut BugFingerprint,
+) -> Result<(), String> {
+    let mut missing = Vec::new();
+    {
+        let session = session.lock().await;
+        for path in fingerprint.image_paths.clone() {
+            match session.image_hashes.get(&path) {
+                Some(hash) => fingerprint.add_image(&path, *hash),
+                None => missing.push(path),
             }
         }
-        Err(e) => Err(format!("序列化配置数据失败: {}", e)),
     }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let hashes = check_unauthorized(session, duplicate::load_image_hashes(client, &missing).await).await?;
+    let mut session = session.lock().await;
+    for image in hashes {
+        session.image_hashes.insert(image.path.clone(), image.hash);
+        fingerprint.images.push(image);
+    }
+    Ok(())
 }
 
-// 从本地文件加载配置
-fn load_config_from_file() -> Result<Option<ZentaoConfig>, String> {
-    let file_path = get_config_file_path()?;
-    
-    if !file_path.exists() {
-        debug!("配置文件不存在: {:?}", file_path);
-        return Ok(None);
-    }
-    
-    match fs::read_to_string(&file_path) {
-        Ok(content) => {
-            match serde_json::from_str::<ZentaoConfig>(&content) {
-                Ok(config) => {
-                    debug!("成功加载配置文件: {:?}", file_path);
-                    Ok(Some(config))
-                }
-                Err(e) => {
-                    error!("解析配置文件失败: {}", e);
-                    // 删除损坏的配置文件
-                    let _ = fs::remove_file(&file_path);
-                    Ok(None)
-                }
-            }
-        }
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                Ok(None)
-            } else {
-                Err(format!("读取配置文件失败: {}", e))
-            }
+// 查找疑似重复的Bug：指定 bug_id 时查找与该Bug重复的Bug，否则与正在填写的草稿比较
+// 不指定产品时在目标Bug所属的产品中查找
+#[tauri::command]
+async fn find_duplicate_bugs(
+    bug_id: Option<i32>,
+    draft: Option<BugDraft>,
+    product_ids: Option<Vec<i32>>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<Vec<DuplicateCandidate>, String> {
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    let mut target = match (bug_id, draft) {
+        (Some(bug_id), _) => BugFingerprint::new(&check_unauthorized(&session, client.get_bug_detail(bug_id).await).await?),
+        (None, Some(draft)) => BugFingerprint::from_draft(&draft),
+        (None, None) => return Err("请指定Bug ID或草稿".to_string()),
+    };
+    let product_ids = product_ids.filter(|ids| !ids.is_empty()).unwrap_or_else(|| vec![target.product]);
+    let product_ids = load_cached_bugs(&window, &session, &client, Some(product_ids), false).await?;
+    let mut candidates = {
+        let session = session.lock().await;
+        product_ids
+            .iter()
+            .filter_map(|id| session.bug_cache.get(id))
+            .flatten()
+            .map(BugFingerprint::new)
+            .collect::<Vec<_>>()
+    };
+
+    // 目标有截图时，只下载文本最相似的一部分候选Bug的截图
+    add_image_hashes(&session, &client, &mut target).await?;
+    if !target.images.is_empty() {
+        for i in duplicate::image_candidates(&target, &candidates) {
+            add_image_hashes(&session, &client, &mut candidates[i]).await?;
         }
     }
-}
 
-// 定义禅道API的数据结构
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ZentaoConfig {
-    #[serde(rename = "baseUrl")]
-    base_url: String,
-    account: String,
-    password: String,
+    let duplicates = duplicate::find_duplicates(&target, &candidates, limit.unwrap_or(duplicate::DEFAULT_LIMIT));
+    debug!("在{}个Bug中找到{}个疑似重复", candidates.len(), duplicates.len());
+    Ok(duplicates)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoginRequest {
-    account: String,
-    password: String,
+// 当前窗口配置中的git仓库设置
+async fn git_config(state: &AppState, label: &str) -> Result<GitConfig, String> {
+    let session = state.session(label).await;
+    let session = session.lock().await;
+    let git = session.config.as_ref().ok_or("配置未找到")?.git.clone();
+    if git.repositories.is_empty() {
+        return Err("尚未配置本地git仓库".to_string());
+    }
+    Ok(git)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoginResponse {
-    token: String,
+// 在配置的本地git仓库中查找提交信息引用了Bug的提交，以及涉及的分支和文件
+#[tauri::command]
+async fn get_bug_commits(bug_id: i32, state: State<'_, AppState>, window: Window) -> Result<BugCommits, String> {
+    let config = git_config(&state, window.label()).await?;
+    tauri::async_runtime::spawn_blocking(move || git::commits_for_bug(&config, bug_id))
+        .await
+        .map_err(|e| format!("读取git仓库失败: {}", e))?
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct BugStatus {
-    code: String,
-    name: String,
+// 列出提交范围中的提交引用的Bug；不指定仓库时查找所有配置的仓库，不包含该范围的仓库跳过
+#[tauri::command]
+async fn get_range_bugs(
+    range: String,
+    repository: Option<String>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<Vec<RangeBugs>, String> {
+    let config = git_config(&state, window.label()).await?;
+    let repositories = repository.map(|repository| vec![repository]).unwrap_or(config.repositories);
+    tauri::async_runtime::spawn_blocking(move || {
+        let (ranges, skipped) = git::bugs_in_repositories(&repositories, &range, &config.patterns)?;
+        for skipped in skipped {
+            debug!("仓库{}中没有提交范围{}: {}", skipped.repository, range, skipped.error);
+        }
+        Ok(ranges)
+    })
+    .await
+    .map_err(|e| format!("读取git仓库失败: {}", e))?
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Bug {
-    id: i32,
-    product: i32,
-    branch: i32,
-    module: i32,
-    project: i32,
-    execution: i32,
-    #[serde(rename = "toTask")]
-    to_task: i32,
-    #[serde(rename = "toStory")]
-    to_story: i32,
-    title: String,
-    keywords: String,
-    severity: i32,
-    pri: i32,
-    #[serde(rename = "type")]
-    bug_type: String,
-    #[serde(default)]
-    os: String,
-    #[serde(default)]
-    browser: String,
-    steps: String,
-    #[serde(default)]
-    task: Option<i32>,
-    #[serde(default)]
-    story: Option<i32>,
-    #[serde(rename = "openedBy")]
-    opened_by: Option<User>,
-    #[serde(rename = "openedDate")]
-    opened_date: String,
-    #[serde(default)]
-    deadline: Option<String>,
-    #[serde(rename = "assignedTo")]
-    assigned_to: Option<User>,
-    #[serde(rename = "assignedDate")]
-    #[serde(default)]
-    assigned_date: Option<String>,
-    #[serde(rename = "resolvedBy")]
-    #[serde(default)]
-    resolved_by: Option<User>,
-    #[serde(rename = "resolvedDate")]
-    #[serde(default)]
-    resolved_date: Option<String>,
-    #[serde(rename = "closedBy")]
-    #[serde(default)]
-    closed_by: Option<User>,
-    #[serde(rename = "closedDate")]
-    #[serde(default)]
-    closed_date: Option<String>,
-    #[serde(deserialize_with = "deserialize_status")]
-    status: BugStatus,
-    // 用于存储基础URL，不参与反序列化，在获取Bug时手动设置
-    #[serde(skip)]
-    pub base_url: Option<String>,
+// 根据Bug生成分支名和提交信息，模板来自当前配置
+#[tauri::command]
+async fn bug_git_templates(bug_id: i32, state: State<'_, AppState>, window: Window) -> Result<BugGitTemplates, String> {
+    let session = state.session(window.label()).await;
+    let (client, config) = {
+        let mut session = session.lock().await;
+        let config = session.config.as_ref().ok_or("配置未找到")?.git.clone();
+        (session.zentao_client()?, config)
+    };
+    let bug = check_unauthorized(&session, client.get_bug_detail(bug_id).await).await?;
+    let product_name = match check_unauthorized(&session, client.get_product_detail(bug.product).await).await {
+        Ok(product) => product.name,
+        Err(e) if e == client::UNAUTHORIZED => return Err(e),
+        Err(e) => {
+            warn!("获取产品{}失败，提交信息中使用产品ID: {}", bug.product, e);
+            format!("产品{}", bug.product)
+        }
+    };
+    Ok(git_template::bug_git_templates(&bug, &product_name, &config))
 }
 
-// 为Bug实现自定义序列化，添加格式化后的时间字段
-impl Serialize for Bug {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("Bug", 24)?;
-        
-        state.serialize_field("id", &self.id)?;
-        state.serialize_field("product", &self.product)?;
-        state.serialize_field("branch", &self.branch)?;
-        state.serialize_field("module", &self.module)?;
-        state.serialize_field("project", &self.project)?;
-        state.serialize_field("execution", &self.execution)?;
-        state.serialize_field("toTask", &self.to_task)?;
-        state.serialize_field("toStory", &self.to_story)?;
-        state.serialize_field("title", &self.title)?;
-        state.serialize_field("keywords", &self.keywords)?;
-        state.serialize_field("severity", &self.severity)?;
-        state.serialize_field("pri", &self.pri)?;
-        state.serialize_field("type", &self.bug_type)?;
-        state.serialize_field("os", &self.os)?;
-        state.serialize_field("browser", &self.browser)?;
-        state.serialize_field("steps", &self.steps)?;
-        // 添加处理过图片路径的steps字段
-        let processed_steps = if let Some(ref base_url) = self.base_url {
-            process_html_images(&self.steps, base_url)
-        } else {
-            self.steps.clone()
-        };
-        state.serialize_field("stepsProcessed", &processed_steps)?;
-        state.serialize_field("task", &self.task)?;
-        state.serialize_field("story", &self.story)?;
-        state.serialize_field("openedBy", &self.opened_by)?;
-        state.serialize_field("openedDate", &self.opened_date)?;
-        state.serialize_field("openedDateFormatted", &format_datetime_to_china(&self.opened_date))?;
-        state.serialize_field("deadline", &self.deadline)?;
-        state.serialize_field("assignedTo", &self.assigned_to)?;
-        state.serialize_field("assignedDate", &self.assigned_date)?;
-        state.serialize_field("assignedDateFormatted", &self.assigned_date.as_ref().map(|d| format_datetime_to_china(d)))?;
-        state.serialize_field("resolvedBy", &self.resolved_by)?;
-        state.serialize_field("resolvedDate", &self.resolved_date)?;
-        state.serialize_field("resolvedDateFormatted", &self.resolved_date.as_ref().map(|d| format_datetime_to_china(d)))?;
-        state.serialize_field("closedBy", &self.closed_by)?;
-        state.serialize_field("closedDate", &self.closed_date)?;
-        state.serialize_field("closedDateFormatted", &self.closed_date.as_ref().map(|d| format_datetime_to_china(d)))?;
-        state.serialize_field("status", &self.status)?;
-        
-        state.end()
-    }
+// 本地标注和列表视图按服务器和账号分别保存
+async fn session_profile(session: &SharedSession) -> Result<(String, String), String> {
+    let session = session.lock().await;
+    let config = session.config.as_ref().ok_or("配置未找到")?;
+    Ok((config.base_url.clone(), config.account.clone()))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BugListResponse {
-    page: i32,
-    total: i32,
-    limit: i32,
-    bugs: Vec<Bug>,
+async fn load_profile_annotations(session: &SharedSession) -> Result<Annotations, String> {
+    let (base_url, account) = session_profile(session).await?;
+    tauri::async_runtime::spawn_blocking(move || annotation::load_annotations(&base_url, &account))
+        .await
+        .map_err(|e| format!("读取本地标注失败: {}", e))?
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UserInfo {
-    id: i32,
-    #[serde(rename = "type")]
-    user_type: String,
-    dept: i32,
-    account: String,
-    realname: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    nickname: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    avatar: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    birthday: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    gender: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    mobile: Option<String>,  
-    #[serde(skip_serializing_if = "Option::is_none")]
-    phone: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    weixin: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    address: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    join: Option<String>,
-    admin: bool,
+// 获取当前账号的全部本地标注（笔记、标签和星标）
+#[tauri::command]
+async fn list_annotations(state: State<'_, AppState>, window: Window) -> Result<Vec<Annotation>, String> {
+    let session = state.session(window.label()).await;
+    Ok(load_profile_annotations(&session).await?.iter().cloned().collect())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct User {
-    id: i32,
-    account: String,
-    avatar: String,
-    realname: String,
+// 保存Bug的本地标注，笔记、标签和星标都为空时删除并返回 null
+#[tauri::command]
+async fn save_annotation(
+    annotation: Annotation,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<Option<Annotation>, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || annotation::save_annotation(&base_url, &account, annotation))
+        .await
+        .map_err(|e| format!("保存本地标注失败: {}", e))?
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Product {
-    id: i32,
-    program: i32,
-    name: String,
-    code: String,
-    line: i32,
-    #[serde(rename = "PO")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    po: Option<User>,
-    #[serde(rename = "QD")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    qd: Option<User>,
-    #[serde(rename = "RD")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    rd: Option<User>,
-    #[serde(rename = "type")]
-    product_type: String, // normal | branch | platform
-    desc: String,
-    acl: String, // open | private
-    #[serde(skip_serializing_if = "Option::is_none")]
-    whitelist: Option<Vec<User>>,
-    #[serde(rename = "createdBy")]
-    created_by: Option<User>,
-    #[serde(rename = "createdDate")]
-    created_date: String,
+// 把本地标注导出到下载目录并在文件管理器中显示，返回文件路径
+#[tauri::command]
+async fn export_annotations(app: AppHandle, state: State<'_, AppState>, window: Window) -> Result<String, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    let dir = dirs::download_dir()
+        .or_else(dirs::data_dir)
+        .ok_or("无法获取下载目录")?;
+    let name = if account.is_empty() { "default" } else { account.as_str() };
+    let path = dir.join(format!("zentao-annotations-{}-{}.json", name, chrono::Local::now().format("%Y%m%d")));
+    let json = tauri::async_runtime::spawn_blocking(move || annotation::export_annotations(&base_url, &account))
+        .await
+        .map_err(|e| format!("导出本地标注失败: {}", e))??;
+    std::fs::write(&path, json).map_err(|e| format!("写入导出文件失败: {}", e))?;
+    info!("已导出本地标注到{:?}", path);
+    if let Err(e) = app.opener().reveal_item_in_dir(&path) {
+        warn!("在文件管理器中显示导出文件失败: {}", e);
+    }
+    Ok(path.to_string_lossy().into_owned())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProductListResponse {
-    page: i32,
-    total: i32,
-    limit: i32,
-    products: Vec<Product>,
+// 导入导出的本地标注JSON，replace 为 true 时先清空当前账号的标注
+#[tauri::command]
+async fn import_annotations(
+    json: String,
+    replace: Option<bool>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<ImportSummary, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        annotation::import_annotations(&base_url, &account, &json, replace.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("导入本地标注失败: {}", e))?
 }
 
-// 获取当前时间戳
-fn get_current_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
+// 获取当前账号保存的Bug列表视图
+#[tauri::command]
+async fn list_views(state: State<'_, AppState>, window: Window) -> Result<Vec<SavedView>, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || view::list_views(&base_url, &account))
+        .await
+        .map_err(|e| format!("读取视图失败: {}", e))?
 }
 
-// 设置 tracing - 同时输出到控制台和文件
-fn setup_tracing() {
-    use tracing_subscriber::{fmt, EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
-    use tracing_appender::rolling;
-    
-    // 获取日志目录
-    let log_dir = match dirs::data_dir() {
-        Some(mut path) => {
-            path.push("zentao_app");
-            path.push("logs");
-            if let Err(_) = fs::create_dir_all(&path) {
-                eprintln!("创建日志目录失败，将只输出到控制台");
-                setup_simple_tracing();
-                return;
-            }
-            path
-        }
-        None => {
-            eprintln!("无法获取用户数据目录，将只输出到控制台");
-            setup_simple_tracing();
-            return;
-        }
-    };
-    
-    // 创建文件输出器 - 每天轮转
-    let file_appender = rolling::daily(&log_dir, "app.log");
-    
-    // 环境过滤器
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| "debug".into());
-    
-    // 组合订阅器 - 控制台和文件分别配置
-    tracing_subscriber::registry()
-        .with(env_filter)
-        .with(
-            fmt::Layer::new()
-                .with_writer(std::io::stdout)
-                .with_ansi(true) // 控制台支持颜色
-        )
-        .with(
-            fmt::Layer::new()
-                .with_writer(file_appender)
-                .with_ansi(false) // 文件不需要颜色代码
-        )
-        .init();
-    
-    println!("Tracing 初始化成功，日志文件目录: {:?}", log_dir);
+// 保存视图，同名视图已存在时覆盖
+#[tauri::command]
+async fn save_view(
+    name: String,
+    settings: ViewSettings,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<SavedView, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || view::save_view(&base_url, &account, &name, settings))
+        .await
+        .map_err(|e| format!("保存视图失败: {}", e))?
 }
 
-// 简单 tracing 设置（仅控制台）
-fn setup_simple_tracing() {
-    use tracing_subscriber::{fmt, EnvFilter};
-    
-    fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "debug".into())
-        )
-        .init();
+#[tauri::command]
+async fn rename_view(
+    name: String,
+    new_name: String,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<SavedView, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || view::rename_view(&base_url, &account, &name, &new_name))
+        .await
+        .map_err(|e| format!("重命名视图失败: {}", e))?
 }
 
-// 检查token是否有效
-fn is_token_valid() -> bool {
-    let state = APP_STATE.lock().unwrap();
-    state.token.is_some() && get_current_timestamp() < state.token_expire_time
+#[tauri::command]
+async fn delete_view(name: String, state: State<'_, AppState>, window: Window) -> Result<bool, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || view::delete_view(&base_url, &account, &name))
+        .await
+        .map_err(|e| format!("删除视图失败: {}", e))?
 }
 
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+// 获取上一次使用的列表设置，从未保存过时返回 null
 #[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
+async fn get_last_view(state: State<'_, AppState>, window: Window) -> Result<Option<ViewSettings>, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || view::load_last_settings(&base_url, &account))
+        .await
+        .map_err(|e| format!("读取列表设置失败: {}", e))?
 }
 
-// 保存配置
 #[tauri::command]
-fn save_config(config: ZentaoConfig) -> Result<String, String> {
-    debug!("保存配置: {:?}", config);
-    
-    {
-        let mut state = APP_STATE.lock().unwrap();
-        state.config = Some(config.clone());
-    }
-    
-    // 将配置保存到文件
-    save_config_to_file(&config)?;
-    
-    Ok("配置保存成功".to_string())
+async fn save_last_view(settings: ViewSettings, state: State<'_, AppState>, window: Window) -> Result<(), String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || view::save_last_settings(&base_url, &account, settings))
+        .await
+        .map_err(|e| format!("保存列表设置失败: {}", e))?
 }
 
-// 加载配置
+// 把视图导出到下载目录并在文件管理器中显示，names 为空时导出全部视图，返回文件路径
 #[tauri::command]
-fn load_config() -> Result<Option<ZentaoConfig>, String> {
-    // 首先尝试从文件加载
-    match load_config_from_file()? {
-        Some(config) => {
-            // 同时更新内存中的状态
-            let mut state = APP_STATE.lock().unwrap();
-            state.config = Some(config.clone());
-            Ok(Some(config))
-        }
-        None => {
-            // 如果文件中没有，再尝试从内存中读取
-            let state = APP_STATE.lock().unwrap();
-            Ok(state.config.clone())
-        }
+async fn export_views(
+    names: Option<Vec<String>>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<String, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    let dir = dirs::download_dir()
+        .or_else(dirs::data_dir)
+        .ok_or("无法获取下载目录")?;
+    let path = dir.join(format!("zentao-views-{}.json", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    let names = names.unwrap_or_default();
+    let json = tauri::async_runtime::spawn_blocking(move || view::export_views(&base_url, &account, &names))
+        .await
+        .map_err(|e| format!("导出视图失败: {}", e))??;
+    std::fs::write(&path, json).map_err(|e| format!("写入导出文件失败: {}", e))?;
+    info!("已导出视图到{:?}", path);
+    if let Err(e) = app.opener().reveal_item_in_dir(&path) {
+        warn!("在文件管理器中显示导出文件失败: {}", e);
     }
+    Ok(path.to_string_lossy().into_owned())
 }
 
-// 初始化应用（加载保存的token）
+// 导入其他人导出的视图，overwrite 为 true 时覆盖同名视图，否则自动改名
 #[tauri::command]
-fn initialize_app() -> Result<bool, String> {
-    match initialize_app_state() {
-        Ok(_) => Ok(is_token_valid()),
-        Err(e) => Err(e),
-    }
+async fn import_views(
+    json: String,
+    overwrite: Option<bool>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<ViewImportSummary, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        view::import_views(&base_url, &account, &json, overwrite.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("导入视图失败: {}", e))?
 }
 
-// 检查登录状态
+// 关注Bug：以当前版本为基准，之后每次同步记录字段变化
 #[tauri::command]
-fn check_login_status() -> Result<bool, String> {
-    Ok(is_token_valid())
+async fn watch_bug(bug_id: i32, state: State<'_, AppState>, window: Window) -> Result<WatchedBug, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    let client = session.lock().await.zentao_client()?;
+    let bug = check_unauthorized(&session, client.get_bug_detail(bug_id).await).await?;
+    tauri::async_runtime::spawn_blocking(move || watch::watch_bug(&base_url, &account, &bug))
+        .await
+        .map_err(|e| format!("关注Bug失败: {}", e))?
 }
 
-// 登录到禅道系统
+// 取消关注Bug，返回之前是否关注了这个Bug
 #[tauri::command]
-async fn login_zentao(base_url: String, account: String, password: String) -> Result<bool, String> {
-    // 如果已有有效token，直接返回成功
-    if is_token_valid() {
-        return Ok(true);
-    }
-
-    // 自动拼接API路径
-    let api_url = if base_url.ends_with("/api.php/v1") {
-        base_url
-    } else {
-        format!("{}/api.php/v1", base_url.trim_end_matches('/'))
-    };
-
-    let client = reqwest::Client::new();
-    let login_url = format!("{}/tokens", api_url);
-    
-    let login_request = LoginRequest {
-        account,
-        password,
-    };
-    
-    info!("尝试登录到: {}", login_url);
-    
-    match client
-        .post(&login_url)
-        .header("Content-Type", "application/json")
-        .json(&login_request)
-        .send()
+async fn unwatch_bug(bug_id: i32, state: State<'_, AppState>, window: Window) -> Result<bool, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || watch::unwatch_bug(&base_url, &account, bug_id))
         .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            debug!("登录响应状态: {}", status);
-            
-            if status.is_success() {
-                match response.text().await {
-                    Ok(text) => {
-                        debug!("登录响应: 成功获取token (长度: {})", text.len());
-                        
-                        // 解码 Unicode 转义序列
-                        let decoded_text = decode_unicode_escapes(&text);
-                        
-                        match serde_json::from_str::<Value>(&decoded_text) {
-                            Ok(json) => {
-                                if let Some(token) = json.get("token") {
-                                    if let Some(token_str) = token.as_str() {
-                                        // 保存token和过期时间（24小时）
-                                        let expire_time = get_current_timestamp() + (24 * 60 * 60);
-                                        let mut state = APP_STATE.lock().unwrap();
-                                        state.token = Some(token_str.to_string());
-                                        state.token_expire_time = expire_time;
-                                        
-                                        // 保存token到本地文件
-                                        if let Err(e) = save_token_to_file(token_str, expire_time) {
-                                            warn!("保存token到文件失败: {}", e);
-                                            // 即使保存失败，登录仍然成功，只是下次需要重新登录
-                                        }
-                                        
-                                        Ok(true)
-                                    } else {
-                                        Err("token字段不是字符串类型".to_string())
-                                    }
-                                } else {
-                                    Err(format!("响应中没有找到token字段: {}", text))
-                                }
-                            }
-                            Err(e) => Err(format!("解析JSON失败: {} - 响应内容: {}", e, text)),
-                        }
-                    }
-                    Err(e) => Err(format!("读取响应内容失败: {}", e)),
-                }
-            } else {
-                match response.text().await {
-                    Ok(error_text) => {
-                        // 解码错误信息中的 Unicode 转义序列
-                        let decoded_error = decode_unicode_escapes(&error_text);
-                        Err(format!("登录失败，状态码: {} - 错误信息: {}", status, decoded_error))
-                    },
-                    Err(_) => Err(format!("登录失败，状态码: {}", status)),
-                }
-            }
-        }
-        Err(e) => Err(format!("请求失败: {}", e)),
-    }
+        .map_err(|e| format!("取消关注Bug失败: {}", e))?
 }
 
-// 退出登录
+// 获取关注列表及每个Bug的变化记录；refresh 为 true 时先获取关注的Bug的最新版本
 #[tauri::command]
-fn logout_zentao() -> Result<(), String> {
-    let mut state = APP_STATE.lock().unwrap();
-    state.token = None;
-    state.token_expire_time = 0;
-    
-    // 删除保存的token文件
-    if let Err(e) = remove_token_file() {
-        warn!("删除token文件失败: {}", e);
-        // 即使删除失败，登出仍然成功
+async fn list_watched(refresh: Option<bool>, state: State<'_, AppState>, window: Window) -> Result<Vec<WatchedBug>, String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    if refresh.unwrap_or(false) {
+        let client = session.lock().await.zentao_client()?;
+        check_unauthorized(&session, poller::refresh_watched(&session, &client).await).await?;
     }
-    
-    Ok(())
+    tauri::async_runtime::spawn_blocking(move || watch::list_watched(&base_url, &account))
+        .await
+        .map_err(|e| format!("读取关注列表失败: {}", e))?
 }
 
-// 获取用户信息
+// 把关注的Bug的变化标记为已读，不指定Bug时标记全部
 #[tauri::command]
-async fn get_user_info() -> Result<UserInfo, String> {
-    // 检查token是否有效
-    if !is_token_valid() {
-        return Err("Token expired".to_string());
-    }
-
-    let (token, base_url) = {
-        let state = APP_STATE.lock().unwrap();
-        let token = state.token.clone().unwrap();
-        let config = state.config.as_ref().ok_or("配置未找到")?;
-        (token, config.base_url.clone())
-    };
-
-    // 自动拼接API路径
-    let api_url = if base_url.ends_with("/api.php/v1") {
-        base_url
-    } else {
-        format!("{}/api.php/v1", base_url.trim_end_matches('/'))
-    };
-
-    let client = reqwest::Client::new();
-    let user_url = format!("{}/user", api_url);
-    
-    debug!("获取用户信息: {}", user_url);
-    
-    match client
-        .get(&user_url)
-        .header("Token", &token)
-        .header("Content-Type", "application/json")
-        .send()
+async fn mark_watch_read(bug_id: Option<i32>, state: State<'_, AppState>, window: Window) -> Result<(), String> {
+    let session = state.session(window.label()).await;
+    let (base_url, account) = session_profile(&session).await?;
+    tauri::async_runtime::spawn_blocking(move || watch::mark_read(&base_url, &account, bug_id))
         .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            debug!("用户信息响应状态: {}", status);
-            
-            if status.is_success() {
-                match response.text().await {
-                    Ok(text) => {
-                        debug!("用户信息响应: 成功获取 (长度: {})", text.len());
-                        
-                        // 解码 Unicode 转义序列
-                        let decoded_text = decode_unicode_escapes(&text);
-                        
-                        match serde_json::from_str::<Value>(&decoded_text) {
-                            Ok(json) => {
-                                // 根据实际API响应结构解析用户信息
-                                // 禅道API返回的是 { "profile": {...} } 格式
-                                if let Some(profile) = json.get("profile") {
-                                    // 尝试解析为UserInfo结构
-                                    match serde_json::from_value::<UserInfo>(profile.clone()) {
-                                        Ok(user_info) => Ok(user_info),
-                                        Err(e) => {
-                                            let preview = if text.len() > 100 {
-                                                format!("{}...", &text[..100])
-                                            } else {
-                                                text.clone()
-                                            };
-                                            Err(format!("解析UserInfo失败: {} - 响应预览: {}", e, preview))
-                                        }
-                                    }
-                                } else {
-                                    let preview = if text.len() > 100 {
-                                        format!("{}...", &text[..100])
-                                    } else {
-                                        text.clone()
-                                    };
-                                    Err(format!("响应中没有找到profile字段 - 响应预览: {}", preview))
-                                }
-                            }
-                            Err(e) => Err(format!("解析用户信息JSON失败: {} - 响应内容: {}", e, text)),
-                        }
-                    }
-                    Err(e) => Err(format!("读取用户信息响应失败: {}", e)),
-                }
-            } else {
-                // 如果是401错误，说明token过期
-                if status == 401 {
-                    let mut state = APP_STATE.lock().unwrap();
-                    state.token = None;
-                    state.token_expire_time = 0;
-                    return Err("Unauthorized".to_string());
-                }
-                
-                match response.text().await {
-                    Ok(error_text) => {
-                        // 解码错误信息中的 Unicode 转义序列
-                        let decoded_error = decode_unicode_escapes(&error_text);
-                        Err(format!("获取用户信息失败，状态码: {} - 错误信息: {}", status, decoded_error))
-                    },
-                    Err(_) => Err(format!("获取用户信息失败，状态码: {}", status)),
-                }
-            }
-        }
-        Err(e) => Err(format!("请求失败: {}", e)),
-    }
+        .map_err(|e| format!("标记已读失败: {}", e))?
 }
 
-// 根据产品ID获取Bug列表
+// 获取产品的Bug数量趋势（燃尽、燃起或按状态/严重程度/指派人分组），数据来自本地快照
 #[tauri::command]
-async fn get_bugs_by_product(product_ids: Vec<i32>) -> Result<Vec<Bug>, String> {
-    // 检查token是否有效
-    if !is_token_valid() {
-        return Err("Token expired".to_string());
-    }
-
-    let (token, base_url) = {
-        let state = APP_STATE.lock().unwrap();
-        let token = state.token.clone().unwrap();
-        let config = state.config.as_ref().ok_or("配置未找到")?;
-        (token, config.base_url.clone())
-    };
-
-    // 自动拼接API路径
-    let api_url = if base_url.ends_with("/api.php/v1") {
-        base_url.clone()
-    } else {
-        format!("{}/api.php/v1", base_url.trim_end_matches('/'))
-    };
+async fn get_trend(
+    product_id: i32,
+    metric: Option<TrendMetric>,
+    range: Option<DateRange>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<Trend, String> {
+    let session = state.session(window.label()).await;
+    let base_url = session.lock().await.config.as_ref().ok_or("配置未找到")?.base_url.clone();
+    let snapshots = tauri::async_runtime::spawn_blocking(move || snapshot::load_snapshots(&base_url, product_id))
+        .await
+        .map_err(|e| format!("读取Bug快照失败: {}", e))??;
+    debug!("产品{}共有{}个Bug快照", product_id, snapshots.len());
+    Ok(snapshot::build_trend(
+        product_id,
+        &snapshots,
+        metric.unwrap_or_default(),
+        range.unwrap_or_default(),
+    ))
+}
 
-    let client = reqwest::Client::new();
-    
-    // 合并多个产品的Bug列表
-    let mut all_bugs = Vec::new();
-    
-    for product_id in product_ids {
-        let bugs_url = format!("{}/products/{}/bugs", api_url, product_id);
-        
-        debug!("获取产品{}的Bug列表: {}", product_id, bugs_url);
-        
-        match client
-            .get(&bugs_url)
-            .header("Token", &token)
-            .header("Content-Type", "application/json")
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status();
-                debug!("产品{}Bug列表响应状态: {}", product_id, status);
-                
-                if status.is_success() {
-                    match response.text().await {
-                        Ok(text) => {
-                            let preview = if text.len() > 200 {
-                                format!("{}...", &text[..200])
-                            } else {
-                                text.clone()
-                            };
-                            debug!("产品{}Bug列表响应 (长度: {}): {}", product_id, text.len(), preview);
-                            
-                            // 解码 Unicode 转义序列
-                            let decoded_text = decode_unicode_escapes(&text);
-                            
-                            match serde_json::from_str::<BugListResponse>(&decoded_text) {
-                                Ok(mut bug_response) => {
-                                    debug!("产品{}解析到{}个Bug", product_id, bug_response.bugs.len());
-                                    // 调试第一个Bug的数据
-                                    if let Some(first_bug) = bug_response.bugs.first() {
-                                        debug!("第一个Bug数据: id={}, opened_date={}", first_bug.id, first_bug.opened_date);
-                                    }
-                                    // 为每个Bug设置base_url
-                                    for bug in &mut bug_response.bugs {
-                                        bug.base_url = Some(api_url.clone());
-                                    }
-                                    all_bugs.extend(bug_response.bugs);
-                                }
-                                Err(e) => {
-                                    let preview = if text.len() > 100 {
-                                        format!("{}...", &text[..100])
-                                    } else {
-                                        text.clone()
-                                    };
-                                    error!("解析产品{}Bug列表JSON失败: {} - 响应预览: {}", product_id, e, preview);
-                                    continue;
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("读取产品{}Bug列表响应失败: {}", product_id, e);
-                            continue;
-                        }
-                    }
-                } else if status == 401 {
-                    let mut state = APP_STATE.lock().unwrap();
-                    state.token = None;
-                    state.token_expire_time = 0;
-                    return Err("Unauthorized".to_string());
-                } else {
-                    warn!("获取产品{}Bug列表失败，状态码: {}", product_id, status);
-                    continue;
-                }
-            }
-            Err(e) => {
-                error!("请求产品{}Bug列表失败: {}", product_id, e);
-                continue;
-            }
-        }
-    }
-    
-    Ok(all_bugs)
+// 获取我的Bug（指派给我、由我创建或由我解决），不区分产品
+#[tauri::command]
+async fn get_my_bugs(mode: Option<MyBugsMode>, state: State<'_, AppState>, window: Window) -> Result<Vec<Bug>, String> {
+    let mode = mode.unwrap_or_default();
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    check_unauthorized(&session, client.get_my_bugs(mode).await).await
 }
 
 // 获取Bug详情
 #[tauri::command]
-async fn get_bug_detail(bug_id: i32) -> Result<Bug, String> {
+async fn get_bug_detail(bug_id: i32, state: State<'_, AppState>, window: Window) -> Result<Bug, String> {
     info!("开始获取Bug详情 - ID: {}", bug_id);
     
-    // 检查token是否有效
-    if !is_token_valid() {
-        warn!("Token无效或已过期，无法获取Bug详情");
-        return Err("Token expired".to_string());
-    }
+    let session = state.session(window.label()).await;
+    let client = session
+        .lock()
+        .await
+        .zentao_client()
+        .inspect_err(|e| warn!("无法获取Bug详情: {}", e))?;
+    check_unauthorized(&session, client.get_bug_detail(bug_id).await).await
+}
 
-    let (token, base_url) = {
-        let state = APP_STATE.lock().unwrap();
-        let token = state.token.clone().unwrap();
-        let config = state.config.as_ref().ok_or("配置未找到")?;
-        (token, config.base_url.clone())
-    };
+// 把重现步骤（HTML）转换为 Markdown 或纯文本，图片指向当前服务器；复制步骤和导出CSV时调用
+#[tauri::command]
+async fn convert_steps(
+    steps: Vec<String>,
+    format: Option<StepsFormat>,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<Vec<String>, String> {
+    let session = state.session(window.label()).await;
+    let base_url = session.lock().await.config.as_ref().ok_or("配置未找到")?.base_url.clone();
+    let root = server::normalize_base_url(&base_url)?.root;
+    let format = format.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        steps.iter().map(|steps| markdown::convert_steps(steps, &root, format)).collect()
+    })
+    .await
+    .map_err(|e| format!("转换重现步骤失败: {}", e))
+}
 
-    // 自动拼接API路径
-    let api_url = if base_url.ends_with("/api.php/v1") {
-        base_url.clone()
-    } else {
-        format!("{}/api.php/v1", base_url.trim_end_matches('/'))
+// 导出Bug报告（HTML或PDF），path 为空时保存到下载目录；返回保存的路径
+#[tauri::command]
+async fn export_bug_report(
+    bug_id: i32,
+    format: Option<ReportFormat>,
+    path: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    window: Window,
+) -> Result<String, String> {
+    let format = format.unwrap_or_default();
+    let file_name = bug_report::report_file_name(bug_id, format);
+    let path = match path.map(|path| std::path::PathBuf::from(path.trim())).filter(|path| !path.as_os_str().is_empty()) {
+        // 指定的是目录时使用默认文件名
+        Some(dir) if dir.is_dir() => dir.join(file_name),
+        Some(path) => path,
+        None => dirs::download_dir()
+            .or_else(dirs::data_dir)
+            .ok_or("无法获取下载目录")?
+            .join(file_name),
     };
-
-    let client = reqwest::Client::new();
-    let bug_url = format!("{}/bugs/{}", api_url, bug_id);
-    
-    debug!("发送请求获取Bug详情: {}", bug_url);
-    
-    match client
-        .get(&bug_url)
-        .header("Token", &token)
-        .header("Content-Type", "application/json")
-        .send()
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    let html = check_unauthorized(&session, bug_report::build_bug_report(&client, bug_id).await).await?;
+    let output = path.clone();
+    tauri::async_runtime::spawn_blocking(move || bug_report::write_bug_report(&html, format, &output))
         .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            debug!("Bug详情响应状态: {}", status);
-            
-            if status.is_success() {
-                match response.text().await {
-                    Ok(text) => {
-                        // 只打印响应长度和前100个字符，避免控制台输出过长
-                        let preview= text.clone();
-                        debug!("Bug详情响应 (长度: {}): {}", text.len(), preview);
-                        
-                        // 解码 Unicode 转义序列
-                        let decoded_text = decode_unicode_escapes(&text);
-                        
-                        match serde_json::from_str::<Bug>(&decoded_text) {
-                            Ok(mut bug) => {
-                                // 设置base_url用于图片路径处理
-                                bug.base_url = Some(api_url.clone());
-                                info!("成功解析Bug详情，ID: {}", bug.id);
-                                Ok(bug)
-                            }
-                            Err(e) => {
-                                let preview = if text.len() > 100 {
-                                    format!("{}...", &text[..100])
-                                } else {
-                                    text.clone()
-                                };
-                                error!("解析Bug详情JSON失败: {} - 响应预览: {}", e, preview);
-                                Err(format!("解析Bug详情JSON失败: {} - 响应预览: {}", e, preview))
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("读取Bug详情响应失败: {}", e);
-                        Err(format!("读取Bug详情响应失败: {}", e))
-                    }
-                }
-            } else if status == 401 {
-                let mut state = APP_STATE.lock().unwrap();
-                state.token = None;
-                state.token_expire_time = 0;
-                Err("Unauthorized".to_string())
-            } else {
-                match response.text().await {
-                    Ok(error_text) => {
-                        // 解码错误信息中的 Unicode 转义序列
-                        let decoded_error = decode_unicode_escapes(&error_text);
-                        Err(format!("获取Bug详情失败，状态码: {} - 错误信息: {}", status, decoded_error))
-                    },
-                    Err(_) => Err(format!("获取Bug详情失败，状态码: {}", status)),
-                }
-            }
-        }
-        Err(e) => {
-            error!("请求Bug详情失败: {}", e);
-            Err(format!("请求失败: {}", e))
-        }
+        .map_err(|e| format!("导出Bug报告失败: {}", e))??;
+    if let Err(e) = app.opener().reveal_item_in_dir(&path) {
+        warn!("在文件管理器中显示导出文件失败: {}", e);
     }
+    Ok(path.to_string_lossy().into_owned())
 }
 
 // 获取产品列表
 #[tauri::command]
-async fn get_products() -> Result<Vec<Product>, String> {
-    // 检查token是否有效
-    if !is_token_valid() {
-        return Err("Token expired".to_string());
-    }
+async fn get_products(state: State<'_, AppState>, window: Window) -> Result<Vec<Product>, String> {
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    check_unauthorized(&session, client.get_products().await).await
+}
 
-    let (token, base_url) = {
-        let state = APP_STATE.lock().unwrap();
-        let token = state.token.clone().unwrap();
-        let config = state.config.as_ref().ok_or("配置未找到")?;
-        (token, config.base_url.clone())
-    };
+// 获取图片数据（Base64编码）
+#[tauri::command]
+async fn get_image(image_path: String, state: State<'_, AppState>, window: Window) -> Result<String, String> {
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    check_unauthorized(&session, client.get_image(&image_path).await).await
+}
 
-    // 自动拼接API路径
-    let api_url = if base_url.ends_with("/api.php/v1") {
-        base_url
-    } else {
-        format!("{}/api.php/v1", base_url.trim_end_matches('/'))
-    };
+// 获取产品详情
+#[tauri::command]
+async fn get_product_detail(product_id: i32, state: State<'_, AppState>, window: Window) -> Result<Product, String> {
+    let session = state.session(window.label()).await;
+    let client = session.lock().await.zentao_client()?;
+    check_unauthorized(&session, client.get_product_detail(product_id).await).await
+}
 
-    let client = reqwest::Client::new();
-    let products_url = format!("{}/products", api_url);
-    
-    debug!("获取产品列表: {}", products_url);
-    
-    match client
-        .get(&products_url)
-        .header("Token", &token)
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            debug!("产品列表响应状态: {}", status);
-            
-            if status.is_success() {
-                match response.text().await {
-                    Ok(text) => {
-                        let preview = if text.len() > 300 {
-                            format!("{}...", &text[..300])
-                        } else {
-                            text.clone()
-                        };
-                        debug!("产品列表响应 (长度: {}): {}", text.len(), preview);
-                        
-                        // 解码 Unicode 转义序列
-                        let decoded_text = decode_unicode_escapes(&text);
-                        
-                        match serde_json::from_str::<Value>(&decoded_text) {
-                            Ok(json) => {
-                                // 检查是否是分页响应格式 {"page":1,"total":2,"limit":100,"products":[]}
-                                if let (Some(page), Some(total), Some(limit), Some(products)) = (
-                                    json.get("page"),
-                                    json.get("total"), 
-                                    json.get("limit"),
-                                    json.get("products")
-                                ) {
-                                    debug!("分页响应 - 页码: {}, 总数: {}, 限制: {}", page, total, limit);
-                                    // 解析products数组
-                                    match serde_json::from_value::<Vec<Product>>(products.clone()) {
-                                        Ok(product_list) => {
-                                            debug!("成功解析 {} 个产品", product_list.len());
-                                            Ok(product_list)
-                                        },
-                                        Err(e) => {
-                                            let preview = if text.len() > 100 {
-                                                format!("{}...", &text[..100])
-                                            } else {
-                                                text.clone()
-                                            };
-                                            Err(format!("解析产品列表失败: {} - 响应预览: {}", e, preview))
-                                        }
-                                    }
-                                } else if let Some(products) = json.get("products") {
-                                    // 兼容旧格式，直接有products字段但没有分页信息
-                                    match serde_json::from_value::<Vec<Product>>(products.clone()) {
-                                        Ok(product_list) => Ok(product_list),
-                                        Err(e) => Err(format!("解析产品列表失败: {} - 响应内容: {}", e, text)),
-                                    }
-                                } else if json.is_array() {
-                                    // 如果直接是数组格式
-                                    match serde_json::from_value::<Vec<Product>>(json) {
-                                        Ok(product_list) => Ok(product_list),
-                                        Err(e) => Err(format!("解析产品数组失败: {} - 响应内容: {}", e, text)),
-                                    }
-                                } else {
-                                    let preview = if text.len() > 100 {
-                                        format!("{}...", &text[..100])
-                                    } else {
-                                        text.clone()
-                                    };
-                                    Err(format!("响应格式不正确，期望分页格式 - 响应预览: {}", preview))
-                                }
-                            }
-                            Err(e) => Err(format!("解析产品列表JSON失败: {} - 响应内容: {}", e, text)),
-                        }
-                    }
-                    Err(e) => Err(format!("读取产品列表响应失败: {}", e)),
-                }
-            } else {
-                // 如果是401错误，说明token过期
-                if status == 401 {
-                    let mut state = APP_STATE.lock().unwrap();
-                    state.token = None;
-                    state.token_expire_time = 0;
-                    return Err("Unauthorized".to_string());
-                }
-                
-                match response.text().await {
-                    Ok(error_text) => {
-                        // 解码错误信息中的 Unicode 转义序列
-                        let decoded_error = decode_unicode_escapes(&error_text);
-                        Err(format!("获取产品列表失败，状态码: {} - 错误信息: {}", status, decoded_error))
-                    },
-                    Err(_) => Err(format!("获取产品列表失败，状态码: {}", status)),
-                }
-            }
-        }
-        Err(e) => Err(format!("请求失败: {}", e)),
+// 通知前端有待打开的链接，并把主窗口带到前台
+const OPEN_LINK: &str = "open-link";
+
+// 处理系统传入的 zentao-viewer:// 链接；不是应用链接时忽略
+fn open_app_link(app: &AppHandle, link: &str) {
+    let Some(target) = link::parse_app_link(link) else {
+        debug!("忽略无法识别的启动参数: {}", link);
+        return;
+    };
+    info!("打开链接: {}", target.app_link());
+    app.state::<AppState>().set_pending_link(target);
+    if let Some(window) = app.get_webview_window(state::MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    if let Err(e) = app.emit(OPEN_LINK, ()) {
+        warn!("发送打开链接事件失败: {}", e);
     }
 }
 
-// 获取图片数据（Base64编码）
+// 取走待打开的链接，前端在登录后和收到 open-link 事件时调用
 #[tauri::command]
-async fn get_image(image_path: String) -> Result<String, String> {
-    // 检查token是否有效
-    if !is_token_valid() {
-        return Err("Token expired".to_string());
-    }
-
-    let (token, base_url) = {
-        let state = APP_STATE.lock().unwrap();
-        let token = state.token.clone().unwrap();
-        let config = state.config.as_ref().ok_or("配置未找到")?;
-        (token, config.base_url.clone())
-    };
+fn take_pending_link(state: State<'_, AppState>) -> Option<LinkTarget> {
+    state.take_pending_link()
+}
 
-    // 自动拼接API路径，然后提取根URL
-    let api_url = if base_url.ends_with("/api.php/v1") {
-        base_url
-    } else {
-        format!("{}/api.php/v1", base_url.trim_end_matches('/'))
-    };
+// 把粘贴的禅道网页链接或 zentao-viewer:// 链接解析为Bug或产品，网页链接必须属于当前配置的服务器
+#[tauri::command]
+async fn parse_zentao_url(url: String, state: State<'_, AppState>, window: Window) -> Result<LinkTarget, String> {
+    let session = state.session(window.label()).await;
+    let base_url = session.lock().await.config.as_ref().ok_or("配置未找到")?.base_url.clone();
+    link::parse_zentao_url(&url, &base_url)
+}
 
-    // 从API URL中提取根URL
-    // api_url 格式: http://192.168.181.130:81/api.php/v1
-    // 需要提取: http://192.168.181.130:81/
-    let root_url = if let Some(pos) = api_url.find("/api.php") {
-        &api_url[..pos]
-    } else {
-        api_url.trim_end_matches('/')
-    };
+// 在单独的窗口中打开Bug详情，已经打开时切换到该窗口；新窗口共用当前窗口的登录会话
+#[tauri::command]
+async fn open_bug_window(bug_id: i32, app: AppHandle, window: Window) -> Result<(), String> {
+    windows::open_bug_window(&app, window.label(), bug_id).await
+}
 
-    let client = reqwest::Client::new();
-    let image_url = format!("{}/{}", root_url, image_path);
-    
-    debug!("获取图片: {} (从根URL: {})", image_url, root_url);
-    
-    match client
-        .get(&image_url)
-        .header("Token", &token)
-        .send()
+// 读取日志，方便用户在应用内查看并附加到问题反馈
+#[tauri::command]
+async fn get_logs(since: Option<String>, level: Option<String>, limit: Option<usize>) -> Result<Vec<LogEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || logging::read_logs(since.as_deref(), level.as_deref(), limit))
         .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            debug!("图片响应状态: {}", status);
-            
-            if status.is_success() {
-                match response.bytes().await {
-                    Ok(bytes) => {
-                        // 将图片数据编码为Base64
-                        let base64_data = general_purpose::STANDARD.encode(&bytes);
-                        
-                        // 根据文件扩展名确定MIME类型
-                        let mime_type = if image_path.to_lowercase().ends_with(".png") {
-                            "image/png"
-                        } else if image_path.to_lowercase().ends_with(".jpg") || image_path.to_lowercase().ends_with(".jpeg") {
-                            "image/jpeg"
-                        } else if image_path.to_lowercase().ends_with(".gif") {
-                            "image/gif"
-                        } else if image_path.to_lowercase().ends_with(".webp") {
-                            "image/webp"
-                        } else {
-                            "image/png" // 默认为PNG
-                        };
-                        
-                        let data_url = format!("data:{};base64,{}", mime_type, base64_data);
-                        info!("成功获取图片，大小: {} bytes", bytes.len());
-                        
-                        Ok(data_url)
-                    }
-                    Err(e) => {
-                        error!("读取图片数据失败: {}", e);
-                        Err(format!("读取图片数据失败: {}", e))
-                    }
-                }
-            } else if status == 401 {
-                let mut state = APP_STATE.lock().unwrap();
-                state.token = None;
-                state.token_expire_time = 0;
-                Err("Unauthorized".to_string())
-            } else {
-                match response.text().await {
-                    Ok(error_text) => {
-                        // 解码错误信息中的 Unicode 转义序列
-                        let decoded_error = decode_unicode_escapes(&error_text);
-                        Err(format!("获取图片失败，状态码: {} - 错误信息: {}", status, decoded_error))
-                    },
-                    Err(_) => Err(format!("获取图片失败，状态码: {}", status)),
-                }
-            }
-        }
-        Err(e) => {
-            error!("请求图片失败: {}", e);
-            Err(format!("请求失败: {}", e))
-        }
-    }
+        .map_err(|e| format!("读取日志失败: {}", e))?
 }
 
-// 获取产品详情
+// 在系统文件管理器中打开日志目录
 #[tauri::command]
-async fn get_product_detail(product_id: i32) -> Result<Product, String> {
-    // 检查token是否有效
-    if !is_token_valid() {
-        return Err("Token expired".to_string());
-    }
+fn open_log_dir(app: AppHandle) -> Result<(), String> {
+    let dir = logging::log_dir().ok_or("无法获取用户数据目录")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("创建日志目录失败: {}", e))?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("打开日志目录失败: {}", e))
+}
 
-    let (token, base_url) = {
-        let state = APP_STATE.lock().unwrap();
-        let token = state.token.clone().unwrap();
-        let config = state.config.as_ref().ok_or("配置未找到")?;
-        (token, config.base_url.clone())
-    };
+// 临时修改日志级别（不保存），例如排查问题时开启 trace
+#[tauri::command]
+fn set_log_level(level: String) -> Result<(), String> {
+    logging::set_log_level(&level)
+}
 
-    // 自动拼接API路径
-    let api_url = if base_url.ends_with("/api.php/v1") {
-        base_url
-    } else {
-        format!("{}/api.php/v1", base_url.trim_end_matches('/'))
-    };
+// 获取日志设置
+#[tauri::command]
+fn get_log_settings() -> LogSettings {
+    logging::load_log_settings()
+}
 
-    let client = reqwest::Client::new();
-    let product_url = format!("{}/products/{}", api_url, product_id);
-    
-    debug!("获取产品详情: {}", product_url);
-    
-    match client
-        .get(&product_url)
-        .header("Token", &token)
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            debug!("产品详情响应状态: {}", status);
-            
-            if status.is_success() {
-                match response.text().await {
-                    Ok(text) => {
-                        debug!("产品详情响应: 成功获取 (长度: {})", text.len());
-                        
-                        // 解码 Unicode 转义序列
-                        let decoded_text = decode_unicode_escapes(&text);
-                        debug!("产品详情响应: {}", decoded_text);
-                        
-                        match serde_json::from_str::<Value>(&decoded_text) {
-                            Ok(json) => {
-                                // 直接解析产品对象
-                                match serde_json::from_value::<Product>(json) {
-                                    Ok(product) => {
-                                        debug!("成功解析产品详情: {}", product.name);
-                                        Ok(product)
-                                    },
-                                    Err(e) => Err(format!("解析产品详情失败: {} - 响应内容: {}", e, text)),
-                                }
-                            }
-                            Err(e) => Err(format!("解析产品详情JSON失败: {} - 响应内容: {}", e, text)),
-                        }
-                    }
-                    Err(e) => Err(format!("读取产品详情响应失败: {}", e)),
-                }
-            } else {
-                // 如果是401错误，说明token过期
-                if status == 401 {
-                    let mut state = APP_STATE.lock().unwrap();
-                    state.token = None;
-                    state.token_expire_time = 0;
-                    return Err("Unauthorized".to_string());
-                }
-                
-                match response.text().await {
-                    Ok(error_text) => {
-                        // 解码错误信息中的 Unicode 转义序列
-                        let decoded_error = decode_unicode_escapes(&error_text);
-                        Err(format!("获取产品详情失败，状态码: {} - 错误信息: {}", status, decoded_error))
-                    },
-                    Err(_) => Err(format!("获取产品详情失败，状态码: {}", status)),
-                }
-            }
-        }
-        Err(e) => Err(format!("请求失败: {}", e)),
+// 保存日志设置：立即应用日志级别并按新的保留策略清理日志
+#[tauri::command]
+async fn save_log_settings(settings: LogSettings) -> Result<(), String> {
+    logging::set_log_level(&settings.level)?;
+    logging::save_log_settings(&settings)?;
+    if let Some(dir) = logging::log_dir() {
+        tauri::async_runtime::spawn_blocking(move || logging::cleanup_logs(&dir, &settings));
     }
+    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1517,23 +927,125 @@ pub fn run() {
     
     info!("禅道应用启动");
     
-    tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+    // 只允许运行一个实例：再次启动（包括点击 zentao-viewer:// 链接）时把参数转交给已有的实例，必须最先注册
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            debug!("收到另一个实例的启动参数: {:?}", argv);
+            let links = argv.iter().skip(1).filter(|arg| arg.starts_with(link::APP_SCHEME)).collect::<Vec<_>>();
+            if links.is_empty() {
+                if let Some(window) = app.get_webview_window(state::MAIN_WINDOW) {
+                    let _ = window.unminimize();
+                    let _ = window.set_focus();
+                }
+            }
+            for link in links {
+                open_app_link(app, link);
+            }
+        }));
+        builder = builder.plugin(tauri_plugin_deep_link::init());
+    }
+
+    builder
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
+        .manage(AppState::default())
+        .setup(|app| {
+            // Windows 和 Linux 上系统通过命令行参数传入 zentao-viewer:// 链接
+            for arg in std::env::args().skip(1) {
+                if arg.starts_with(link::APP_SCHEME) {
+                    open_app_link(app.handle(), &arg);
+                }
+            }
+            #[cfg(desktop)]
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                // macOS 上系统通过 Opened 事件传入链接，应用已经运行时也会收到
+                let handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    for url in event.urls() {
+                        open_app_link(&handle, url.as_str());
+                    }
+                });
+                // Linux 和开发模式下的 Windows 安装包没有注册协议，运行时注册
+                #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+                if let Err(e) = app.deep_link().register_all() {
+                    warn!("注册 {} 链接失败: {}", link::APP_SCHEME, e);
+                }
+            }
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            // 关闭Bug窗口前记住它的位置和大小
+            if let WindowEvent::CloseRequested { .. } = event {
+                if windows::is_bug_window(window.label()) {
+                    windows::remember_geometry(window);
+                }
+            }
+            // 窗口关闭后释放它的会话
+            if let WindowEvent::Destroyed = event {
+                let app = window.app_handle().clone();
+                let label = window.label().to_string();
+                tauri::async_runtime::spawn(async move {
+                    app.state::<AppState>().remove_session(&label).await;
+                });
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             greet, 
             initialize_app,
             login_zentao, 
             logout_zentao,
             get_bugs_by_product,
+            get_my_bugs,
+            query_bugs,
+            search_bugs,
+            bug_report,
+            bug_report_markdown,
+            get_trend,
+            find_duplicate_bugs,
+            get_bug_commits,
+            get_range_bugs,
+            bug_git_templates,
+            watch_bug,
+            unwatch_bug,
+            list_watched,
+            mark_watch_read,
+            list_annotations,
+            save_annotation,
+            export_annotations,
+            import_annotations,
+            list_views,
+            save_view,
+            rename_view,
+            delete_view,
+            get_last_view,
+            save_last_view,
+            export_views,
+            import_views,
             get_bug_detail,
             get_user_info,
             get_products,
             get_product_detail,
             get_image,
+            export_bug_report,
+            convert_steps,
+            take_pending_link,
+            open_bug_window,
+            parse_zentao_url,
             save_config, 
             load_config, 
+            test_connection,
+            get_logs,
+            open_log_dir,
+            set_log_level,
+            get_log_settings,
+            save_log_settings,
             check_login_status
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, _event| {});
 }
+
@@ -0,0 +1,395 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{info, warn, Level};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use zentao_core::redact::RedactingWriter;
+
+// 日志文件名前缀，按天轮转后文件名为 app.log.YYYY-MM-DD
+const LOG_FILE_PREFIX: &str = "app.log";
+
+// 压缩后的日志文件扩展名
+const COMPRESSED_SUFFIX: &str = ".gz";
+
+// get_logs 默认和最多返回的日志条数
+const DEFAULT_LOG_LIMIT: usize = 500;
+const MAX_LOG_LIMIT: usize = 5000;
+
+// 日志目录：用户数据目录下的 zentao_app/logs
+pub fn log_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|mut path| {
+        path.push("zentao_app");
+        path.push("logs");
+        path
+    })
+}
+
+// 运行时修改日志级别用的句柄
+static RELOAD_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_max_days() -> u32 {
+    30
+}
+
+fn default_max_total_mb() -> u64 {
+    200
+}
+
+fn default_compress() -> bool {
+    true
+}
+
+// 日志设置：级别和保留策略，与禅道配置分开保存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSettings {
+    // 日志级别或过滤指令，如 info、debug、zentao_core=trace,info
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    // 最多保留的天数，0 表示不按天数清理
+    #[serde(rename = "maxDays")]
+    #[serde(default = "default_max_days")]
+    pub max_days: u32,
+    // 日志目录的总大小上限（MB），0 表示不限制
+    #[serde(rename = "maxTotalMb")]
+    #[serde(default = "default_max_total_mb")]
+    pub max_total_mb: u64,
+    // 是否压缩之前的日志文件
+    #[serde(default = "default_compress")]
+    pub compress: bool,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            max_days: default_max_days(),
+            max_total_mb: default_max_total_mb(),
+            compress: default_compress(),
+        }
+    }
+}
+
+fn get_log_settings_file_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|mut path| {
+        path.push("zentao_app");
+        path.push("log_settings.json");
+        path
+    })
+}
+
+// 加载日志设置，文件不存在或损坏时使用默认值
+pub fn load_log_settings() -> LogSettings {
+    get_log_settings_file_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_log_settings(settings: &LogSettings) -> Result<(), String> {
+    let path = get_log_settings_file_path().ok_or("无法获取用户数据目录")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| format!("序列化日志设置失败: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("写入文件失败: {}", e))
+}
+
+fn parse_filter(level: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(level).map_err(|e| format!("无效的日志级别 {}: {}", level, e))
+}
+
+// 运行时修改日志级别，不需要重启应用
+pub fn set_log_level(level: &str) -> Result<(), String> {
+    let filter = parse_filter(level)?;
+    let handle = RELOAD_HANDLE.get().ok_or("日志系统尚未初始化")?;
+    handle.reload(filter).map_err(|e| format!("修改日志级别失败: {}", e))?;
+    info!("日志级别已修改为: {}", level);
+    Ok(())
+}
+
+// 写入前对日志内容脱敏的输出器
+#[derive(Clone)]
+struct RedactingMakeWriter<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter::new(self.0.make_writer())
+    }
+}
+
+// 设置 tracing - 同时输出到控制台和文件
+// RUST_LOG 环境变量优先于日志设置中的级别
+pub fn setup_tracing() {
+    use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
+    use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+    let settings = load_log_settings();
+    let env_filter = EnvFilter::try_from_default_env()
+        .or_else(|_| parse_filter(&settings.level))
+        .unwrap_or_else(|_| default_log_level().into());
+    let (filter_layer, handle) = reload::Layer::new(env_filter);
+    let _ = RELOAD_HANDLE.set(handle);
+
+    // 获取日志目录，失败时只输出到控制台
+    let log_dir = match log_dir() {
+        Some(path) if fs::create_dir_all(&path).is_ok() => Some(path),
+        Some(_) => {
+            eprintln!("创建日志目录失败，将只输出到控制台");
+            None
+        }
+        None => {
+            eprintln!("无法获取用户数据目录，将只输出到控制台");
+            None
+        }
+    };
+
+    // 创建文件输出器 - 每天轮转
+    let file_layer = log_dir.as_ref().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .build(dir)
+            .inspect_err(|e| eprintln!("创建日志文件失败，将只输出到控制台: {}", e))
+            .ok()
+            .map(|appender| {
+                fmt::Layer::new()
+                    .with_writer(RedactingMakeWriter(appender))
+                    .with_ansi(false) // 文件不需要颜色代码
+            })
+    });
+
+    // 组合订阅器 - 控制台和文件分别配置，输出前统一脱敏
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(
+            fmt::Layer::new()
+                .with_writer(RedactingMakeWriter(std::io::stdout))
+                .with_ansi(true) // 控制台支持颜色
+        )
+        .with(file_layer)
+        .init();
+
+    if let Some(dir) = log_dir {
+        println!("Tracing 初始化成功，日志文件目录: {:?}", dir);
+        cleanup_logs(&dir, &settings);
+    }
+}
+
+// 日志文件名中的日期，当天正在写入的文件不处理
+fn log_file_date(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    let date = name
+        .strip_prefix(LOG_FILE_PREFIX)?
+        .trim_start_matches('.');
+    let date = date.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(date);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+fn compress_log_file(path: &Path) -> io::Result<PathBuf> {
+    let mut compressed_name = path.as_os_str().to_owned();
+    compressed_name.push(COMPRESSED_SUFFIX);
+    let compressed_path = PathBuf::from(compressed_name);
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&compressed_path)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)?;
+    Ok(compressed_path)
+}
+
+// 启动时按保留策略清理日志：删除过期文件、压缩之前的文件、超出总大小时从最旧的开始删除
+pub fn cleanup_logs(dir: &Path, settings: &LogSettings) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    // tracing_appender 按 UTC 日期轮转
+    let today = Utc::now().date_naive();
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| log_file_date(&path).map(|date| (date, path)))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut deleted = 0;
+    let mut compressed = 0;
+    let mut kept = Vec::new();
+    for (date, path) in files {
+        if date >= today {
+            kept.push(path);
+            continue;
+        }
+        let age_days = (today - date).num_days();
+        if settings.max_days > 0 && age_days >= i64::from(settings.max_days) {
+            match fs::remove_file(&path) {
+                Ok(()) => deleted += 1,
+                Err(e) => warn!("删除过期日志失败 {:?}: {}", path, e),
+            }
+            continue;
+        }
+        let is_compressed = path.extension().is_some_and(|ext| ext == "gz");
+        if settings.compress && !is_compressed {
+            match compress_log_file(&path) {
+                Ok(compressed_path) => {
+                    compressed += 1;
+                    kept.push(compressed_path);
+                }
+                Err(e) => {
+                    warn!("压缩日志失败 {:?}: {}", path, e);
+                    kept.push(path);
+                }
+            }
+        } else {
+            kept.push(path);
+        }
+    }
+
+    // 超出总大小上限时从最旧的文件开始删除，保留当天的文件
+    if settings.max_total_mb > 0 {
+        let limit = settings.max_total_mb * 1024 * 1024;
+        let size = |path: &PathBuf| fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+        let mut total: u64 = kept.iter().map(size).sum();
+        for path in &kept {
+            if total <= limit || log_file_date(path).is_some_and(|date| date >= today) {
+                break;
+            }
+            let file_size = size(path);
+            match fs::remove_file(path) {
+                Ok(()) => {
+                    total = total.saturating_sub(file_size);
+                    deleted += 1;
+                }
+                Err(e) => warn!("删除日志失败 {:?}: {}", path, e),
+            }
+        }
+    }
+
+    if deleted > 0 || compressed > 0 {
+        info!("日志清理完成: 压缩{}个文件，删除{}个文件", compressed, deleted);
+    }
+}
+
+fn read_log_file(path: &Path) -> io::Result<String> {
+    let mut content = String::new();
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        GzDecoder::new(File::open(path)?).read_to_string(&mut content)?;
+    } else {
+        File::open(path)?.read_to_string(&mut content)?;
+    }
+    Ok(content)
+}
+
+// 一条日志记录
+#[derive(Debug, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+// 解析日志行：2025-01-01T08:00:00.123456Z  INFO tauri_app_lib: 消息
+fn parse_log_line(line: &str) -> Option<(DateTime<FixedOffset>, LogEntry)> {
+    let mut parts = line.splitn(2, ' ');
+    let timestamp = DateTime::parse_from_rfc3339(parts.next()?).ok()?;
+    let rest = parts.next()?.trim_start();
+    let (level, rest) = rest.split_once(' ')?;
+    level.parse::<Level>().ok()?;
+    let (target, message) = rest.split_once(": ").unwrap_or(("", rest));
+    Some((
+        timestamp,
+        LogEntry {
+            timestamp: timestamp.to_rfc3339(),
+            level: level.to_string(),
+            target: target.to_string(),
+            message: message.to_string(),
+        },
+    ))
+}
+
+// 读取日志文件，返回 since 之后、不低于 level 级别的最后 limit 条日志
+pub fn read_logs(since: Option<&str>, level: Option<&str>, limit: Option<usize>) -> Result<Vec<LogEntry>, String> {
+    let since = since
+        .filter(|s| !s.is_empty())
+        .map(|s| DateTime::parse_from_rfc3339(s).map_err(|e| format!("无效的起始时间 {}: {}", s, e)))
+        .transpose()?;
+    let min_level = level
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<Level>().map_err(|_| format!("无效的日志级别: {}", s)))
+        .transpose()?
+        .unwrap_or(Level::TRACE);
+    let limit = limit.unwrap_or(DEFAULT_LOG_LIMIT).clamp(1, MAX_LOG_LIMIT);
+
+    let dir = log_dir().ok_or("无法获取用户数据目录")?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = fs::read_dir(&dir)
+        .map_err(|e| format!("读取日志目录失败: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX))
+        })
+        .collect::<Vec<_>>();
+    // 按日期排序，当天未压缩的文件排在最后
+    files.sort_by_key(|path| log_file_date(path));
+
+    let since_date = since.map(|since| since.with_timezone(&Utc).date_naive());
+    let mut entries = VecDeque::new();
+    for path in files {
+        // 跳过起始时间之前的日志文件
+        if let (Some(since_date), Some(date)) = (since_date, log_file_date(&path)) {
+            if date < since_date {
+                continue;
+            }
+        }
+
+        let content = read_log_file(&path).map_err(|e| format!("读取日志文件失败: {}", e))?;
+        // 多行日志的后续行附加到上一条记录，跟随上一条的过滤结果
+        let mut keep_last = false;
+        for line in content.lines() {
+            match parse_log_line(line) {
+                Some((timestamp, entry)) => {
+                    let level = entry.level.parse::<Level>().unwrap_or(Level::TRACE);
+                    // Level 的比较中越详细越大，ERROR 最小
+                    keep_last = level <= min_level && since.is_none_or(|since| timestamp >= since);
+                    if keep_last {
+                        if entries.len() == limit {
+                            entries.pop_front();
+                        }
+                        entries.push_back(entry);
+                    }
+                }
+                None if keep_last => {
+                    if let Some(last) = entries.back_mut() {
+                        last.message.push('\n');
+                        last.message.push_str(line);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    Ok(entries.into())
+}
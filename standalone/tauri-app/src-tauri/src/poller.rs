@@ -0,0 +1,387 @@
+// 后台轮询：定时获取指派给我的Bug，与上一次的结果比较后发送事件和桌面通知；
+// 每次轮询还会获取关注的Bug并记录变化。
+// 每日快照与轮询开关无关：登录期间每天为今天还没有快照的产品获取一次Bug列表并记录快照
+use chrono::{Local, NaiveDate};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::{Mutex, Notify};
+use tracing::{debug, info, warn};
+
+use zentao_core::client::{MyBugsMode, ZentaoClient, UNAUTHORIZED};
+use zentao_core::config::PollingConfig;
+use zentao_core::diff::{diff_bugs, is_resolved, FieldChange};
+use zentao_core::models::Bug;
+use zentao_core::snapshot;
+use zentao_core::watch::{self, WatchedBug};
+
+use crate::state::{Session, SharedSession};
+
+pub const BUG_ASSIGNED: &str = "bug-assigned";
+pub const BUG_UPDATED: &str = "bug-updated";
+pub const BUG_RESOLVED: &str = "bug-resolved";
+pub const WATCHED_BUG_CHANGED: &str = "watched-bug-changed";
+
+// 最短轮询间隔，避免给服务器造成压力
+const MIN_INTERVAL_SECS: u64 = 30;
+// 出错后退避的最长等待时间
+const MAX_BACKOFF_SECS: u64 = 30 * 60;
+// 未登录或关闭轮询时，重新检查状态的间隔
+const IDLE_CHECK_SECS: u64 = 60;
+// 每日快照失败后重试的间隔
+const SNAPSHOT_RETRY_SECS: u64 = 10 * 60;
+// 一次轮询最多弹出的通知数，超出时合并为一条
+const MAX_NOTIFICATIONS: usize = 5;
+
+// 发送给前端的Bug变化事件
+#[derive(Debug, Clone, Serialize)]
+pub struct BugEvent {
+    #[serde(rename = "bugId")]
+    pub bug_id: i32,
+    pub title: String,
+    // 变化的字段，新指派的Bug为空
+    pub changes: Vec<FieldChange>,
+    pub bug: Bug,
+}
+
+// 确保窗口的轮询任务在运行，登录后调用
+pub async fn ensure_running(app: &AppHandle, label: &str, session: &SharedSession) {
+    let mut guard = session.lock().await;
+    guard.poll_wakeup.notify_one();
+    if guard.poller.as_ref().is_some_and(|poller| !poller.inner().is_finished()) {
+        return;
+    }
+
+    let app = app.clone();
+    let label = label.to_string();
+    let weak = Arc::downgrade(session);
+    let wakeup = guard.poll_wakeup.clone();
+    guard.poller = Some(tauri::async_runtime::spawn(run(app, label, weak, wakeup)));
+    debug!("已启动后台轮询");
+}
+
+// 本轮要做的事
+enum PollStep {
+    // 未登录，或轮询和每日快照都关闭了
+    Idle,
+    Run(ZentaoClient, PollingConfig),
+}
+
+async fn next_step(session: &Mutex<Session>) -> PollStep {
+    let mut session = session.lock().await;
+    let polling = session
+        .config
+        .as_ref()
+        .map(|config| config.polling.clone())
+        .unwrap_or_default();
+    if !(polling.enabled || polling.daily_snapshot) || !session.is_token_valid() {
+        return PollStep::Idle;
+    }
+    match session.zentao_client() {
+        Ok(client) => PollStep::Run(client, polling),
+        Err(e) => {
+            warn!("后台轮询无法创建客户端: {}", e);
+            PollStep::Idle
+        }
+    }
+}
+
+async fn run(app: AppHandle, label: String, session: Weak<Mutex<Session>>, wakeup: Arc<Notify>) {
+    // 上一次获取的Bug，None 表示还没有基线（首次轮询不发送事件）
+    let mut snapshot: Option<HashMap<i32, Bug>> = None;
+    // 当前登录的账号，通过 get_user_info 获取
+    let mut account: Option<String> = None;
+    let mut failures: u32 = 0;
+    // 已经完成每日快照的日期，以及快照失败后下次重试的时间
+    let mut snapshot_date: Option<NaiveDate> = None;
+    let mut snapshot_retry: Option<Instant> = None;
+
+    loop {
+        let Some(shared) = session.upgrade() else {
+            break;
+        };
+
+        let delay = match next_step(&shared).await {
+            PollStep::Idle => {
+                // 退出登录后重新建立基线，避免重新登录时把所有Bug都当作新指派
+                snapshot = None;
+                account = None;
+                failures = 0;
+                Duration::from_secs(IDLE_CHECK_SECS)
+            }
+            PollStep::Run(client, polling) => {
+                let mut delay = Duration::from_secs(IDLE_CHECK_SECS);
+                let mut online = true;
+                if polling.enabled {
+                    let interval = polling.interval_secs.max(MIN_INTERVAL_SECS);
+                    match poll_once(&app, &label, &shared, &client, &polling, &mut account, &mut snapshot).await {
+                        Ok(()) => {
+                            failures = 0;
+                            match refresh_watched(&shared, &client).await {
+                                Ok(changed) => report_watched(&app, &label, &polling, &changed),
+                                Err(e) => warn!("同步关注的Bug失败: {}", e),
+                            }
+                            delay = Duration::from_secs(interval);
+                        }
+                        Err(e) => {
+                            if e == UNAUTHORIZED {
+                                shared.lock().await.clear_token();
+                            }
+                            failures = failures.saturating_add(1);
+                            let backoff = interval
+                                .saturating_mul(1u64 << failures.min(10))
+                                .min(MAX_BACKOFF_SECS);
+                            warn!("后台轮询失败（连续{}次），{}秒后重试: {}", failures, backoff, e);
+                            delay = Duration::from_secs(backoff);
+                            online = false;
+                        }
+                    }
+                } else {
+                    // 关闭轮询后重新打开时重新建立基线
+                    snapshot = None;
+                    account = None;
+                    failures = 0;
+                }
+
+                // 关闭轮询时按 IDLE_CHECK_SECS 检查日期，跨天后记录新的快照
+                let today = Local::now().date_naive();
+                let due = snapshot_retry.is_none_or(|retry| Instant::now() >= retry);
+                if online && polling.daily_snapshot && snapshot_date != Some(today) && due {
+                    match daily_snapshot(&shared, &client).await {
+                        Ok(()) => {
+                            snapshot_date = Some(today);
+                            snapshot_retry = None;
+                        }
+                        Err(e) => {
+                            if e == UNAUTHORIZED {
+                                shared.lock().await.clear_token();
+                            }
+                            warn!("记录每日Bug快照失败，{}秒后重试: {}", SNAPSHOT_RETRY_SECS, e);
+                            snapshot_retry = Some(Instant::now() + Duration::from_secs(SNAPSHOT_RETRY_SECS));
+                        }
+                    }
+                }
+                delay
+            }
+        };
+        drop(shared);
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = wakeup.notified() => {}
+        }
+    }
+    debug!("窗口{}的后台轮询已结束", label);
+}
+
+async fn poll_once(
+    app: &AppHandle,
+    label: &str,
+    session: &Mutex<Session>,
+    client: &ZentaoClient,
+    polling: &PollingConfig,
+    account: &mut Option<String>,
+    snapshot: &mut Option<HashMap<i32, Bug>>,
+) -> Result<(), String> {
+    let me = match account {
+        Some(me) => me.clone(),
+        None => account.insert(client.get_user_info().await?.account).clone(),
+    };
+
+    let current = client
+        .get_my_bugs_for(&me, MyBugsMode::AssignedTo)
+        .await?
+        .into_iter()
+        .map(|bug| (bug.id, bug))
+        .collect::<HashMap<_, _>>();
+    debug!("后台轮询获取到{}个指派给我的Bug", current.len());
+    session.lock().await.sync_bugs(current.values());
+
+    let Some(previous) = snapshot.replace(current.clone()) else {
+        return Ok(());
+    };
+
+    let mut events = Vec::new();
+    for (id, bug) in &current {
+        match previous.get(id) {
+            None => events.push((BUG_ASSIGNED, bug_event(bug, Vec::new()))),
+            Some(old) => {
+                let changes = diff_bugs(old, bug);
+                if changes.is_empty() {
+                    continue;
+                }
+                let name = if is_resolved(bug) && !is_resolved(old) { BUG_RESOLVED } else { BUG_UPDATED };
+                events.push((name, bug_event(bug, changes)));
+            }
+        }
+    }
+
+    // 不再指派给我的Bug：解决后通常会指派回创建者，获取详情确认状态
+    for (id, old) in previous.iter().filter(|(id, _)| !current.contains_key(id)) {
+        match client.get_bug_detail(*id).await {
+            Ok(bug) => {
+                let name = if is_resolved(&bug) { BUG_RESOLVED } else { BUG_UPDATED };
+                events.push((name, bug_event(&bug, diff_bugs(old, &bug))));
+            }
+            Err(e) => debug!("获取Bug{}详情失败，跳过: {}", id, e),
+        }
+    }
+
+    if events.is_empty() {
+        return Ok(());
+    }
+    info!("后台轮询发现{}个Bug变化", events.len());
+
+    for (name, event) in &events {
+        if let Err(e) = app.emit_to(label, name, event) {
+            warn!("发送{}事件失败: {}", name, e);
+        }
+    }
+    if polling.notify {
+        notify(app, &events);
+    }
+    Ok(())
+}
+
+// 为今天还没有快照的产品获取Bug列表，缓存后由 cache_bugs 记录快照
+async fn daily_snapshot(session: &Mutex<Session>, client: &ZentaoClient) -> Result<(), String> {
+    let Some(base_url) = session.lock().await.config.as_ref().map(|config| config.base_url.clone()) else {
+        return Ok(());
+    };
+    let product_ids = client.get_products().await?.iter().map(|product| product.id).collect::<Vec<_>>();
+    let missing = tauri::async_runtime::spawn_blocking(move || {
+        product_ids
+            .into_iter()
+            .filter(|id| !snapshot::has_snapshot_today(&base_url, *id))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| format!("检查Bug快照失败: {}", e))?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let bugs = client.get_bugs_by_product(&missing).await?;
+    session.lock().await.cache_bugs(&missing, &bugs);
+    info!("已记录{}个产品的每日Bug快照", missing.len());
+    Ok(())
+}
+
+// 获取关注的Bug的最新版本并记录变化，返回本次有变化的关注记录
+pub async fn refresh_watched(session: &Mutex<Session>, client: &ZentaoClient) -> Result<Vec<WatchedBug>, String> {
+    let Some((base_url, account)) =
+        session.lock().await.config.as_ref().map(|config| (config.base_url.clone(), config.account.clone()))
+    else {
+        return Ok(Vec::new());
+    };
+    let ids = {
+        let (base_url, account) = (base_url.clone(), account.clone());
+        tauri::async_runtime::spawn_blocking(move || watch::watched_ids(&base_url, &account))
+            .await
+            .map_err(|e| format!("读取关注列表失败: {}", e))??
+    };
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut bugs = Vec::new();
+    for id in ids {
+        match client.get_bug_detail(id).await {
+            Ok(bug) => bugs.push(bug),
+            Err(e) if e == UNAUTHORIZED => return Err(e),
+            // 已删除或没有权限的Bug保留在关注列表中，下次再试
+            Err(e) => debug!("获取关注的Bug{}详情失败，跳过: {}", id, e),
+        }
+    }
+    session.lock().await.sync_bugs(&bugs);
+    tauri::async_runtime::spawn_blocking(move || watch::update_watched(&base_url, &account, &bugs))
+        .await
+        .map_err(|e| format!("更新关注列表失败: {}", e))?
+}
+
+// 手动获取Bug列表后在后台同步关注的Bug，有变化时和轮询一样发送事件并按设置弹出通知
+pub fn spawn_refresh_watched(app: &AppHandle, label: &str, session: &SharedSession, client: ZentaoClient) {
+    let (app, label, session) = (app.clone(), label.to_string(), session.clone());
+    tauri::async_runtime::spawn(async move {
+        let polling = session.lock().await.config.as_ref().map(|config| config.polling.clone()).unwrap_or_default();
+        match refresh_watched(&session, &client).await {
+            Ok(changed) => report_watched(&app, &label, &polling, &changed),
+            Err(e) => {
+                if e == UNAUTHORIZED {
+                    session.lock().await.clear_token();
+                }
+                warn!("同步关注的Bug失败: {}", e);
+            }
+        }
+    });
+}
+
+// 把关注的Bug的变化发送给前端，并按设置弹出桌面通知
+fn report_watched(app: &AppHandle, label: &str, polling: &PollingConfig, changed: &[WatchedBug]) {
+    if changed.is_empty() {
+        return;
+    }
+    info!("{}个关注的Bug有变化", changed.len());
+    for watched in changed {
+        if let Err(e) = app.emit_to(label, WATCHED_BUG_CHANGED, watched) {
+            warn!("发送{}事件失败: {}", WATCHED_BUG_CHANGED, e);
+        }
+    }
+    if !polling.notify {
+        return;
+    }
+    let show = |title: String, body: String| {
+        if let Err(e) = app.notification().builder().title(title).body(body).show() {
+            warn!("显示桌面通知失败: {}", e);
+        }
+    };
+    if changed.len() > MAX_NOTIFICATIONS {
+        show("关注的Bug有更新".to_string(), format!("{}个关注的Bug有变化", changed.len()));
+        return;
+    }
+    for watched in changed {
+        let summary = watched.changes.last().map(|change| change.summary.clone()).unwrap_or_default();
+        show(format!("关注的Bug有更新 #{}", watched.bug_id), format!("{}\n{}", watched.title, summary));
+    }
+}
+
+fn bug_event(bug: &Bug, changes: Vec<FieldChange>) -> BugEvent {
+    BugEvent {
+        bug_id: bug.id,
+        title: bug.title.clone(),
+        changes,
+        bug: bug.clone(),
+    }
+}
+
+fn notification_title(name: &str, bug_id: i32) -> String {
+    match name {
+        BUG_ASSIGNED => format!("新指派的Bug #{}", bug_id),
+        BUG_RESOLVED => format!("Bug已解决 #{}", bug_id),
+        _ => format!("Bug有更新 #{}", bug_id),
+    }
+}
+
+// 弹出桌面通知，变化较多时合并为一条
+fn notify(app: &AppHandle, events: &[(&str, BugEvent)]) {
+    let show = |title: String, body: String| {
+        if let Err(e) = app.notification().builder().title(title).body(body).show() {
+            warn!("显示桌面通知失败: {}", e);
+        }
+    };
+
+    if events.len() > MAX_NOTIFICATIONS {
+        let assigned = events.iter().filter(|(name, _)| *name == BUG_ASSIGNED).count();
+        show(
+            "禅道Bug有更新".to_string(),
+            format!("{}个Bug有变化，其中{}个新指派给你", events.len(), assigned),
+        );
+        return;
+    }
+    for (name, event) in events {
+        show(notification_title(name, event.bug_id), event.title.clone());
+    }
+}
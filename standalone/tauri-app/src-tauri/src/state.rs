@@ -0,0 +1,364 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+use tokio::sync::{Mutex, Notify};
+use tracing::{info, warn, debug};
+
+use zentao_core::client::{ZentaoClient, UNAUTHORIZED};
+use zentao_core::config::{load_config_from_file, ZentaoConfig, DEFAULT_PROFILE};
+use zentao_core::http::{self, HttpClient};
+use zentao_core::link::LinkTarget;
+use zentao_core::models::Bug;
+use zentao_core::search::SearchIndex;
+use zentao_core::server::ApiKind;
+use zentao_core::snapshot;
+use zentao_core::token::{get_current_timestamp, load_token_from_file};
+
+// 主窗口的标签，它使用默认配置档
+pub const MAIN_WINDOW: &str = "main";
+
+// 窗口使用的配置档：主窗口使用默认配置档，其他窗口按标签使用各自的配置档
+pub fn profile_of_window(label: &str) -> &str {
+    if label == MAIN_WINDOW { DEFAULT_PROFILE } else { label }
+}
+
+// 单个窗口的登录会话
+#[derive(Default)]
+pub struct Session {
+    // 配置档名称，决定保存token和配置的文件
+    pub profile: String,
+    pub token: Option<String>,
+    pub token_expire_time: u64,
+    pub config: Option<ZentaoConfig>,
+    // 按当前配置的网络设置构建的HTTP客户端，配置变化时清空
+    pub client: Option<HttpClient>,
+    // 登录时检测到的API类型
+    pub api: ApiKind,
+    // 后台轮询任务
+    pub poller: Option<JoinHandle<()>>,
+    // 登录或修改配置后唤醒轮询任务
+    pub poll_wakeup: Arc<Notify>,
+    // 按产品ID缓存最近获取的Bug列表，供查询使用
+    pub bug_cache: HashMap<i32, Vec<Bug>>,
+    // 缓存中Bug的全文索引，随缓存增量更新
+    pub search_index: SearchIndex,
+    // 图片路径 -> 感知哈希，用于重复Bug检测（附件内容不会变化）
+    pub image_hashes: HashMap<String, u64>,
+}
+
+pub type SharedSession = Arc<Mutex<Session>>;
+
+impl Session {
+    pub fn new(profile: &str) -> Self {
+        Self { profile: profile.to_string(), ..Default::default() }
+    }
+
+    // 从配置档的本地文件加载token和配置
+    pub fn load_saved(&mut self) {
+        // 加载token
+        match load_token_from_file(&self.profile) {
+            Ok(Some((token, expire_time, api))) => {
+                self.token = Some(token);
+                self.token_expire_time = expire_time;
+                self.api = api;
+                info!("成功加载保存的token");
+            }
+            Ok(None) => {
+                debug!("没有找到有效的保存token");
+            }
+            Err(e) => {
+                warn!("加载保存的token失败: {}", e);
+            }
+        }
+
+        // 加载配置
+        match load_config_from_file(&self.profile) {
+            Ok(Some(config)) => {
+                self.set_config(config);
+                info!("成功加载保存的配置");
+            }
+            Ok(None) => {
+                debug!("没有找到保存的配置");
+            }
+            Err(e) => {
+                warn!("加载保存的配置失败: {}", e);
+            }
+        }
+    }
+
+    // 更新配置，同时清空按旧配置构建的HTTP客户端
+    pub fn set_config(&mut self, config: ZentaoConfig) {
+        self.config = Some(config);
+        self.client = None;
+        self.bug_cache.clear();
+        self.search_index.clear();
+        self.image_hashes.clear();
+        self.poll_wakeup.notify_one();
+    }
+
+    // 检查token是否有效
+    pub fn is_token_valid(&self) -> bool {
+        self.token.is_some() && get_current_timestamp() < self.token_expire_time
+    }
+
+    pub fn clear_token(&mut self) {
+        self.token = None;
+        self.token_expire_time = 0;
+        self.bug_cache.clear();
+        self.search_index.clear();
+    }
+
+    // 按产品缓存Bug列表，没有Bug的产品也记录为空列表；同时更新全文索引
+    pub fn cache_bugs(&mut self, product_ids: &[i32], bugs: &[Bug]) {
+        for product_id in product_ids {
+            self.bug_cache.insert(*product_id, Vec::new());
+        }
+        for bug in bugs {
+            if let Some(cached) = self.bug_cache.get_mut(&bug.product) {
+                cached.push(bug.clone());
+            }
+        }
+
+        // 从索引中移除这些产品下已经不存在的Bug
+        let current = bugs.iter().map(|bug| bug.id).collect::<HashSet<_>>();
+        for product_id in product_ids {
+            for bug_id in self.search_index.bug_ids_of_product(*product_id) {
+                if !current.contains(&bug_id) {
+                    self.search_index.remove(bug_id);
+                }
+            }
+        }
+        let updated = bugs.iter().filter(|bug| self.search_index.upsert(bug)).count();
+        debug!("全文索引更新了{}个Bug，共{}个", updated, self.search_index.len());
+
+        self.record_snapshots(product_ids, bugs);
+    }
+
+    // 在后台线程记录这些产品今天的Bug快照，同一天再次同步时覆盖；关闭每日快照时不记录
+    fn record_snapshots(&self, product_ids: &[i32], bugs: &[Bug]) {
+        let Some(base_url) = self
+            .config
+            .as_ref()
+            .filter(|config| config.polling.daily_snapshot)
+            .map(|config| config.base_url.clone())
+        else {
+            return;
+        };
+        let product_ids = product_ids.to_vec();
+        let bugs = bugs.to_vec();
+        tauri::async_runtime::spawn_blocking(move || {
+            if let Err(e) = snapshot::record_snapshots(&base_url, &product_ids, &bugs) {
+                warn!("记录Bug快照失败: {}", e);
+            }
+        });
+    }
+
+    // 用后台轮询获取的Bug更新缓存和全文索引，只更新已缓存产品中的Bug
+    pub fn sync_bugs<'a>(&mut self, bugs: impl IntoIterator<Item = &'a Bug>) {
+        for bug in bugs {
+            let Some(cached) = self.bug_cache.get_mut(&bug.product) else {
+                continue;
+            };
+            match cached.iter_mut().find(|cached| cached.id == bug.id) {
+                Some(cached) => *cached = bug.clone(),
+                None => cached.push(bug.clone()),
+            }
+            self.search_index.upsert(bug);
+        }
+    }
+
+    // 获取HTTP客户端（按配置中的网络设置构建，并缓存到会话）
+    pub fn http_client(&mut self) -> Result<HttpClient, String> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+
+        let network = self
+            .config
+            .as_ref()
+            .map(|config| config.network.clone())
+            .unwrap_or_default();
+        let client = http::build_http_client(&network)?;
+        self.client = Some(client.clone());
+        Ok(client)
+    }
+
+    // 根据当前登录状态和配置创建禅道API客户端
+    pub fn zentao_client(&mut self) -> Result<ZentaoClient, String> {
+        // 检查token是否有效
+        if !self.is_token_valid() {
+            return Err("Token expired".to_string());
+        }
+
+        let token = self.token.clone().unwrap();
+        let base_url = self.config.as_ref().ok_or("配置未找到")?.base_url.clone();
+        let api = self.api.clone();
+        ZentaoClient::new(self.http_client()?, &base_url, token, api)
+    }
+}
+
+// token失效（401或旧版API会话过期）时清除会话中的token
+pub async fn check_unauthorized<T>(session: &SharedSession, result: Result<T, String>) -> Result<T, String> {
+    if matches!(&result, Err(e) if e == UNAUTHORIZED) {
+        session.lock().await.clear_token();
+    }
+    result
+}
+
+// 窗口标签到会话的映射，不依赖Tauri运行时
+#[derive(Default)]
+pub struct Sessions {
+    by_label: HashMap<String, SharedSession>,
+}
+
+impl Sessions {
+    // 获取窗口的会话，不存在时按窗口的配置档创建一个空会话
+    pub fn get_or_create(&mut self, label: &str) -> SharedSession {
+        self.by_label
+            .entry(label.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(Session::new(profile_of_window(label)))))
+            .clone()
+    }
+
+    // 让窗口使用另一个窗口的会话
+    pub fn share(&mut self, label: &str, from: &str) {
+        let session = self.get_or_create(from);
+        self.by_label.insert(label.to_string(), session);
+    }
+
+    // 移除窗口的会话；没有其他窗口共用时返回这个会话，由调用方停止它的后台轮询
+    pub fn remove(&mut self, label: &str) -> Option<SharedSession> {
+        let session = self.by_label.remove(label)?;
+        if self.by_label.values().any(|other| Arc::ptr_eq(other, &session)) {
+            debug!("已移除窗口会话: {}（仍有其他窗口使用）", label);
+            return None;
+        }
+        Some(session)
+    }
+}
+
+// 应用状态：按窗口标签保存会话，每个窗口可以使用不同的配置档登录不同的禅道
+#[derive(Default)]
+pub struct AppState {
+    sessions: Mutex<Sessions>,
+    // 通过 zentao-viewer:// 链接启动或唤起应用时待打开的对象，由前端取走
+    pending_link: std::sync::Mutex<Option<LinkTarget>>,
+}
+
+impl AppState {
+    // 获取窗口的会话，不存在时创建一个空会话
+    pub async fn session(&self, label: &str) -> SharedSession {
+        self.sessions.lock().await.get_or_create(label)
+    }
+
+    // 让新窗口使用已有窗口的会话，共用登录状态、配置和Bug缓存，不需要再次登录
+    pub async fn share_session(&self, label: &str, from: &str) {
+        self.sessions.lock().await.share(label, from);
+    }
+
+    // 窗口关闭时移除会话；没有其他窗口共用这个会话时停止它的后台轮询
+    pub async fn remove_session(&self, label: &str) {
+        let Some(session) = self.sessions.lock().await.remove(label) else {
+            return;
+        };
+        if let Some(poller) = session.lock().await.poller.take() {
+            poller.abort();
+        }
+        debug!("已移除窗口会话: {}", label);
+    }
+
+    // 只保留最近一次的链接
+    pub fn set_pending_link(&self, target: LinkTarget) {
+        *self.pending_link.lock().unwrap_or_else(|e| e.into_inner()) = Some(target);
+    }
+
+    pub fn take_pending_link(&self) -> Option<LinkTarget> {
+        self.pending_link.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bug(id: i32, product: i32, title: &str) -> Bug {
+        serde_json::from_value(json!({
+            "id": id, "product": product, "branch": 0, "module": 0, "project": 0, "execution": 0,
+            "toTask": 0, "toStory": 0, "title": title, "keywords": "", "severity": 3, "pri": 3,
+            "type": "codeerror", "steps": "", "openedBy": null, "openedDate": "2025-01-01",
+            "assignedTo": null, "status": "active"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn windows_get_their_own_profile() {
+        let mut sessions = Sessions::default();
+        let main = sessions.get_or_create(MAIN_WINDOW);
+        let work = sessions.get_or_create("work");
+        assert!(Arc::ptr_eq(&main, &sessions.get_or_create(MAIN_WINDOW)));
+        assert!(!Arc::ptr_eq(&main, &work));
+        assert_eq!(main.try_lock().unwrap().profile, DEFAULT_PROFILE);
+        assert_eq!(work.try_lock().unwrap().profile, "work");
+    }
+
+    #[test]
+    fn shared_session_is_removed_with_the_last_window() {
+        let mut sessions = Sessions::default();
+        let main = sessions.get_or_create(MAIN_WINDOW);
+        sessions.share("bug-1", MAIN_WINDOW);
+        sessions.share("bug-2", MAIN_WINDOW);
+        assert!(Arc::ptr_eq(&main, &sessions.get_or_create("bug-1")));
+        assert_eq!(sessions.get_or_create("bug-2").try_lock().unwrap().profile, DEFAULT_PROFILE);
+
+        assert!(sessions.remove("bug-1").is_none());
+        assert!(sessions.remove(MAIN_WINDOW).is_none());
+        assert!(sessions.remove("bug-1").is_none());
+        let last = sessions.remove("bug-2").expect("最后一个窗口关闭时返回会话");
+        assert!(Arc::ptr_eq(&main, &last));
+    }
+
+    #[test]
+    fn cache_bugs_replaces_products_and_index() {
+        let mut session = Session::new(DEFAULT_PROFILE);
+        session.cache_bugs(&[1, 2], &[bug(10, 1, "登录失败"), bug(11, 1, "导出乱码"), bug(20, 2, "崩溃")]);
+        assert_eq!(session.bug_cache[&1].len(), 2);
+        assert_eq!(session.bug_cache[&2].len(), 1);
+        assert_eq!(session.search_index.len(), 3);
+
+        // 重新获取产品1时移除已经不存在的Bug，产品2不受影响；没有Bug的产品记录为空列表
+        session.cache_bugs(&[1, 3], &[bug(11, 1, "导出乱码")]);
+        assert_eq!(session.bug_cache[&1].iter().map(|bug| bug.id).collect::<Vec<_>>(), vec![11]);
+        assert_eq!(session.bug_cache[&2].len(), 1);
+        assert!(session.bug_cache[&3].is_empty());
+        assert_eq!(session.search_index.bug_ids_of_product(1), vec![11]);
+        assert_eq!(session.search_index.len(), 2);
+    }
+
+    #[test]
+    fn sync_bugs_only_updates_cached_products() {
+        let mut session = Session::new(DEFAULT_PROFILE);
+        session.cache_bugs(&[1], &[bug(10, 1, "登录失败")]);
+        session.sync_bugs(&[bug(10, 1, "登录超时"), bug(12, 1, "新Bug"), bug(30, 3, "未缓存的产品")]);
+        let titles = session.bug_cache[&1].iter().map(|bug| bug.title.as_str()).collect::<Vec<_>>();
+        assert_eq!(titles, vec!["登录超时", "新Bug"]);
+        assert!(!session.bug_cache.contains_key(&3));
+        assert_eq!(session.search_index.len(), 2);
+    }
+
+    #[test]
+    fn clear_token_drops_login_and_cache() {
+        let mut session = Session::new(DEFAULT_PROFILE);
+        session.token = Some("token".to_string());
+        session.token_expire_time = get_current_timestamp() + 60;
+        assert!(session.is_token_valid());
+        session.cache_bugs(&[1], &[bug(10, 1, "登录失败")]);
+
+        session.clear_token();
+        assert!(!session.is_token_valid());
+        assert!(session.bug_cache.is_empty());
+        assert_eq!(session.search_index.len(), 0);
+        assert!(session.zentao_client().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+// Bug详情窗口：每个Bug一个窗口，方便并排比较多个Bug
+// 窗口与打开它的窗口共用会话（登录状态、配置和Bug缓存），关闭时记住位置和大小，下次打开Bug窗口时沿用
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, Window};
+use tracing::{debug, info, warn};
+
+use crate::state::AppState;
+
+// Bug窗口标签的前缀，标签为 bug-<Bug ID>
+const BUG_WINDOW_PREFIX: &str = "bug-";
+const DEFAULT_WIDTH: f64 = 1000.0;
+const DEFAULT_HEIGHT: f64 = 800.0;
+// 已有其他Bug窗口时，新窗口错开的距离
+const CASCADE_OFFSET: f64 = 30.0;
+
+// 窗口的位置和大小（逻辑像素）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct WindowGeometry {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+pub fn bug_window_label(bug_id: i32) -> String {
+    format!("{}{}", BUG_WINDOW_PREFIX, bug_id)
+}
+
+pub fn is_bug_window(label: &str) -> bool {
+    label.starts_with(BUG_WINDOW_PREFIX)
+}
+
+fn geometry_file_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|mut path| {
+        path.push("zentao_app");
+        path.push("bug_window.json");
+        path
+    })
+}
+
+fn load_geometry() -> Option<WindowGeometry> {
+    geometry_file_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn save_geometry(geometry: &WindowGeometry) -> Result<(), String> {
+    let path = geometry_file_path().ok_or("无法获取用户数据目录")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(geometry).map_err(|e| format!("序列化窗口位置失败: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("写入文件失败: {}", e))
+}
+
+// 关闭Bug窗口前记住它的位置和大小；最小化或最大化时不记录
+pub fn remember_geometry(window: &Window) {
+    if window.is_minimized().unwrap_or(false) || window.is_maximized().unwrap_or(false) {
+        return;
+    }
+    let (Ok(scale), Ok(position), Ok(size)) = (window.scale_factor(), window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let position = position.to_logical::<f64>(scale);
+    let size = size.to_logical::<f64>(scale);
+    let geometry = WindowGeometry { x: position.x, y: position.y, width: size.width, height: size.height };
+    if let Err(e) = save_geometry(&geometry) {
+        warn!("保存Bug窗口位置失败: {}", e);
+    }
+}
+
+// 保存的位置是否还在某个显示器内，显示器拔掉后不再使用原来的位置
+fn on_screen(app: &AppHandle, geometry: &WindowGeometry) -> bool {
+    let Ok(monitors) = app.available_monitors() else {
+        return false;
+    };
+    monitors.iter().any(|monitor| {
+        let scale = monitor.scale_factor();
+        let position = monitor.position().to_logical::<f64>(scale);
+        let size = monitor.size().to_logical::<f64>(scale);
+        geometry.x >= position.x
+            && geometry.y >= position.y
+            && geometry.x < position.x + size.width
+            && geometry.y < position.y + size.height
+    })
+}
+
+// 打开Bug窗口，已经打开时把窗口带到前台；from 为打开它的窗口，新窗口共用它的会话
+pub async fn open_bug_window(app: &AppHandle, from: &str, bug_id: i32) -> Result<(), String> {
+    let label = bug_window_label(bug_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        debug!("Bug{}的窗口已打开", bug_id);
+        let _ = window.unminimize();
+        return window.set_focus().map_err(|e| format!("切换到Bug窗口失败: {}", e));
+    }
+
+    app.state::<AppState>().share_session(&label, from).await;
+
+    let url = WebviewUrl::App(format!("index.html?bug={}", bug_id).into());
+    let mut builder = WebviewWindowBuilder::new(app, &label, url)
+        .title(format!("Bug #{}", bug_id))
+        .min_inner_size(600.0, 400.0);
+    match load_geometry() {
+        Some(saved) => {
+            // 与已经打开的Bug窗口错开，避免完全重叠；错开后超出屏幕时使用原位置
+            let open = app.webview_windows().keys().filter(|label| is_bug_window(label)).count() as f64;
+            let offset = CASCADE_OFFSET * open;
+            let cascaded = WindowGeometry { x: saved.x + offset, y: saved.y + offset, ..saved };
+            builder = builder.inner_size(saved.width, saved.height);
+            builder = if on_screen(app, &cascaded) {
+                builder.position(cascaded.x, cascaded.y)
+            } else if on_screen(app, &saved) {
+                builder.position(saved.x, saved.y)
+            } else {
+                builder.center()
+            };
+        }
+        None => builder = builder.inner_size(DEFAULT_WIDTH, DEFAULT_HEIGHT).center(),
+    }
+
+    match builder.build() {
+        Ok(_) => {
+            info!("已打开Bug{}的窗口", bug_id);
+            Ok(())
+        }
+        Err(e) => {
+            app.state::<AppState>().remove_session(&label).await;
+            Err(format!("打开Bug窗口失败: {}", e))
+        }
+    }
+}
@@ -0,0 +1,43 @@
+// 后台轮询事件的组合式函数：监听 bug-assigned / bug-updated / bug-resolved，以及关注的Bug的 watched-bug-changed
+import { onMounted, onUnmounted, ref } from 'vue';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { BugEvent, WatchedBug } from '../types';
+
+export type BugEventName = 'bug-assigned' | 'bug-updated' | 'bug-resolved';
+
+export function useBugEvents(handler: (name: BugEventName, event: BugEvent) => void) {
+  // 最近收到的事件，最新的在前
+  const recentEvents = ref<{ name: BugEventName; event: BugEvent; receivedAt: Date }[]>([]);
+  const unlisteners: UnlistenFn[] = [];
+
+  onMounted(async () => {
+    const names: BugEventName[] = ['bug-assigned', 'bug-updated', 'bug-resolved'];
+    for (const name of names) {
+      unlisteners.push(await listen<BugEvent>(name, ({ payload }) => {
+        recentEvents.value = [{ name, event: payload, receivedAt: new Date() }, ...recentEvents.value].slice(0, 50);
+        handler(name, payload);
+      }));
+    }
+  });
+
+  onUnmounted(() => {
+    unlisteners.forEach(unlisten => unlisten());
+  });
+
+  return {
+    recentEvents
+  };
+}
+
+// 关注的Bug有变化时调用 handler，payload 为更新后的关注记录
+export function useWatchedBugEvents(handler: (watched: WatchedBug) => void) {
+  let unlisten: UnlistenFn | null = null;
+
+  onMounted(async () => {
+    unlisten = await listen<WatchedBug>('watched-bug-changed', ({ payload }) => handler(payload));
+  });
+
+  onUnmounted(() => {
+    unlisten?.();
+  });
+}
@@ -2,16 +2,19 @@
 import { ref } from 'vue';
 import { ElMessage } from 'element-plus';
 import { ZentaoApiService } from '../services/api';
-import type { Bug } from '../types';
+import type { Bug, MyBugsMode } from '../types';
 
 export function useBugs() {
   const bugs = ref<Bug[]>([]);
   const loading = ref(false);
+  // 查询语法错误（包含出错位置的提示）
+  const queryError = ref('');
 
-  const getBugs = async (productIds: number[]) => {
+  const loadBugs = async (fetch: () => Promise<Bug[]>) => {
     loading.value = true;
+    queryError.value = '';
     try {
-      const result = await ZentaoApiService.getBugs(productIds);
+      const result = await fetch();
       bugs.value = result;
       if (result.length > 0) {
         ElMessage.success(`获取到 ${bugs.value.length} 个Bug`);
@@ -22,6 +25,8 @@ export function useBugs() {
       // 如果token过期，需要重新登录
       if (error === 'Token expired' || error === 'Unauthorized') {
         throw new Error('LOGIN_REQUIRED');
+      } else if (String(error).startsWith('查询语法错误')) {
+        queryError.value = String(error);
       } else {
         ElMessage.error(`获取Bug列表失败: ${error}`);
       }
@@ -30,9 +35,21 @@ export function useBugs() {
     }
   };
 
+  const getBugs = (productIds: number[]) => loadBugs(() => ZentaoApiService.getBugs(productIds));
+
+  // 获取我的Bug，不区分产品
+  const getMyBugs = (mode: MyBugsMode) => loadBugs(() => ZentaoApiService.getMyBugs(mode));
+
+  // 高级查询，空查询等同于获取全部Bug
+  const queryBugs = (query: string, productIds: number[]) =>
+    loadBugs(() => ZentaoApiService.queryBugs(query, productIds));
+
   return {
     bugs,
     loading,
-    getBugs
+    queryError,
+    getBugs,
+    getMyBugs,
+    queryBugs
   };
 }
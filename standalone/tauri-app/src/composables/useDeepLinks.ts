@@ -0,0 +1,34 @@
+// 深度链接：系统通过 zentao-viewer:// 链接启动或唤起应用时，后端保存待打开的对象并发出 open-link 事件
+import { onMounted, onUnmounted } from 'vue';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { LinkTarget } from '../types';
+import { ZentaoApiService } from '../services/api';
+
+// ready 为 false（如尚未登录）时链接留在后端，之后调用 openPendingLink 再打开
+export function useDeepLinks(ready: () => boolean, handler: (target: LinkTarget) => void) {
+  let unlisten: UnlistenFn | null = null;
+
+  const openPendingLink = async () => {
+    if (!ready()) return;
+    try {
+      const target = await ZentaoApiService.takePendingLink();
+      if (target) {
+        handler(target);
+      }
+    } catch (error) {
+      console.error('获取待打开的链接失败:', error);
+    }
+  };
+
+  onMounted(async () => {
+    unlisten = await listen('open-link', () => openPendingLink());
+  });
+
+  onUnmounted(() => {
+    unlisten?.();
+  });
+
+  return {
+    openPendingLink
+  };
+}
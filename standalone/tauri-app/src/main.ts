@@ -1,10 +1,13 @@
 import { createApp } from "vue";
 import App from "./App.vue";
+import BugWindow from "./components/BugWindow.vue";
 import ElementPlus from 'element-plus';
 import 'element-plus/dist/index.css';
 import * as ElementPlusIconsVue from '@element-plus/icons-vue';
 
-const app = createApp(App);
+// Bug窗口的地址为 index.html?bug=<Bug ID>，只显示Bug详情
+const bugId = Number(new URLSearchParams(window.location.search).get('bug'));
+const app = bugId > 0 ? createApp(BugWindow, { bugId }) : createApp(App);
 
 // 注册Element Plus
 app.use(ElementPlus);
@@ -1,6 +1,6 @@
 // API服务层 - 封装所有与后端的交互
 import { invoke } from "@tauri-apps/api/core";
-import type { Bug, UserInfo, Product, ZentaoConfig } from '../types';
+import type { Bug, UserInfo, Product, ZentaoConfig, MyBugsMode, SearchHit, BugReport, DateRange, ReportGranularity, Trend, TrendMetric, BugDraft, DuplicateCandidate, BugCommits, RangeBugs, BugGitTemplates, WatchedBug, Annotation, AnnotationImportSummary, SavedView, ViewSettings, ViewImportSummary, LinkTarget, ReportFormat, StepsFormat, ConnectionDiagnosis, LogEntry, LogQuery, LogSettings } from '../types';
 
 export class ZentaoApiService {
   // 应用初始化
@@ -40,6 +40,153 @@ export class ZentaoApiService {
     return await invoke('get_bugs_by_product', { productIds });
   }
 
+  // 按查询语言过滤Bug，productIds 为空时查询全部产品
+  static async queryBugs(query: string, productIds: number[] = [], refresh = false): Promise<Bug[]> {
+    return await invoke('query_bugs', { query, productIds, refresh });
+  }
+
+  // 在标题和重现步骤中全文搜索
+  static async searchBugs(query: string, limit?: number): Promise<SearchHit[]> {
+    return await invoke('search_bugs', { query, limit });
+  }
+
+  // 统计报表，productIds 为空时统计全部产品
+  static async bugReport(productIds: number[], dateRange: DateRange, granularity: ReportGranularity, refresh = false): Promise<BugReport> {
+    return await invoke('bug_report', { productIds, dateRange, granularity, refresh });
+  }
+
+  static async bugReportMarkdown(productIds: number[], dateRange: DateRange, granularity: ReportGranularity): Promise<string> {
+    return await invoke('bug_report_markdown', { productIds, dateRange, granularity });
+  }
+
+  // Bug数量趋势，数据来自每次同步和每天记录的本地快照
+  static async getTrend(productId: number, metric: TrendMetric, range: DateRange = {}): Promise<Trend> {
+    return await invoke('get_trend', { productId, metric, range });
+  }
+
+  // 疑似重复的Bug：传 bugId 时与已有Bug比较，否则与草稿比较；productIds 为空时在目标Bug所属产品中查找
+  static async findDuplicateBugs(target: { bugId?: number; draft?: BugDraft }, productIds: number[] = [], limit?: number): Promise<DuplicateCandidate[]> {
+    return await invoke('find_duplicate_bugs', { bugId: target.bugId, draft: target.draft, productIds, limit });
+  }
+
+  // 本地git仓库中引用了Bug的提交
+  static async getBugCommits(bugId: number): Promise<BugCommits> {
+    return await invoke('get_bug_commits', { bugId });
+  }
+
+  // 提交范围（如 v1.0..v1.1）中引用的Bug，不指定仓库时查找所有配置的仓库
+  static async getRangeBugs(range: string, repository?: string): Promise<RangeBugs[]> {
+    return await invoke('get_range_bugs', { range, repository });
+  }
+
+  // 根据Bug生成分支名和提交信息
+  static async bugGitTemplates(bugId: number): Promise<BugGitTemplates> {
+    return await invoke('bug_git_templates', { bugId });
+  }
+
+  // 关注列表
+  static async watchBug(bugId: number): Promise<WatchedBug> {
+    return await invoke('watch_bug', { bugId });
+  }
+
+  static async unwatchBug(bugId: number): Promise<boolean> {
+    return await invoke('unwatch_bug', { bugId });
+  }
+
+  // refresh 为 true 时先获取关注的Bug的最新版本
+  static async listWatched(refresh = false): Promise<WatchedBug[]> {
+    return await invoke('list_watched', { refresh });
+  }
+
+  // 不指定Bug时把全部变化标记为已读
+  static async markWatchRead(bugId?: number): Promise<void> {
+    return await invoke('mark_watch_read', { bugId });
+  }
+
+  // 本地标注
+  static async listAnnotations(): Promise<Annotation[]> {
+    return await invoke('list_annotations');
+  }
+
+  // 笔记、标签和星标都为空时删除标注并返回 null
+  static async saveAnnotation(annotation: Annotation): Promise<Annotation | null> {
+    return await invoke('save_annotation', { annotation });
+  }
+
+  // 导出到下载目录，返回文件路径
+  static async exportAnnotations(): Promise<string> {
+    return await invoke('export_annotations');
+  }
+
+  static async importAnnotations(json: string, replace = false): Promise<AnnotationImportSummary> {
+    return await invoke('import_annotations', { json, replace });
+  }
+
+  // Bug列表视图，按服务器和账号保存在本机
+  static async listViews(): Promise<SavedView[]> {
+    return await invoke('list_views');
+  }
+
+  // 同名视图已存在时覆盖
+  static async saveView(name: string, settings: ViewSettings): Promise<SavedView> {
+    return await invoke('save_view', { name, settings });
+  }
+
+  static async renameView(name: string, newName: string): Promise<SavedView> {
+    return await invoke('rename_view', { name, newName });
+  }
+
+  static async deleteView(name: string): Promise<boolean> {
+    return await invoke('delete_view', { name });
+  }
+
+  // 上一次使用的列表设置，从未保存过时为 null
+  static async getLastView(): Promise<ViewSettings | null> {
+    return await invoke('get_last_view');
+  }
+
+  static async saveLastView(settings: ViewSettings): Promise<void> {
+    return await invoke('save_last_view', { settings });
+  }
+
+  // 导出到下载目录，不指定名称时导出全部视图，返回文件路径
+  static async exportViews(names?: string[]): Promise<string> {
+    return await invoke('export_views', { names });
+  }
+
+  static async importViews(json: string, overwrite = false): Promise<ViewImportSummary> {
+    return await invoke('import_views', { json, overwrite });
+  }
+
+  // 导出Bug报告（HTML或PDF），path 为空时保存到下载目录，返回文件路径
+  static async exportBugReport(bugId: number, format: ReportFormat, path?: string): Promise<string> {
+    return await invoke('export_bug_report', { bugId, format, path });
+  }
+
+  // 把重现步骤（HTML）转换为 Markdown 或纯文本，图片指向禅道服务器
+  static async convertSteps(steps: string[], format: StepsFormat): Promise<string[]> {
+    return await invoke('convert_steps', { steps, format });
+  }
+
+  // 在单独的窗口中打开Bug，已经打开时切换到该窗口
+  static async openBugWindow(bugId: number): Promise<void> {
+    return await invoke('open_bug_window', { bugId });
+  }
+
+  // 取走通过 zentao-viewer:// 链接传入的待打开对象，没有时为 null
+  static async takePendingLink(): Promise<LinkTarget | null> {
+    return await invoke('take_pending_link');
+  }
+
+  // 解析粘贴的禅道网页链接或 zentao-viewer:// 链接，网页链接必须属于当前服务器
+  static async parseZentaoUrl(url: string): Promise<LinkTarget> {
+    return await invoke('parse_zentao_url', { url });
+  }
+
+  static async getMyBugs(mode: MyBugsMode = 'assignedTo'): Promise<Bug[]> {
+    return await invoke('get_my_bugs', { mode });
+  }
+
   static async getBugDetail(bugId: number): Promise<Bug> {
     return await invoke('get_bug_detail', { bugId });
   }
@@ -57,4 +204,29 @@ export class ZentaoApiService {
   static async loadConfig(): Promise<ZentaoConfig | null> {
     return await invoke('load_config');
   }
+
+  static async testConnection(config: ZentaoConfig): Promise<ConnectionDiagnosis> {
+    return await invoke('test_connection', { config });
+  }
+
+  // 日志相关
+  static async getLogs(query: LogQuery = {}): Promise<LogEntry[]> {
+    return await invoke('get_logs', { since: query.since, level: query.level, limit: query.limit });
+  }
+
+  static async openLogDir(): Promise<void> {
+    return await invoke('open_log_dir');
+  }
+
+  static async setLogLevel(level: string): Promise<void> {
+    return await invoke('set_log_level', { level });
+  }
+
+  static async getLogSettings(): Promise<LogSettings> {
+    return await invoke('get_log_settings');
+  }
+
+  static async saveLogSettings(settings: LogSettings): Promise<void> {
+    return await invoke('save_log_settings', { settings });
+  }
 }
@@ -39,9 +39,31 @@ export interface Bug {
   closed_by?: User;
   closed_date?: string;
   closedDateFormatted?: string; // 格式化后的关闭时间
+  activatedCount?: number; // 被激活（重新打开）的次数
   status: BugStatus;
+  actions?: BugAction[]; // 历史记录，只有Bug详情包含
 }
 
+export interface ActionChange {
+  field: string;
+  old: string;
+  new: string;
+}
+
+// Bug的一条历史记录
+export interface BugAction {
+  actor: string;
+  action: string; // opened、assigned、resolved、closed、commented 等
+  date: string;
+  comment: string;
+  history: ActionChange[];
+}
+
+export type ReportFormat = 'html' | 'pdf';
+
+// 重现步骤转换的格式
+export type StepsFormat = 'markdown' | 'text';
+
 export interface BugPaginatedResponse {
   page: number;
   total: number;
@@ -115,8 +137,312 @@ export interface LoginResponse {
   token: string;
 }
 
+export interface RetryConfig {
+  maxAttempts: number; // 包含第一次请求，1 表示不重试
+  baseDelayMs: number;
+  maxDelayMs: number;
+  jitter: number; // 0.0 - 1.0
+}
+
+export interface NetworkConfig {
+  proxyUrl?: string;
+  caCertPath?: string;
+  acceptInvalidCerts: boolean;
+  connectTimeoutSecs: number; // 0 表示不限制
+  readTimeoutSecs: number; // 0 表示不限制
+  userAgent?: string;
+  rateLimitPerSec?: number; // 0 表示不限制
+  rateLimitBurst?: number;
+  retry?: RetryConfig;
+}
+
+export type ApiMode = 'auto' | 'rest' | 'legacy';
+
+// 我的Bug：指派给我、由我创建、由我解决
+export type MyBugsMode = 'assignedTo' | 'openedBy' | 'resolvedBy';
+
 export interface ZentaoConfig {
   baseUrl: string;
   account: string;
   password: string;
+  apiMode?: ApiMode; // 默认 auto：自动检测，RESTful API 不可用时使用旧版 JSON API
+  network?: NetworkConfig;
+  polling?: PollingConfig;
+  git?: GitConfig;
+}
+
+// 本地git仓库：从提交信息中查找引用的Bug
+export interface GitConfig {
+  repositories: string[];
+  // 提取Bug ID的正则表达式，第一个捕获组为Bug ID；不填时使用默认规则
+  patterns?: string[];
+  // 分支名和提交信息模板，占位符：{id} {title} {slug} {product} {productId} {type} {severity} {pri}
+  branchTemplate?: string;
+  commitTemplate?: string;
+  // 分支名中的中文标题转为拼音（pinyin）还是去掉（drop）
+  chineseTitle?: 'pinyin' | 'drop';
+}
+
+export interface BugGitTemplates {
+  bugId: number;
+  branch: string;
+  commitMessage: string;
+}
+
+// 后台提醒：定时检查指派给我的Bug
+export interface PollingConfig {
+  enabled: boolean;
+  intervalSecs: number;
+  notify: boolean;
+  // 每天记录一次全部产品的Bug快照
+  dailySnapshot: boolean;
+}
+
+// Bug字段变化
+export interface FieldChange {
+  field: string;
+  old: unknown;
+  new: unknown;
+}
+
+// 后台轮询发出的 bug-assigned / bug-updated / bug-resolved 事件
+export interface BugEvent {
+  bugId: number;
+  title: string;
+  changes: FieldChange[];
+  bug: Bug;
+}
+
+// 本地标注：只保存在本机的笔记（Markdown）、个人标签和星标
+export interface Annotation {
+  bugId: number;
+  note: string;
+  tags: string[];
+  starred: boolean;
+  updatedAt?: string;
+}
+
+export interface AnnotationImportSummary {
+  imported: number;
+  skipped: number;
+}
+
+// 链接指向的Bug或产品：zentao-viewer://bug/123 或从禅道网页复制的链接
+export interface LinkTarget {
+  kind: 'bug' | 'product';
+  id: number;
+}
+
+// Bug列表视图：产品选择、筛选条件或查询、排序、分组和显示的列
+export interface ViewSort {
+  prop: string;
+  order: 'ascending' | 'descending';
+}
+
+export interface ViewSettings {
+  products: number[];
+  status: string[];
+  severity: number[];
+  // 指派人姓名，unassigned 表示未指派
+  assignedTo: string[];
+  keyword: string;
+  query: string;
+  sort: ViewSort | null;
+  groupBy: string;
+  // 显示的列，为空时使用默认列
+  columns: string[];
+}
+
+export interface SavedView extends ViewSettings {
+  name: string;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface ViewImportSummary {
+  // 导入后的视图名称，重名时自动改名
+  imported: string[];
+  renamed: number;
+}
+
+// 关注的Bug：每次同步时记录的一次变化
+export interface WatchChange {
+  at: string;
+  changes: FieldChange[];
+  // 解决或关闭时为解决者、关闭者，其他变化为空
+  by?: string | null;
+  // 如“状态: 激活 → 已解决（张三）”
+  summary: string;
+  unread: boolean;
+}
+
+export interface WatchedBug {
+  bugId: number;
+  product: number;
+  title: string;
+  status: string;
+  watchedAt: string;
+  syncedAt: string;
+  lastSeen: Record<string, unknown>;
+  changes: WatchChange[];
+}
+
+export type DiagnosisStepStatus = 'ok' | 'warning' | 'failed' | 'skipped';
+
+export interface DiagnosisStep {
+  name: string; // 'url' | 'network' | 'reachability' | 'version' | 'api' | 'login'
+  title: string;
+  status: DiagnosisStepStatus;
+  message: string;
+  elapsedMs: number;
+}
+
+export interface ConnectionDiagnosis {
+  success: boolean;
+  rootUrl?: string;
+  apiUrl?: string;
+  version?: string;
+  restApi?: boolean;
+  apiMode?: ApiMode; // 实际使用的API
+  steps: DiagnosisStep[];
+}
+
+// 全文搜索结果：title 和 snippet 已转义HTML，命中的词用 <mark> 标出
+export interface SearchHit {
+  bugId: number;
+  product: number;
+  score: number;
+  title: string;
+  snippet: string;
+}
+
+// Bug统计报表
+export type ReportGranularity = 'day' | 'week';
+
+// 日期格式 YYYY-MM-DD，不指定时不限制
+export interface DateRange {
+  from?: string;
+  to?: string;
+}
+
+export interface CountItem {
+  key: string;
+  label: string;
+  count: number;
+}
+
+export interface TrendPoint {
+  period: string;
+  opened: number;
+  resolved: number;
+}
+
+export interface BugReport {
+  productIds: number[];
+  range: DateRange;
+  granularity: ReportGranularity;
+  asOf: string;
+  total: number;
+  byStatus: CountItem[];
+  bySeverity: CountItem[];
+  byPri: CountItem[];
+  byType: CountItem[];
+  byAssignee: CountItem[];
+  byModule: CountItem[];
+  trend: TrendPoint[];
+  resolveTime: { count: number; meanHours: number | null; medianHours: number | null };
+  reopenRate: { reopened: number; resolved: number; rate: number | null };
+  aging: CountItem[];
+}
+
+// Bug数量趋势（来自本地每日快照）
+export type TrendMetric = 'burndown' | 'burnup' | 'status' | 'severity' | 'assignee';
+
+export interface TrendSeries {
+  // 状态代码、严重程度或指派人账号（未指派为空字符串）；燃尽为 active，燃起为 total 和 done
+  key: string;
+  points: { date: string; value: number }[];
+}
+
+export interface Trend {
+  productId: number;
+  metric: TrendMetric;
+  dates: string[];
+  series: TrendSeries[];
+}
+
+// 重复Bug检测
+export interface BugDraft {
+  product: number;
+  title: string;
+  steps?: string;
+  // 截图，data URL 或 Base64
+  screenshots?: string[];
+}
+
+export interface DuplicateEvidence {
+  kind: 'title' | 'steps' | 'screenshot';
+  similarity: number;
+  detail: string;
+}
+
+export interface DuplicateCandidate {
+  bugId: number;
+  product: number;
+  title: string;
+  status: string;
+  score: number;
+  evidence: DuplicateEvidence[];
+}
+
+// 关联的git提交
+export interface CommitInfo {
+  repository: string;
+  id: string;
+  shortId: string;
+  summary: string;
+  message: string;
+  author: string;
+  email: string;
+  time: string;
+  bugIds: number[];
+  files: string[];
+  branches: string[];
+}
+
+export interface BugCommits {
+  bugId: number;
+  commits: CommitInfo[];
+  branches: string[];
+  files: string[];
+}
+
+export interface RangeBugs {
+  repository: string;
+  range: string;
+  bugIds: number[];
+  commits: CommitInfo[];
+}
+
+// 日志记录
+export interface LogEntry {
+  timestamp: string;
+  level: string;
+  target: string;
+  message: string;
+}
+
+// 日志查询条件：since 为 RFC3339 时间，level 为最低级别
+export interface LogQuery {
+  since?: string;
+  level?: string;
+  limit?: number;
+}
+
+// 日志设置：level 为日志级别或过滤指令，maxDays / maxTotalMb 为 0 表示不限制
+export interface LogSettings {
+  level: string;
+  maxDays: number;
+  maxTotalMb: number;
+  compress: boolean;
 }